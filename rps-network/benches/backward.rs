@@ -0,0 +1,55 @@
+//! Training step of networks with growing hidden layers, to compare the cost
+//! of `backward()` against `forward()`. Run with `cargo bench`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rps_network::{NetworkConfig, RPSNetwork};
+
+const INPUT_SIZE: usize = 3;
+const HISTORY_SIZE: usize = 10;
+
+fn network(hidden_size: usize) -> RPSNetwork {
+    NetworkConfig::new()
+        .input_size(INPUT_SIZE)
+        .history_size(HISTORY_SIZE)
+        .hidden_sizes(&[hidden_size, hidden_size])
+        .output_size(INPUT_SIZE)
+        .seed(42)
+        .build()
+        .unwrap()
+}
+
+fn rounds(c: &mut Criterion) {
+    let mut group = c.benchmark_group("round");
+    for hidden_size in [16, 64, 256] {
+        let mut network = network(hidden_size);
+        let mut round = 0;
+        group.bench_with_input(
+            BenchmarkId::new("forward", hidden_size),
+            &hidden_size,
+            |b, _| {
+                b.iter(|| {
+                    let mut input = [0.0; INPUT_SIZE];
+                    input[round % INPUT_SIZE] = 1.0;
+                    round += 1;
+                    network.forward(black_box(&input)).unwrap();
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("backward", hidden_size),
+            &hidden_size,
+            |b, _| {
+                b.iter(|| {
+                    round += 1;
+                    network
+                        .backward(black_box(round % INPUT_SIZE), None)
+                        .unwrap();
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, rounds);
+criterion_main!(benches);
@@ -0,0 +1,44 @@
+/* C API of rps-network, built with `cargo build --release --features ffi`.
+ * See src/ffi.rs for the documentation of each function. */
+
+#ifndef RPS_NETWORK_H
+#define RPS_NETWORK_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Define RPS_NETWORK_F64 when the library is built with the f64 feature. */
+#ifdef RPS_NETWORK_F64
+typedef double rps_real;
+#else
+typedef float rps_real;
+#endif
+
+typedef struct RPSNetwork RPSNetwork;
+
+const char *rps_network_last_error(void);
+
+RPSNetwork *rps_network_new(size_t input_size, size_t history_size,
+                            size_t hidden_size, size_t output_size,
+                            uint64_t seed);
+void rps_network_free(RPSNetwork *network);
+
+int32_t rps_network_forward(RPSNetwork *network, const rps_real *input,
+                            size_t len);
+int32_t rps_network_backward(RPSNetwork *network, size_t label,
+                             rps_real learning_rate);
+size_t rps_network_probs(const RPSNetwork *network, rps_real *out, size_t len);
+
+uint8_t *rps_network_to_bytes(const RPSNetwork *network, size_t *len);
+void rps_network_bytes_free(uint8_t *bytes, size_t len);
+RPSNetwork *rps_network_from_bytes(const uint8_t *bytes, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,156 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use ndarray::{Array1, ArrayD, ArrayView2, ArrayViewD, ArrayViewMut2, ArrayViewMutD, Axis};
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::Real;
+
+/// Soft attention over the slots of the history window. Each slot gets a
+/// score `history[t] · query + position[t]`, and is scaled by its softmax
+/// weight times the number of slots before reaching the first hidden layer,
+/// so that uniform weights leave the history unchanged.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Attention {
+    pub(crate) query: Array1<Real>,
+    pub(crate) position: Array1<Real>,
+    /// Weights of the last `forward()`, from the oldest slot to the newest.
+    pub(crate) weights: Array1<Real>,
+}
+
+impl Attention {
+    /// Starts from uniform weights.
+    pub(crate) fn new(input_size: usize, history_size: usize) -> Self {
+        Self {
+            query: Array1::zeros(input_size),
+            position: Array1::zeros(history_size),
+            weights: Array1::from_elem(history_size, 1.0 / history_size as Real),
+        }
+    }
+
+    /// Updates the attention weights for `history`, in place.
+    pub(crate) fn forward(&mut self, history: ArrayView2<Real>) {
+        for ((weight, slot), position) in self
+            .weights
+            .iter_mut()
+            .zip(history.rows())
+            .zip(&self.position)
+        {
+            *weight = slot.dot(&self.query) + position;
+        }
+        let max = self.weights.fold(Real::NEG_INFINITY, |a, &b| a.max(b));
+        self.weights.mapv_inplace(|s| (s - max).exp());
+        let sum = self.weights.sum();
+        self.weights /= sum;
+    }
+
+    /// Weighted history, flattened slot by slot.
+    pub(crate) fn apply(&self, history: ArrayView2<Real>) -> Array1<Real> {
+        let mut weighted = history.to_owned();
+        self.apply_in_place(weighted.view_mut());
+        weighted.iter().cloned().collect()
+    }
+
+    /// Weights the slots of `history` like `apply()`, without allocating.
+    pub(crate) fn apply_in_place(&self, mut history: ArrayViewMut2<Real>) {
+        let len = self.weights.len() as Real;
+        for (mut slot, weight) in history.rows_mut().into_iter().zip(&self.weights) {
+            slot *= weight * len;
+        }
+    }
+
+    /// Gradients of `query` and `position`, given the gradient `dinput` of
+    /// the loss with respect to the weighted history.
+    pub(crate) fn backward(
+        &self,
+        history: ArrayView2<Real>,
+        dinput: ArrayView2<Real>,
+    ) -> Vec<ArrayD<Real>> {
+        let dweights = (&dinput * &history).sum_axis(Axis(1)) * self.weights.len() as Real;
+        let mean = dweights.dot(&self.weights);
+        let dscores = &self.weights * &(dweights - mean);
+        let dquery = history.t().dot(&dscores);
+        vec![dquery.into_dyn(), dscores.into_dyn()]
+    }
+
+    pub(crate) fn params(&self) -> Vec<ArrayViewD<'_, Real>> {
+        vec![
+            self.query.view().into_dyn(),
+            self.position.view().into_dyn(),
+        ]
+    }
+
+    pub(crate) fn params_mut(&mut self) -> Vec<ArrayViewMutD<'_, Real>> {
+        vec![
+            self.query.view_mut().into_dyn(),
+            self.position.view_mut().into_dyn(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array2;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn uniform_weights_keep_history() {
+        let mut attention = Attention::new(3, 4);
+        let history = Array2::from_shape_fn((4, 3), |(t, i)| (t * 3 + i) as Real);
+
+        attention.forward(history.view());
+
+        assert_eq!(
+            attention.apply(history.view()),
+            Array1::range(0.0, 12.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn gradients() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut random = |shape| Array2::from_shape_simple_fn(shape, || rng.gen_range(-1.0..1.0));
+        let history = random((4, 3));
+        let dinput = random((4, 3));
+        let mut attention = Attention::new(3, 4);
+        attention.query.assign(&random((1, 3)).row(0));
+        attention.position.assign(&random((1, 4)).row(0));
+        let loss = |attention: &mut Attention| {
+            attention.forward(history.view());
+            attention
+                .apply(history.view())
+                .dot(&Array1::from_iter(dinput.iter().cloned()))
+        };
+
+        loss(&mut attention);
+        let grads = attention.backward(history.view(), dinput.view());
+
+        let eps = 1e-2;
+        for (k, grad) in grads.iter().enumerate() {
+            for (j, analytical) in grad.iter().enumerate() {
+                let perturbed = |delta| {
+                    let mut attention = attention.clone();
+                    attention.params_mut()[k].as_slice_mut().unwrap()[j] += delta;
+                    loss(&mut attention)
+                };
+                let numerical = (perturbed(eps) - perturbed(-eps)) / (2.0 * eps);
+
+                assert!(
+                    (numerical - analytical).abs() < 1e-2,
+                    "param {}[{}]: {}, expected {}",
+                    k,
+                    j,
+                    analytical,
+                    numerical
+                );
+            }
+        }
+    }
+}
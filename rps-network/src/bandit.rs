@@ -0,0 +1,372 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::Beta;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    check_input, check_label, CycleDetector, Error, FrequencyPredictor, MarkovPredictor,
+    PatternDetector, Prediction, RPSNetwork, Real, RpsPredictor,
+};
+
+/// How a `BanditSelector` picks the predictor of each round.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionRule {
+    /// UCB1: the best hit rate plus an exploration bonus for predictors
+    /// picked less often.
+    Ucb,
+    /// Thompson sampling: the best hit rate drawn from each predictor's Beta
+    /// posterior.
+    Thompson,
+}
+
+/// Picks one predictor of a pool to act on each round, treating them as the
+/// arms of a bandit whose reward is 1 when the picked predictor's most
+/// likely move was played. Simpler than an `Ensemble`, and handy to compare
+/// architectures live. Every predictor sees and learns from every round,
+/// but only the picked one is scored, and older rounds count less with a
+/// `discount` below 1.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct BanditSelector {
+    predictors: Vec<Box<dyn RpsPredictor>>,
+    rule: SelectionRule,
+    discount: Real,
+    /// Discounted number of times each predictor was picked.
+    pulls: Vec<Real>,
+    /// Discounted number of correct predictions of each predictor when
+    /// picked.
+    hits: Vec<Real>,
+    /// Predictor picked by the last `forward()`.
+    selected: usize,
+    rng: ChaCha8Rng,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl BanditSelector {
+    /// Starts an empty pool picking by `rule`, with rewards multiplied by
+    /// `discount`, between 0 excluded and 1, after every round. `seed`
+    /// drives Thompson sampling.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(rule: SelectionRule, discount: Real, seed: u64) -> Result<BanditSelector, Error> {
+        if !(discount > 0.0 && discount <= 1.0) {
+            return Err(Error::OutOfRange {
+                name: "discount",
+                value: discount,
+                min: Real::MIN_POSITIVE,
+                max: 1.0,
+            });
+        }
+        Ok(Self {
+            predictors: Vec::new(),
+            rule,
+            discount,
+            pulls: Vec::new(),
+            hits: Vec::new(),
+            selected: 0,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        })
+    }
+
+    /// Adds a network, see `push()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn add_network(&mut self, network: RPSNetwork) -> Result<(), Error> {
+        self.push(Box::new(network))
+    }
+
+    /// Adds a Markov baseline, see `push()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn add_markov(&mut self, predictor: MarkovPredictor) -> Result<(), Error> {
+        self.push(Box::new(predictor))
+    }
+
+    /// Adds a frequency baseline, see `push()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn add_frequency(&mut self, predictor: FrequencyPredictor) -> Result<(), Error> {
+        self.push(Box::new(predictor))
+    }
+
+    /// Adds a pattern detector, see `push()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn add_pattern_detector(&mut self, detector: PatternDetector) -> Result<(), Error> {
+        self.push(Box::new(detector))
+    }
+
+    /// Adds a cycle detector, see `push()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn add_cycle_detector(&mut self, detector: CycleDetector) -> Result<(), Error> {
+        self.push(Box::new(detector))
+    }
+
+    /// Passes the moves of a new round to every predictor, then picks the
+    /// one whose prediction of the next move to use.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        if self.predictors.is_empty() {
+            return Err(Error::InvalidSize {
+                name: "predictors",
+                value: 0,
+                min: 1,
+            });
+        }
+        // Check the input before any predictor sees it, so that one failing
+        // doesn't leave the others a round ahead
+        if let Some(input_size) = RpsPredictor::input_size(self) {
+            check_input(input, input_size)?;
+        }
+        for predictor in self.predictors.iter_mut() {
+            predictor.forward(input)?;
+        }
+        self.selected = self.select();
+        Ok(())
+    }
+
+    /// Rewards the picked predictor if it predicted `label`, then trains
+    /// every predictor towards it with `learning_rate`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn backward(&mut self, label: usize, learning_rate: Real) -> Result<(), Error> {
+        check_label(
+            label,
+            self.predictors.first().map_or(0, |p| p.probs().len()),
+        )?;
+        for (pulls, hits) in self.pulls.iter_mut().zip(self.hits.iter_mut()) {
+            *pulls *= self.discount;
+            *hits *= self.discount;
+        }
+        let hit = self.predictors[self.selected].predict().index == label;
+        self.pulls[self.selected] += 1.0;
+        self.hits[self.selected] += hit as u8 as Real;
+        for predictor in self.predictors.iter_mut() {
+            predictor.backward(label, learning_rate)?;
+        }
+        Ok(())
+    }
+
+    /// Output distribution of the predictor picked by the last `forward()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn probs(&self) -> Vec<Real> {
+        self.predictors
+            .get(self.selected)
+            .map_or_else(Vec::new, |p| p.probs())
+    }
+
+    /// Most likely move according to `probs()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn predict(&self) -> Prediction {
+        Prediction::argmax(&self.probs())
+    }
+
+    /// Index of the predictor picked by the last `forward()`, in the order
+    /// they were added.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Discounted number of times each predictor was picked.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn pulls(&self) -> Vec<Real> {
+        self.pulls.clone()
+    }
+
+    /// Discounted hit rate of each predictor when picked, 0 for predictors
+    /// not picked yet.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn hit_rates(&self) -> Vec<Real> {
+        self.pulls
+            .iter()
+            .zip(&self.hits)
+            .map(|(&pulls, &hits)| if pulls > 0.0 { hits / pulls } else { 0.0 })
+            .collect()
+    }
+
+    /// Number of predictors.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn len(&self) -> usize {
+        self.predictors.len()
+    }
+
+    /// Whether no predictor was added yet.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn is_empty(&self) -> bool {
+        self.predictors.is_empty()
+    }
+}
+
+impl BanditSelector {
+    /// Adds `predictor`, which must predict as many moves as the others and
+    /// take inputs of the same length if it needs one.
+    pub fn push(&mut self, predictor: Box<dyn RpsPredictor>) -> Result<(), Error> {
+        if let Some(first) = self.predictors.first() {
+            let (expected, found) = (first.probs().len(), predictor.probs().len());
+            if found != expected {
+                return Err(Error::ShapeMismatch {
+                    name: "probs",
+                    expected: vec![expected],
+                    found: vec![found],
+                });
+            }
+        }
+        if let (Some(expected), Some(found)) =
+            (RpsPredictor::input_size(self), predictor.input_size())
+        {
+            if found != expected {
+                return Err(Error::ShapeMismatch {
+                    name: "input",
+                    expected: vec![expected],
+                    found: vec![found],
+                });
+            }
+        }
+        self.predictors.push(predictor);
+        self.pulls.push(0.0);
+        self.hits.push(0.0);
+        Ok(())
+    }
+
+    /// Predictors in the order they were added.
+    pub fn predictors(&self) -> &[Box<dyn RpsPredictor>] {
+        &self.predictors
+    }
+
+    /// Predictor to use for the next round. Predictors never picked come
+    /// first.
+    fn select(&mut self) -> usize {
+        if let Some(untried) = self.pulls.iter().position(|&pulls| pulls == 0.0) {
+            return untried;
+        }
+        let total: Real = self.pulls.iter().sum();
+        let scores: Vec<Real> = match self.rule {
+            SelectionRule::Ucb => self
+                .hit_rates()
+                .iter()
+                .zip(&self.pulls)
+                .map(|(rate, pulls)| rate + (2.0 * total.ln().max(0.0) / pulls).sqrt())
+                .collect(),
+            SelectionRule::Thompson => {
+                let (pulls, hits) = (&self.pulls, &self.hits);
+                pulls
+                    .iter()
+                    .zip(hits)
+                    .map(|(&pulls, &hits)| {
+                        let beta = Beta::new(hits + 1.0, pulls - hits + 1.0)
+                            .expect("counts are non-negative");
+                        self.rng.sample(beta)
+                    })
+                    .collect()
+            }
+        };
+        Prediction::argmax(&scores).index
+    }
+}
+
+impl RpsPredictor for BanditSelector {
+    fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        BanditSelector::forward(self, input)
+    }
+
+    fn backward(&mut self, label: usize, learning_rate: Real) -> Result<(), Error> {
+        BanditSelector::backward(self, label, learning_rate)
+    }
+
+    fn input_size(&self) -> Option<usize> {
+        self.predictors.iter().find_map(|p| p.input_size())
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        BanditSelector::probs(self)
+    }
+
+    fn predict(&self) -> Prediction {
+        BanditSelector::predict(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Move, NetworkConfig};
+
+    fn selector(rule: SelectionRule) -> BanditSelector {
+        let mut selector = BanditSelector::new(rule, 0.95, 42).unwrap();
+        selector
+            .add_frequency(FrequencyPredictor::new(3, 1.0).unwrap())
+            .unwrap();
+        selector
+            .add_markov(MarkovPredictor::new(1, 3).unwrap())
+            .unwrap();
+        selector
+            .add_pattern_detector(PatternDetector::new(10).unwrap())
+            .unwrap();
+        selector
+    }
+
+    #[test]
+    fn picks_best_predictor() {
+        for rule in [SelectionRule::Ucb, SelectionRule::Thompson] {
+            let mut selector = selector(rule);
+            // Only the Markov baseline can learn the cycle
+            let mut markov_picks = 0;
+            for round in 0..100 {
+                let opponent = Move::ALL[round % 3];
+                if round > 0 {
+                    selector.backward(opponent.index(), 0.1).unwrap();
+                }
+                let bot = Move::ALL[(round * 2) % 3];
+                selector
+                    .forward(&[opponent.one_hot(), bot.one_hot()].concat())
+                    .unwrap();
+                if round >= 50 && selector.selected() == 1 {
+                    markov_picks += 1;
+                }
+            }
+            assert!(markov_picks > 30, "{:?}: {}", rule, markov_picks);
+            assert_eq!(selector.probs().len(), 3);
+            assert!(selector.hit_rates()[1] > 0.9);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(BanditSelector::new(SelectionRule::Ucb, 0.0, 0).is_err());
+        assert!(BanditSelector::new(SelectionRule::Ucb, 1.5, 0).is_err());
+        let mut selector = BanditSelector::new(SelectionRule::Ucb, 1.0, 0).unwrap();
+        assert!(selector.forward(&[1.0, 0.0, 0.0]).is_err());
+        assert!(selector.backward(0, 0.1).is_err());
+        selector
+            .add_markov(MarkovPredictor::new(1, 3).unwrap())
+            .unwrap();
+        assert!(selector
+            .add_frequency(FrequencyPredictor::new(4, 1.0).unwrap())
+            .is_err());
+
+        // Networks must take the same inputs, so that forward() either
+        // feeds the round to every predictor or to none
+        let network = NetworkConfig::new().moves(3).seed(1).build().unwrap();
+        selector.add_network(network).unwrap();
+        let longer = NetworkConfig::new().input_size(6).seed(1).build().unwrap();
+        assert!(matches!(
+            selector.add_network(longer),
+            Err(Error::ShapeMismatch { name: "input", .. })
+        ));
+        for _ in 0..3 {
+            selector.forward(&[1.0, 0.0, 0.0]).unwrap();
+            selector.backward(1, 0.1).unwrap();
+        }
+        let markov = selector.predictors()[0].probs();
+        assert!(selector.forward(&[0.0, 1.0, 0.0, 0.0, 0.0, 1.0]).is_err());
+        assert_eq!(selector.predictors()[0].probs(), markov);
+    }
+}
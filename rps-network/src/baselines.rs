@@ -0,0 +1,798 @@
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::moves::one_hot;
+use crate::{check_input, check_label, Beats, Error, Outcome, Prediction, Real, RpsPredictor};
+
+/// Largest transition table a `MarkovPredictor` may allocate, in counts.
+const MAX_COUNTS: usize = 1 << 20;
+/// Shortest cycle a `CycleDetector` looks for.
+const MIN_PERIOD: usize = 2;
+/// Longest cycle a `CycleDetector` looks for.
+const MAX_PERIOD: usize = 8;
+
+/// Classic order-k Markov baseline: predicts the opponent's next move from
+/// how often each move followed their last `order` moves, with add-one
+/// smoothing. It reads the opponent's move from the first `moves` inputs, so
+/// it takes the same inputs as a network fed by any `Encoding`, and ignores
+/// the learning rate.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkovPredictor {
+    order: usize,
+    moves: usize,
+    /// Opponent's last `order` moves, from the oldest to the newest.
+    context: VecDeque<usize>,
+    /// Number of times each move followed each context, by context then
+    /// move.
+    counts: Vec<u32>,
+    probs: Vec<Real>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl MarkovPredictor {
+    /// Counts transitions from the last `order` moves in a game of `moves`
+    /// moves. Order 0 counts how often each move is played.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(order: usize, moves: usize) -> Result<MarkovPredictor, Error> {
+        if moves < 2 {
+            return Err(Error::InvalidSize {
+                name: "moves",
+                value: moves,
+                min: 2,
+            });
+        }
+        let counts = u32::try_from(order)
+            .ok()
+            .and_then(|order| moves.checked_pow(order))
+            .and_then(|contexts| contexts.checked_mul(moves))
+            .filter(|&counts| counts <= MAX_COUNTS)
+            .ok_or(Error::Unsupported(
+                "the transition table of this order is too large",
+            ))?;
+        Ok(Self {
+            order,
+            moves,
+            context: VecDeque::with_capacity(order + 1),
+            counts: vec![0; counts],
+            probs: vec![1.0 / moves as Real; moves],
+        })
+    }
+
+    /// Adds the opponent's move of a new round, one-hot encoded in the first
+    /// `moves` values of `input`, and predicts the next one.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        self.context.push_back(read_move(input, 0, self.moves)?);
+        if self.context.len() > self.order {
+            self.context.pop_front();
+        }
+
+        let counts = self.counts();
+        let total: u32 = counts.iter().sum();
+        let denominator = (total as usize + self.moves) as Real;
+        self.probs = counts
+            .iter()
+            .map(|&count| (count + 1) as Real / denominator)
+            .collect();
+        Ok(())
+    }
+
+    /// Counts `label` as the move following the current context.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn backward(&mut self, label: usize) -> Result<(), Error> {
+        check_label(label, self.moves)?;
+        if let Some(context) = self.context_index() {
+            self.counts[context * self.moves + label] += 1;
+        }
+        Ok(())
+    }
+
+    /// Output distribution of the last `forward()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn probs(&self) -> Vec<Real> {
+        self.probs.clone()
+    }
+
+    /// Most likely move according to `probs()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn predict(&self) -> Prediction {
+        Prediction::argmax(&self.probs)
+    }
+
+    /// Number of past moves the prediction depends on.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn order(&self) -> usize {
+        self.order
+    }
+}
+
+impl MarkovPredictor {
+    /// Index of the current context in the transition table, or `None`
+    /// until `order` moves were seen.
+    fn context_index(&self) -> Option<usize> {
+        (self.context.len() == self.order).then(|| {
+            self.context
+                .iter()
+                .fold(0, |index, &m| index * self.moves + m)
+        })
+    }
+
+    /// Counts of the moves following the current context, all zero until
+    /// `order` moves were seen.
+    fn counts(&self) -> Vec<u32> {
+        match self.context_index() {
+            Some(context) => self.counts[context * self.moves..(context + 1) * self.moves].to_vec(),
+            None => vec![0; self.moves],
+        }
+    }
+}
+
+/// Frequency baseline: predicts each of the opponent's moves in proportion
+/// to how often it was played, smoothed by a symmetric Dirichlet prior. Much
+/// better than an untrained network over the first rounds; `mix()` combines
+/// it with the network's distribution. Takes the same inputs as
+/// `MarkovPredictor`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrequencyPredictor {
+    moves: usize,
+    /// Concentration of the prior, added to the count of every move.
+    prior: Real,
+    counts: Vec<u32>,
+    probs: Vec<Real>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl FrequencyPredictor {
+    /// Counts the moves of a game of `moves` moves, starting from `prior`
+    /// pseudo-counts per move, which must be positive: 1 is Laplace
+    /// smoothing, and larger values take longer to trust the counts.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(moves: usize, prior: Real) -> Result<FrequencyPredictor, Error> {
+        if moves < 2 {
+            return Err(Error::InvalidSize {
+                name: "moves",
+                value: moves,
+                min: 2,
+            });
+        }
+        if !(prior > 0.0 && prior.is_finite()) {
+            return Err(Error::OutOfRange {
+                name: "prior",
+                value: prior,
+                min: Real::MIN_POSITIVE,
+                max: Real::INFINITY,
+            });
+        }
+        Ok(Self {
+            moves,
+            prior,
+            counts: vec![0; moves],
+            probs: vec![1.0 / moves as Real; moves],
+        })
+    }
+
+    /// Checks the opponent's move of a new round, one-hot encoded in the
+    /// first `moves` values of `input`, and predicts the next one from the
+    /// counts so far.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        read_move(input, 0, self.moves)?;
+        let denominator = self.rounds() as Real + self.prior * self.moves as Real;
+        self.probs = self
+            .counts
+            .iter()
+            .map(|&count| (count as Real + self.prior) / denominator)
+            .collect();
+        Ok(())
+    }
+
+    /// Counts `label` as a move of the opponent.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn backward(&mut self, label: usize) -> Result<(), Error> {
+        check_label(label, self.moves)?;
+        self.counts[label] += 1;
+        Ok(())
+    }
+
+    /// Output distribution of the last `forward()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn probs(&self) -> Vec<Real> {
+        self.probs.clone()
+    }
+
+    /// Most likely move according to `probs()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn predict(&self) -> Prediction {
+        Prediction::argmax(&self.probs)
+    }
+
+    /// Number of moves counted so far.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn rounds(&self) -> u32 {
+        self.counts.iter().sum()
+    }
+
+    /// Mixes `probs()` with another distribution over the same moves, such
+    /// as a network's, giving it `weight` between 0 and 1. Raising the
+    /// weight as `rounds()` grows hands the prediction over to the network
+    /// once it had time to learn.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn mix(&self, probs: &[Real], weight: Real) -> Result<Vec<Real>, Error> {
+        check_input(probs, self.moves)?;
+        if !(0.0..=1.0).contains(&weight) {
+            return Err(Error::OutOfRange {
+                name: "weight",
+                value: weight,
+                min: 0.0,
+                max: 1.0,
+            });
+        }
+        Ok(self
+            .probs
+            .iter()
+            .zip(probs)
+            .map(|(own, other)| (1.0 - weight) * own + weight * other)
+            .collect())
+    }
+}
+
+impl RpsPredictor for FrequencyPredictor {
+    fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        FrequencyPredictor::forward(self, input)
+    }
+
+    fn backward(&mut self, label: usize, _learning_rate: Real) -> Result<(), Error> {
+        FrequencyPredictor::backward(self, label)
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        FrequencyPredictor::probs(self)
+    }
+
+    fn predict(&self) -> Prediction {
+        FrequencyPredictor::predict(self)
+    }
+}
+
+/// Common human habit spotted by a `PatternDetector`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// Repeat a winning move, otherwise switch to the move that beats the
+    /// bot's last one.
+    WinStayLoseShift = 0,
+    /// Play the bot's last move.
+    CopyBot = 1,
+}
+
+impl Pattern {
+    /// Every pattern, in index order.
+    pub const ALL: [Pattern; 2] = [Pattern::WinStayLoseShift, Pattern::CopyBot];
+
+    /// Move the pattern plays after the opponent played `opponent` against
+    /// the bot's `bot`.
+    fn next_move(self, rules: &Beats, opponent: usize, bot: usize) -> Result<usize, Error> {
+        let moves = rules.moves();
+        Ok(match self {
+            Pattern::WinStayLoseShift if rules.judge(opponent, bot)? == Outcome::Win => opponent,
+            Pattern::WinStayLoseShift => rules.counter(&one_hot(bot, moves), moves)?,
+            Pattern::CopyBot => bot,
+        })
+    }
+}
+
+/// Rule-based baseline that checks how often the opponent followed each
+/// `Pattern` over the last rounds, and predicts the next move of the one
+/// followed most often, with that hit rate as its probability. Needs both
+/// moves of each round, as in `Encoding::BothMoves` inputs.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternDetector {
+    rules: Beats,
+    window: usize,
+    /// Move each pattern predicts for the next round, if a round was seen.
+    predictions: Option<Vec<usize>>,
+    /// Whether each pattern predicted the last `window` rounds, by pattern.
+    hits: Vec<VecDeque<bool>>,
+    probs: Vec<Real>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl PatternDetector {
+    /// Detects patterns in rock-paper-scissors over the last `window`
+    /// rounds.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(window: usize) -> Result<PatternDetector, Error> {
+        Self::with_rules(&Beats::rock_paper_scissors(), window)
+    }
+
+    /// Detects patterns in the game described by `rules` over the last
+    /// `window` rounds.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn with_rules(rules: &Beats, window: usize) -> Result<PatternDetector, Error> {
+        if window == 0 {
+            return Err(Error::InvalidSize {
+                name: "window",
+                value: 0,
+                min: 1,
+            });
+        }
+        let moves = rules.moves();
+        Ok(Self {
+            rules: rules.clone(),
+            window,
+            predictions: None,
+            hits: vec![VecDeque::with_capacity(window + 1); Pattern::ALL.len()],
+            probs: vec![1.0 / moves as Real; moves],
+        })
+    }
+
+    /// Adds a round, the opponent's and then the bot's one-hot moves in the
+    /// first `2 * moves` values of `input`, and predicts the next move.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        let moves = self.rules.moves();
+        let opponent = read_move(input, 0, moves)?;
+        let bot = read_move(input, moves, moves)?;
+        let predictions = Pattern::ALL
+            .iter()
+            .map(|pattern| pattern.next_move(&self.rules, opponent, bot))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Put the hit rate of the best pattern on its move, spreading the
+        // rest evenly, unless no pattern does better than chance
+        let best = self.detected();
+        let chance = 1.0 / moves as Real;
+        self.probs = vec![chance; moves];
+        if let Some(pattern) = best {
+            let rate = self.hit_rate(pattern);
+            self.probs.fill((1.0 - rate) / (moves - 1) as Real);
+            self.probs[predictions[pattern as usize]] = rate;
+        }
+        self.predictions = Some(predictions);
+        Ok(())
+    }
+
+    /// Scores each pattern against the opponent's actual move `label`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn backward(&mut self, label: usize) -> Result<(), Error> {
+        check_label(label, self.rules.moves())?;
+        if let Some(predictions) = &self.predictions {
+            for (hits, &prediction) in self.hits.iter_mut().zip(predictions) {
+                hits.push_back(prediction == label);
+                if hits.len() > self.window {
+                    hits.pop_front();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Output distribution of the last `forward()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn probs(&self) -> Vec<Real> {
+        self.probs.clone()
+    }
+
+    /// Most likely move according to `probs()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn predict(&self) -> Prediction {
+        Prediction::argmax(&self.probs)
+    }
+
+    /// Share of the last rounds that followed `pattern`, smoothed towards
+    /// chance while few rounds were seen.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn hit_rate(&self, pattern: Pattern) -> Real {
+        let hits = &self.hits[pattern as usize];
+        let count = hits.iter().filter(|&&hit| hit).count();
+        (count + 1) as Real / (hits.len() + self.rules.moves()) as Real
+    }
+
+    /// Pattern the opponent follows most often, if any does better than
+    /// chance.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn detected(&self) -> Option<Pattern> {
+        let chance = 1.0 / self.rules.moves() as Real;
+        let mut best = None;
+        for pattern in Pattern::ALL {
+            let rate = self.hit_rate(pattern);
+            if rate > best.map_or(chance, |best| self.hit_rate(best)) {
+                best = Some(pattern);
+            }
+        }
+        best
+    }
+}
+
+impl RpsPredictor for PatternDetector {
+    fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        PatternDetector::forward(self, input)
+    }
+
+    fn backward(&mut self, label: usize, _learning_rate: Real) -> Result<(), Error> {
+        PatternDetector::backward(self, label)
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        PatternDetector::probs(self)
+    }
+
+    fn predict(&self) -> Prediction {
+        PatternDetector::predict(self)
+    }
+}
+
+/// Baseline for opponents going through a fixed cycle of moves, such as
+/// rock, rock, paper, scissors over and over. For every period from 2 to 8,
+/// it checks how often each of the opponent's last `window` moves repeated
+/// the move played that many rounds before, and predicts that the shortest
+/// period with the best hit rate goes on, with that rate as its
+/// probability. Takes the same inputs as `MarkovPredictor`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CycleDetector {
+    moves: usize,
+    window: usize,
+    /// Opponent's last `window + MAX_PERIOD` moves, from the oldest to the
+    /// newest.
+    history: VecDeque<usize>,
+    /// Period detected by the last `forward()`.
+    period: Option<usize>,
+    probs: Vec<Real>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl CycleDetector {
+    /// Detects cycles over the last `window` moves, at least 8, in a game
+    /// of `moves` moves.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(window: usize, moves: usize) -> Result<CycleDetector, Error> {
+        if moves < 2 {
+            return Err(Error::InvalidSize {
+                name: "moves",
+                value: moves,
+                min: 2,
+            });
+        }
+        if window < MAX_PERIOD {
+            return Err(Error::InvalidSize {
+                name: "window",
+                value: window,
+                min: MAX_PERIOD,
+            });
+        }
+        Ok(Self {
+            moves,
+            window,
+            history: VecDeque::with_capacity(window + MAX_PERIOD + 1),
+            period: None,
+            probs: vec![1.0 / moves as Real; moves],
+        })
+    }
+
+    /// Adds the opponent's move of a new round, one-hot encoded in the first
+    /// `moves` values of `input`, and predicts the next one.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        self.history.push_back(read_move(input, 0, self.moves)?);
+        if self.history.len() > self.window + MAX_PERIOD {
+            self.history.pop_front();
+        }
+
+        // Put the hit rate of the best period on the move it predicts,
+        // spreading the rest evenly, as `PatternDetector` does
+        let chance = 1.0 / self.moves as Real;
+        let mut best: Option<(usize, Real)> = None;
+        for period in MIN_PERIOD..=MAX_PERIOD {
+            let rate = self.hit_rate(period);
+            if rate > best.map_or(chance, |(_, best)| best) {
+                best = Some((period, rate));
+            }
+        }
+        self.period = best.map(|(period, _)| period);
+        self.probs = vec![chance; self.moves];
+        if let (Some((_, rate)), Some(next)) = (best, self.next_move()) {
+            self.probs.fill((1.0 - rate) / (self.moves - 1) as Real);
+            self.probs[next] = rate;
+        }
+        Ok(())
+    }
+
+    /// Only checks `label`: the moves are read by `forward()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn backward(&mut self, label: usize) -> Result<(), Error> {
+        check_label(label, self.moves)
+    }
+
+    /// Output distribution of the last `forward()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn probs(&self) -> Vec<Real> {
+        self.probs.clone()
+    }
+
+    /// Most likely move according to `probs()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn predict(&self) -> Prediction {
+        Prediction::argmax(&self.probs)
+    }
+
+    /// Length of the cycle the opponent follows, if any period does better
+    /// than chance.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn period(&self) -> Option<usize> {
+        self.period
+    }
+
+    /// Moves of the detected cycle, in the order the opponent plays them
+    /// from the next round, or none without a cycle.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn cycle(&self) -> Vec<usize> {
+        self.period.map_or_else(Vec::new, |period| {
+            self.history
+                .range(self.history.len() - period..)
+                .copied()
+                .collect()
+        })
+    }
+
+    /// Move the detected cycle plays next.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn next_move(&self) -> Option<usize> {
+        self.period
+            .map(|period| self.history[self.history.len() - period])
+    }
+
+    /// Share of the last moves that repeated the move `period` rounds
+    /// before, smoothed towards chance while few rounds were seen, or 0 for
+    /// periods out of the range from 2 to 8.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn hit_rate(&self, period: usize) -> Real {
+        if !(MIN_PERIOD..=MAX_PERIOD).contains(&period) {
+            return 0.0;
+        }
+        let compared = self.history.len().saturating_sub(period).min(self.window);
+        let start = self.history.len() - compared;
+        let hits = (start..self.history.len())
+            .filter(|&i| self.history[i] == self.history[i - period])
+            .count();
+        (hits + 1) as Real / (compared + self.moves) as Real
+    }
+}
+
+impl RpsPredictor for CycleDetector {
+    fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        CycleDetector::forward(self, input)
+    }
+
+    fn backward(&mut self, label: usize, _learning_rate: Real) -> Result<(), Error> {
+        CycleDetector::backward(self, label)
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        CycleDetector::probs(self)
+    }
+
+    fn predict(&self) -> Prediction {
+        CycleDetector::predict(self)
+    }
+}
+
+/// Move one-hot encoded in the `moves` values of `input` from `offset`.
+fn read_move(input: &[Real], offset: usize, moves: usize) -> Result<usize, Error> {
+    if input.len() < offset + moves {
+        return Err(Error::ShapeMismatch {
+            name: "input",
+            expected: vec![offset + moves],
+            found: vec![input.len()],
+        });
+    }
+    Ok(Prediction::argmax(&input[offset..offset + moves]).index)
+}
+
+impl RpsPredictor for MarkovPredictor {
+    fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        MarkovPredictor::forward(self, input)
+    }
+
+    fn backward(&mut self, label: usize, _learning_rate: Real) -> Result<(), Error> {
+        MarkovPredictor::backward(self, label)
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        MarkovPredictor::probs(self)
+    }
+
+    fn predict(&self) -> Prediction {
+        MarkovPredictor::predict(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Move;
+
+    /// Plays `pattern` for `rounds` rounds, returning how many of the last
+    /// 30 were predicted.
+    fn play(predictor: &mut MarkovPredictor, pattern: &[usize], rounds: usize) -> usize {
+        let mut correct = 0;
+        for round in 0..rounds {
+            let m = pattern[round % pattern.len()];
+            if round > 0 {
+                if round >= rounds - 30 && predictor.predict().index == m {
+                    correct += 1;
+                }
+                predictor.backward(m).unwrap();
+            }
+            predictor.forward(&one_hot(m, 3)).unwrap();
+        }
+        correct
+    }
+
+    #[test]
+    fn order_matters() {
+        // Each move is followed by every move, but the last two moves
+        // determine the next one
+        let pattern = [0, 0, 1, 1, 2, 2];
+        assert_eq!(
+            play(&mut MarkovPredictor::new(2, 3).unwrap(), &pattern, 120),
+            30
+        );
+        assert!(play(&mut MarkovPredictor::new(1, 3).unwrap(), &pattern, 120) <= 20);
+
+        let mut frequencies = MarkovPredictor::new(0, 3).unwrap();
+        play(&mut frequencies, &[0, 0, 1], 30);
+        assert_eq!(
+            frequencies.probs(),
+            vec![20.0 / 32.0, 11.0 / 32.0, 1.0 / 32.0]
+        );
+    }
+
+    #[test]
+    fn frequencies() {
+        let mut predictor = FrequencyPredictor::new(3, 2.0).unwrap();
+        for m in [0, 0, 0, 1] {
+            predictor.forward(&one_hot(m, 3)).unwrap();
+            predictor.backward(m).unwrap();
+        }
+        predictor.forward(&one_hot(1, 3)).unwrap();
+        assert_eq!(predictor.rounds(), 4);
+        assert_eq!(predictor.probs(), vec![0.5, 0.3, 0.2]);
+        assert_eq!(predictor.predict().index, 0);
+
+        assert_eq!(
+            predictor.mix(&[0.0, 0.0, 1.0], 0.5),
+            Ok(vec![0.25, 0.15, 0.6])
+        );
+        assert!(predictor.mix(&[0.5, 0.5], 0.5).is_err());
+        assert!(predictor.mix(&[0.0, 0.0, 1.0], 2.0).is_err());
+        assert!(FrequencyPredictor::new(3, 0.0).is_err());
+    }
+
+    #[test]
+    fn detects_patterns() {
+        let round = |opponent: Move, bot: Move| [opponent.one_hot(), bot.one_hot()].concat();
+        let bot_moves = [0, 2, 2, 1, 0, 1, 1, 2, 0, 0, 2, 1];
+
+        // Copies the bot's last move
+        let mut detector = PatternDetector::new(10).unwrap();
+        let mut opponent = Move::Rock;
+        for (i, &bot) in bot_moves.iter().cycle().take(30).enumerate() {
+            if i > 0 {
+                detector.backward(opponent.index()).unwrap();
+            }
+            let bot = Move::ALL[bot];
+            detector.forward(&round(opponent, bot)).unwrap();
+            assert_eq!(detector.predict().index, bot.index());
+            opponent = bot;
+        }
+        assert_eq!(detector.detected(), Some(Pattern::CopyBot));
+        assert_eq!(detector.hit_rate(Pattern::CopyBot), 11.0 / 13.0);
+        assert_eq!(detector.predict().probability, 11.0 / 13.0);
+
+        // Keeps winning moves, and plays what beats the bot's last move
+        // otherwise
+        let mut detector = PatternDetector::new(10).unwrap();
+        let mut opponent = Move::Rock;
+        let mut predicted = 0;
+        for (i, &bot) in bot_moves.iter().cycle().take(30).enumerate() {
+            if i > 0 {
+                predicted += (detector.predict().index == opponent.index()) as usize;
+                detector.backward(opponent.index()).unwrap();
+            }
+            let bot = Move::ALL[bot];
+            detector.forward(&round(opponent, bot)).unwrap();
+            if opponent.against(bot) != Outcome::Win {
+                opponent = bot.beaten_by();
+            }
+        }
+        assert_eq!(detector.detected(), Some(Pattern::WinStayLoseShift));
+        assert!(predicted >= 27);
+
+        // Random moves follow no pattern
+        let mut detector = PatternDetector::new(10).unwrap();
+        detector.forward(&round(Move::Rock, Move::Paper)).unwrap();
+        assert_eq!(detector.detected(), None);
+        assert_eq!(detector.probs(), vec![1.0 / 3.0; 3]);
+        assert!(detector.forward(&Move::Rock.one_hot()).is_err());
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let cycle = [0, 0, 1, 2, 1];
+        let mut detector = CycleDetector::new(20, 3).unwrap();
+        let mut predicted = 0;
+        for round in 0..40 {
+            let m = cycle[round % cycle.len()];
+            if round > 0 {
+                predicted += (detector.predict().index == m) as usize;
+                detector.backward(m).unwrap();
+            }
+            detector.forward(&one_hot(m, 3)).unwrap();
+        }
+        assert_eq!(detector.period(), Some(5));
+        // The last move played was the fifth of the cycle
+        assert_eq!(detector.cycle(), vec![0, 0, 1, 2, 1]);
+        assert_eq!(detector.next_move(), Some(0));
+        assert_eq!(detector.hit_rate(5), 21.0 / 23.0);
+        assert_eq!(detector.predict().probability, 21.0 / 23.0);
+        assert!(predicted >= 30, "{}", predicted);
+
+        // Alternating moves also repeat every 4 or 6 rounds, but the
+        // shortest period comes first
+        let mut detector = CycleDetector::new(8, 3).unwrap();
+        for round in 0..20 {
+            detector.forward(&one_hot(round % 2, 3)).unwrap();
+        }
+        assert_eq!(detector.period(), Some(2));
+        assert_eq!(detector.next_move(), Some(0));
+
+        let mut detector = CycleDetector::new(8, 3).unwrap();
+        detector.forward(&one_hot(1, 3)).unwrap();
+        assert_eq!(detector.period(), None);
+        assert!(detector.cycle().is_empty());
+        assert_eq!(detector.probs(), vec![1.0 / 3.0; 3]);
+        assert_eq!(detector.hit_rate(1), 0.0);
+        assert!(detector.backward(3).is_err());
+        assert!(CycleDetector::new(7, 3).is_err());
+        assert!(CycleDetector::new(8, 1).is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(MarkovPredictor::new(1, 1).is_err());
+        assert!(MarkovPredictor::new(20, 3).is_err());
+        let mut predictor = MarkovPredictor::new(1, 3).unwrap();
+        assert!(predictor.forward(&[1.0, 0.0]).is_err());
+        assert!(predictor.backward(3).is_err());
+    }
+
+    #[test]
+    fn generic_predictor() {
+        let mut predictor: Box<dyn RpsPredictor> = Box::new(MarkovPredictor::new(1, 3).unwrap());
+        // Opponent and bot moves, as with `Encoding::BothMoves`
+        let input = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        for _ in 0..5 {
+            predictor.forward(&input).unwrap();
+            predictor.backward(1, 0.1).unwrap();
+        }
+        predictor.forward(&input).unwrap();
+        assert_eq!(predictor.predict().index, 1);
+        assert_eq!(predictor.probs(), vec![1.0 / 8.0, 6.0 / 8.0, 1.0 / 8.0]);
+    }
+}
@@ -0,0 +1,361 @@
+//! Plays rock-paper-scissors against the network in the terminal, or pits it
+//! against the scripted bots with `--bot`. Run `rpsnn --help` for the flags.
+
+#[cfg(feature = "tui")]
+mod tui;
+
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use rps_network::{
+    simulate_players, Beats, Encoding, GameEngine, Move, NetworkConfig, Outcome, RPSNetwork,
+    ScriptedBot,
+};
+
+const USAGE: &str = "\
+Usage: rpsnn [OPTIONS]
+
+Plays rock-paper-scissors against the network, which learns your habits as
+you play. Type r, p or s for each round, and q to quit.
+
+Options:
+  --config <FILE>   Builds the network from a JSON NetworkConfig
+  --load <FILE>     Loads a saved network instead of building one
+  --save <FILE>     Saves the network when done
+  --seed <N>        Seeds the network built from the configuration, not
+                    with --load
+  --bot <NAME>      Plays against a scripted bot instead of you: constant,
+                    cycle, copy, beat-last, de-bruijn, or all of them in turn
+  --rounds <N>      Rounds against each bot (default 200)
+  --tui             Plays full-screen, with live probabilities, accuracy
+                    and loss curves (needs the tui feature)
+  --serve <ADDR>    Serves the network over HTTP on ADDR, e.g.
+                    127.0.0.1:3000, instead of playing (needs the server
+                    feature)
+  -h, --help        Prints this help
+
+Networks are saved as JSON when the file name ends in .json, and in the
+compact binary encoding otherwise.";
+
+/// Names of the bots of `ScriptedBot::library()`, in the same order.
+const BOTS: [&str; 5] = ["constant", "cycle", "copy", "beat-last", "de-bruijn"];
+
+#[derive(Debug, Default)]
+struct Args {
+    config: Option<PathBuf>,
+    load: Option<PathBuf>,
+    save: Option<PathBuf>,
+    seed: Option<u64>,
+    bot: Option<String>,
+    rounds: Option<u32>,
+    tui: bool,
+    serve: Option<String>,
+    help: bool,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} expects a value", arg));
+            match arg.as_str() {
+                "--config" => parsed.config = Some(value()?.into()),
+                "--load" => parsed.load = Some(value()?.into()),
+                "--save" => parsed.save = Some(value()?.into()),
+                "--seed" => parsed.seed = Some(number(&arg, &value()?)?),
+                "--bot" => parsed.bot = Some(value()?),
+                "--rounds" => parsed.rounds = Some(number(&arg, &value()?)?),
+                "--tui" => parsed.tui = true,
+                "--serve" => parsed.serve = Some(value()?),
+                "-h" | "--help" => parsed.help = true,
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+        if parsed.config.is_some() && parsed.load.is_some() {
+            return Err("--config and --load can't be used together".into());
+        }
+        if parsed.seed.is_some() && parsed.load.is_some() {
+            return Err("--seed only applies to a new network, not with --load".into());
+        }
+        if parsed.tui && parsed.bot.is_some() {
+            return Err("--tui and --bot can't be used together".into());
+        }
+        if parsed.serve.is_some() && (parsed.tui || parsed.bot.is_some()) {
+            return Err("--serve doesn't play, so it can't be used with --tui or --bot".into());
+        }
+        if parsed.serve.is_some() && parsed.save.is_some() {
+            return Err(
+                "--serve doesn't stop, download the network with GET /model instead of --save"
+                    .into(),
+            );
+        }
+        if let Some(bot) = &parsed.bot {
+            if bot != "all" && !BOTS.contains(&bot.as_str()) {
+                return Err(format!("unknown bot {}", bot));
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, not {}", flag, value))
+}
+
+fn main() -> ExitCode {
+    let result = Args::parse(std::env::args().skip(1)).and_then(|args| {
+        if args.help {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        run(&args)
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("rpsnn: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let network = network(args)?;
+    if let Some(addr) = &args.serve {
+        return serve(&network, addr);
+    }
+    // The engine needs to know how the network takes rounds, which only its
+    // input size tells
+    let encoding = Encoding::ALL
+        .into_iter()
+        .find(|encoding| encoding.input_size(Move::ALL.len()) == network.input_size)
+        .ok_or(format!(
+            "a network with {} inputs doesn't take rock-paper-scissors rounds",
+            network.input_size
+        ))?;
+    let mut engine = GameEngine::with_encoding(network, &Beats::rock_paper_scissors(), encoding)
+        .map_err(|err| err.to_string())?;
+
+    match &args.bot {
+        Some(bot) => play_bots(&mut engine, bot, args.rounds.unwrap_or(200))?,
+        None if args.tui => play_tui(&mut engine)?,
+        None => play_human(&mut engine).map_err(|err| err.to_string())?,
+    }
+
+    if let Some(path) = &args.save {
+        save(engine.network(), path)?;
+        println!("Saved the network to {}", path.display());
+    }
+    Ok(())
+}
+
+fn network(args: &Args) -> Result<RPSNetwork, String> {
+    if let Some(path) = &args.load {
+        let read = |err: io::Error| format!("can't read {}: {}", path.display(), err);
+        let network = if is_json(path) {
+            RPSNetwork::from_json(&std::fs::read_to_string(path).map_err(read)?)
+        } else {
+            RPSNetwork::from_bytes(&std::fs::read(path).map_err(read)?)
+        };
+        return network.map_err(|err| format!("can't load {}: {}", path.display(), err));
+    }
+    let mut config = match &args.config {
+        Some(path) => {
+            let json = std::fs::read_to_string(path)
+                .map_err(|err| format!("can't read {}: {}", path.display(), err))?;
+            serde_json::from_str(&json)
+                .map_err(|err| format!("invalid configuration {}: {}", path.display(), err))?
+        }
+        None => NetworkConfig::new().moves(Move::ALL.len()),
+    };
+    if let Some(seed) = args.seed {
+        config = config.seed(seed);
+    }
+    config.build().map_err(|err| err.to_string())
+}
+
+fn save(network: &RPSNetwork, path: &Path) -> Result<(), String> {
+    let bytes = if is_json(path) {
+        network.to_json().map(String::into_bytes)
+    } else {
+        network.to_bytes()
+    }
+    .map_err(|err| err.to_string())?;
+    std::fs::write(path, bytes).map_err(|err| format!("can't write {}: {}", path.display(), err))
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "json")
+}
+
+/// Plays `rounds` rounds against the bot named `name`, or against each bot
+/// in turn for `all`, with the same network throughout.
+fn play_bots(engine: &mut GameEngine, name: &str, rounds: u32) -> Result<(), String> {
+    let rules = Beats::rock_paper_scissors();
+    println!(
+        "{:<10} {:>6} {:>6} {:>6} {:>8}",
+        "bot", "wins", "losses", "draws", "win rate"
+    );
+    for (bot_name, mut bot) in BOTS.into_iter().zip(ScriptedBot::library()) {
+        if name != "all" && name != bot_name {
+            continue;
+        }
+        let simulation =
+            simulate_players(engine, &mut bot, &rules, rounds).map_err(|err| err.to_string())?;
+        println!(
+            "{:<10} {:>6} {:>6} {:>6} {:>7.1}%",
+            bot_name,
+            simulation.a_wins,
+            simulation.b_wins,
+            simulation.draws,
+            100.0 * simulation.a_win_rate()
+        );
+    }
+    Ok(())
+}
+
+fn play_tui(engine: &mut GameEngine) -> Result<(), String> {
+    #[cfg(feature = "tui")]
+    return tui::play(engine).map_err(|err| err.to_string());
+    #[cfg(not(feature = "tui"))]
+    {
+        let _ = engine;
+        Err("rpsnn was built without the tui feature".into())
+    }
+}
+
+fn serve(network: &RPSNetwork, addr: &str) -> Result<(), String> {
+    #[cfg(feature = "server")]
+    {
+        let router = rps_network::router(network);
+        let runtime = tokio::runtime::Runtime::new().map_err(|err| err.to_string())?;
+        runtime
+            .block_on(async {
+                let listener = tokio::net::TcpListener::bind(addr).await?;
+                println!("Serving the network on http://{}", listener.local_addr()?);
+                axum::serve(listener, router).await
+            })
+            .map_err(|err| format!("can't serve on {}: {}", addr, err))
+    }
+    #[cfg(not(feature = "server"))]
+    {
+        let _ = (network, addr);
+        Err("rpsnn was built without the server feature".into())
+    }
+}
+
+/// Plays rounds read from stdin until the player quits or the input ends.
+fn play_human(engine: &mut GameEngine) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        // The bot commits to its move before reading the player's
+        let bot = engine.bot_move();
+        print!("Your move (r/p/s, q to quit): ");
+        io::stdout().flush()?;
+        let player = loop {
+            let Some(line) = lines.next().transpose()? else {
+                println!();
+                summary(engine);
+                return Ok(());
+            };
+            match line.trim().to_lowercase().as_str() {
+                "r" | "rock" => break Move::Rock,
+                "p" | "paper" => break Move::Paper,
+                "s" | "scissors" => break Move::Scissors,
+                "q" | "quit" => {
+                    summary(engine);
+                    return Ok(());
+                }
+                "" => {}
+                other => println!("Unknown move {}, type r, p or s", other),
+            }
+            print!("Your move (r/p/s, q to quit): ");
+            io::stdout().flush()?;
+        };
+
+        let outcome = engine
+            .play(player.index(), bot)
+            .map_err(|err| io::Error::other(err.to_string()))?;
+        let verdict = match outcome {
+            Outcome::Win => "you win",
+            Outcome::Lose => "you lose",
+            Outcome::Draw => "draw",
+        };
+        println!(
+            "You played {}, the network played {}: {}. Score {}-{}-{}",
+            name(player),
+            name(Move::from_index(bot).expect("the game has 3 moves")),
+            verdict,
+            engine.player_wins(),
+            engine.bot_wins(),
+            engine.draws()
+        );
+    }
+}
+
+fn summary(engine: &GameEngine) {
+    let stats = engine.stats();
+    println!(
+        "{} rounds: {} won, {} lost, {} drawn",
+        stats.rounds, stats.player_wins, stats.bot_wins, stats.draws
+    );
+}
+
+fn name(m: Move) -> &'static str {
+    match m {
+        Move::Rock => "rock",
+        Move::Paper => "paper",
+        Move::Scissors => "scissors",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_flags() {
+        let args = parse(&["--load", "model.json", "--bot", "cycle", "--rounds", "50"]).unwrap();
+        assert_eq!(args.load, Some(PathBuf::from("model.json")));
+        assert_eq!(args.bot.as_deref(), Some("cycle"));
+        assert_eq!(args.rounds, Some(50));
+        assert!(parse(&["--help"]).unwrap().help);
+
+        assert!(parse(&["--rounds"]).is_err());
+        assert!(parse(&["--rounds", "many"]).is_err());
+        assert!(parse(&["--bot", "unknown"]).is_err());
+        assert!(parse(&["--config", "a.json", "--load", "b.json"]).is_err());
+        assert!(parse(&["--seed", "1", "--load", "b.json"]).is_err());
+        assert!(parse(&["--tui", "--bot", "all"]).is_err());
+        assert!(parse(&["--serve", "127.0.0.1:3000", "--save", "a.json"]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+    }
+
+    #[test]
+    fn beats_bots() {
+        let args = parse(&["--seed", "42"]).unwrap();
+        let mut engine = GameEngine::new(network(&args).unwrap()).unwrap();
+        play_bots(&mut engine, "constant", 100).unwrap();
+        assert!(engine.bot_wins() > 80);
+
+        let path = std::env::temp_dir().join("rpsnn-beats-bots.json");
+        save(engine.network(), &path).unwrap();
+        let load = Args {
+            load: Some(path.clone()),
+            ..Args::default()
+        };
+        let restored = network(&load).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(restored.probs(), engine.network().probs());
+    }
+}
@@ -0,0 +1,235 @@
+//! Full-screen front-end of `rpsnn --tui`, showing what the network expects
+//! and how well it learns while you play.
+
+use std::collections::VecDeque;
+use std::io;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::symbols::Marker;
+use ratatui::widgets::{
+    Axis, Bar, BarChart, BarGroup, Block, Chart, Dataset, GraphType, Paragraph,
+};
+use ratatui::{DefaultTerminal, Frame};
+
+use rps_network::{GameEngine, Move, Outcome, Real};
+
+use crate::name;
+
+/// Number of past rounds listed under the charts.
+const LOG_SIZE: usize = 6;
+
+/// `value` as plotted by the charts.
+#[cfg(not(feature = "f64"))]
+fn plot(value: Real) -> f64 {
+    f64::from(value)
+}
+#[cfg(feature = "f64")]
+fn plot(value: Real) -> f64 {
+    value
+}
+
+struct App<'a> {
+    engine: &'a mut GameEngine,
+    /// Move the network committed to for the coming round.
+    bot: usize,
+    /// Recent accuracy of the network after each round.
+    accuracy: Vec<(f64, f64)>,
+    /// Descriptions of the last rounds, the newest first.
+    log: VecDeque<String>,
+}
+
+/// Plays rounds in the terminal until the player quits.
+pub(crate) fn play(engine: &mut GameEngine) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, engine);
+    ratatui::restore();
+    result
+}
+
+fn run(terminal: &mut DefaultTerminal, engine: &mut GameEngine) -> io::Result<()> {
+    let mut app = App::new(engine);
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let player = match key.code {
+            KeyCode::Char('r') => Move::Rock,
+            KeyCode::Char('p') => Move::Paper,
+            KeyCode::Char('s') => Move::Scissors,
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            _ => continue,
+        };
+        app.play(player)?;
+    }
+}
+
+impl<'a> App<'a> {
+    fn new(engine: &'a mut GameEngine) -> Self {
+        // The bot commits to its move before the player's key press
+        let bot = engine.bot_move();
+        Self {
+            engine,
+            bot,
+            accuracy: Vec::new(),
+            log: VecDeque::with_capacity(LOG_SIZE),
+        }
+    }
+
+    fn play(&mut self, player: Move) -> io::Result<()> {
+        let outcome = self
+            .engine
+            .play(player.index(), self.bot)
+            .map_err(|err| io::Error::other(err.to_string()))?;
+        let verdict = match outcome {
+            Outcome::Win => "you win",
+            Outcome::Lose => "you lose",
+            Outcome::Draw => "draw",
+        };
+        if self.log.len() == LOG_SIZE {
+            self.log.pop_back();
+        }
+        self.log.push_front(format!(
+            "Round {}: you played {}, the network played {}: {}",
+            self.engine.rounds(),
+            name(player),
+            name(Move::from_index(self.bot).expect("the game has 3 moves")),
+            verdict
+        ));
+        self.accuracy.push((
+            self.engine.rounds() as f64,
+            plot(self.engine.network().recent_accuracy()),
+        ));
+        self.bot = self.engine.bot_move();
+        Ok(())
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [score, middle, log] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(10),
+            Constraint::Length(LOG_SIZE as u16 + 2),
+        ])
+        .areas(frame.area());
+        let [probs, curves] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(middle);
+        let [accuracy, loss] =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(curves);
+
+        let network = self.engine.network();
+        frame.render_widget(
+            Paragraph::new(format!(
+                "You {} - Network {} - Draws {}   Accuracy {:.0}%   \
+                 r/p/s to play, q to quit",
+                self.engine.player_wins(),
+                self.engine.bot_wins(),
+                self.engine.draws(),
+                100.0 * network.recent_accuracy(),
+            ))
+            .block(Block::bordered().title("rpsnn")),
+            score,
+        );
+
+        let bars: Vec<Bar> = Move::ALL
+            .into_iter()
+            .zip(self.engine.probs())
+            .map(|(m, p)| {
+                Bar::default()
+                    .label(name(m))
+                    .value((100.0 * p).round() as u64)
+                    .text_value(format!("{:.0}%", 100.0 * p))
+            })
+            .collect();
+        frame.render_widget(
+            BarChart::default()
+                .block(Block::bordered().title("Your next move, as expected"))
+                .data(BarGroup::default().bars(&bars))
+                .bar_width(8)
+                .bar_gap(2)
+                .max(100),
+            probs,
+        );
+
+        frame.render_widget(curve("Recent accuracy", &self.accuracy, 1.0), accuracy);
+        let losses: Vec<(f64, f64)> = network
+            .loss_history()
+            .into_iter()
+            .enumerate()
+            .map(|(i, loss)| (i as f64, plot(loss)))
+            .collect();
+        let max = losses
+            .iter()
+            .fold(1.0, |max: f64, &(_, loss)| max.max(loss));
+        frame.render_widget(curve("Training loss", &losses, max), loss);
+
+        let lines: Vec<&str> = self.log.iter().map(String::as_str).collect();
+        frame.render_widget(
+            Paragraph::new(lines.join("\n")).block(Block::bordered().title("Rounds")),
+            log,
+        );
+    }
+}
+
+/// Line chart of `data`, with values from 0 to `max`.
+fn curve<'a>(title: &'a str, data: &'a [(f64, f64)], max: f64) -> Chart<'a> {
+    let first = data.first().map_or(0.0, |&(x, _)| x);
+    let last = data.last().map_or(1.0, |&(x, _)| x);
+    Chart::new(vec![Dataset::default()
+        .marker(Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(Color::Cyan))
+        .data(data)])
+    .block(Block::bordered().title(title))
+    .x_axis(Axis::default().bounds([first, last.max(first + 1.0)]))
+    .y_axis(
+        Axis::default()
+            .bounds([0.0, max])
+            .labels(["0".to_string(), format!("{:.1}", max)]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use rps_network::NetworkConfig;
+
+    #[test]
+    fn draws_statistics() {
+        let network = NetworkConfig::new().moves(3).seed(42).build().unwrap();
+        let mut engine = GameEngine::new(network).unwrap();
+        let mut app = App::new(&mut engine);
+        for _ in 0..10 {
+            app.play(Move::Rock).unwrap();
+        }
+        assert_eq!(app.accuracy.len(), 10);
+        assert_eq!(app.log.len(), LOG_SIZE);
+        assert!(app.log[0].starts_with("Round 10: you played rock"));
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        for text in [
+            "Your next move",
+            "Recent accuracy",
+            "Training loss",
+            "Round 10",
+        ] {
+            assert!(screen.contains(text), "{}", text);
+        }
+    }
+}
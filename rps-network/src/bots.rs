@@ -0,0 +1,299 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::moves::one_hot;
+use crate::{check_label, Beats, Error, Real};
+
+/// Longest sequence a `ScriptedBot` may loop over.
+const MAX_SEQUENCE: usize = 1 << 20;
+
+/// A player of repeated rounds, such as a scripted bot, that simulations
+/// can pit against another.
+pub trait Player {
+    /// Move for the coming round.
+    fn next_move(&mut self) -> usize;
+
+    /// Records a round where this player played `own` and the other one
+    /// `opponent`.
+    fn record(&mut self, own: usize, opponent: usize) -> Result<(), Error>;
+
+    /// Predicted distribution over the other player's coming move, for
+    /// players that predict it.
+    fn probs(&self) -> Option<Vec<Real>> {
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Script {
+    Random(Box<ChaCha8Rng>),
+    /// Plays the moves in a loop.
+    Sequence(Vec<usize>),
+    CopyOpponent,
+    BeatOwnLast(Beats),
+}
+
+/// Classic deterministic (or seeded) opponent, for training and evaluation
+/// harnesses. Moves are identified by their index, e.g. as a `Move`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone)]
+pub struct ScriptedBot {
+    script: Script,
+    moves: usize,
+    round: usize,
+    /// Moves of the last round, its own first.
+    last: Option<(usize, usize)>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl ScriptedBot {
+    /// Plays uniformly at random, from `seed`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn uniform_random(moves: usize, seed: u64) -> Result<ScriptedBot, Error> {
+        Self::new(
+            Script::Random(Box::new(ChaCha8Rng::seed_from_u64(seed))),
+            moves,
+        )
+    }
+
+    /// Always plays `m`, e.g. always rock.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn constant(m: usize, moves: usize) -> Result<ScriptedBot, Error> {
+        Self::cycle(&[m], moves)
+    }
+
+    /// Plays the moves of `sequence` in a loop.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn cycle(sequence: &[usize], moves: usize) -> Result<ScriptedBot, Error> {
+        if sequence.is_empty() {
+            return Err(Error::InvalidSize {
+                name: "sequence",
+                value: 0,
+                min: 1,
+            });
+        }
+        for &m in sequence {
+            check_label(m, moves)?;
+        }
+        Self::new(Script::Sequence(sequence.to_vec()), moves)
+    }
+
+    /// Plays the opponent's last move, starting with the first move.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn copy_opponent(moves: usize) -> Result<ScriptedBot, Error> {
+        Self::new(Script::CopyOpponent, moves)
+    }
+
+    /// Plays the move that beats its own last move under `rules`, starting
+    /// with the first move.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn beat_own_last(rules: &Beats) -> Result<ScriptedBot, Error> {
+        Self::new(Script::BeatOwnLast(rules.clone()), rules.moves())
+    }
+
+    /// Plays a de Bruijn sequence of the given `order` in a loop: every run
+    /// of `order` moves shows up exactly once per loop, so that no
+    /// predictor looking at fewer moves can do better than chance.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn de_bruijn(order: usize, moves: usize) -> Result<ScriptedBot, Error> {
+        if order == 0 {
+            return Err(Error::InvalidSize {
+                name: "order",
+                value: 0,
+                min: 1,
+            });
+        }
+        if moves < 2 {
+            return Err(Error::InvalidSize {
+                name: "moves",
+                value: moves,
+                min: 2,
+            });
+        }
+        u32::try_from(order)
+            .ok()
+            .and_then(|order| moves.checked_pow(order))
+            .filter(|&len| len <= MAX_SEQUENCE)
+            .ok_or(Error::Unsupported(
+                "the de Bruijn sequence of this order is too long",
+            ))?;
+        Self::new(Script::Sequence(de_bruijn(order, moves)), moves)
+    }
+
+    /// Move for the coming round.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn next_move(&mut self) -> usize {
+        match (&mut self.script, self.last) {
+            (Script::Random(rng), _) => rng.gen_range(0..self.moves),
+            (Script::Sequence(sequence), _) => sequence[self.round % sequence.len()],
+            (Script::CopyOpponent, Some((_, opponent))) => opponent,
+            (Script::BeatOwnLast(rules), Some((own, _))) => rules
+                .counter(&one_hot(own, self.moves), self.moves)
+                .expect("the rules cover every move"),
+            (_, None) => 0,
+        }
+    }
+
+    /// Number of moves of the game played.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn moves(&self) -> usize {
+        self.moves
+    }
+
+    /// Records a round where the bot played `own` and the other player
+    /// `opponent`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn record(&mut self, own: usize, opponent: usize) -> Result<(), Error> {
+        check_label(own, self.moves)?;
+        check_label(opponent, self.moves)?;
+        self.last = Some((own, opponent));
+        self.round += 1;
+        Ok(())
+    }
+}
+
+impl ScriptedBot {
+    /// The classic scripted bots of rock-paper-scissors, to train and
+    /// evaluate against: constant rock, the rock-paper-scissors cycle,
+    /// copying the opponent, beating its own last move, and an order-2 de
+    /// Bruijn sequence.
+    pub fn library() -> Vec<ScriptedBot> {
+        let rules = Beats::rock_paper_scissors();
+        [
+            ScriptedBot::constant(0, 3),
+            ScriptedBot::cycle(&[0, 1, 2], 3),
+            ScriptedBot::copy_opponent(3),
+            ScriptedBot::beat_own_last(&rules),
+            ScriptedBot::de_bruijn(2, 3),
+        ]
+        .into_iter()
+        .map(|bot| bot.expect("the scripts are valid"))
+        .collect()
+    }
+
+    fn new(script: Script, moves: usize) -> Result<Self, Error> {
+        if moves < 2 {
+            return Err(Error::InvalidSize {
+                name: "moves",
+                value: moves,
+                min: 2,
+            });
+        }
+        Ok(Self {
+            script,
+            moves,
+            round: 0,
+            last: None,
+        })
+    }
+}
+
+impl Player for ScriptedBot {
+    fn next_move(&mut self) -> usize {
+        ScriptedBot::next_move(self)
+    }
+
+    fn record(&mut self, own: usize, opponent: usize) -> Result<(), Error> {
+        ScriptedBot::record(self, own, opponent)
+    }
+}
+
+/// De Bruijn sequence of `order` over `moves` symbols: the concatenation,
+/// in lexicographic order, of the Lyndon words whose length divides
+/// `order`, generated by Duval's algorithm.
+fn de_bruijn(order: usize, moves: usize) -> Vec<usize> {
+    let mut sequence = Vec::with_capacity(moves.pow(order as u32));
+    let mut word = vec![0];
+    loop {
+        if order.is_multiple_of(word.len()) {
+            sequence.extend_from_slice(&word);
+        }
+        let period = word.len();
+        while word.len() < order {
+            word.push(word[word.len() - period]);
+        }
+        while word.last() == Some(&(moves - 1)) {
+            word.pop();
+        }
+        match word.last_mut() {
+            Some(last) => *last += 1,
+            None => return sequence,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Move;
+
+    /// Plays `bot` against `moves` in turn, returning the bot's moves.
+    fn play(bot: &mut ScriptedBot, moves: &[usize]) -> Vec<usize> {
+        moves
+            .iter()
+            .map(|&opponent| {
+                let own = bot.next_move();
+                bot.record(own, opponent).unwrap();
+                own
+            })
+            .collect()
+    }
+
+    #[test]
+    fn scripts() {
+        let opponent = [2, 1, 0, 0, 1];
+        let mut constant = ScriptedBot::constant(Move::Rock.index(), 3).unwrap();
+        assert_eq!(play(&mut constant, &opponent), vec![0; 5]);
+        let mut cycle = ScriptedBot::cycle(&[0, 2], 3).unwrap();
+        assert_eq!(play(&mut cycle, &opponent), vec![0, 2, 0, 2, 0]);
+        let mut copy = ScriptedBot::copy_opponent(3).unwrap();
+        assert_eq!(play(&mut copy, &opponent), vec![0, 2, 1, 0, 0]);
+        let mut beat = ScriptedBot::beat_own_last(&Beats::rock_paper_scissors()).unwrap();
+        assert_eq!(play(&mut beat, &opponent), vec![0, 1, 2, 0, 1]);
+
+        let mut random = ScriptedBot::uniform_random(3, 42).unwrap();
+        let moves = play(&mut random, &[0; 300]);
+        for m in 0..3 {
+            let count = moves.iter().filter(|&&played| played == m).count();
+            assert!((70..130).contains(&count));
+        }
+        let mut same_seed = ScriptedBot::uniform_random(3, 42).unwrap();
+        assert_eq!(play(&mut same_seed, &[0; 300]), moves);
+    }
+
+    #[test]
+    fn de_bruijn_sequence() {
+        assert_eq!(de_bruijn(1, 3), vec![0, 1, 2]);
+        assert_eq!(de_bruijn(2, 3), vec![0, 0, 1, 0, 2, 1, 1, 2, 2]);
+
+        // Every run of 3 moves shows up exactly once per loop
+        let sequence = de_bruijn(3, 3);
+        assert_eq!(sequence.len(), 27);
+        let mut seen = [false; 27];
+        for i in 0..27 {
+            let run = (0..3).fold(0, |run, j| run * 3 + sequence[(i + j) % 27]);
+            assert!(!seen[run]);
+            seen[run] = true;
+        }
+
+        let mut bot = ScriptedBot::de_bruijn(2, 3).unwrap();
+        assert_eq!(play(&mut bot, &[0; 10]), vec![0, 0, 1, 0, 2, 1, 1, 2, 2, 0]);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(ScriptedBot::constant(3, 3).is_err());
+        assert!(ScriptedBot::cycle(&[], 3).is_err());
+        assert!(ScriptedBot::uniform_random(1, 0).is_err());
+        assert!(ScriptedBot::de_bruijn(0, 3).is_err());
+        assert!(ScriptedBot::de_bruijn(20, 3).is_err());
+        assert!(ScriptedBot::copy_opponent(3).unwrap().record(0, 3).is_err());
+    }
+}
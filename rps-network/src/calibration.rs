@@ -0,0 +1,190 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::Real;
+
+#[cfg(feature = "serde")]
+use alloc::vec;
+
+#[cfg(feature = "serde")]
+use crate::Error;
+
+/// Number of recent rounds that `RPSNetwork::calibrate()` fits on.
+pub(crate) const LOG_SIZE: usize = 100;
+
+/// Bounds of the fitted temperature.
+const MIN_TEMPERATURE: Real = 0.05;
+const MAX_TEMPERATURE: Real = 20.0;
+
+/// Iterations of the golden-section search, each narrowing the range of
+/// the inverse temperature by 38%.
+const ITERATIONS: usize = 60;
+
+/// Inverse of the golden ratio.
+const GOLDEN: Real = 0.618_034;
+
+/// Temperature scaling of the output distribution, fitted on the logits of
+/// the last rounds and the moves actually played.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Calibration {
+    /// Logits of the last predictions and the actual moves, oldest first.
+    log: VecDeque<(Vec<Real>, usize)>,
+    pub(crate) temperature: Real,
+}
+
+impl Calibration {
+    pub(crate) fn new() -> Self {
+        Self {
+            log: VecDeque::with_capacity(LOG_SIZE),
+            temperature: 1.0,
+        }
+    }
+
+    /// Checks the logged logits and moves against the network's number of
+    /// moves.
+    #[cfg(feature = "serde")]
+    pub(crate) fn validate(&self, moves: usize) -> Result<(), Error> {
+        if self.log.len() > LOG_SIZE {
+            return Err(Error::ShapeMismatch {
+                name: "calibration",
+                expected: vec![LOG_SIZE],
+                found: vec![self.log.len()],
+            });
+        }
+        for (logits, label) in &self.log {
+            if logits.len() != moves {
+                return Err(Error::ShapeMismatch {
+                    name: "logits",
+                    expected: vec![moves],
+                    found: vec![logits.len()],
+                });
+            }
+            if *label >= moves {
+                return Err(Error::InvalidMove {
+                    index: *label,
+                    moves,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a round, returning the oldest one if it had to be dropped.
+    pub(crate) fn record(&mut self, logits: Vec<Real>, label: usize) -> Option<(Vec<Real>, usize)> {
+        let evicted = if self.log.len() == LOG_SIZE {
+            self.log.pop_front()
+        } else {
+            None
+        };
+        self.log.push_back((logits, label));
+        evicted
+    }
+
+    /// Reverts the last `record()`, given the round it returned.
+    pub(crate) fn unrecord(&mut self, evicted: Option<(Vec<Real>, usize)>) {
+        self.log.pop_back();
+        if let Some(round) = evicted {
+            self.log.push_front(round);
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.log.len()
+    }
+
+    /// Sets the temperature to the one minimizing the cross-entropy over the
+    /// log, keeping it when the log is empty.
+    pub(crate) fn fit(&mut self) -> Real {
+        if self.log.is_empty() {
+            return self.temperature;
+        }
+        // The loss is convex in the inverse temperature, so a golden-section
+        // search finds its minimum
+        let (mut low, mut high) = (1.0 / MAX_TEMPERATURE, 1.0 / MIN_TEMPERATURE);
+        for _ in 0..ITERATIONS {
+            let a = high - GOLDEN * (high - low);
+            let b = low + GOLDEN * (high - low);
+            if self.loss(a) < self.loss(b) {
+                high = b;
+            } else {
+                low = a;
+            }
+        }
+        self.temperature = 2.0 / (low + high);
+        self.temperature
+    }
+
+    /// Mean cross-entropy of the log with logits scaled by `beta`.
+    fn loss(&self, beta: Real) -> Real {
+        let total: Real = self
+            .log
+            .iter()
+            .map(|(logits, label)| {
+                let max = logits.iter().fold(Real::NEG_INFINITY, |a, &b| a.max(b));
+                let sum: Real = logits.iter().map(|z| (beta * (z - max)).exp()).sum();
+                sum.ln() - beta * (logits[*label] - max)
+            })
+            .sum();
+        total / self.log.len() as Real
+    }
+
+    /// Distribution given by `logits` at the fitted temperature.
+    pub(crate) fn probs(&self, logits: &[Real]) -> Vec<Real> {
+        let max = logits.iter().fold(Real::NEG_INFINITY, |a, &b| a.max(b));
+        let exp: Vec<Real> = logits
+            .iter()
+            .map(|z| ((z - max) / self.temperature).exp())
+            .collect();
+        let sum: Real = exp.iter().sum();
+        exp.into_iter().map(|e| e / sum).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn fits_temperature() {
+        // Logits claiming 88% confidence for predictions right half the time
+        let mut calibration = Calibration::new();
+        for round in 0..40 {
+            let label = if round % 2 == 0 { 0 } else { 1 + round / 2 % 2 };
+            calibration.record(vec![2.0, 0.0, 0.0], label);
+        }
+        let temperature = calibration.fit();
+        assert!(temperature > 1.0);
+        let probs = calibration.probs(&[2.0, 0.0, 0.0]);
+        assert!((probs[0] - 0.5).abs() < 0.01, "{:?}", probs);
+
+        // Predictions always right call for sharper probabilities
+        let mut calibration = Calibration::new();
+        for _ in 0..20 {
+            calibration.record(vec![1.0, 0.0, 0.0], 0);
+        }
+        assert!(calibration.fit() < 0.1);
+    }
+
+    #[test]
+    fn record() {
+        let mut calibration = Calibration::new();
+        assert_eq!(calibration.fit(), 1.0);
+        for label in 0..LOG_SIZE {
+            assert_eq!(calibration.record(vec![0.0, 1.0], label % 2), None);
+        }
+        let evicted = calibration.record(vec![1.0, 0.0], 0);
+        assert_eq!(evicted, Some((vec![0.0, 1.0], 0)));
+        assert_eq!(calibration.log.len(), LOG_SIZE);
+        calibration.unrecord(evicted);
+        assert_eq!(calibration.log[0], (vec![0.0, 1.0], 0));
+        assert_eq!(calibration.log[LOG_SIZE - 1], (vec![0.0, 1.0], 1));
+    }
+}
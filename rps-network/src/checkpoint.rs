@@ -0,0 +1,11 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::RPSNetwork;
+
+/// Opaque copy of a network taken by `RPSNetwork::snapshot()`, which can be
+/// restored any number of times.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Checkpoint {
+    pub(crate) network: RPSNetwork,
+}
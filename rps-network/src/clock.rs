@@ -0,0 +1,78 @@
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+use wasm_bindgen::{JsCast, JsValue};
+
+/// Measures wall-clock time, with `performance.now()` in the browser, where
+/// `std::time::Instant` isn't available, and `Instant` elsewhere.
+pub(crate) struct Stopwatch {
+    #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+    start: f64,
+    #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+    start: std::time::Instant,
+}
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+        let start = now();
+        #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+        let start = std::time::Instant::now();
+        Self { start }
+    }
+
+    /// Milliseconds since `start()`.
+    pub(crate) fn elapsed_ms(&self) -> f64 {
+        #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+        return now() - self.start;
+        #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+        return self.start.elapsed().as_secs_f64() * 1000.0;
+    }
+}
+
+/// Wall-clock time in whole milliseconds since the Unix epoch, with
+/// `Date.now()` in the browser, so that it survives a round trip through
+/// JSON.
+pub(crate) fn unix_ms() -> f64 {
+    #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+    return js_sys::Date::now();
+    #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+    return std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_millis() as f64);
+}
+
+/// `performance.now()` from the global scope, which may be a window or a
+/// worker, falling back to `Date.now()` where there is no `performance`.
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+fn now() -> f64 {
+    let performance = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("performance"))
+        .ok()
+        .filter(|performance| !performance.is_undefined());
+    performance
+        .and_then(|performance| {
+            let now = js_sys::Reflect::get(&performance, &JsValue::from_str("now")).ok()?;
+            now.dyn_into::<js_sys::Function>()
+                .ok()?
+                .call0(&performance)
+                .ok()?
+                .as_f64()
+        })
+        .unwrap_or_else(js_sys::Date::now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elapsed() {
+        let stopwatch = Stopwatch::start();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert!(stopwatch.elapsed_ms() >= 5.0);
+    }
+
+    #[test]
+    fn unix_time() {
+        // Later than 2023-01-01
+        assert!(unix_ms() > 1.672e12);
+    }
+}
@@ -0,0 +1,647 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::StandardNormal;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Architecture, Error, LegacyRPSNetwork, Optimizer, RPSNetwork, Real};
+
+/// Slope of `Activation::LeakyReLU` for negative inputs.
+pub(crate) const LEAKY_RELU_SLOPE: Real = 0.01;
+
+/// Non-linearity applied to a hidden layer.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activation {
+    Tanh,
+    ReLU,
+    LeakyReLU,
+    Sigmoid,
+}
+
+impl Activation {
+    pub(crate) fn apply(self, x: Real) -> Real {
+        match self {
+            Activation::Tanh => x.tanh(),
+            Activation::ReLU => x.max(0.0),
+            Activation::LeakyReLU => {
+                if x > 0.0 {
+                    x
+                } else {
+                    LEAKY_RELU_SLOPE * x
+                }
+            }
+            Activation::Sigmoid => 1.0 / (1.0 + (-x).exp()),
+        }
+    }
+
+    /// Derivative of the activation, expressed in terms of its output `y`.
+    /// Every supported activation is monotonic, so `y` determines it.
+    pub(crate) fn derivative(self, y: Real) -> Real {
+        match self {
+            Activation::Tanh => 1.0 - y * y,
+            Activation::ReLU => {
+                if y > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Activation::LeakyReLU => {
+                if y > 0.0 {
+                    1.0
+                } else {
+                    LEAKY_RELU_SLOPE
+                }
+            }
+            Activation::Sigmoid => y * (1.0 - y),
+        }
+    }
+}
+
+/// Distribution of the initial weights. `fan_in` and `fan_out` are the number
+/// of inputs and outputs of the layer a weight belongs to.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Init {
+    /// N(0, 1), the historical `RPSNetwork` default.
+    StandardNormal,
+    /// Glorot normal: N(0, 2 / (fan_in + fan_out)), suited to tanh.
+    Xavier,
+    /// He normal: N(0, 2 / fan_in), suited to ReLU-like activations.
+    He,
+    /// U(-0.1, 0.1), the historical `LegacyRPSNetwork` default.
+    Uniform,
+}
+
+impl Init {
+    pub(crate) fn sample<R: Rng>(self, fan_in: usize, fan_out: usize, rng: &mut R) -> Real {
+        match self {
+            Init::StandardNormal => rng.sample(StandardNormal),
+            Init::Xavier => {
+                let std = (2.0 / (fan_in + fan_out) as Real).sqrt();
+                std * rng.sample::<Real, _>(StandardNormal)
+            }
+            Init::He => {
+                let std = (2.0 / fan_in as Real).sqrt();
+                std * rng.sample::<Real, _>(StandardNormal)
+            }
+            Init::Uniform => rng.gen::<Real>() * 0.2 - 0.1,
+        }
+    }
+}
+
+/// Builder for both network implementations. Sizes are validated when the
+/// network is built, instead of failing later inside `forward()`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkConfig {
+    pub(crate) input_size: usize,
+    pub(crate) history_size: usize,
+    pub(crate) hidden_sizes: Vec<usize>,
+    pub(crate) output_size: usize,
+    pub(crate) architecture: Architecture,
+    pub(crate) bptt_steps: Option<usize>,
+    pub(crate) attention: bool,
+    pub(crate) activation: Activation,
+    layer_activations: Vec<(usize, Activation)>,
+    pub(crate) optimizer: Optimizer,
+    pub(crate) init: Option<Init>,
+    pub(crate) dropout: Real,
+    pub(crate) weight_decay: Real,
+    pub(crate) label_smoothing: Real,
+    pub(crate) max_grad_norm: Option<Real>,
+    pub(crate) accuracy_window: usize,
+    pub(crate) loss_history_size: usize,
+    pub(crate) replay_capacity: usize,
+    pub(crate) outcome_head: bool,
+    seed: Option<u64>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl NetworkConfig {
+    /// Starts from the configuration used by the demo app: the player's and the
+    /// computer's one-hot moves over the last 5 rounds, predicting 3 moves.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> Self {
+        Self {
+            input_size: 6,
+            history_size: 5,
+            hidden_sizes: vec![40],
+            output_size: 3,
+            architecture: Architecture::Feedforward,
+            bptt_steps: None,
+            attention: false,
+            activation: Activation::Tanh,
+            layer_activations: Vec::new(),
+            optimizer: Optimizer::Sgd,
+            init: None,
+            dropout: 0.0,
+            weight_decay: 0.0,
+            label_smoothing: 0.0,
+            max_grad_norm: None,
+            accuracy_window: 20,
+            loss_history_size: 100,
+            replay_capacity: 0,
+            outcome_head: false,
+            seed: None,
+        }
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn input_size(mut self, input_size: usize) -> Self {
+        self.input_size = input_size;
+        self
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn history_size(mut self, history_size: usize) -> Self {
+        self.history_size = history_size;
+        self
+    }
+
+    /// Uses a single hidden layer of `hidden_size` units.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn hidden_size(mut self, hidden_size: usize) -> Self {
+        self.hidden_sizes = vec![hidden_size];
+        self
+    }
+
+    /// Uses one hidden layer per entry, from the input side to the output
+    /// side. Only `RPSNetwork` supports more than one hidden layer.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn hidden_sizes(mut self, hidden_sizes: &[usize]) -> Self {
+        self.hidden_sizes = hidden_sizes.to_vec();
+        self
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn output_size(mut self, output_size: usize) -> Self {
+        self.output_size = output_size;
+        self
+    }
+
+    /// Sets both the input and output sizes to `moves`, for a network fed
+    /// one-hot moves of a game such as the ones described by `Beats`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn moves(self, moves: usize) -> Self {
+        self.input_size(moves).output_size(moves)
+    }
+
+    /// Kind of first hidden layer. With a recurrent architecture, the first
+    /// entry of `hidden_sizes` is the size of the recurrent state. Only
+    /// `RPSNetwork` supports recurrent architectures.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn architecture(mut self, architecture: Architecture) -> Self {
+        self.architecture = architecture;
+        self
+    }
+
+    /// Number of past rounds that a recurrent layer is trained through.
+    /// Defaults to `history_size`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn bptt_steps(mut self, bptt_steps: usize) -> Self {
+        self.bptt_steps = Some(bptt_steps);
+        self
+    }
+
+    /// Weights the history slots with a learned attention before the first
+    /// hidden layer. Only supported by feedforward `RPSNetwork`s.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn attention(mut self, attention: bool) -> Self {
+        self.attention = attention;
+        self
+    }
+
+    /// Activation of every hidden layer without a `layer_activation()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn activation(mut self, activation: Activation) -> Self {
+        self.activation = activation;
+        self
+    }
+
+    /// Activation of the hidden layer number `layer`, counting from the
+    /// input side.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn layer_activation(mut self, layer: usize, activation: Activation) -> Self {
+        self.layer_activations.retain(|(l, _)| *l != layer);
+        self.layer_activations.push((layer, activation));
+        self
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn optimizer(mut self, optimizer: Optimizer) -> Self {
+        self.optimizer = optimizer;
+        self
+    }
+
+    /// Weight initialization for every weight matrix. Defaults to
+    /// `Init::StandardNormal` for `RPSNetwork` and `Init::Uniform` for
+    /// `LegacyRPSNetwork`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn init(mut self, init: Init) -> Self {
+        self.init = Some(init);
+        self
+    }
+
+    /// Probability of dropping each hidden unit during training. Only used by
+    /// `RPSNetwork`, when its training flag is set.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn dropout(mut self, dropout: Real) -> Self {
+        self.dropout = dropout;
+        self
+    }
+
+    /// L2 regularization coefficient applied to the weights (not the biases)
+    /// in `backward()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn weight_decay(mut self, weight_decay: Real) -> Self {
+        self.weight_decay = weight_decay;
+        self
+    }
+
+    /// Share of the target that `RPSNetwork::backward()` spreads evenly over
+    /// every move instead of putting it all on the label, from 0 to 1
+    /// excluded, so that a few repeated moves don't push the prediction to
+    /// certainty. Defaults to 0. Only used by `RPSNetwork`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn label_smoothing(mut self, label_smoothing: Real) -> Self {
+        self.label_smoothing = label_smoothing;
+        self
+    }
+
+    /// Rescales the gradients in `RPSNetwork::backward()` whenever their
+    /// global L2 norm exceeds `max_grad_norm`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn max_grad_norm(mut self, max_grad_norm: Real) -> Self {
+        self.max_grad_norm = Some(max_grad_norm);
+        self
+    }
+
+    /// Number of recent rounds over which `RPSNetwork::recent_accuracy()` is
+    /// measured. Defaults to 20.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn accuracy_window(mut self, accuracy_window: usize) -> Self {
+        self.accuracy_window = accuracy_window;
+        self
+    }
+
+    /// Number of recent losses returned by `RPSNetwork::loss_history()`.
+    /// Defaults to 100, and 0 keeps none.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn loss_history_size(mut self, loss_history_size: usize) -> Self {
+        self.loss_history_size = loss_history_size;
+        self
+    }
+
+    /// Number of past rounds kept by `RPSNetwork::backward()` for
+    /// `RPSNetwork::train_from_replay()`. Defaults to 0, which keeps none.
+    /// Not supported by recurrent architectures.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn replay_capacity(mut self, replay_capacity: usize) -> Self {
+        self.replay_capacity = replay_capacity;
+        self
+    }
+
+    /// Adds a second output head predicting the outcome of the next round
+    /// for the side playing against the predicted moves, from the last
+    /// hidden layer. `RPSNetwork::backward_with_outcome()` trains it along
+    /// with the move prediction, which the shared hidden layers then learn
+    /// from too. Only used by `RPSNetwork`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn outcome_head(mut self, outcome_head: bool) -> Self {
+        self.outcome_head = outcome_head;
+        self
+    }
+
+    /// Makes weight initialization reproducible.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn build(&self) -> Result<RPSNetwork, Error> {
+        self.validate()?;
+        if self.attention && self.architecture != Architecture::Feedforward {
+            return Err(Error::Unsupported(
+                "attention is only supported by the feedforward architecture",
+            ));
+        }
+        if self.replay_capacity > 0 && self.architecture != Architecture::Feedforward {
+            return Err(Error::Unsupported(
+                "experience replay is only supported by the feedforward architecture",
+            ));
+        }
+        Ok(RPSNetwork::with_rng(self, self.rng()?))
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn build_legacy(&self) -> Result<LegacyRPSNetwork, Error> {
+        self.validate()?;
+        if self.hidden_sizes.len() != 1 {
+            return Err(Error::Unsupported(
+                "LegacyRPSNetwork has exactly one hidden layer",
+            ));
+        }
+        if self.architecture != Architecture::Feedforward {
+            return Err(Error::Unsupported("LegacyRPSNetwork is feedforward only"));
+        }
+        if self.attention {
+            return Err(Error::Unsupported("LegacyRPSNetwork has no attention"));
+        }
+        if self.outcome_head {
+            return Err(Error::Unsupported("LegacyRPSNetwork has no outcome head"));
+        }
+        Ok(LegacyRPSNetwork::with_rng(self, &mut self.rng()?))
+    }
+}
+
+impl NetworkConfig {
+    /// Configuration matching the positional constructor arguments, with
+    /// default options.
+    pub(crate) fn with_sizes(
+        input_size: usize,
+        history_size: usize,
+        hidden_size: usize,
+        output_size: usize,
+    ) -> Self {
+        Self::new()
+            .input_size(input_size)
+            .history_size(history_size)
+            .hidden_size(hidden_size)
+            .output_size(output_size)
+    }
+
+    /// Activation of the hidden layer number `layer`.
+    pub(crate) fn layer_activation_or_default(&self, layer: usize) -> Activation {
+        self.layer_activations
+            .iter()
+            .find(|(l, _)| *l == layer)
+            .map_or(self.activation, |(_, activation)| *activation)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        let check = |name, value, min| {
+            if value >= min {
+                Ok(())
+            } else {
+                Err(Error::InvalidSize { name, value, min })
+            }
+        };
+
+        check("input_size", self.input_size, 1)?;
+        check("history_size", self.history_size, 1)?;
+        check("hidden layer count", self.hidden_sizes.len(), 1)?;
+        for &hidden_size in &self.hidden_sizes {
+            check("hidden_size", hidden_size, 1)?;
+        }
+        if let Some(bptt_steps) = self.bptt_steps {
+            check("bptt_steps", bptt_steps, 1)?;
+        }
+        check("accuracy_window", self.accuracy_window, 1)?;
+        for &(layer, _) in &self.layer_activations {
+            if layer >= self.hidden_sizes.len() {
+                return Err(Error::InvalidLayer {
+                    layer,
+                    layers: self.hidden_sizes.len(),
+                });
+            }
+        }
+        // A single output class would always be predicted with probability 1.
+        check("output_size", self.output_size, 2)?;
+
+        if !(0.0..1.0).contains(&self.dropout) {
+            return Err(Error::OutOfRange {
+                name: "dropout",
+                value: self.dropout,
+                min: 0.0,
+                max: 1.0,
+            });
+        }
+        if !(0.0..1.0).contains(&self.label_smoothing) {
+            return Err(Error::OutOfRange {
+                name: "label_smoothing",
+                value: self.label_smoothing,
+                min: 0.0,
+                max: 1.0,
+            });
+        }
+        if !(self.weight_decay >= 0.0 && self.weight_decay.is_finite()) {
+            return Err(Error::OutOfRange {
+                name: "weight_decay",
+                value: self.weight_decay,
+                min: 0.0,
+                max: Real::INFINITY,
+            });
+        }
+        if let Some(max_grad_norm) = self.max_grad_norm {
+            if !(max_grad_norm > 0.0 && max_grad_norm.is_finite()) {
+                return Err(Error::OutOfRange {
+                    name: "max_grad_norm",
+                    value: max_grad_norm,
+                    min: Real::MIN_POSITIVE,
+                    max: Real::INFINITY,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn rng(&self) -> Result<ChaCha8Rng, Error> {
+        match self.seed {
+            Some(seed) => Ok(ChaCha8Rng::seed_from_u64(seed)),
+            #[cfg(feature = "std")]
+            None => Ok(ChaCha8Rng::from_entropy()),
+            #[cfg(not(feature = "std"))]
+            None => Err(Error::Unsupported(
+                "a seed is required without the std feature",
+            )),
+        }
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_with_sizes() {
+        let network = NetworkConfig::new()
+            .input_size(3)
+            .history_size(4)
+            .hidden_size(8)
+            .output_size(3)
+            .build()
+            .unwrap();
+
+        assert_eq!(network.input_size, 3);
+        assert_eq!(network.history_size, 4);
+        assert_eq!(network.hidden_size, 8);
+        assert_eq!(network.output_size, 3);
+
+        let legacy = NetworkConfig::new().hidden_size(8).build_legacy().unwrap();
+
+        assert_eq!(legacy.input_size, 6);
+        assert_eq!(legacy.hidden_size, 8);
+    }
+
+    #[test]
+    fn layer_activations() {
+        let config = NetworkConfig::new()
+            .hidden_sizes(&[8, 4, 2])
+            .activation(Activation::Sigmoid)
+            .layer_activation(1, Activation::Tanh)
+            .layer_activation(1, Activation::ReLU);
+
+        assert_eq!(config.layer_activation_or_default(0), Activation::Sigmoid);
+        assert_eq!(config.layer_activation_or_default(1), Activation::ReLU);
+        assert_eq!(config.layer_activation_or_default(2), Activation::Sigmoid);
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        assert_eq!(
+            NetworkConfig::new().output_size(0).build().err(),
+            Some(Error::InvalidSize {
+                name: "output_size",
+                value: 0,
+                min: 2
+            })
+        );
+        assert!(NetworkConfig::new().hidden_size(0).build().is_err());
+        assert!(NetworkConfig::new().hidden_sizes(&[]).build().is_err());
+        assert!(NetworkConfig::new().hidden_sizes(&[8, 0]).build().is_err());
+        assert_eq!(
+            NetworkConfig::new()
+                .hidden_sizes(&[8, 4])
+                .layer_activation(2, Activation::ReLU)
+                .build()
+                .err(),
+            Some(Error::InvalidLayer {
+                layer: 2,
+                layers: 2
+            })
+        );
+        assert!(NetworkConfig::new()
+            .hidden_sizes(&[8, 4])
+            .build_legacy()
+            .is_err());
+        assert!(NetworkConfig::new().history_size(0).build_legacy().is_err());
+        assert!(NetworkConfig::new().input_size(0).build_legacy().is_err());
+        assert!(NetworkConfig::new().dropout(1.0).build().is_err());
+        assert!(NetworkConfig::new().dropout(-0.1).build().is_err());
+        assert!(NetworkConfig::new().label_smoothing(1.0).build().is_err());
+        assert!(NetworkConfig::new()
+            .label_smoothing(Real::NAN)
+            .build()
+            .is_err());
+        assert!(NetworkConfig::new()
+            .weight_decay(-1.0)
+            .build_legacy()
+            .is_err());
+        assert!(NetworkConfig::new()
+            .architecture(Architecture::Elman)
+            .build_legacy()
+            .is_err());
+        assert!(NetworkConfig::new()
+            .architecture(Architecture::Elman)
+            .bptt_steps(0)
+            .build()
+            .is_err());
+        assert!(NetworkConfig::new()
+            .architecture(Architecture::Gru)
+            .attention(true)
+            .build()
+            .is_err());
+        assert!(NetworkConfig::new().attention(true).build_legacy().is_err());
+        assert!(NetworkConfig::new().accuracy_window(0).build().is_err());
+        assert!(NetworkConfig::new()
+            .architecture(Architecture::Lstm)
+            .replay_capacity(10)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn activation_derivatives() {
+        let activations = [
+            Activation::Tanh,
+            Activation::ReLU,
+            Activation::LeakyReLU,
+            Activation::Sigmoid,
+        ];
+        let eps = 1e-3;
+
+        for activation in activations {
+            for x in [-2.0, -0.5, 0.3, 1.7] {
+                let numerical =
+                    (activation.apply(x + eps) - activation.apply(x - eps)) / (2.0 * eps);
+                let analytical = activation.derivative(activation.apply(x));
+
+                assert!(
+                    (numerical - analytical).abs() < 1e-2,
+                    "{:?}'({}) = {}, expected {}",
+                    activation,
+                    x,
+                    analytical,
+                    numerical
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn init_scale() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let std = |init: Init, rng: &mut ChaCha8Rng| {
+            let samples: Vec<Real> = (0..10000).map(|_| init.sample(30, 10, rng)).collect();
+            (samples.iter().map(|x| x * x).sum::<Real>() / samples.len() as Real).sqrt()
+        };
+
+        assert!((std(Init::StandardNormal, &mut rng) - 1.0).abs() < 0.05);
+        assert!((std(Init::Xavier, &mut rng) - (0.05 as Real).sqrt()).abs() < 0.01);
+        assert!((std(Init::He, &mut rng) - (2.0 as Real / 30.0).sqrt()).abs() < 0.01);
+        assert!((0..100).all(|_| Init::Uniform.sample(30, 10, &mut rng).abs() <= 0.1));
+    }
+
+    #[test]
+    fn seed_is_reproducible() {
+        let config = NetworkConfig::new().seed(7);
+        let input = vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+
+        let mut a = config.build().unwrap();
+        let mut b = config.build().unwrap();
+        a.forward(&input).unwrap();
+        b.forward(&input).unwrap();
+
+        assert_eq!(a.probs(), b.probs());
+
+        let mut a = config.build_legacy().unwrap();
+        let mut b = config.build_legacy().unwrap();
+        a.forward(&input).unwrap();
+        b.forward(&input).unwrap();
+
+        assert_eq!(a.probs(), b.probs());
+    }
+}
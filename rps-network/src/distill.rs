@@ -0,0 +1,112 @@
+use alloc::vec;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{cross_entropy, Error, RPSNetwork, Real};
+
+/// Trains `student` to match the output distributions of `teacher` over a
+/// recorded game, e.g. to replace a large network by a small one on
+/// low-end devices. `samples` holds the rounds one after the other, each
+/// `input_size` long, as they were passed to `forward()`. Both networks
+/// replay them from a cleared history, and after each round the student is
+/// trained towards the teacher's distribution, ignoring exploration, at the
+/// rate of its schedule. The teacher is left unchanged, while the student's
+/// history ends up holding the last rounds of `samples`. Returns the mean
+/// Kullback-Leibler divergence from the teacher to the student before each
+/// update, which falls towards 0 as calls repeat. Both networks must have
+/// the same input and output sizes.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn distill(
+    teacher: &RPSNetwork,
+    student: &mut RPSNetwork,
+    samples: &[Real],
+) -> Result<Real, Error> {
+    for (name, expected, found) in [
+        ("input_size", teacher.input_size, student.input_size),
+        ("output_size", teacher.output_size, student.output_size),
+    ] {
+        if expected != found {
+            return Err(Error::ShapeMismatch {
+                name,
+                expected: vec![expected],
+                found: vec![found],
+            });
+        }
+    }
+    let input_size = teacher.input_size;
+    let rounds = samples.len() / input_size;
+    if rounds == 0 || !samples.len().is_multiple_of(input_size) {
+        return Err(Error::ShapeMismatch {
+            name: "samples",
+            expected: vec![rounds.max(1) * input_size],
+            found: vec![samples.len()],
+        });
+    }
+
+    let mut teacher = teacher.clone();
+    teacher.reset_history();
+    student.reset_history();
+    // The updates don't go through `backward()`
+    student.undo = None;
+    let mut divergence = 0.0;
+    for input in samples.chunks(input_size) {
+        teacher.forward(input)?;
+        student.forward(input)?;
+        divergence += teacher
+            .probs
+            .iter()
+            .zip(&student.probs)
+            .map(|(&t, &s)| t * (cross_entropy(s) - cross_entropy(t)))
+            .sum::<Real>();
+        student.update_towards(teacher.probs.view(), None, None);
+    }
+    Ok(divergence / rounds as Real)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::one_hot;
+    use crate::NetworkConfig;
+    use alloc::vec::Vec;
+
+    fn game(rounds: usize) -> Vec<Real> {
+        (0..rounds).flat_map(|i| one_hot(i * i % 3, 3)).collect()
+    }
+
+    #[test]
+    fn student_matches_teacher() {
+        let config = NetworkConfig::new().moves(3).history_size(2).seed(42);
+        let mut teacher = config.clone().hidden_size(40).build().unwrap();
+        let samples = game(30);
+        for input in samples.chunks(3).take(29) {
+            teacher.forward(input).unwrap();
+            teacher.backward(0, Some(0.05)).unwrap();
+        }
+        let history = teacher.history();
+
+        let mut student = config.hidden_size(4).build().unwrap();
+        student.set_schedule(&crate::Schedule::constant(0.1).unwrap());
+        let first = distill(&teacher, &mut student, &samples).unwrap();
+        let mut last = first;
+        for _ in 0..30 {
+            last = distill(&teacher, &mut student, &samples).unwrap();
+        }
+        assert!(last < first / 4.0, "{} {}", first, last);
+        assert_eq!(teacher.history(), history);
+        assert_eq!(student.history(), samples[84..]);
+        assert!(!student.undo_backward());
+    }
+
+    #[test]
+    fn invalid() {
+        let config = NetworkConfig::new().moves(3).seed(42);
+        let teacher = config.build().unwrap();
+        let mut student = config.clone().hidden_size(4).build().unwrap();
+        assert!(distill(&teacher, &mut student, &[]).is_err());
+        assert!(distill(&teacher, &mut student, &[1.0, 0.0]).is_err());
+        let mut five = NetworkConfig::new().moves(5).seed(0).build().unwrap();
+        assert!(distill(&teacher, &mut five, &game(3)).is_err());
+    }
+}
@@ -0,0 +1,116 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::Real;
+
+/// Drop in accuracy, per round, that the drift score tolerates, so that the
+/// noise of a steady opponent doesn't add up to a drift.
+const TOLERANCE: Real = 0.05;
+
+/// Drift score above which a drift is detected, unless set otherwise.
+pub(crate) const DEFAULT_THRESHOLD: Real = 5.0;
+
+/// Factor applied to the learning rate by `DriftResponse::BoostLearningRate`.
+pub(crate) const BOOST: Real = 3.0;
+
+/// What a network does when it detects that the opponent changed strategy,
+/// besides reporting it through `drift_detected()`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriftResponse {
+    /// Only reports the drift.
+    Signal,
+    /// Draws new output layer weights, keeping the hidden layers, so that
+    /// the new strategy is learned from the features of the old one.
+    ResetOutput,
+    /// Triples the learning rate for as many rounds as the accuracy window.
+    BoostLearningRate,
+}
+
+/// Page-Hinkley test over the correctness of the predictions: the score is
+/// how far the cumulative accuracy, less a tolerance, has fallen below its
+/// best level, and a drift is detected when it exceeds the threshold.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DriftDetector {
+    pub(crate) threshold: Real,
+    pub(crate) response: DriftResponse,
+    rounds: u32,
+    /// Accuracy since the last detection.
+    mean: Real,
+    /// Cumulative deviation of the hits from the mean, plus the tolerance.
+    sum: Real,
+    /// Highest `sum` since the last detection.
+    max: Real,
+    /// Whether the last recorded prediction detected a drift.
+    pub(crate) detected: bool,
+    pub(crate) detections: u32,
+    /// Rounds left with a boosted learning rate.
+    pub(crate) boost_rounds: usize,
+}
+
+impl DriftDetector {
+    pub(crate) fn new(threshold: Real, response: DriftResponse) -> Self {
+        Self {
+            threshold,
+            response,
+            rounds: 0,
+            mean: 0.0,
+            sum: 0.0,
+            max: 0.0,
+            detected: false,
+            detections: 0,
+            boost_rounds: 0,
+        }
+    }
+
+    /// Records whether a prediction was correct, and returns whether it
+    /// reveals a drift, in which case the test starts over.
+    pub(crate) fn record(&mut self, hit: bool) -> bool {
+        let x = hit as u8 as Real;
+        self.rounds += 1;
+        self.mean += (x - self.mean) / self.rounds as Real;
+        self.sum += x - self.mean + TOLERANCE;
+        self.max = self.max.max(self.sum);
+        self.detected = self.score() > self.threshold;
+        if self.detected {
+            self.detections += 1;
+            *self = Self {
+                detections: self.detections,
+                detected: true,
+                boost_rounds: self.boost_rounds,
+                ..Self::new(self.threshold, self.response)
+            };
+        }
+        self.detected
+    }
+
+    /// Current drift score, from 0 while the accuracy holds.
+    pub(crate) fn score(&self) -> Real {
+        self.max - self.sum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_accuracy_drop() {
+        let mut detector = DriftDetector::new(5.0, DriftResponse::Signal);
+        // A steady 2 in 3 accuracy raises no alarm
+        for round in 0..300 {
+            assert!(!detector.record(round % 3 != 0));
+        }
+        assert!(detector.score() < 5.0);
+
+        let detected = (0..30).position(|_| detector.record(false));
+        assert!(matches!(detected, Some(5..=15)), "{:?}", detected);
+        assert_eq!(detector.detections, 1);
+        assert_eq!(detector.score(), 0.0);
+    }
+}
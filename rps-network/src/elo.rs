@@ -0,0 +1,232 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Real, Simulation};
+
+/// Rating of a player before its first match.
+const INITIAL_RATING: Real = 1500.0;
+
+/// Elo ratings of any set of players, such as network configurations and
+/// scripted bots, identified by name and updated from the results of
+/// simulated matches, so that changes to a model can be judged by a single
+/// number. Each match counts as one game whose score is the share of
+/// rounds won, draws counting half.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EloRatings {
+    k: Real,
+    /// Name and rating of each player, in the order they were added.
+    players: Vec<(String, Real)>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl EloRatings {
+    /// Starts without players, moving ratings by at most `k` points per
+    /// match, e.g. 32.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(k: Real) -> Result<EloRatings, Error> {
+        if !(k > 0.0 && k.is_finite()) {
+            return Err(Error::OutOfRange {
+                name: "k",
+                value: k,
+                min: Real::MIN_POSITIVE,
+                max: Real::INFINITY,
+            });
+        }
+        Ok(Self {
+            k,
+            players: Vec::new(),
+        })
+    }
+
+    /// Adds player `name` with the initial rating of 1500, unless it is
+    /// already rated.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn add(&mut self, name: &str) {
+        self.index(name);
+    }
+
+    /// Updates the ratings of players `a` and `b`, added if new, from a
+    /// match between them where `a` was player A of `simulation`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn record(&mut self, a: &str, b: &str, simulation: &Simulation) -> Result<(), Error> {
+        if simulation.rounds == 0 {
+            return Err(Error::InvalidSize {
+                name: "rounds",
+                value: 0,
+                min: 1,
+            });
+        }
+        let score = (simulation.a_wins as Real + 0.5 * simulation.draws as Real)
+            / simulation.rounds as Real;
+        let (a, b) = (self.index(a), self.index(b));
+        let change = self.k * (score - expected_score(self.players[a].1, self.players[b].1));
+        self.players[a].1 += change;
+        self.players[b].1 -= change;
+        Ok(())
+    }
+
+    /// Rating of player `name`, if rated.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn rating(&self, name: &str) -> Option<Real> {
+        self.position(name).map(|i| self.players[i].1)
+    }
+
+    /// Score `a` is expected to make against `b` according to their
+    /// ratings, from 0 to 1, if both are rated.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn expected_score(&self, a: &str, b: &str) -> Option<Real> {
+        Some(expected_score(self.rating(a)?, self.rating(b)?))
+    }
+
+    /// Names of the players, from the highest rated.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn ranking(&self) -> Vec<String> {
+        let mut players: Vec<&(String, Real)> = self.players.iter().collect();
+        players.sort_by(|a, b| b.1.total_cmp(&a.1));
+        players.into_iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Number of rated players.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn len(&self) -> usize {
+        self.players.len()
+    }
+
+    /// Whether no player is rated.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn is_empty(&self) -> bool {
+        self.players.is_empty()
+    }
+}
+
+impl EloRatings {
+    /// Records the results of `tournament()`, played between the
+    /// configurations named `configs` and the bots named `bots`, in the
+    /// order of `configs` and `bots`.
+    pub fn record_tournament(
+        &mut self,
+        configs: &[&str],
+        bots: &[&str],
+        results: &[Simulation],
+    ) -> Result<(), Error> {
+        if results.len() != configs.len() * bots.len() {
+            return Err(Error::ShapeMismatch {
+                name: "results",
+                expected: alloc::vec![configs.len() * bots.len()],
+                found: alloc::vec![results.len()],
+            });
+        }
+        let matches = configs
+            .iter()
+            .flat_map(|config| bots.iter().map(move |bot| (config, bot)));
+        for ((config, bot), simulation) in matches.zip(results) {
+            self.record(config, bot, simulation)?;
+        }
+        Ok(())
+    }
+
+    /// Names and ratings of the players, in the order they were added.
+    pub fn players(&self) -> &[(String, Real)] {
+        &self.players
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.players.iter().position(|(n, _)| n == name)
+    }
+
+    /// Index of player `name`, added with the initial rating if new.
+    fn index(&mut self, name: &str) -> usize {
+        self.position(name).unwrap_or_else(|| {
+            self.players.push((name.to_string(), INITIAL_RATING));
+            self.players.len() - 1
+        })
+    }
+}
+
+/// Expected score of a player rated `a` against one rated `b`.
+fn expected_score(a: Real, b: Real) -> Real {
+    1.0 / (1.0 + (10.0 as Real).powf((b - a) / 400.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{simulate_players, tournament, Beats, NetworkConfig, ScriptedBot};
+
+    fn simulation(a: usize, b: usize, rounds: u32) -> Simulation {
+        let rules = Beats::rock_paper_scissors();
+        let mut a = ScriptedBot::constant(a, 3).unwrap();
+        let mut b = ScriptedBot::constant(b, 3).unwrap();
+        simulate_players(&mut a, &mut b, &rules, rounds).unwrap()
+    }
+
+    #[test]
+    fn updates_ratings() {
+        let mut ratings = EloRatings::new(32.0).unwrap();
+        ratings.add("rock");
+        assert_eq!(ratings.rating("rock"), Some(1500.0));
+        assert_eq!(ratings.rating("paper"), None);
+
+        // Paper beats rock every round
+        ratings
+            .record("rock", "paper", &simulation(0, 1, 10))
+            .unwrap();
+        assert_eq!(ratings.rating("rock"), Some(1484.0));
+        assert_eq!(ratings.rating("paper"), Some(1516.0));
+        assert_eq!(ratings.ranking(), ["paper", "rock"]);
+        assert!(ratings.expected_score("paper", "rock").unwrap() > 0.5);
+
+        // A draw moves the ratings back towards each other
+        ratings
+            .record("paper", "rock", &simulation(0, 0, 10))
+            .unwrap();
+        let paper = ratings.rating("paper").unwrap();
+        assert!(paper < 1516.0 && paper > 1500.0);
+        assert_eq!(ratings.rating("rock").unwrap() + paper, 3000.0);
+        assert_eq!(ratings.len(), 2);
+
+        assert!(ratings
+            .record("rock", "paper", &simulation(0, 1, 0))
+            .is_err());
+        assert!(EloRatings::new(0.0).is_err());
+    }
+
+    #[test]
+    fn rates_tournaments() {
+        let configs = [
+            NetworkConfig::new().moves(3).seed(1),
+            NetworkConfig::new().moves(3).hidden_size(2).seed(1),
+        ];
+        let bots = [
+            ScriptedBot::constant(0, 3).unwrap(),
+            ScriptedBot::cycle(&[0, 1, 2], 3).unwrap(),
+        ];
+        let results = tournament(&configs, &bots, 0.1, 100).unwrap();
+
+        let mut ratings = EloRatings::new(32.0).unwrap();
+        ratings
+            .record_tournament(&["large", "small"], &["rock", "cycle"], &results)
+            .unwrap();
+        assert_eq!(ratings.len(), 4);
+        // The networks learn to beat both bots
+        for network in ["large", "small"] {
+            for bot in ["rock", "cycle"] {
+                assert!(ratings.rating(network) > ratings.rating(bot));
+            }
+        }
+        assert!(ratings
+            .record_tournament(&["large"], &["rock", "cycle"], &results)
+            .is_err());
+    }
+}
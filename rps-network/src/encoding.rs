@@ -0,0 +1,181 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{check_label, Error, Outcome, Real};
+
+/// Features of a round fed to the network, each one-hot encoded and
+/// concatenated in the order listed.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// The opponent's move only.
+    OpponentMove,
+    /// The opponent's move, then the bot's own move.
+    BothMoves,
+    /// Both moves, then the outcome of the round for the opponent, so that
+    /// the network can pick up strategies such as "play what beats the
+    /// bot's last move" or "switch after losing".
+    BothMovesAndOutcome,
+    /// Everything in `BothMovesAndOutcome`, then how the opponent's move
+    /// relates to the bot's move of the round before: the number of moves
+    /// from that move to the opponent's, in index order. In
+    /// rock-paper-scissors, 0 repeats the bot's last move, 1 beats it and 2
+    /// loses to it, so that the network can model players who deliberately
+    /// counter the bot's last play. Left at zero on the first round.
+    Reaction,
+}
+
+impl Encoding {
+    /// Every encoding, from the smallest input to the largest.
+    pub const ALL: [Encoding; 4] = [
+        Encoding::OpponentMove,
+        Encoding::BothMoves,
+        Encoding::BothMovesAndOutcome,
+        Encoding::Reaction,
+    ];
+
+    /// Length of the input vector for a game of `moves` moves.
+    pub fn input_size(self, moves: usize) -> usize {
+        match self {
+            Encoding::OpponentMove => moves,
+            Encoding::BothMoves => 2 * moves,
+            Encoding::BothMovesAndOutcome => 2 * moves + Outcome::ALL.len(),
+            Encoding::Reaction => 3 * moves + Outcome::ALL.len(),
+        }
+    }
+
+    /// Input vector of a round where the opponent played `opponent` and the
+    /// bot `mine`, with the given `outcome` for the opponent. The reaction
+    /// of `Encoding::Reaction` is left at zero, see `encode_after()`.
+    pub fn encode(
+        self,
+        opponent: usize,
+        mine: usize,
+        outcome: Outcome,
+        moves: usize,
+    ) -> Result<Vec<Real>, Error> {
+        self.encode_after(opponent, mine, outcome, None, moves)
+    }
+
+    /// Same as `encode()`, where the bot played `previous` in the round
+    /// before, if there was one.
+    pub fn encode_after(
+        self,
+        opponent: usize,
+        mine: usize,
+        outcome: Outcome,
+        previous: Option<usize>,
+        moves: usize,
+    ) -> Result<Vec<Real>, Error> {
+        check_label(opponent, moves)?;
+        check_label(mine, moves)?;
+        if let Some(previous) = previous {
+            check_label(previous, moves)?;
+        }
+
+        let mut input = vec![0.0; self.input_size(moves)];
+        input[opponent] = 1.0;
+        if self != Encoding::OpponentMove {
+            input[moves + mine] = 1.0;
+        }
+        if matches!(self, Encoding::BothMovesAndOutcome | Encoding::Reaction) {
+            input[2 * moves + outcome.index()] = 1.0;
+        }
+        if let (Encoding::Reaction, Some(previous)) = (self, previous) {
+            let reaction = (opponent + moves - previous) % moves;
+            input[2 * moves + Outcome::ALL.len() + reaction] = 1.0;
+        }
+        Ok(input)
+    }
+}
+
+/// Length of the input vector of `encoding` for a game of `moves` moves.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn encoding_input_size(encoding: Encoding, moves: usize) -> usize {
+    encoding.input_size(moves)
+}
+
+/// Input vector of a round with `encoding`, see `Encoding::encode()`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn encode_round(
+    encoding: Encoding,
+    opponent: usize,
+    mine: usize,
+    outcome: Outcome,
+    moves: usize,
+) -> Result<Vec<Real>, Error> {
+    encoding.encode(opponent, mine, outcome, moves)
+}
+
+/// Input vector of a round with `encoding`, after a round where the bot
+/// played `previous`, see `Encoding::encode_after()`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn encode_round_after(
+    encoding: Encoding,
+    opponent: usize,
+    mine: usize,
+    outcome: Outcome,
+    previous: Option<usize>,
+    moves: usize,
+) -> Result<Vec<Real>, Error> {
+    encoding.encode_after(opponent, mine, outcome, previous, moves)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode() {
+        assert_eq!(
+            Encoding::OpponentMove.encode(1, 2, Outcome::Lose, 3),
+            Ok(vec![0.0, 1.0, 0.0])
+        );
+        assert_eq!(
+            Encoding::BothMoves.encode(1, 2, Outcome::Lose, 3),
+            Ok(vec![0.0, 1.0, 0.0, 0.0, 0.0, 1.0])
+        );
+        assert_eq!(
+            Encoding::BothMovesAndOutcome.encode(1, 2, Outcome::Lose, 3),
+            Ok(vec![0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0])
+        );
+        assert_eq!(Encoding::BothMovesAndOutcome.input_size(5), 13);
+        assert!(Encoding::BothMoves.encode(1, 3, Outcome::Win, 3).is_err());
+    }
+
+    #[test]
+    fn reaction() {
+        // Scissors after the bot's paper: the move beating it
+        assert_eq!(
+            Encoding::Reaction.encode_after(2, 0, Outcome::Lose, Some(1), 3),
+            Ok(vec![
+                0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0
+            ])
+        );
+        // Rock after the bot's paper: the move losing to it
+        let input = Encoding::Reaction
+            .encode_after(0, 0, Outcome::Draw, Some(1), 3)
+            .unwrap();
+        assert_eq!(&input[9..], &[0.0, 0.0, 1.0]);
+        let input = Encoding::Reaction.encode(0, 0, Outcome::Draw, 3).unwrap();
+        assert_eq!(&input[9..], &[0.0; 3]);
+        // The other encodings ignore the previous move
+        assert_eq!(
+            Encoding::BothMoves.encode_after(1, 2, Outcome::Lose, Some(0), 3),
+            Encoding::BothMoves.encode(1, 2, Outcome::Lose, 3)
+        );
+        assert!(Encoding::Reaction
+            .encode_after(0, 0, Outcome::Draw, Some(3), 3)
+            .is_err());
+
+        let sizes: Vec<usize> = Encoding::ALL.iter().map(|e| e.input_size(3)).collect();
+        assert_eq!(sizes, vec![3, 6, 9, 12]);
+    }
+}
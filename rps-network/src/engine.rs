@@ -0,0 +1,660 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::gamelog::{RoundRecord, Setup};
+use crate::{
+    Beats, Encoding, Error, GameLog, Outcome, PredictionResult, RPSNetwork, Real, SessionStats,
+};
+
+/// Plays rock-paper-scissors, or any game described by a `Beats` table,
+/// against a player, judging each round, keeping the score and training the
+/// network to predict the player's next move. The network must predict
+/// single moves, i.e. have one output per move, and take rounds as encoded
+/// by its `Encoding`, the player's move only by default. Moves are
+/// identified by their index, e.g. as a `Move`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct GameEngine {
+    network: RPSNetwork,
+    rules: Beats,
+    encoding: Encoding,
+    stats: SessionStats,
+    /// Least probability of every move in the bot's policy.
+    policy_floor: Real,
+    /// Entropy of the prediction above which the bot plays uniformly.
+    nash_threshold: Option<Real>,
+    /// Move of the bot in the last round.
+    previous: Option<usize>,
+    /// Rounds played while recording.
+    log: GameLog,
+    recording: bool,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl GameEngine {
+    /// Plays rock-paper-scissors with `network`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(network: RPSNetwork) -> Result<GameEngine, Error> {
+        Self::with_rules(network, &Beats::rock_paper_scissors())
+    }
+
+    /// Plays the game described by `rules` with `network`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn with_rules(network: RPSNetwork, rules: &Beats) -> Result<GameEngine, Error> {
+        Self::with_encoding(network, rules, Encoding::OpponentMove)
+    }
+
+    /// Plays the game described by `rules` with `network`, feeding it each
+    /// round as encoded by `encoding`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn with_encoding(
+        network: RPSNetwork,
+        rules: &Beats,
+        encoding: Encoding,
+    ) -> Result<GameEngine, Error> {
+        let moves = rules.moves();
+        for (name, size, expected) in [
+            ("input", network.input_size, encoding.input_size(moves)),
+            ("output", network.output_size, moves),
+        ] {
+            if size != expected {
+                return Err(Error::ShapeMismatch {
+                    name,
+                    expected: vec![expected],
+                    found: vec![size],
+                });
+            }
+        }
+        Ok(Self {
+            network,
+            rules: rules.clone(),
+            encoding,
+            stats: SessionStats::new(moves),
+            policy_floor: 0.0,
+            nash_threshold: None,
+            previous: None,
+            log: GameLog::new(),
+            recording: false,
+        })
+    }
+
+    /// Move the bot should play next: one that beats the player's most
+    /// likely move, or, with a policy floor or when falling back to the
+    /// Nash strategy, a move drawn from `policy()` with the network's RNG.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn bot_move(&mut self) -> usize {
+        if self.policy_floor == 0.0 && !self.guessing() {
+            return self.counter_move();
+        }
+        let policy = self.policy();
+        self.network.sample(&policy)
+    }
+
+    /// Distribution `bot_move()` draws from: the policy floor on every
+    /// move, and the rest on the move beating the player's most likely one,
+    /// or the uniform distribution when the network is guessing.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn policy(&self) -> Vec<Real> {
+        let moves = self.rules.moves();
+        if self.guessing() {
+            return vec![1.0 / moves as Real; moves];
+        }
+        let mut policy = vec![self.policy_floor; moves];
+        policy[self.counter_move()] += 1.0 - self.policy_floor * moves as Real;
+        policy
+    }
+
+    /// Gives every move at least a `floor` probability of being played by
+    /// the bot, from 0 (off, the default) to 1 over the number of moves
+    /// (uniformly random play), so that the bot never becomes fully
+    /// deterministic and a player can't exploit its counter pattern.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_policy_floor(&mut self, floor: Real) -> Result<(), Error> {
+        let max = 1.0 / self.rules.moves() as Real;
+        if !(0.0..=max).contains(&floor) {
+            return Err(Error::OutOfRange {
+                name: "policy_floor",
+                value: floor,
+                min: 0.0,
+                max,
+            });
+        }
+        self.policy_floor = floor;
+        Ok(())
+    }
+
+    /// Plays uniformly at random, the Nash equilibrium of
+    /// rock-paper-scissors and other balanced games, whenever the entropy of
+    /// the prediction is above `threshold` nats, i.e. when the network is
+    /// basically guessing and countering its most likely move would be
+    /// arbitrary. The entropy is at most ln(3) = 1.1 for 3 moves. Without a
+    /// threshold (the default), the bot always counters.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_nash_threshold(&mut self, threshold: Option<Real>) -> Result<(), Error> {
+        if let Some(value) = threshold.filter(|t| t.is_nan() || *t < 0.0) {
+            return Err(Error::OutOfRange {
+                name: "nash_threshold",
+                value,
+                min: 0.0,
+                max: Real::INFINITY,
+            });
+        }
+        self.nash_threshold = threshold;
+        Ok(())
+    }
+
+    /// Whether the entropy of the prediction is above the Nash threshold,
+    /// so that the bot plays uniformly.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn guessing(&self) -> bool {
+        self.nash_threshold
+            .is_some_and(|threshold| self.network.entropy() > threshold)
+    }
+
+    /// Judges a round, updates the score, and trains the network on the
+    /// player's move, and its outcome head on the bot's outcome, before
+    /// predicting the next one. Returns the outcome for the player.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn play(&mut self, player: usize, bot: usize) -> Result<Outcome, Error> {
+        let outcome = self.rules.judge(player, bot)?;
+        if self.recording {
+            self.log.record(RoundRecord {
+                round: self.stats.rounds,
+                player,
+                bot,
+                outcome,
+                probs: self.network.probs(),
+                loss: self.network.loss(player)?,
+                timestamp: 0.0,
+                rng_position: self.network.rng.get_word_pos(),
+            });
+        }
+        // The first round has no prediction to learn from
+        if self.stats.rounds > 0 {
+            if self.network.outcome_probs().is_some() {
+                let bot_outcome = self.rules.judge(bot, player)?;
+                self.network
+                    .backward_with_outcome(player, bot_outcome, None)?;
+            } else {
+                self.network.backward(player, None)?;
+            }
+        }
+        let input =
+            self.encoding
+                .encode_after(player, bot, outcome, self.previous, self.rules.moves())?;
+        self.previous = Some(bot);
+        self.network.forward(&input)?;
+        let payoff = self.rules.payoff(player, bot);
+        self.stats.record(player, bot, outcome, payoff);
+        Ok(outcome)
+    }
+
+    /// Statistics of the rounds played so far.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn stats(&self) -> SessionStats {
+        self.stats.clone()
+    }
+
+    /// Records every round played from now on in `log()`, with both moves,
+    /// the prediction the bot played against and its loss, until recording
+    /// is turned off again. Off by default. Turn it on before the first
+    /// round to be able to `replay()` the log.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_recording(&mut self, recording: bool) {
+        if recording && self.log.setup.is_none() {
+            self.log.setup = Some(self.setup());
+        }
+        self.recording = recording;
+    }
+
+    /// Rounds recorded so far, see `set_recording()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn log(&self) -> GameLog {
+        self.log.clone()
+    }
+
+    /// Number of rounds played.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn rounds(&self) -> u32 {
+        self.stats.rounds
+    }
+
+    /// Rounds won by the player.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn player_wins(&self) -> u32 {
+        self.stats.player_wins
+    }
+
+    /// Rounds won by the bot.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn bot_wins(&self) -> u32 {
+        self.stats.bot_wins
+    }
+
+    /// Rounds ending in a draw.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn draws(&self) -> u32 {
+        self.stats.draws
+    }
+
+    /// Length of the current winning streak: positive for the player,
+    /// negative for the bot, and 0 right after a draw.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn streak(&self) -> i32 {
+        self.stats.streak
+    }
+
+    /// How rounds are fed to the network.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Output distribution over the player's next move.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn probs(&self) -> Vec<Real> {
+        self.network.probs()
+    }
+
+    /// Prediction of the player's next move, with the move countering it
+    /// under the engine's rules.
+    #[cfg_attr(
+        feature = "wasm",
+        wasm_bindgen(unchecked_return_type = "PredictionResult")
+    )]
+    pub fn prediction_result(&self) -> PredictionResult {
+        PredictionResult::new(self.probs(), self.counter_move())
+    }
+
+    /// Plays the rounds of `log` again, from the first one, with a network
+    /// built with `seed` from the configuration and settings recorded when
+    /// recording started, and returns the engine after the last round. When
+    /// the original network was built with the same seed, every round gets
+    /// the same prediction and training step, down to the dropout masks, so
+    /// the weights come out bit-identical, e.g. to inspect a model just
+    /// before it got worse after `GameLog::truncate()`. The returned engine
+    /// records its own log, to compare the predictions. Fails if the log
+    /// doesn't cover every round from the first one.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn replay(log: &GameLog, seed: u64) -> Result<GameEngine, Error> {
+        let Some(setup) = &log.setup else {
+            return Err(Error::Unsupported(
+                "the log wasn't recorded by a game engine",
+            ));
+        };
+        if log
+            .rounds()
+            .iter()
+            .enumerate()
+            .any(|(i, round)| round.round as usize != i)
+        {
+            return Err(Error::Unsupported(
+                "the log doesn't cover every round from the first one",
+            ));
+        }
+
+        let mut network = setup.config.clone().seed(seed).build()?;
+        network.set_schedule(&setup.schedule);
+        network.set_temperature(setup.temperature)?;
+        network.set_exploration(setup.exploration)?;
+        network.set_history_decay(setup.history_decay)?;
+        network.set_drift_detection(setup.drift_threshold, setup.drift_response)?;
+        network.set_training(setup.training);
+        let mut engine = Self::with_encoding(network, &setup.rules, setup.encoding)?;
+        engine.set_recording(true);
+        for round in log.rounds() {
+            engine.network.rng.set_word_pos(round.rng_position);
+            engine.play(round.player, round.bot)?;
+        }
+        Ok(engine)
+    }
+}
+
+impl GameEngine {
+    /// What `replay()` needs to start the game over.
+    fn setup(&self) -> Setup {
+        let network = &self.network;
+        Setup {
+            config: network.config.clone(),
+            rules: self.rules.clone(),
+            encoding: self.encoding,
+            schedule: network.schedule,
+            temperature: network.temperature,
+            exploration: network.exploration,
+            history_decay: network.history_decay,
+            drift_threshold: network.drift.threshold,
+            drift_response: network.drift.response,
+            training: network.training,
+        }
+    }
+
+    fn counter_move(&self) -> usize {
+        self.network
+            .counter_move(&self.rules)
+            .expect("the rules cover every output")
+    }
+
+    /// Network predicting the player's moves.
+    pub fn network(&self) -> &RPSNetwork {
+        &self.network
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Move, NetworkConfig};
+
+    fn engine() -> GameEngine {
+        GameEngine::new(RPSNetwork::new_with_seed(3, 5, 10, 3, 42).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn rejects_other_sizes() {
+        assert!(GameEngine::new(RPSNetwork::new_with_seed(6, 5, 10, 3, 42).unwrap()).is_err());
+        assert!(GameEngine::new(RPSNetwork::new_with_seed(3, 5, 10, 5, 42).unwrap()).is_err());
+        let rpsls = Beats::rock_paper_scissors_lizard_spock();
+        assert!(GameEngine::with_rules(
+            RPSNetwork::new_with_seed(3, 5, 10, 3, 42).unwrap(),
+            &rpsls
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn score_and_streaks() {
+        let mut engine = engine();
+        let rounds = [
+            (Move::Rock, Move::Scissors, Outcome::Win, 1),
+            (Move::Paper, Move::Rock, Outcome::Win, 2),
+            (Move::Paper, Move::Scissors, Outcome::Lose, -1),
+            (Move::Rock, Move::Rock, Outcome::Draw, 0),
+            (Move::Scissors, Move::Rock, Outcome::Lose, -1),
+        ];
+        for (player, bot, outcome, streak) in rounds {
+            assert_eq!(engine.play(player.index(), bot.index()), Ok(outcome));
+            assert_eq!(engine.streak(), streak);
+        }
+
+        assert_eq!(engine.rounds(), 5);
+        assert_eq!(engine.player_wins(), 2);
+        assert_eq!(engine.bot_wins(), 2);
+        assert_eq!(engine.draws(), 1);
+        assert_eq!(engine.stats().longest_player_streak, 2);
+        // The first round had no prediction to learn from
+        let trained: u32 = engine.network().confusion_matrix().iter().sum();
+        assert_eq!(trained, 4);
+    }
+
+    #[test]
+    fn records_rounds() {
+        let mut engine = engine();
+        engine.play(0, 0).unwrap();
+        assert!(engine.log().is_empty());
+
+        engine.set_recording(true);
+        let probs = engine.probs();
+        engine.play(1, 2).unwrap();
+        engine.play(2, 2).unwrap();
+        engine.set_recording(false);
+        engine.play(0, 0).unwrap();
+
+        let log = engine.log();
+        let rounds = log.rounds();
+        assert_eq!(rounds.len(), 2);
+        assert_eq!((rounds[0].player, rounds[0].bot), (1, 2));
+        assert_eq!(rounds[0].outcome, Outcome::Lose);
+        assert_eq!(rounds[0].probs, probs);
+        assert_eq!(rounds[0].loss, -probs[1].ln());
+        assert_eq!(rounds[1].outcome, Outcome::Draw);
+        assert!(rounds[1].timestamp >= rounds[0].timestamp);
+    }
+
+    #[test]
+    fn replay() {
+        let config = NetworkConfig::new()
+            .moves(3)
+            .dropout(0.3)
+            .optimizer(crate::Optimizer::Adam)
+            .seed(42);
+        let mut network = config.build().unwrap();
+        network.set_training(true);
+        network.set_exploration(0.2).unwrap();
+        network
+            .set_drift_detection(1.0, crate::DriftResponse::ResetOutput)
+            .unwrap();
+        let mut engine = GameEngine::new(network).unwrap();
+        engine.set_recording(true);
+        engine.set_policy_floor(0.1).unwrap();
+        for round in 0..150 {
+            let player = if round < 80 { round % 3 } else { 2 - round % 3 };
+            let bot = engine.bot_move();
+            engine.play(player, bot).unwrap();
+        }
+        assert!(engine.network().drift_count() > 0);
+        let log = engine.log();
+
+        let replayed = GameEngine::replay(&log, 42).unwrap();
+        assert_eq!(replayed.network().layers, engine.network().layers);
+        assert_eq!(replayed.probs(), engine.probs());
+        assert_eq!(replayed.stats(), engine.stats());
+        let probs = |log: &GameLog| -> Vec<Vec<Real>> {
+            log.rounds()
+                .iter()
+                .map(|round| round.probs.clone())
+                .collect()
+        };
+        assert_eq!(probs(&replayed.log()), probs(&log));
+
+        // Up to a given round
+        let mut start = log.clone();
+        start.truncate(100);
+        let replayed = GameEngine::replay(&start, 42).unwrap();
+        assert_eq!(replayed.rounds(), 100);
+        assert_ne!(replayed.network().layers, engine.network().layers);
+
+        let other = GameEngine::replay(&log, 43).unwrap();
+        assert_ne!(other.network().layers, engine.network().layers);
+    }
+
+    #[test]
+    fn replay_needs_every_round() {
+        assert!(GameEngine::replay(&GameLog::new(), 42).is_err());
+
+        let mut late = engine();
+        late.play(0, 0).unwrap();
+        late.set_recording(true);
+        late.play(0, 0).unwrap();
+        assert!(GameEngine::replay(&late.log(), 42).is_err());
+
+        let mut engine = engine();
+        engine.set_recording(true);
+        engine.play(0, 0).unwrap();
+        engine.set_recording(false);
+        engine.play(1, 0).unwrap();
+        engine.set_recording(true);
+        engine.play(2, 0).unwrap();
+        assert_eq!(engine.log().len(), 2);
+        assert!(GameEngine::replay(&engine.log(), 42).is_err());
+    }
+
+    #[test]
+    fn bot_learns_to_counter() {
+        let mut engine = engine();
+        let mut bot_wins = 0;
+        for round in 0..300 {
+            let player = round % 3;
+            let bot = engine.bot_move();
+            if engine.play(player, bot).unwrap() == Outcome::Lose && round >= 200 {
+                bot_wins += 1;
+            }
+        }
+        assert!(bot_wins > 90);
+    }
+
+    #[test]
+    fn policy_floor() {
+        let mut engine = engine();
+        for _ in 0..10 {
+            engine.play(0, 0).unwrap();
+        }
+        // Paper beats the predicted rock
+        assert_eq!(engine.policy(), vec![0.0, 1.0, 0.0]);
+        assert_eq!(engine.bot_move(), 1);
+
+        engine.set_policy_floor(0.1).unwrap();
+        let policy = engine.policy();
+        assert!((policy[1] - 0.8).abs() < 1e-6);
+        assert_eq!(policy[0], 0.1);
+        let mut counts = [0; 3];
+        for _ in 0..1000 {
+            counts[engine.bot_move()] += 1;
+        }
+        assert!((700..900).contains(&counts[1]), "{:?}", counts);
+        assert!(counts[0] > 50 && counts[2] > 50, "{:?}", counts);
+
+        assert!(engine.set_policy_floor(0.5).is_err());
+        assert!(engine.set_policy_floor(-0.1).is_err());
+    }
+
+    #[test]
+    fn trains_outcome_head() {
+        let network = NetworkConfig::new()
+            .moves(3)
+            .outcome_head(true)
+            .seed(42)
+            .build()
+            .unwrap();
+        let mut engine = GameEngine::new(network).unwrap();
+        for _ in 0..50 {
+            let bot = engine.bot_move();
+            engine.play(0, bot).unwrap();
+        }
+        assert_eq!(engine.network().expected_outcome(), Some(Outcome::Win));
+    }
+
+    #[test]
+    fn nash_fallback() {
+        let mut engine = engine();
+        engine.set_nash_threshold(Some(1.0)).unwrap();
+        // Nothing is known about the player yet
+        assert!(engine.guessing());
+        assert_eq!(engine.policy(), vec![1.0 / 3.0; 3]);
+        let mut counts = [0; 3];
+        for _ in 0..300 {
+            counts[engine.bot_move()] += 1;
+        }
+        assert!(counts.iter().all(|&count| count > 70), "{:?}", counts);
+
+        for _ in 0..20 {
+            engine.play(0, 0).unwrap();
+        }
+        assert!(!engine.guessing());
+        assert_eq!(engine.bot_move(), 1);
+
+        engine.set_nash_threshold(None).unwrap();
+        assert!(engine.set_nash_threshold(Some(-1.0)).is_err());
+        assert!(engine.set_nash_threshold(Some(Real::NAN)).is_err());
+    }
+
+    #[test]
+    fn encoding_sees_bot_moves() {
+        let rules = Beats::rock_paper_scissors();
+        let network = RPSNetwork::new_with_seed(6, 5, 10, 3, 42).unwrap();
+        assert!(GameEngine::new(network.clone()).is_err());
+        let mut engine = GameEngine::with_encoding(network, &rules, Encoding::BothMoves).unwrap();
+        assert_eq!(engine.encoding(), Encoding::BothMoves);
+
+        // The player always plays what beats the bot's last move
+        let mut last_bot = Move::Rock;
+        let mut bot_wins = 0;
+        for round in 0..300 {
+            let player = last_bot.beaten_by();
+            let bot = Move::ALL[engine.bot_move()];
+            if engine.play(player.index(), bot.index()).unwrap() == Outcome::Lose && round >= 200 {
+                bot_wins += 1;
+            }
+            last_bot = bot;
+        }
+        assert!(bot_wins > 90);
+    }
+
+    #[test]
+    fn encoding_sees_reactions() {
+        let rules = Beats::rock_paper_scissors();
+        let network = RPSNetwork::new_with_seed(12, 1, 10, 3, 42).unwrap();
+        let mut engine = GameEngine::with_encoding(network, &rules, Encoding::Reaction).unwrap();
+
+        // The player always plays what the bot's last move beats
+        let mut last_bot = Move::Rock;
+        let mut bot_wins = 0;
+        for round in 0..300 {
+            let player = last_bot.beaten_by().beaten_by();
+            let bot = Move::ALL[engine.bot_move()];
+            if engine.play(player.index(), bot.index()).unwrap() == Outcome::Lose && round >= 200 {
+                bot_wins += 1;
+            }
+            last_bot = bot;
+        }
+        assert!(bot_wins > 90);
+    }
+
+    #[test]
+    fn rock_paper_scissors_lizard_spock() {
+        let rules = Beats::rock_paper_scissors_lizard_spock();
+        let mut engine =
+            GameEngine::with_rules(RPSNetwork::new_with_seed(5, 5, 10, 5, 42).unwrap(), &rules)
+                .unwrap();
+
+        let pattern = [0, 3, 4, 1];
+        let mut bot_wins = 0;
+        for round in 0..400 {
+            let player = pattern[round % pattern.len()];
+            let bot = engine.bot_move();
+            if engine.play(player, bot).unwrap() == Outcome::Lose && round >= 300 {
+                bot_wins += 1;
+            }
+        }
+        assert!(bot_wins > 90);
+        assert_eq!(engine.stats().player_moves(), vec![100, 100, 0, 100, 100]);
+        assert!(engine.play(5, 0).is_err());
+    }
+
+    #[test]
+    fn custom_game() {
+        // 7 moves, each beating the next 3 in a cycle, with higher payoffs for
+        // closer wins
+        let n = 7;
+        let mut payoff = vec![0.0; n * n];
+        for a in 0..n {
+            for d in 1..=3 {
+                let b = (a + d) % n;
+                payoff[a * n + b] = (4 - d) as Real;
+                payoff[b * n + a] = -((4 - d) as Real);
+            }
+        }
+        let rules = Beats::from_payoff(&payoff).unwrap();
+        let network = crate::NetworkConfig::new()
+            .moves(n)
+            .seed(42)
+            .build()
+            .unwrap();
+        let mut engine = GameEngine::with_rules(network, &rules).unwrap();
+
+        let mut score = 0.0;
+        for round in 0..200 {
+            let player = (round * 2) % n;
+            let bot = engine.bot_move();
+            engine.play(player, bot).unwrap();
+            score += payoff[player * n + bot];
+        }
+
+        let stats = engine.stats();
+        assert_eq!(stats.player_moves().len(), n);
+        assert_eq!(stats.bot_moves().iter().sum::<u32>(), 200);
+        assert_eq!(stats.player_score, score);
+        assert!(stats.bot_wins > stats.player_wins);
+    }
+}
@@ -0,0 +1,329 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    check_input, check_label, CycleDetector, Error, FrequencyPredictor, MarkovPredictor,
+    PatternDetector, Prediction, RPSNetwork, Real, RpsPredictor,
+};
+
+/// Combines several predictors fed the same inputs, such as a network and
+/// the baselines, into a single one. The output distribution averages the
+/// predictors' with Hedge weights: after every round, each weight is
+/// multiplied by `exp(-eta * loss)`, the loss being one minus the
+/// probability the predictor gave to the actual move.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Ensemble {
+    predictors: Vec<Box<dyn RpsPredictor>>,
+    /// Weight of each predictor, summing to 1.
+    weights: Vec<Real>,
+    eta: Real,
+    probs: Vec<Real>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Ensemble {
+    /// Starts an empty ensemble whose weights move at rate `eta`, which
+    /// must be positive: larger values switch to the best predictor faster.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(eta: Real) -> Result<Ensemble, Error> {
+        if !(eta > 0.0 && eta.is_finite()) {
+            return Err(Error::OutOfRange {
+                name: "eta",
+                value: eta,
+                min: Real::MIN_POSITIVE,
+                max: Real::INFINITY,
+            });
+        }
+        Ok(Self {
+            predictors: Vec::new(),
+            weights: Vec::new(),
+            eta,
+            probs: Vec::new(),
+        })
+    }
+
+    /// Adds a network, see `push()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn add_network(&mut self, network: RPSNetwork) -> Result<(), Error> {
+        self.push(Box::new(network))
+    }
+
+    /// Adds a Markov baseline, see `push()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn add_markov(&mut self, predictor: MarkovPredictor) -> Result<(), Error> {
+        self.push(Box::new(predictor))
+    }
+
+    /// Adds a frequency baseline, see `push()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn add_frequency(&mut self, predictor: FrequencyPredictor) -> Result<(), Error> {
+        self.push(Box::new(predictor))
+    }
+
+    /// Adds a pattern detector, see `push()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn add_pattern_detector(&mut self, detector: PatternDetector) -> Result<(), Error> {
+        self.push(Box::new(detector))
+    }
+
+    /// Adds a cycle detector, see `push()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn add_cycle_detector(&mut self, detector: CycleDetector) -> Result<(), Error> {
+        self.push(Box::new(detector))
+    }
+
+    /// Passes the moves of a new round to every predictor and combines
+    /// their predictions of the next one.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        if self.predictors.is_empty() {
+            return Err(Error::InvalidSize {
+                name: "predictors",
+                value: 0,
+                min: 1,
+            });
+        }
+        // Check the input before any predictor sees it, so that one failing
+        // doesn't leave the others a round ahead
+        if let Some(input_size) = RpsPredictor::input_size(self) {
+            check_input(input, input_size)?;
+        }
+        self.probs.fill(0.0);
+        for (predictor, &weight) in self.predictors.iter_mut().zip(&self.weights) {
+            predictor.forward(input)?;
+            for (p, q) in self.probs.iter_mut().zip(predictor.probs()) {
+                *p += weight * q;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reweights the predictors by how well they predicted `label`, then
+    /// trains each of them towards it with `learning_rate`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn backward(&mut self, label: usize, learning_rate: Real) -> Result<(), Error> {
+        check_label(label, self.probs.len())?;
+        for (predictor, weight) in self.predictors.iter_mut().zip(&mut self.weights) {
+            let loss = 1.0 - predictor.probs()[label];
+            *weight *= (-self.eta * loss).exp();
+            predictor.backward(label, learning_rate)?;
+        }
+        // Normalize so that the weights never underflow
+        let total: Real = self.weights.iter().sum();
+        for weight in self.weights.iter_mut() {
+            *weight /= total;
+        }
+        Ok(())
+    }
+
+    /// Combined output distribution of the last `forward()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn probs(&self) -> Vec<Real> {
+        self.probs.clone()
+    }
+
+    /// Most likely move according to `probs()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn predict(&self) -> Prediction {
+        Prediction::argmax(&self.probs)
+    }
+
+    /// Current weight of each predictor, in the order they were added.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn weights(&self) -> Vec<Real> {
+        self.weights.clone()
+    }
+
+    /// Number of predictors.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn len(&self) -> usize {
+        self.predictors.len()
+    }
+
+    /// Whether no predictor was added yet.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn is_empty(&self) -> bool {
+        self.predictors.is_empty()
+    }
+}
+
+impl Ensemble {
+    /// Adds `predictor`, which must predict as many moves as the others and
+    /// take inputs of the same length if it needs one, with the average
+    /// weight of the current predictors.
+    pub fn push(&mut self, predictor: Box<dyn RpsPredictor>) -> Result<(), Error> {
+        let moves = predictor.probs().len();
+        if !self.predictors.is_empty() && moves != self.probs.len() {
+            return Err(Error::ShapeMismatch {
+                name: "probs",
+                expected: vec![self.probs.len()],
+                found: vec![moves],
+            });
+        }
+        if let (Some(expected), Some(found)) =
+            (RpsPredictor::input_size(self), predictor.input_size())
+        {
+            if found != expected {
+                return Err(Error::ShapeMismatch {
+                    name: "input",
+                    expected: vec![expected],
+                    found: vec![found],
+                });
+            }
+        }
+        if self.predictors.is_empty() {
+            self.probs = vec![1.0 / moves as Real; moves];
+        }
+
+        self.predictors.push(predictor);
+        let n = self.predictors.len() as Real;
+        for weight in self.weights.iter_mut() {
+            *weight *= (n - 1.0) / n;
+        }
+        self.weights.push(1.0 / n);
+        Ok(())
+    }
+
+    /// Predictors in the order they were added.
+    pub fn predictors(&self) -> &[Box<dyn RpsPredictor>] {
+        &self.predictors
+    }
+}
+
+impl RpsPredictor for Ensemble {
+    fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        Ensemble::forward(self, input)
+    }
+
+    fn backward(&mut self, label: usize, learning_rate: Real) -> Result<(), Error> {
+        Ensemble::backward(self, label, learning_rate)
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        Ensemble::probs(self)
+    }
+
+    fn predict(&self) -> Prediction {
+        Ensemble::predict(self)
+    }
+
+    fn input_size(&self) -> Option<usize> {
+        self.predictors.iter().find_map(|p| p.input_size())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Move, NetworkConfig};
+
+    #[test]
+    fn follows_best_predictor() {
+        let mut ensemble = Ensemble::new(0.5).unwrap();
+        ensemble
+            .add_frequency(FrequencyPredictor::new(3, 1.0).unwrap())
+            .unwrap();
+        ensemble
+            .add_markov(MarkovPredictor::new(1, 3).unwrap())
+            .unwrap();
+        ensemble
+            .add_network(NetworkConfig::new().seed(0).build().unwrap())
+            .unwrap();
+        ensemble
+            .add_pattern_detector(PatternDetector::new(10).unwrap())
+            .unwrap();
+        assert_eq!(ensemble.weights(), vec![0.25; 4]);
+
+        // The opponent cycles through the moves, which only the Markov
+        // baseline and the network can learn
+        let mut predicted = 0;
+        for round in 0..60 {
+            let opponent = Move::ALL[round % 3];
+            if round > 0 {
+                if round >= 50 && ensemble.predict().index == opponent.index() {
+                    predicted += 1;
+                }
+                ensemble.backward(opponent.index(), 0.1).unwrap();
+            }
+            let bot = Move::ALL[(round * 2) % 3];
+            ensemble
+                .forward(&[opponent.one_hot(), bot.one_hot()].concat())
+                .unwrap();
+        }
+        assert_eq!(predicted, 10);
+
+        let weights = ensemble.weights();
+        assert!((weights.iter().sum::<Real>() - 1.0).abs() < 1e-5);
+        assert!(weights[1] > weights[0]);
+        assert!(weights[1] > weights[3]);
+        assert!((ensemble.probs().iter().sum::<Real>() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn combines_cycle_detector() {
+        let mut ensemble = Ensemble::new(0.5).unwrap();
+        ensemble
+            .add_markov(MarkovPredictor::new(1, 3).unwrap())
+            .unwrap();
+        ensemble
+            .add_cycle_detector(CycleDetector::new(12, 3).unwrap())
+            .unwrap();
+
+        // Every move is followed by two different ones in this cycle, so
+        // the Markov baseline can't learn it
+        let cycle = [0, 1, 1, 2, 0, 2];
+        let mut predicted = 0;
+        for round in 0..60 {
+            let opponent = cycle[round % cycle.len()];
+            if round > 0 {
+                if round >= 50 && ensemble.predict().index == opponent {
+                    predicted += 1;
+                }
+                ensemble.backward(opponent, 0.1).unwrap();
+            }
+            ensemble.forward(&Move::ALL[opponent].one_hot()).unwrap();
+        }
+        assert_eq!(predicted, 10);
+        let weights = ensemble.weights();
+        assert!(weights[1] > weights[0], "{:?}", weights);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Ensemble::new(0.0).is_err());
+        let mut ensemble = Ensemble::new(1.0).unwrap();
+        assert!(ensemble.forward(&[1.0, 0.0, 0.0]).is_err());
+        ensemble
+            .add_markov(MarkovPredictor::new(1, 3).unwrap())
+            .unwrap();
+        assert!(ensemble
+            .add_markov(MarkovPredictor::new(1, 5).unwrap())
+            .is_err());
+        assert_eq!(ensemble.len(), 1);
+        assert!(ensemble.backward(3, 0.1).is_err());
+
+        // Networks must take the same inputs, so that forward() either
+        // feeds the round to every predictor or to none
+        let network = NetworkConfig::new().moves(3).seed(1).build().unwrap();
+        ensemble.add_network(network).unwrap();
+        let longer = NetworkConfig::new().input_size(6).seed(1).build().unwrap();
+        assert!(matches!(
+            ensemble.add_network(longer),
+            Err(Error::ShapeMismatch { name: "input", .. })
+        ));
+        for _ in 0..3 {
+            ensemble.forward(&[1.0, 0.0, 0.0]).unwrap();
+            ensemble.backward(1, 0.1).unwrap();
+        }
+        let markov = ensemble.predictors()[0].probs();
+        assert!(ensemble.forward(&[0.0, 1.0, 0.0, 0.0, 0.0, 1.0]).is_err());
+        assert_eq!(ensemble.predictors()[0].probs(), markov);
+    }
+}
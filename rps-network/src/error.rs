@@ -0,0 +1,144 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::Real;
+
+/// Errors returned by the network API. On the JS side they are thrown as
+/// `Error` objects carrying the formatted message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A serialized model could not be encoded or decoded.
+    Serialization(String),
+    /// A tensor does not have the shape implied by the network sizes.
+    ShapeMismatch {
+        name: &'static str,
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+    /// A configured size is below its allowed minimum.
+    InvalidSize {
+        name: &'static str,
+        value: usize,
+        min: usize,
+    },
+    /// A per-layer option refers to a hidden layer that doesn't exist.
+    InvalidLayer { layer: usize, layers: usize },
+    /// A move index refers to a move that doesn't exist.
+    InvalidMove { index: usize, moves: usize },
+    /// A parameter index refers to a parameter tensor that doesn't exist.
+    InvalidParameter { index: usize, parameters: usize },
+    /// A parameter tensor doesn't have the shape of the same tensor of
+    /// another network.
+    ParameterMismatch {
+        index: usize,
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+    /// The requested configuration isn't supported by this implementation.
+    Unsupported(&'static str),
+    /// Browser storage is unavailable or an operation on it failed.
+    Storage(String),
+    /// A configured value is outside of its allowed range `[min, max)`.
+    OutOfRange {
+        name: &'static str,
+        value: Real,
+        min: Real,
+        max: Real,
+    },
+    /// A saved model uses a later version of the format than `supported`.
+    UnsupportedFormat { version: u32, supported: u32 },
+    /// A saved model doesn't start with the magic of the format.
+    InvalidMagic,
+    /// The sizes of a saved model don't match the hash in its header.
+    ConfigMismatch { expected: u64, found: u64 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Serialization(msg) => write!(f, "serialization error: {}", msg),
+            Error::ShapeMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "shape mismatch for {}: expected {:?}, found {:?}",
+                name, expected, found
+            ),
+            Error::InvalidSize { name, value, min } => {
+                write!(f, "{} must be at least {}, got {}", name, min, value)
+            }
+            Error::InvalidLayer { layer, layers } => write!(
+                f,
+                "hidden layer {} doesn't exist, the network has {}",
+                layer, layers
+            ),
+            Error::InvalidMove { index, moves } => {
+                write!(f, "move {} doesn't exist, there are {}", index, moves)
+            }
+            Error::InvalidParameter { index, parameters } => write!(
+                f,
+                "parameter {} doesn't exist, the network has {}",
+                index, parameters
+            ),
+            Error::ParameterMismatch {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "parameter {} has shape {:?}, expected {:?}",
+                index, found, expected
+            ),
+            Error::Unsupported(msg) => write!(f, "unsupported configuration: {}", msg),
+            Error::Storage(msg) => write!(f, "storage error: {}", msg),
+            Error::OutOfRange {
+                name,
+                value,
+                min,
+                max,
+            } => write!(f, "{} must be in [{}, {}), got {}", name, min, max, value),
+            Error::UnsupportedFormat { version, supported } => write!(
+                f,
+                "the model was saved in format version {}, this version of the library \
+                 loads up to {}: load and save it with a newer one",
+                version, supported
+            ),
+            Error::InvalidMagic => write!(f, "not a saved model: the magic is missing"),
+            Error::ConfigMismatch { expected, found } => write!(
+                f,
+                "the model doesn't match its header: config hash {:#x}, expected {:#x}",
+                found, expected
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+#[cfg(feature = "wasm")]
+impl From<Error> for JsValue {
+    fn from(err: Error) -> Self {
+        JsError::from(err).into()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Serialization(err.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<bincode::Error> for Error {
+    fn from(err: bincode::Error) -> Self {
+        Error::Serialization(err.to_string())
+    }
+}
@@ -0,0 +1,353 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::StandardNormal;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::simulation::encoding_of;
+use crate::{
+    simulate_players, Beats, Encoding, Error, NetworkConfig, Player, RPSNetwork, Real, ScriptedBot,
+};
+
+/// Population-based trainer, an alternative to backpropagation: each
+/// generation, every network plays every opponent of the pool without
+/// learning, the best ones are kept, and the rest of the population is
+/// replaced by mutated copies of them. A network's fitness is its worst
+/// score against the opponents, from -1 to 1, so that evolution favors
+/// strategies robust to all of them over the exploitation of a single one.
+/// Networks play rock-paper-scissors, fed rounds in the `Encoding` matching
+/// their input size.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Evolution {
+    rules: Beats,
+    encoding: Encoding,
+    /// Networks, sorted from the fittest after `evolve()`.
+    population: Vec<RPSNetwork>,
+    /// Fitness of each network, empty before the first evaluation.
+    fitness: Vec<Real>,
+    opponents: Vec<ScriptedBot>,
+    /// Standard deviation of the noise added to every weight of a child.
+    mutation: Real,
+    /// Number of networks kept unchanged from a generation to the next.
+    elite: usize,
+    /// Rounds played against each opponent.
+    rounds: u32,
+    generation: u32,
+    rng: ChaCha8Rng,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Evolution {
+    /// Builds `size` networks from `config` with seeds drawn from `seed`,
+    /// to evolve against `ScriptedBot::library()`. A quarter of the population
+    /// is kept each generation, and children are mutated with a standard
+    /// deviation of 0.1.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(config: &NetworkConfig, size: usize, seed: u64) -> Result<Evolution, Error> {
+        if size < 2 {
+            return Err(Error::InvalidSize {
+                name: "population",
+                value: size,
+                min: 2,
+            });
+        }
+        let rules = Beats::rock_paper_scissors();
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let population = (0..size)
+            .map(|_| config.clone().seed(rng.gen()).build())
+            .collect::<Result<Vec<_>, _>>()?;
+        let encoding = encoding_of(&population[0], &rules)?;
+        Ok(Self {
+            rules,
+            encoding,
+            population,
+            fitness: Vec::new(),
+            opponents: ScriptedBot::library(),
+            mutation: 0.1,
+            elite: (size / 4).max(1),
+            rounds: 50,
+            generation: 0,
+            rng,
+        })
+    }
+
+    /// Sets the standard deviation of the Gaussian noise added to every
+    /// weight of a child, which must be positive.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_mutation(&mut self, mutation: Real) -> Result<(), Error> {
+        if !(mutation > 0.0 && mutation.is_finite()) {
+            return Err(Error::OutOfRange {
+                name: "mutation",
+                value: mutation,
+                min: Real::MIN_POSITIVE,
+                max: Real::INFINITY,
+            });
+        }
+        self.mutation = mutation;
+        Ok(())
+    }
+
+    /// Sets how many of the fittest networks are kept unchanged, and bred,
+    /// from a generation to the next. Must leave room for at least one
+    /// child.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_elite(&mut self, elite: usize) -> Result<(), Error> {
+        if elite == 0 || elite >= self.population.len() {
+            return Err(Error::InvalidSize {
+                name: "elite",
+                value: elite,
+                min: 1,
+            });
+        }
+        self.elite = elite;
+        Ok(())
+    }
+
+    /// Sets the number of rounds played against each opponent.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_rounds(&mut self, rounds: u32) -> Result<(), Error> {
+        if rounds == 0 {
+            return Err(Error::InvalidSize {
+                name: "rounds",
+                value: 0,
+                min: 1,
+            });
+        }
+        self.rounds = rounds;
+        Ok(())
+    }
+
+    /// Adds `bot` to the opponents, which must play rock-paper-scissors.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn add_opponent(&mut self, bot: ScriptedBot) -> Result<(), Error> {
+        if bot.moves() != self.rules.moves() {
+            return Err(Error::ShapeMismatch {
+                name: "moves",
+                expected: vec![self.rules.moves()],
+                found: vec![bot.moves()],
+            });
+        }
+        self.opponents.push(bot);
+        Ok(())
+    }
+
+    /// Removes every opponent, e.g. to replace the default ones.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn clear_opponents(&mut self) {
+        self.opponents.clear();
+    }
+
+    /// Runs `generations` generations, then evaluates the last one and
+    /// returns the best fitness.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn evolve(&mut self, generations: u32) -> Result<Real, Error> {
+        if self.opponents.is_empty() {
+            return Err(Error::InvalidSize {
+                name: "opponents",
+                value: 0,
+                min: 1,
+            });
+        }
+        for _ in 0..generations {
+            self.evaluate()?;
+            self.breed();
+            self.generation += 1;
+        }
+        self.evaluate()?;
+        Ok(self.fitness[0])
+    }
+
+    /// Copy of the fittest network of the last evaluation, with a cleared
+    /// history.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn best(&self) -> RPSNetwork {
+        let mut best = self.population[0].clone();
+        best.reset_history();
+        best.evaluate();
+        best
+    }
+
+    /// Fitness of each network from the last evaluation, from the fittest.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn fitness(&self) -> Vec<Real> {
+        self.fitness.clone()
+    }
+
+    /// Number of generations bred so far.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Number of networks in the population.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn len(&self) -> usize {
+        self.population.len()
+    }
+
+    /// Always false: a population holds at least two networks.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn is_empty(&self) -> bool {
+        self.population.is_empty()
+    }
+}
+
+impl Evolution {
+    /// Networks, sorted from the fittest after `evolve()`.
+    pub fn population(&self) -> &[RPSNetwork] {
+        &self.population
+    }
+
+    /// Scores every network and sorts the population from the fittest,
+    /// keeping the current order on ties.
+    fn evaluate(&mut self) -> Result<(), Error> {
+        let mut scored = Vec::with_capacity(self.population.len());
+        for network in self.population.drain(..) {
+            let mut fitness = Real::INFINITY;
+            for bot in &self.opponents {
+                let mut player = Frozen::new(&network, &self.rules, self.encoding);
+                let mut bot = bot.clone();
+                let simulation = simulate_players(&mut player, &mut bot, &self.rules, self.rounds)?;
+                fitness = fitness.min(simulation.a_win_rate() - simulation.b_win_rate());
+            }
+            scored.push((fitness, network));
+        }
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).expect("fitness is finite"));
+        (self.fitness, self.population) = scored.into_iter().unzip();
+        Ok(())
+    }
+
+    /// Replaces every network but the elite by a mutated copy of one of
+    /// them, drawn at random.
+    fn breed(&mut self) {
+        for i in self.elite..self.population.len() {
+            let parent = self.rng.gen_range(0..self.elite);
+            let mut child = self.population[parent].clone();
+            for mut param in child.params_mut() {
+                for w in param.iter_mut() {
+                    *w += self.mutation * self.rng.sample::<Real, _>(StandardNormal);
+                }
+            }
+            self.population[i] = child;
+        }
+    }
+}
+
+/// Plays a copy of a network without training it, starting from a cleared
+/// history.
+struct Frozen<'a> {
+    network: RPSNetwork,
+    rules: &'a Beats,
+    encoding: Encoding,
+    /// Own move in the last round.
+    previous: Option<usize>,
+}
+
+impl<'a> Frozen<'a> {
+    fn new(network: &RPSNetwork, rules: &'a Beats, encoding: Encoding) -> Self {
+        let mut network = network.clone();
+        network.reset_history();
+        network.evaluate();
+        Self {
+            network,
+            rules,
+            encoding,
+            previous: None,
+        }
+    }
+}
+
+impl Player for Frozen<'_> {
+    fn next_move(&mut self) -> usize {
+        self.network
+            .counter_move(self.rules)
+            .expect("the rules cover every output")
+    }
+
+    fn record(&mut self, own: usize, opponent: usize) -> Result<(), Error> {
+        let outcome = self.rules.judge(opponent, own)?;
+        let moves = self.rules.moves();
+        let input = self
+            .encoding
+            .encode_after(opponent, own, outcome, self.previous, moves)?;
+        self.previous = Some(own);
+        self.network.forward(&input)
+    }
+
+    fn probs(&self) -> Option<Vec<Real>> {
+        Some(self.network.probs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evolution() -> Evolution {
+        let config = NetworkConfig::new().moves(3).history_size(2);
+        Evolution::new(&config, 12, 42).unwrap()
+    }
+
+    #[test]
+    fn improves_fitness() {
+        let mut evolution = evolution();
+        evolution.clear_opponents();
+        for bot in [
+            ScriptedBot::constant(1, 3).unwrap(),
+            ScriptedBot::cycle(&[0, 1, 2], 3).unwrap(),
+        ] {
+            evolution.add_opponent(bot).unwrap();
+        }
+        let initial = evolution.evolve(0).unwrap();
+        let mut best = initial;
+        for _ in 0..5 {
+            // The elite is kept and evaluated the same way, so the best
+            // fitness never drops
+            let fitness = evolution.evolve(2).unwrap();
+            assert!(fitness >= best);
+            best = fitness;
+        }
+        assert!(best > initial, "{} {}", initial, best);
+        assert_eq!(evolution.generation(), 10);
+        let fitness = evolution.fitness();
+        assert_eq!(fitness.len(), 12);
+        assert!(fitness.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn learns_single_opponent() {
+        let mut evolution = evolution();
+        evolution.clear_opponents();
+        evolution
+            .add_opponent(ScriptedBot::constant(0, 3).unwrap())
+            .unwrap();
+        evolution.set_rounds(20).unwrap();
+        assert!(evolution.evolve(10).unwrap() > 0.9);
+
+        // Paper beats rock from the first round
+        let mut best = evolution.best();
+        assert_eq!(best.counter_move(&Beats::rock_paper_scissors()).unwrap(), 1);
+        best.forward(&[1.0, 0.0, 0.0]).unwrap();
+        assert_eq!(best.counter_move(&Beats::rock_paper_scissors()).unwrap(), 1);
+    }
+
+    #[test]
+    fn invalid() {
+        let config = NetworkConfig::new().moves(3);
+        assert!(Evolution::new(&config, 1, 0).is_err());
+        assert!(Evolution::new(&NetworkConfig::new().input_size(4), 4, 0).is_err());
+        let mut evolution = Evolution::new(&config, 4, 0).unwrap();
+        assert!(evolution.set_mutation(0.0).is_err());
+        assert!(evolution.set_elite(4).is_err());
+        assert!(evolution.set_rounds(0).is_err());
+        assert!(evolution
+            .add_opponent(ScriptedBot::constant(0, 5).unwrap())
+            .is_err());
+        evolution.clear_opponents();
+        assert!(evolution.evolve(1).is_err());
+    }
+}
@@ -0,0 +1,225 @@
+//! C API, for game clients that can't load the wasm-bindgen output, such as
+//! Unity or C++ engines. Networks are opaque pointers created by
+//! `rps_network_new()` or `rps_network_from_bytes()` and released with
+//! `rps_network_free()`. Values are `float`, or `double` with the `f64`
+//! feature.
+//!
+//! Fallible functions return 0 or a non-null pointer on success, and -1 or
+//! null on failure, in which case `rps_network_last_error()` describes the
+//! error.
+
+use alloc::boxed::Box;
+use alloc::ffi::CString;
+use alloc::string::ToString;
+use core::ffi::c_char;
+use core::{ptr, slice};
+use std::cell::RefCell;
+
+use crate::{Error, NetworkConfig, RPSNetwork, Real};
+
+std::thread_local! {
+    /// Message of the last error on this thread.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Records the error of `result`, if any, and returns its value.
+fn check<T>(result: Result<T, Error>) -> Option<T> {
+    result
+        .map_err(|error| {
+            // Messages don't contain NUL bytes
+            let message = CString::new(error.to_string()).unwrap_or_default();
+            LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+        })
+        .ok()
+}
+
+fn status(result: Result<(), Error>) -> i32 {
+    check(result).map_or(-1, |_| 0)
+}
+
+/// Message of the last error on the calling thread, or null if there was
+/// none. The string is valid until the next failing call on the thread.
+#[no_mangle]
+pub extern "C" fn rps_network_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
+
+/// Creates a network like `RPSNetwork::new_with_seed()`, or returns null if
+/// a size is invalid.
+#[no_mangle]
+pub extern "C" fn rps_network_new(
+    input_size: usize,
+    history_size: usize,
+    hidden_size: usize,
+    output_size: usize,
+    seed: u64,
+) -> *mut RPSNetwork {
+    let config =
+        NetworkConfig::with_sizes(input_size, history_size, hidden_size, output_size).seed(seed);
+    check(config.build()).map_or(ptr::null_mut(), |network| Box::into_raw(Box::new(network)))
+}
+
+/// Releases a network. Null is ignored.
+///
+/// # Safety
+///
+/// `network` must be null or come from this API, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rps_network_free(network: *mut RPSNetwork) {
+    if !network.is_null() {
+        drop(Box::from_raw(network));
+    }
+}
+
+/// Calls `RPSNetwork::forward()` with the `len` values at `input`.
+///
+/// # Safety
+///
+/// `network` must be a live network and `input` point to `len` values.
+#[no_mangle]
+pub unsafe extern "C" fn rps_network_forward(
+    network: *mut RPSNetwork,
+    input: *const Real,
+    len: usize,
+) -> i32 {
+    let input = if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(input, len)
+    };
+    status((*network).forward(input))
+}
+
+/// Calls `RPSNetwork::backward()`, with the configured learning rate when
+/// `learning_rate` isn't positive.
+///
+/// # Safety
+///
+/// `network` must be a live network.
+#[no_mangle]
+pub unsafe extern "C" fn rps_network_backward(
+    network: *mut RPSNetwork,
+    label: usize,
+    learning_rate: Real,
+) -> i32 {
+    let learning_rate = (learning_rate > 0.0).then_some(learning_rate);
+    status((*network).backward(label, learning_rate))
+}
+
+/// Copies up to `len` values of `RPSNetwork::probs()` to `out`, and returns
+/// the number of values, `output_size`, so that a null `out` queries it.
+///
+/// # Safety
+///
+/// `network` must be a live network and `out` null or point to room for
+/// `len` values.
+#[no_mangle]
+pub unsafe extern "C" fn rps_network_probs(
+    network: *const RPSNetwork,
+    out: *mut Real,
+    len: usize,
+) -> usize {
+    let probs = &(*network).mixed_probs;
+    if !out.is_null() {
+        let n = len.min(probs.len());
+        ptr::copy_nonoverlapping(probs.as_ptr(), out, n);
+    }
+    probs.len()
+}
+
+/// Serializes a network like `RPSNetwork::to_bytes()`, storing the length
+/// in `len`. Release the buffer with `rps_network_bytes_free()`.
+///
+/// # Safety
+///
+/// `network` must be a live network and `len` point to a `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn rps_network_to_bytes(
+    network: *const RPSNetwork,
+    len: *mut usize,
+) -> *mut u8 {
+    match check((*network).to_bytes()) {
+        Some(bytes) => {
+            let bytes = bytes.into_boxed_slice();
+            *len = bytes.len();
+            Box::into_raw(bytes) as *mut u8
+        }
+        None => ptr::null_mut(),
+    }
+}
+
+/// Releases a buffer of `rps_network_to_bytes()`. Null is ignored.
+///
+/// # Safety
+///
+/// `bytes` must be null or come from `rps_network_to_bytes()` along with
+/// `len`, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rps_network_bytes_free(bytes: *mut u8, len: usize) {
+    if !bytes.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(bytes, len)));
+    }
+}
+
+/// Loads a network serialized by `rps_network_to_bytes()`, or returns null
+/// if the bytes aren't a valid model.
+///
+/// # Safety
+///
+/// `bytes` must point to `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rps_network_from_bytes(bytes: *const u8, len: usize) -> *mut RPSNetwork {
+    let bytes = if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(bytes, len)
+    };
+    check(RPSNetwork::from_bytes(bytes))
+        .map_or(ptr::null_mut(), |network| Box::into_raw(Box::new(network)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ffi::CStr;
+
+    #[test]
+    fn round_trips() {
+        unsafe {
+            let network = rps_network_new(3, 5, 10, 3, 42);
+            assert!(!network.is_null());
+            assert_eq!(rps_network_probs(network, ptr::null_mut(), 0), 3);
+            for _ in 0..20 {
+                assert_eq!(rps_network_forward(network, [1.0, 0.0, 0.0].as_ptr(), 3), 0);
+                assert_eq!(rps_network_backward(network, 0, 0.0), 0);
+            }
+            let mut probs = [0.0; 3];
+            rps_network_probs(network, probs.as_mut_ptr(), 3);
+            assert!(probs[0] > 0.5);
+
+            let mut len = 0;
+            let bytes = rps_network_to_bytes(network, &mut len);
+            let copy = rps_network_from_bytes(bytes, len);
+            rps_network_bytes_free(bytes, len);
+            let mut copied = [0.0; 3];
+            rps_network_probs(copy, copied.as_mut_ptr(), 3);
+            assert_eq!(copied, probs);
+            rps_network_free(copy);
+            rps_network_free(network);
+        }
+    }
+
+    #[test]
+    fn reports_errors() {
+        unsafe {
+            assert!(rps_network_new(3, 0, 10, 3, 42).is_null());
+            let network = rps_network_new(3, 5, 10, 3, 42);
+            assert_eq!(rps_network_forward(network, [1.0].as_ptr(), 1), -1);
+            let message = CStr::from_ptr(rps_network_last_error());
+            assert!(message.to_str().unwrap().contains("shape mismatch"));
+            assert_eq!(rps_network_backward(network, 3, 0.1), -1);
+            assert!(rps_network_from_bytes([1, 2, 3].as_ptr(), 3).is_null());
+            rps_network_free(network);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "serde")]
+use crate::Error;
+use crate::{Beats, DriftResponse, Encoding, NetworkConfig, Outcome, Real, Schedule};
+
+/// Every round of a game, as recorded by `GameEngine::set_recording()`, for
+/// analyzing sessions offline or attaching them to a report about the
+/// behavior of a model.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GameLog {
+    /// State of the game when recording started.
+    pub(crate) setup: Option<Setup>,
+    rounds: Vec<RoundRecord>,
+}
+
+/// What `GameEngine::replay()` needs besides the seed to rebuild the
+/// network and engine of a recorded game.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Setup {
+    pub(crate) config: NetworkConfig,
+    pub(crate) rules: Beats,
+    pub(crate) encoding: Encoding,
+    pub(crate) schedule: Schedule,
+    pub(crate) temperature: Real,
+    pub(crate) exploration: Real,
+    pub(crate) history_decay: Real,
+    pub(crate) drift_threshold: Real,
+    pub(crate) drift_response: DriftResponse,
+    pub(crate) training: bool,
+}
+
+/// A round of a `GameLog`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundRecord {
+    /// Number of the round in the game, from 0.
+    pub round: u32,
+    /// Move of the player.
+    pub player: usize,
+    /// Move of the bot.
+    pub bot: usize,
+    /// Outcome of the round for the player.
+    pub outcome: Outcome,
+    /// Distribution predicted for the player's move before it was played,
+    /// as returned by `RPSNetwork::probs()`.
+    pub probs: Vec<Real>,
+    /// Cross-entropy loss of that prediction.
+    pub loss: Real,
+    /// Time the round was played, in milliseconds since the Unix epoch, or
+    /// 0 without the `std` feature.
+    pub timestamp: f64,
+    /// Position of the network's RNG before the round, so that a replay
+    /// draws the same dropout masks and weights.
+    pub(crate) rng_position: u128,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl GameLog {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of rounds recorded.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn len(&self) -> usize {
+        self.rounds.len()
+    }
+
+    /// Whether no round was recorded.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn is_empty(&self) -> bool {
+        self.rounds.is_empty()
+    }
+
+    /// Serializes the log to JSON, with the rounds in order under `rounds`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Restores a log serialized with `to_json()`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn from_json(json: &str) -> Result<GameLog, Error> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Formats the log as CSV, one line per round after a header:
+    /// `round,timestamp,player,bot,outcome,loss`, then one `p{i}` column per
+    /// move for the predicted distribution.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn to_csv(&self) -> String {
+        let moves = self.rounds.first().map_or(0, |round| round.probs.len());
+        let mut csv = String::from("round,timestamp,player,bot,outcome,loss");
+        for i in 0..moves {
+            write!(csv, ",p{}", i).unwrap();
+        }
+        csv.push('\n');
+        for round in &self.rounds {
+            write!(
+                csv,
+                "{},{},{},{},{:?},{}",
+                round.round, round.timestamp, round.player, round.bot, round.outcome, round.loss
+            )
+            .unwrap();
+            for p in &round.probs {
+                write!(csv, ",{}", p).unwrap();
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+impl GameLog {
+    /// Rounds recorded, in the order played.
+    pub fn rounds(&self) -> &[RoundRecord] {
+        &self.rounds
+    }
+
+    /// Keeps the first `rounds` rounds, e.g. to replay a game up to the
+    /// round where the model started to misbehave.
+    pub fn truncate(&mut self, rounds: usize) {
+        self.rounds.truncate(rounds);
+    }
+
+    /// Appends `round`, stamped with the current time.
+    pub(crate) fn record(&mut self, mut round: RoundRecord) {
+        #[cfg(feature = "std")]
+        {
+            round.timestamp = crate::clock::unix_ms();
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            round.timestamp = 0.0;
+        }
+        self.rounds.push(round);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log() -> GameLog {
+        let mut log = GameLog::new();
+        for (round, player, bot, outcome) in [(0, 0, 2, Outcome::Win), (1, 1, 1, Outcome::Draw)] {
+            log.record(RoundRecord {
+                round,
+                player,
+                bot,
+                outcome,
+                probs: alloc::vec![0.5, 0.25, 0.25],
+                loss: 0.5,
+                timestamp: 0.0,
+                rng_position: 0,
+            });
+        }
+        log
+    }
+
+    #[test]
+    fn to_csv() {
+        let mut log = log();
+        assert_eq!(log.len(), 2);
+        for round in &mut log.rounds {
+            round.timestamp = 1000.0;
+        }
+
+        assert_eq!(
+            log.to_csv(),
+            "round,timestamp,player,bot,outcome,loss,p0,p1,p2\n\
+             0,1000,0,2,Win,0.5,0.5,0.25,0.25\n\
+             1,1000,1,1,Draw,0.5,0.5,0.25,0.25\n"
+        );
+        assert_eq!(
+            GameLog::new().to_csv(),
+            "round,timestamp,player,bot,outcome,loss\n"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip() {
+        let log = log();
+        assert!(log.rounds().iter().all(|round| round.timestamp > 0.0));
+
+        assert_eq!(GameLog::from_json(&log.to_json().unwrap()), Ok(log));
+        assert!(GameLog::from_json("{}").is_err());
+    }
+}
@@ -0,0 +1,224 @@
+// Finite-difference checks of the hand-written backpropagation of both
+// network implementations.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::optim::OptimizerState;
+use crate::{check_label, Error, LegacyRPSNetwork, Optimizer, RPSNetwork, Real};
+
+/// Step of the central differences.
+const EPSILON: Real = 1e-2;
+/// Gradients smaller than this are compared absolutely, as finite
+/// differences can't resolve them in single precision.
+const FLOOR: Real = 1e-2;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl RPSNetwork {
+    /// Compares the gradients computed by `backward()` with central finite
+    /// differences of the loss, and returns the largest relative error of
+    /// each parameter tensor, in the order of `get_weights()`. The network
+    /// is copied with an empty history, plays the rounds of `inputs`,
+    /// `input_size` values each, and is trained towards `label`, without
+    /// dropout, weight decay, label smoothing or clipping. Recurrent layers
+    /// are only checked exactly when `inputs` fits in their BPTT window.
+    /// Errors above 1e-2 point to a bug in the backpropagation, unless a
+    /// ReLU sits right at its kink.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn grad_check(&self, inputs: &[Real], label: usize) -> Result<Vec<Real>, Error> {
+        check_rounds(inputs, self.input_size)?;
+        check_label(label, self.output_size)?;
+
+        let mut fresh = self.clone();
+        fresh.reset_history();
+        fresh.optim = OptimizerState::new(Optimizer::Sgd);
+        fresh.weight_decay = 0.0;
+        fresh.max_grad_norm = None;
+        fresh.config.label_smoothing = 0.0;
+        fresh.training = false;
+        fresh.undo = None;
+        let loss = |network: &mut RPSNetwork| {
+            for input in inputs.chunks(network.input_size) {
+                network.forward(input).unwrap();
+            }
+            network.loss(label).unwrap()
+        };
+
+        // A step of plain SGD with a learning rate of 1 subtracts the
+        // gradient from the weights
+        let mut trained = fresh.clone();
+        loss(&mut trained);
+        trained.update(label, None, Some(1.0));
+
+        let errors = fresh
+            .params()
+            .iter()
+            .zip(trained.params())
+            .enumerate()
+            .map(|(k, (before, after))| {
+                let analytical: Vec<Real> = before.iter().zip(&after).map(|(b, a)| b - a).collect();
+                max_error(&analytical, |j, delta| {
+                    let mut network = fresh.clone();
+                    *network.params_mut()[k].iter_mut().nth(j).unwrap() += delta;
+                    loss(&mut network)
+                })
+            })
+            .collect();
+        Ok(errors)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl LegacyRPSNetwork {
+    /// Same as `RPSNetwork::grad_check()`, for the tensors `w1`, `b1`, `w2`
+    /// and `b2`, in that order.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn grad_check(&self, inputs: &[Real], label: usize) -> Result<Vec<Real>, Error> {
+        check_rounds(inputs, self.input_size)?;
+        check_label(label, self.output_size)?;
+
+        let mut fresh = self.clone();
+        fresh.history.fill(0.0);
+        fresh.optim = OptimizerState::new(Optimizer::Sgd);
+        fresh.weight_decay = 0.0;
+        let loss = |network: &mut LegacyRPSNetwork| {
+            for input in inputs.chunks(network.input_size) {
+                network.forward(input).unwrap();
+            }
+            network.loss(label).unwrap()
+        };
+        let params = |network: &LegacyRPSNetwork| {
+            [
+                network.w1.clone(),
+                network.b1.clone(),
+                network.w2.clone(),
+                network.b2.clone(),
+            ]
+        };
+
+        let mut trained = fresh.clone();
+        loss(&mut trained);
+        trained.backward(label, 1.0)?;
+
+        let errors = params(&fresh)
+            .iter()
+            .zip(params(&trained))
+            .enumerate()
+            .map(|(k, (before, after))| {
+                let analytical: Vec<Real> = before.iter().zip(&after).map(|(b, a)| b - a).collect();
+                max_error(&analytical, |j, delta| {
+                    let mut network = fresh.clone();
+                    let param = match k {
+                        0 => &mut network.w1,
+                        1 => &mut network.b1,
+                        2 => &mut network.w2,
+                        _ => &mut network.b2,
+                    };
+                    param[j] += delta;
+                    loss(&mut network)
+                })
+            })
+            .collect();
+        Ok(errors)
+    }
+}
+
+/// Checks that `inputs` holds one or more rounds of `input_size` values.
+fn check_rounds(inputs: &[Real], input_size: usize) -> Result<(), Error> {
+    if inputs.is_empty() || !inputs.len().is_multiple_of(input_size) {
+        return Err(Error::ShapeMismatch {
+            name: "inputs",
+            expected: vec![input_size],
+            found: vec![inputs.len()],
+        });
+    }
+    Ok(())
+}
+
+/// Largest relative error between the values of `analytical` and central
+/// differences of `loss`, which gives the loss with value `j` moved by
+/// `delta`.
+fn max_error(analytical: &[Real], mut loss: impl FnMut(usize, Real) -> Real) -> Real {
+    analytical
+        .iter()
+        .enumerate()
+        .map(|(j, &analytical)| {
+            let numerical = (loss(j, EPSILON) - loss(j, -EPSILON)) / (2.0 * EPSILON);
+            (analytical - numerical).abs() / analytical.abs().max(numerical.abs()).max(FLOOR)
+        })
+        .fold(0.0, Real::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Architecture, Init, NetworkConfig};
+
+    const INPUTS: [Real; 9] = [0.5, -1.0, 0.0, 1.0, 0.0, 0.3, -0.2, 0.0, 1.0];
+    const TOLERANCE: Real = 1e-2;
+
+    fn config() -> NetworkConfig {
+        NetworkConfig::new()
+            .input_size(3)
+            .history_size(3)
+            .output_size(3)
+            .init(Init::Xavier)
+            .seed(0)
+    }
+
+    fn assert_below_tolerance(errors: &[Real], what: &str) {
+        assert!(
+            errors.iter().all(|e| *e < TOLERANCE),
+            "{}: {:?}",
+            what,
+            errors
+        );
+    }
+
+    #[test]
+    fn feedforward_gradients() {
+        for (what, config) in [
+            ("deep", config().hidden_sizes(&[8, 4])),
+            ("attention", config().hidden_size(8).attention(true)),
+            ("outcome head", config().hidden_size(8).outcome_head(true)),
+        ] {
+            let network = config.build().unwrap();
+            let errors = network.grad_check(&INPUTS, 2).unwrap();
+            assert_eq!(errors.len(), network.parameter_count());
+            assert_below_tolerance(&errors, what);
+        }
+    }
+
+    #[test]
+    fn recurrent_gradients() {
+        for architecture in [Architecture::Elman, Architecture::Lstm, Architecture::Gru] {
+            let network = config()
+                .hidden_size(4)
+                .architecture(architecture)
+                .build()
+                .unwrap();
+            let errors = network.grad_check(&INPUTS, 1).unwrap();
+            assert_below_tolerance(&errors, &alloc::format!("{:?}", architecture));
+        }
+    }
+
+    #[test]
+    fn legacy_gradients() {
+        let network = config().hidden_size(8).build_legacy().unwrap();
+        let errors = network.grad_check(&INPUTS, 0).unwrap();
+        assert_eq!(errors.len(), 4);
+        assert_below_tolerance(&errors, "legacy");
+    }
+
+    #[test]
+    fn rejects_invalid_rounds() {
+        let network = config().hidden_size(8).build().unwrap();
+
+        assert!(network.grad_check(&[], 0).is_err());
+        assert!(network.grad_check(&INPUTS[..4], 0).is_err());
+        assert!(network.grad_check(&INPUTS, 3).is_err());
+    }
+}
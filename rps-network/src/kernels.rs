@@ -0,0 +1,174 @@
+// Matrix-vector products shared by both network implementations. With the
+// `simd` feature, they use WebAssembly SIMD when the target supports it,
+// i.e. when building for wasm32 with `-C target-feature=+simd128`. The
+// kernels are marked `#[inline]`, as the release profile optimizes for size
+// and would otherwise call them once per row.
+
+use ndarray::linalg::general_mat_vec_mul;
+use ndarray::{Array1, ArrayView1, ArrayView2, ArrayViewMut1};
+
+use crate::Real;
+
+#[cfg(not(all(
+    feature = "simd",
+    target_arch = "wasm32",
+    target_feature = "simd128",
+    not(feature = "f64")
+)))]
+pub(crate) use scalar::{axpy, dot};
+#[cfg(all(
+    feature = "simd",
+    target_arch = "wasm32",
+    target_feature = "simd128",
+    not(feature = "f64")
+))]
+pub(crate) use simd128::{axpy, dot};
+
+/// Adds `x · w` to `out`, where `w` is a `(x.len(), out.len())` matrix
+/// flattened row by row.
+pub(crate) fn vec_mat_add(x: &[Real], w: &[Real], out: &mut [Real]) {
+    debug_assert_eq!(x.len() * out.len(), w.len());
+    for (&x, row) in x.iter().zip(w.chunks_exact(out.len())) {
+        axpy(x, row, out);
+    }
+}
+
+/// Writes `x · w` to `out`, for a `(inputs, outputs)` matrix `w`.
+pub(crate) fn vec_mat_into(x: ArrayView1<Real>, w: ArrayView2<Real>, mut out: ArrayViewMut1<Real>) {
+    out.fill(0.0);
+    match (x.as_slice(), w.as_slice(), out.as_slice_mut()) {
+        (Some(xs), Some(ws), Some(outs)) => vec_mat_add(xs, ws, outs),
+        _ => general_mat_vec_mul(1.0, &w.t(), &x, 0.0, &mut out),
+    }
+}
+
+/// `w · x` for a `(outputs, inputs)` matrix `w`.
+pub(crate) fn mat_vec(w: ArrayView2<Real>, x: ArrayView1<Real>) -> Array1<Real> {
+    match (w.as_slice(), x.as_slice()) {
+        (Some(ws), Some(xs)) if !xs.is_empty() => {
+            ws.chunks_exact(xs.len()).map(|row| dot(row, xs)).collect()
+        }
+        _ => w.dot(&x),
+    }
+}
+
+/// Writes `w · x` to `out`, for a `(outputs, inputs)` matrix `w`.
+pub(crate) fn mat_vec_into(w: ArrayView2<Real>, x: ArrayView1<Real>, mut out: ArrayViewMut1<Real>) {
+    match (w.as_slice(), x.as_slice(), out.as_slice_mut()) {
+        (Some(ws), Some(xs), Some(outs)) if !xs.is_empty() => {
+            for (out, row) in outs.iter_mut().zip(ws.chunks_exact(xs.len())) {
+                *out = dot(row, xs);
+            }
+        }
+        _ => general_mat_vec_mul(1.0, &w, &x, 0.0, &mut out),
+    }
+}
+
+#[cfg(not(all(
+    feature = "simd",
+    target_arch = "wasm32",
+    target_feature = "simd128",
+    not(feature = "f64")
+)))]
+mod scalar {
+    use crate::Real;
+
+    /// Dot product of `a` and `b`, which must have the same length.
+    #[inline]
+    pub(crate) fn dot(a: &[Real], b: &[Real]) -> Real {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    /// Adds `alpha * x` to `y`, which must have the same length.
+    #[inline]
+    pub(crate) fn axpy(alpha: Real, x: &[Real], y: &mut [Real]) {
+        for (y, x) in y.iter_mut().zip(x) {
+            *y += alpha * x;
+        }
+    }
+}
+
+#[cfg(all(
+    feature = "simd",
+    target_arch = "wasm32",
+    target_feature = "simd128",
+    not(feature = "f64")
+))]
+mod simd128 {
+    use core::arch::wasm32::*;
+
+    #[inline]
+    pub(crate) fn dot(a: &[f32], b: &[f32]) -> f32 {
+        let len = a.len().min(b.len());
+        let n = len - len % 4;
+        let mut acc = f32x4_splat(0.0);
+        for i in (0..n).step_by(4) {
+            // SAFETY: `i + 3 < n`, which is within both slices, and
+            // `v128_load` doesn't need aligned pointers.
+            let (x, y) = unsafe {
+                (
+                    v128_load(a.as_ptr().add(i) as *const v128),
+                    v128_load(b.as_ptr().add(i) as *const v128),
+                )
+            };
+            acc = f32x4_add(acc, f32x4_mul(x, y));
+        }
+        let sum = f32x4_extract_lane::<0>(acc)
+            + f32x4_extract_lane::<1>(acc)
+            + f32x4_extract_lane::<2>(acc)
+            + f32x4_extract_lane::<3>(acc);
+        sum + a[n..len]
+            .iter()
+            .zip(&b[n..len])
+            .map(|(x, y)| x * y)
+            .sum::<f32>()
+    }
+
+    #[inline]
+    pub(crate) fn axpy(alpha: f32, x: &[f32], y: &mut [f32]) {
+        let len = x.len().min(y.len());
+        let n = len - len % 4;
+        let alpha4 = f32x4_splat(alpha);
+        for i in (0..n).step_by(4) {
+            // SAFETY: as in `dot()`
+            unsafe {
+                let py = y.as_mut_ptr().add(i) as *mut v128;
+                let px = x.as_ptr().add(i) as *const v128;
+                v128_store(
+                    py,
+                    f32x4_add(v128_load(py), f32x4_mul(alpha4, v128_load(px))),
+                );
+            }
+        }
+        for (y, x) in y[n..len].iter_mut().zip(&x[n..len]) {
+            *y += alpha * x;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{arr1, arr2};
+
+    #[test]
+    fn products() {
+        let w = arr2(&[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+
+        let mut out = arr1(&[1.0; 3]);
+        vec_mat_into(arr1(&[1.0, -1.0]).view(), w.view(), out.view_mut());
+        assert_eq!(out, arr1(&[-3.0, -3.0, -3.0]));
+        assert_eq!(
+            mat_vec(w.view(), arr1(&[1.0, 0.0, -1.0]).view()),
+            arr1(&[-2.0, -2.0])
+        );
+        let mut out = arr1(&[1.0; 2]);
+        mat_vec_into(w.view(), arr1(&[1.0, 0.0, -1.0]).view(), out.view_mut());
+        assert_eq!(out, arr1(&[-2.0, -2.0]));
+        // Strided views fall back to ndarray
+        let mut out = arr1(&[0.0; 2]);
+        vec_mat_into(arr1(&[1.0, 1.0, 1.0]).view(), w.t(), out.view_mut());
+        assert_eq!(out, arr1(&[6.0, 15.0]));
+        assert_eq!(dot(&[1.0, 2.0, 3.0, 4.0, 5.0], &[1.0; 5]), 15.0);
+    }
+}
@@ -0,0 +1,154 @@
+use ndarray::{
+    s, Array1, Array2, ArrayView1, ArrayView2, ArrayViewMut1, ArrayViewMut2, Axis, Ix1, Zip,
+};
+use rand::Rng;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::kernels::vec_mat_into;
+use crate::{fit, keep_first, Activation, Init, Real};
+
+/// A fully connected layer. Hidden layers apply an activation and dropout;
+/// the output layer has no activation and leaves its logits to the softmax.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Layer {
+    /// `(inputs, outputs)` weight matrix.
+    pub(crate) weights: Array2<Real>,
+    pub(crate) bias: Array1<Real>,
+    pub(crate) activation: Option<Activation>,
+    /// Weighted input of the last `forward()`, before the activation.
+    pub(crate) pre_activation: Array1<Real>,
+    /// Output of the last `forward()`, after the activation but before
+    /// dropout.
+    pub(crate) output: Array1<Real>,
+    /// Scale applied to each output by the last `forward()`: `0` for dropped
+    /// units, `1 / (1 - dropout)` for kept ones, `1` outside training.
+    pub(crate) dropout_mask: Array1<Real>,
+}
+
+impl Layer {
+    pub(crate) fn new<R: Rng>(
+        inputs: usize,
+        outputs: usize,
+        activation: Option<Activation>,
+        init: Init,
+        rng: &mut R,
+    ) -> Self {
+        Self {
+            weights: Array2::from_shape_simple_fn((inputs, outputs), || {
+                init.sample(inputs, outputs, rng)
+            }),
+            bias: Array1::zeros(outputs),
+            activation,
+            pre_activation: Array1::zeros(outputs),
+            output: Array1::zeros(outputs),
+            dropout_mask: Array1::ones(outputs),
+        }
+    }
+
+    pub(crate) fn outputs(&self) -> usize {
+        self.weights.ncols()
+    }
+
+    /// Computes the output of the layer for `input`, dropping each unit with
+    /// probability `dropout`.
+    pub(crate) fn forward<R: Rng>(&mut self, input: ArrayView1<Real>, dropout: Real, rng: &mut R) {
+        vec_mat_into(input, self.weights.view(), self.pre_activation.view_mut());
+        self.pre_activation += &self.bias;
+        let activation = self.activation;
+        self.output.zip_mut_with(&self.pre_activation, |y, &v| {
+            *y = activation.map_or(v, |activation| activation.apply(v))
+        });
+
+        if dropout > 0.0 {
+            let keep = 1.0 - dropout;
+            self.dropout_mask.mapv_inplace(|_| {
+                if rng.gen::<Real>() < keep {
+                    1.0 / keep
+                } else {
+                    0.0
+                }
+            });
+        } else {
+            self.dropout_mask.fill(1.0);
+        }
+    }
+
+    /// Outputs of the layer for each row of `input`, without dropout and
+    /// without touching the state of the last `forward()`.
+    pub(crate) fn forward_batch(&self, input: ArrayView2<Real>) -> Array2<Real> {
+        let a = input.dot(&self.weights) + &self.bias;
+        match self.activation {
+            Some(activation) => a.mapv(|v| activation.apply(v)),
+            None => a,
+        }
+    }
+
+    /// Resizes the layer to `outputs` units, keeping the weights of the
+    /// first ones and drawing the incoming weights of new ones from `init`.
+    pub(crate) fn resize_outputs<R: Rng>(&mut self, outputs: usize, init: Init, rng: &mut R) {
+        let inputs = self.weights.nrows();
+        let kept = outputs.min(self.outputs());
+        let mut weights = keep_first(self.weights.view(), inputs, outputs);
+        weights
+            .slice_mut(s![.., kept..])
+            .mapv_inplace(|_| init.sample(inputs, outputs, rng));
+        self.weights = weights;
+        let bias = self.bias.view().insert_axis(Axis(0));
+        self.bias = keep_first(bias, 1, outputs).remove_axis(Axis(0));
+        self.pre_activation = Array1::zeros(outputs);
+        self.output = Array1::zeros(outputs);
+        self.dropout_mask = Array1::ones(outputs);
+    }
+
+    /// Resizes the layer to `inputs` inputs, keeping the weights of the first
+    /// ones and starting those of new ones at zero.
+    pub(crate) fn resize_inputs(&mut self, inputs: usize) {
+        self.weights = keep_first(self.weights.view(), inputs, self.outputs());
+    }
+
+    /// Output of the last `forward()` as seen by the next layer.
+    pub(crate) fn dropped_output(&self) -> Array1<Real> {
+        &self.output * &self.dropout_mask
+    }
+
+    /// Writes `dropped_output()` to `out`, which only allocates if `out`
+    /// doesn't have the size of the layer yet.
+    pub(crate) fn dropped_output_into(&self, out: &mut Array1<Real>) {
+        fit(out, Ix1(self.outputs()));
+        Zip::from(out)
+            .and(&self.output)
+            .and(&self.dropout_mask)
+            .for_each(|y, &output, &mask| *y = output * mask);
+    }
+
+    /// Turns the gradient of the loss with respect to `dropped_output()` into
+    /// the gradient with respect to the pre-activation values.
+    pub(crate) fn backprop(&self, mut doutput: Array1<Real>) -> Array1<Real> {
+        self.backprop_in_place(doutput.view_mut());
+        doutput
+    }
+
+    /// `backprop()` without allocating.
+    pub(crate) fn backprop_in_place(&self, doutput: ArrayViewMut1<Real>) {
+        let activation = self.activation.expect("only hidden layers backpropagate");
+        Zip::from(doutput)
+            .and(&self.dropout_mask)
+            .and(&self.output)
+            .for_each(|d, &mask, &y| *d = *d * mask * activation.derivative(y));
+    }
+}
+
+/// Outer product of two vectors, as a `(a.len(), b.len())` matrix.
+pub(crate) fn outer(a: ArrayView1<Real>, b: ArrayView1<Real>) -> Array2<Real> {
+    a.insert_axis(Axis(1)).dot(&b.insert_axis(Axis(0)))
+}
+
+/// Writes `outer(a, b)` to `out`.
+pub(crate) fn outer_into(a: ArrayView1<Real>, b: ArrayView1<Real>, mut out: ArrayViewMut2<Real>) {
+    for (mut row, &a) in out.rows_mut().into_iter().zip(&a) {
+        Zip::from(&mut row).and(&b).for_each(|out, &b| *out = a * b);
+    }
+}
@@ -1,69 +1,79 @@
+// The legacy network is written with plain index loops over flat buffers.
+#![allow(clippy::needless_range_loop)]
+
+#[cfg(feature = "serde")]
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use ndarray::{ArrayView1, ArrayViewMut1};
 use rand::Rng;
-use std::f32;
 
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-// When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
-// allocator.
-#[cfg(feature = "wee_alloc")]
-#[global_allocator]
-static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::kernels::{dot, vec_mat_add};
+#[cfg(feature = "serde")]
+use crate::migration::{self, Saved, Saving};
+use crate::optim::OptimizerState;
+use crate::{check_input, check_label, cross_entropy, entropy};
+use crate::{Activation, Beats, Error, Init, NetworkConfig, Prediction, Real};
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
 pub struct LegacyRPSNetwork {
     pub input_size: usize,
     pub history_size: usize,
     pub hidden_size: usize,
     pub output_size: usize,
-    history: Vec<f32>,
-    w1: Vec<f32>,
-    b1: Vec<f32>,
-    hidden: Vec<f32>,
-    w2: Vec<f32>,
-    b2: Vec<f32>,
-    probs: Vec<f32>,
+    pub(crate) history: Vec<Real>,
+    pub(crate) w1: Vec<Real>,
+    pub(crate) b1: Vec<Real>,
+    hidden: Vec<Real>,
+    pub(crate) w2: Vec<Real>,
+    pub(crate) b2: Vec<Real>,
+    probs: Vec<Real>,
+    pub(crate) activation: Activation,
+    pub(crate) optim: OptimizerState,
+    pub(crate) weight_decay: Real,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    scratch: Scratch,
+}
+
+/// Gradients reused by every `backward()`, sized on first use.
+#[derive(Clone, Default)]
+struct Scratch {
+    dprobs: Vec<Real>,
+    dhidden: Vec<Real>,
+    dw1: Vec<Real>,
+    dw2: Vec<Real>,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl LegacyRPSNetwork {
-    #[wasm_bindgen(constructor)]
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new(
         input_size: usize,
         history_size: usize,
         hidden_size: usize,
         output_size: usize,
-    ) -> Self {
-        let mut rng = rand::thread_rng();
-
-        let history = vec![0.0; input_size * history_size];
-        let w1 = (0..input_size * history_size * hidden_size)
-            .map(|_| rng.gen::<f32>() * 0.2 - 0.1)
-            .collect();
-        let b1 = vec![0.0; hidden_size];
-        let hidden = vec![0.0; hidden_size];
-        let w2 = (0..hidden_size * output_size)
-            .map(|_| rng.gen::<f32>() * 0.2 - 0.1)
-            .collect();
-        let b2 = vec![0.0; output_size];
-        let probs = vec![1.0 / (output_size as f32); output_size];
-
-        Self {
-            input_size,
-            history_size,
-            hidden_size,
-            output_size,
-            w1,
-            b1,
-            w2,
-            b2,
-            history,
-            hidden,
-            probs,
-        }
+    ) -> Result<LegacyRPSNetwork, Error> {
+        let config = NetworkConfig::with_sizes(input_size, history_size, hidden_size, output_size);
+        config.validate()?;
+        Ok(Self::with_rng(&config, &mut rand::thread_rng()))
     }
 
-    #[wasm_bindgen]
-    pub fn forward(&mut self, input: &[f32]) {
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        check_input(input, self.input_size)?;
+
         // Shift history items and add new item
         for i in 1..self.history_size {
             for j in 0..self.input_size {
@@ -75,27 +85,25 @@ impl LegacyRPSNetwork {
         }
 
         // Compute hidden layer activations
+        self.hidden.fill(0.0);
+        vec_mat_add(&self.history, &self.w1, &mut self.hidden);
         for i in 0..self.hidden_size {
-            let mut h = 0.0;
-            for j in 0..self.input_size * self.history_size {
-                h += self.history[j] * self.w1[j * self.hidden_size + i];
-            }
-            h += self.b1[i];
-            self.hidden[i] = h.tanh();
+            self.hidden[i] = self.activation.apply(self.hidden[i] + self.b1[i]);
         }
 
         // Compute output probabilities
+        self.probs.fill(0.0);
+        vec_mat_add(&self.hidden, &self.w2, &mut self.probs);
         for i in 0..self.output_size {
-            let mut o = 0.0;
-            for j in 0..self.hidden_size {
-                o += self.hidden[j] * self.w2[j * self.output_size + i];
-            }
-            o += self.b2[i];
-            self.probs[i] = o;
+            self.probs[i] += self.b2[i];
         }
 
         // Apply softmax to output probabilities
-        let max_probs = self.probs.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let max_probs = self
+            .probs
+            .iter()
+            .cloned()
+            .fold(Real::NEG_INFINITY, Real::max);
         let mut sum = 0.0;
         for i in 0..self.output_size {
             self.probs[i] = (self.probs[i] - max_probs).exp();
@@ -104,12 +112,25 @@ impl LegacyRPSNetwork {
         for i in 0..self.output_size {
             self.probs[i] /= sum;
         }
+        Ok(())
     }
 
-    #[wasm_bindgen]
-    pub fn backward(&mut self, label: usize, learning_rate: f32) {
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn backward(&mut self, label: usize, learning_rate: Real) -> Result<(), Error> {
+        check_label(label, self.output_size)?;
+        let mut scratch = core::mem::take(&mut self.scratch);
+        let Scratch {
+            dprobs,
+            dhidden,
+            dw1,
+            dw2,
+        } = &mut scratch;
+        dprobs.resize(self.output_size, 0.0);
+        dhidden.resize(self.hidden_size, 0.0);
+        dw1.resize(self.input_size * self.history_size * self.hidden_size, 0.0);
+        dw2.resize(self.hidden_size * self.output_size, 0.0);
+
         // Compute the error between the predicted and actual output
-        let mut dprobs = vec![0.0; self.output_size];
         for i in 0..self.output_size {
             if i == label {
                 dprobs[i] = self.probs[i] - 1.0;
@@ -119,37 +140,369 @@ impl LegacyRPSNetwork {
         }
 
         // Compute the hidden layer gradient
-        let mut dhidden = vec![0.0; self.hidden_size];
         for i in 0..self.hidden_size {
-            let mut dh = 0.0;
-            for j in 0..self.output_size {
-                dh += self.w2[i * self.output_size + j] * dprobs[j];
-            }
-            dh *= 1.0 - self.hidden[i] * self.hidden[i];
-            dhidden[i] = dh;
+            let row = &self.w2[i * self.output_size..(i + 1) * self.output_size];
+            dhidden[i] = dot(row, dprobs) * self.activation.derivative(self.hidden[i]);
         }
 
-        // Update the weights and biases
+        // Compute the weight gradients, decaying the weights towards zero
         for i in 0..self.hidden_size {
             for j in 0..self.output_size {
-                self.w2[i * self.output_size + j] -= learning_rate * self.hidden[i] * dprobs[j];
+                dw2[i * self.output_size + j] = self.hidden[i] * dprobs[j]
+                    + self.weight_decay * self.w2[i * self.output_size + j];
             }
         }
         for i in 0..self.input_size * self.history_size {
             for j in 0..self.hidden_size {
-                self.w1[i * self.hidden_size + j] -= learning_rate * self.history[i] * dhidden[j];
+                dw1[i * self.hidden_size + j] = self.history[i] * dhidden[j]
+                    + self.weight_decay * self.w1[i * self.hidden_size + j];
             }
         }
-        for i in 0..self.hidden_size {
-            self.b1[i] -= learning_rate * dhidden[i];
-        }
-        for i in 0..self.output_size {
-            self.b2[i] -= learning_rate * dprobs[i];
+
+        // Update the weights and biases
+        self.optim.begin_step();
+        let params = [
+            (&mut self.w1, &*dw1),
+            (&mut self.b1, &*dhidden),
+            (&mut self.w2, &*dw2),
+            (&mut self.b2, &*dprobs),
+        ];
+        for (i, (param, grad)) in params.into_iter().enumerate() {
+            self.optim.update(
+                i,
+                ArrayViewMut1::from(&mut param[..]),
+                ArrayView1::from(&grad[..]),
+                learning_rate,
+            );
         }
+        self.scratch = scratch;
+        Ok(())
+    }
+
+    /// Cross-entropy loss of the last `forward()` against `label`, without
+    /// changing the network.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn loss(&self, label: usize) -> Result<Real, Error> {
+        check_label(label, self.output_size)?;
+        Ok(cross_entropy(self.probs[label]))
+    }
+
+    /// Runs `forward()` on `input` then `backward()` towards `label`, and
+    /// returns the cross-entropy loss of the prediction made before the
+    /// update.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn train_step(
+        &mut self,
+        input: &[Real],
+        label: usize,
+        learning_rate: Real,
+    ) -> Result<Real, Error> {
+        check_label(label, self.output_size)?;
+        self.forward(input)?;
+        let loss = self.loss(label)?;
+        self.backward(label, learning_rate)?;
+        Ok(loss)
     }
 
-    #[wasm_bindgen]
-    pub fn probs(&mut self) -> Vec<f32> {
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn probs(&self) -> Vec<Real> {
         self.probs.clone()
     }
+
+    /// Shannon entropy of `probs()` in nats: 0 when the network is certain of
+    /// the next move, ln(output_size) when it has no idea.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn entropy(&self) -> Real {
+        entropy(&self.probs)
+    }
+
+    /// Most likely opponent move according to `probs()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn predict(&self) -> Prediction {
+        Prediction::argmax(&self.probs)
+    }
+
+    /// Move that beats the predicted one according to `beats`, which must
+    /// cover every output. When several moves beat it, picks the one doing
+    /// best against the whole of `probs()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn counter_move(&self, beats: &Beats) -> Result<usize, Error> {
+        beats.counter(&self.probs, self.output_size)
+    }
+
+    /// Inputs of the last `history_size` rounds, from the oldest to the
+    /// newest, flattened round by round.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn history(&self) -> Vec<Real> {
+        self.history.clone()
+    }
+
+    /// Replaces the history with `history`, flattened like `history()`
+    /// returns it, e.g. to resume a game without replaying its rounds.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn seed_history(&mut self, history: &[Real]) -> Result<(), Error> {
+        if history.len() != self.history.len() {
+            return Err(Error::ShapeMismatch {
+                name: "history",
+                expected: vec![self.history.len()],
+                found: vec![history.len()],
+            });
+        }
+        self.history.copy_from_slice(history);
+        Ok(())
+    }
+
+    /// L2 norms of the weight matrices, from the input layer to the output
+    /// layer.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn weight_norms(&self) -> Vec<Real> {
+        let mut w1 = 0.0;
+        for i in 0..self.w1.len() {
+            w1 += self.w1[i] * self.w1[i];
+        }
+        let mut w2 = 0.0;
+        for i in 0..self.w2.len() {
+            w2 += self.w2[i] * self.w2[i];
+        }
+        vec![Real::sqrt(w1), Real::sqrt(w2)]
+    }
+
+    /// Serializes the network to a JSON string, which
+    /// `RPSNetwork::from_json()` also loads, converting it with
+    /// `RPSNetwork::from_legacy()`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn to_json(&self) -> Result<String, Error> {
+        migration::to_json(Saving::Legacy(self))
+    }
+
+    /// Restores a network previously serialized with `to_json()`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn from_json(json: &str) -> Result<LegacyRPSNetwork, Error> {
+        Self::validated(migration::from_json(json, Saved::unversioned_json)?)
+    }
+
+    /// Serializes the network to a compact binary encoding, which
+    /// `RPSNetwork::from_bytes()` also loads.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        migration::to_bytes(Saving::Legacy(self))
+    }
+
+    /// Restores a network previously serialized with `to_bytes()`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<LegacyRPSNetwork, Error> {
+        Self::validated(migration::from_bytes(bytes, Saved::unversioned_bytes)?)
+    }
+}
+
+impl LegacyRPSNetwork {
+    /// Checks that every buffer has the length implied by the network
+    /// sizes, like `RPSNetwork::from_json()` does.
+    #[cfg(feature = "serde")]
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        let inputs = self.input_size * self.history_size;
+        for (name, expected, found) in [
+            ("history", inputs, self.history.len()),
+            ("w1", inputs * self.hidden_size, self.w1.len()),
+            ("b1", self.hidden_size, self.b1.len()),
+            ("hidden", self.hidden_size, self.hidden.len()),
+            ("w2", self.hidden_size * self.output_size, self.w2.len()),
+            ("b2", self.output_size, self.b2.len()),
+            ("probs", self.output_size, self.probs.len()),
+        ] {
+            if expected != found {
+                return Err(Error::ShapeMismatch {
+                    name,
+                    expected: vec![expected],
+                    found: vec![found],
+                });
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    fn validated(saved: Saved) -> Result<Self, Error> {
+        match saved {
+            Saved::Legacy(legacy) => {
+                legacy.validate()?;
+                Ok(*legacy)
+            }
+            Saved::Network(_) => Err(Error::Unsupported(
+                "the model is an RPSNetwork, not a LegacyRPSNetwork",
+            )),
+            _ => Err(Error::Unsupported("the model isn't a LegacyRPSNetwork")),
+        }
+    }
+
+    pub(crate) fn with_rng<R: Rng>(config: &NetworkConfig, rng: &mut R) -> Self {
+        let NetworkConfig {
+            input_size,
+            history_size,
+            output_size,
+            ..
+        } = *config;
+        let hidden_size = config.hidden_sizes[0];
+
+        let history = vec![0.0; input_size * history_size];
+        let init = config.init.unwrap_or(Init::Uniform);
+        let w1 = (0..input_size * history_size * hidden_size)
+            .map(|_| init.sample(input_size * history_size, hidden_size, rng))
+            .collect();
+        let b1 = vec![0.0; hidden_size];
+        let hidden = vec![0.0; hidden_size];
+        let w2 = (0..hidden_size * output_size)
+            .map(|_| init.sample(hidden_size, output_size, rng))
+            .collect();
+        let b2 = vec![0.0; output_size];
+        let probs = vec![1.0 / (output_size as Real); output_size];
+
+        Self {
+            input_size,
+            history_size,
+            hidden_size,
+            output_size,
+            w1,
+            b1,
+            w2,
+            b2,
+            history,
+            hidden,
+            probs,
+            activation: config.activation,
+            optim: OptimizerState::new(config.optimizer),
+            weight_decay: config.weight_decay,
+            scratch: Scratch::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Optimizer;
+
+    #[test]
+    fn backward_pass() {
+        for optimizer in [Optimizer::Sgd, Optimizer::Adam] {
+            let mut network = NetworkConfig::new()
+                .input_size(3)
+                .history_size(3)
+                .hidden_size(8)
+                .output_size(3)
+                .optimizer(optimizer)
+                .seed(0)
+                .build_legacy()
+                .unwrap();
+
+            let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+            network.forward(&input).unwrap();
+
+            let paper_prob = network.probs()[1];
+
+            for _ in 0..100 {
+                network.backward(1, 0.01).unwrap();
+                network.forward(&input).unwrap();
+            }
+
+            assert!(paper_prob < network.probs()[1]);
+            assert!(network.entropy() < (3.0 as Real).ln());
+            assert_eq!(network.predict().index, 1);
+            assert_eq!(network.counter_move(&Beats::rock_paper_scissors()), Ok(2));
+        }
+    }
+
+    #[test]
+    fn backward_reuses_buffers() {
+        let mut network = NetworkConfig::with_sizes(3, 3, 8, 3)
+            .seed(0)
+            .build_legacy()
+            .unwrap();
+        let buffers = |network: &LegacyRPSNetwork| {
+            let scratch = &network.scratch;
+            [
+                scratch.dprobs.as_ptr(),
+                scratch.dhidden.as_ptr(),
+                scratch.dw1.as_ptr(),
+                scratch.dw2.as_ptr(),
+            ]
+        };
+        network.train_step(&[1.0, 0.0, 0.0], 1, 0.01).unwrap();
+        let expected = buffers(&network);
+        for round in 0..10 {
+            let mut input = [0.0; 3];
+            input[round % 3] = 1.0;
+            network.train_step(&input, round % 3, 0.01).unwrap();
+            assert_eq!(buffers(&network), expected);
+        }
+    }
+
+    #[test]
+    fn train_step_reduces_loss() {
+        let mut network = NetworkConfig::new().seed(0).build_legacy().unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+
+        let first = network.train_step(&input, 1, 0.1).unwrap();
+        let mut last = first;
+        for _ in 0..20 {
+            last = network.train_step(&input, 1, 0.1).unwrap();
+        }
+        assert!(last < first);
+
+        network.forward(&input).unwrap();
+        assert_eq!(network.loss(1).unwrap(), -network.probs()[1].ln());
+        assert!(network.loss(3).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_rounds() {
+        let mut network = NetworkConfig::new().seed(0).build_legacy().unwrap();
+
+        assert!(network.forward(&[1.0, 0.0, 0.0]).is_err());
+        assert!(network.backward(3, 0.1).is_err());
+        assert!(network.train_step(&[0.0; 6], 3, 0.1).is_err());
+    }
+
+    #[test]
+    fn seed_history() {
+        let config = NetworkConfig::new().history_size(2).seed(0);
+        let mut played = config.build_legacy().unwrap();
+        let mut seeded = config.build_legacy().unwrap();
+
+        played.forward(&[1.0, 0.0, 0.0, 0.0, 1.0, 0.0]).unwrap();
+        seeded.seed_history(&played.history()).unwrap();
+
+        played.forward(&[0.0, 0.0, 1.0, 1.0, 0.0, 0.0]).unwrap();
+        seeded.forward(&[0.0, 0.0, 1.0, 1.0, 0.0, 0.0]).unwrap();
+        assert_eq!(seeded.probs(), played.probs());
+        assert!(seeded.seed_history(&[0.0; 6]).is_err());
+    }
+
+    #[test]
+    fn weight_decay_shrinks_weights() {
+        let config = NetworkConfig::new().seed(0);
+        let mut plain = config.build_legacy().unwrap();
+        let mut decayed = config.weight_decay(0.5).build_legacy().unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+
+        for _ in 0..20 {
+            plain.forward(&input).unwrap();
+            plain.backward(1, 0.1).unwrap();
+            decayed.forward(&input).unwrap();
+            decayed.backward(1, 0.1).unwrap();
+        }
+
+        let plain_norms = plain.weight_norms();
+        let decayed_norms = decayed.weight_norms();
+
+        assert!(decayed_norms[0] < plain_norms[0]);
+        assert!(decayed_norms[1] < plain_norms[1]);
+    }
 }
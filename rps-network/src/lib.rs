@@ -1,200 +1,4416 @@
-use ndarray::{arr1, s, Array2};
-use ndarray_rand::rand_distr::StandardNormal;
-use ndarray_rand::RandomExt;
-use ndarray_stats::QuantileExt;
-use std::f32;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use ndarray::{
+    aview1, s, Array, Array1, Array2, ArrayD, ArrayView1, ArrayView2, ArrayViewD, ArrayViewMutD,
+    Axis, Dimension, Ix1, Zip,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+mod attention;
+mod bandit;
+mod baselines;
+mod bots;
+mod calibration;
+mod checkpoint;
+#[cfg(feature = "std")]
+mod clock;
+mod config;
+mod distill;
+mod drift;
+mod elo;
+mod encoding;
+mod engine;
+mod ensemble;
+mod error;
+mod evolution;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod gamelog;
+#[cfg(any(test, feature = "grad-check"))]
+mod gradcheck;
+mod kernels;
+mod layer;
+mod legacy_lib;
+mod meta;
+mod metrics;
+mod migration;
+mod moves;
+#[cfg(feature = "net")]
+mod net;
+mod onnx;
+mod optim;
+mod prediction;
+mod predictor;
+#[cfg(feature = "python")]
+mod python;
+mod quantized;
+mod recurrent;
+mod replay;
+#[cfg(feature = "safetensors")]
+mod safetensors;
+mod schedule;
+mod search;
+#[cfg(feature = "server")]
+mod server;
+mod session;
+#[cfg(feature = "std")]
+mod shared;
+mod simulation;
+mod stats;
+#[cfg(feature = "web-storage")]
+mod storage;
+mod store;
+mod training;
+mod uncertainty;
+#[cfg(feature = "serde")]
+mod worker;
+
+pub use bandit::{BanditSelector, SelectionRule};
+pub use baselines::{CycleDetector, FrequencyPredictor, MarkovPredictor, Pattern, PatternDetector};
+pub use bots::{Player, ScriptedBot};
+pub use checkpoint::Checkpoint;
+pub use config::{Activation, Init, NetworkConfig};
+pub use distill::distill;
+pub use drift::DriftResponse;
+pub use elo::EloRatings;
+pub use encoding::{encode_round, encode_round_after, encoding_input_size, Encoding};
+pub use engine::GameEngine;
+pub use ensemble::Ensemble;
+pub use error::Error;
+pub use evolution::Evolution;
+#[cfg(feature = "ffi")]
+pub use ffi::{
+    rps_network_backward, rps_network_bytes_free, rps_network_forward, rps_network_free,
+    rps_network_from_bytes, rps_network_last_error, rps_network_new, rps_network_probs,
+    rps_network_to_bytes,
+};
+pub use gamelog::{GameLog, RoundRecord};
+pub use legacy_lib::LegacyRPSNetwork;
+pub use meta::{MetaStrategy, Strategy};
+pub use migration::FORMAT_VERSION;
+pub use moves::{
+    judge_move, move_from_one_hot, move_to_one_hot, outcome_from_one_hot, outcome_to_one_hot, Move,
+    Outcome,
+};
+#[cfg(feature = "net")]
+pub use net::{commitment, ClientMessage, MatchCoordinator, ServerMessage};
+pub use optim::Optimizer;
+pub use prediction::{Beats, Prediction, PredictionResult};
+pub use predictor::RpsPredictor;
+pub use quantized::QuantizedNetwork;
+pub use recurrent::Architecture;
+pub use schedule::{Schedule, DEFAULT_LEARNING_RATE};
+pub use search::{grid_search, random_search, SearchResult, SearchSpace};
+#[cfg(all(feature = "server", feature = "net"))]
+pub use server::match_router;
+#[cfg(feature = "server")]
+pub use server::router;
+pub use session::GameSession;
+#[cfg(feature = "std")]
+pub use shared::SharedNetwork;
+pub use simulation::{
+    self_play, self_play_snapshot, simulate, simulate_players, simulate_with_rules, tournament,
+    Contestant, PredictorPlayer, Simulation,
+};
+pub use stats::SessionStats;
+pub use store::ModelStore;
+pub use training::{TrainingOptions, TrainingReport};
+#[cfg(feature = "serde")]
+pub use worker::{Dispatcher, Request, Response};
+
+use attention::Attention;
+use calibration::Calibration;
+use drift::DriftDetector;
+use kernels::{mat_vec, mat_vec_into};
+use layer::{outer_into, Layer};
+use metrics::Metrics;
+#[cfg(feature = "serde")]
+use migration::{Saved, Saving};
+use optim::OptimizerState;
+use recurrent::Recurrent;
+use replay::ReplayBuffer;
+use uncertainty::Uncertainty;
+
+/// Floating-point type of every weight, activation and probability: `f32` by
+/// default, or `f64` with the `f64` feature for offline analysis.
+#[cfg(not(feature = "f64"))]
+pub type Real = f32;
+#[cfg(feature = "f64")]
+pub type Real = f64;
+
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
 #[cfg(feature = "wee_alloc")]
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
 pub struct RPSNetwork {
     pub input_size: usize,
     pub history_size: usize,
+    /// Size of the first hidden layer, see `hidden_sizes()` for all of them.
     pub hidden_size: usize,
     pub output_size: usize,
-    w1: Vec<Array2<f32>>,
-    b1: Vec<f32>,
-    history: Array2<f32>,
-    w2: Array2<f32>,
-    b2: Array2<f32>,
-    hidden: Array2<f32>,
-    w3: Array2<f32>,
-    b3: Array2<f32>,
-    probs: Array2<f32>,
+    history: Array2<Real>,
+    /// Recurrent first hidden layer, when the architecture isn't
+    /// `Architecture::Feedforward`.
+    recurrent: Option<Recurrent>,
+    /// Attention over the history window, for feedforward networks.
+    attention: Option<Attention>,
+    /// Hidden layers followed by the output layer. The first layer sees the
+    /// recurrent state if any, otherwise the whole history window, flattened
+    /// slot by slot and weighted by the attention if any.
+    layers: Vec<Layer>,
+    probs: Array1<Real>,
+    /// Head predicting the outcome of the next round from the input of the
+    /// output layer, when configured.
+    outcome: Option<Layer>,
+    /// Softmax of the outcome head, in the order of `Outcome`.
+    outcome_probs: Array1<Real>,
+    optim: OptimizerState,
+    schedule: Schedule,
+    dropout: Real,
+    weight_decay: Real,
+    max_grad_norm: Option<Real>,
+    clip_count: u32,
+    /// Divides the logits in the softmax: above 1 flattens the output
+    /// distribution, below 1 sharpens it.
+    temperature: Real,
+    /// Weight of the uniform distribution mixed into `probs()`.
+    exploration: Real,
+    /// Factor applied to the history slots once per round of age before
+    /// they reach the first layer, 1 leaving the history as is.
+    history_decay: Real,
+    /// `probs()`, kept up to date for `probs_ptr()`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    mixed_probs: Vec<Real>,
+    metrics: Metrics,
+    drift: DriftDetector,
+    calibration: Calibration,
+    replay: ReplayBuffer,
+    /// State before the last `backward()`, for `undo_backward()`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    undo: Option<Box<Undo>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    scratch: Scratch,
+    /// Perturbed copies evaluated by `forward()`, see `set_uncertainty()`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    uncertainty: Option<Uncertainty>,
+    /// Configuration the network was built from, to rebuild it in `reset()`.
+    config: NetworkConfig,
+    training: bool,
+    rng: ChaCha8Rng,
+    /// Called after every training step, see `on_train_step()`.
+    #[cfg(feature = "wasm")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    on_train_step: Option<TrainCallback>,
 }
 
-#[wasm_bindgen]
+/// Callback of `RPSNetwork::on_train_step()`.
+#[cfg(feature = "wasm")]
+#[derive(Clone)]
+struct TrainCallback(js_sys::Function);
+
+// SAFETY: a JS value must stay on the thread that created it. Without the
+// `atomics` target feature, wasm32 runs a single thread, and other targets
+// can't create JS values at all, so a network can't be moved away from the
+// thread of its callback.
+#[cfg(all(feature = "wasm", not(target_feature = "atomics")))]
+unsafe impl Send for TrainCallback {}
+#[cfg(all(feature = "wasm", not(target_feature = "atomics")))]
+unsafe impl Sync for TrainCallback {}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl RPSNetwork {
-    #[wasm_bindgen(constructor)]
+    /// Creates a network with one hidden layer. Fails like
+    /// `NetworkConfig::build()` if a size is 0, or if there are fewer than 2
+    /// outputs.
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new(
         input_size: usize,
         history_size: usize,
         hidden_size: usize,
         output_size: usize,
-    ) -> Self {
-        let w1 = (0..history_size)
-            .map(|_| Array2::random((input_size, 1), StandardNormal))
-            .collect();
-        let b1 = vec![0.0; input_size];
-        let history = Array2::zeros((history_size, 1));
-        let w2 = Array2::random((history_size, hidden_size), StandardNormal);
-        let b2 = Array2::zeros((hidden_size, 1));
-        let hidden = Array2::zeros((hidden_size, 1));
-        let w3 = Array2::random((hidden_size, output_size), StandardNormal);
-        let b3 = Array2::zeros((output_size, 1));
-        let probs = Array2::from_elem((output_size, 1), 1.0 / (output_size as f32));
+    ) -> Result<RPSNetwork, Error> {
+        let config = NetworkConfig::with_sizes(input_size, history_size, hidden_size, output_size);
+        config.validate()?;
+        Ok(Self::with_rng(&config, ChaCha8Rng::from_entropy()))
+    }
 
-        Self {
-            input_size,
-            history_size,
-            hidden_size,
-            output_size,
-            w1,
-            b1,
-            history,
-            w2,
-            b2,
-            hidden,
-            w3,
-            b3,
-            probs,
-        }
+    /// Creates a network whose weights are drawn from an RNG seeded with
+    /// `seed`, so that two networks built with the same seed and trained on
+    /// the same rounds follow exactly the same trajectory. Fails like
+    /// `new()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn new_with_seed(
+        input_size: usize,
+        history_size: usize,
+        hidden_size: usize,
+        output_size: usize,
+        seed: u64,
+    ) -> Result<RPSNetwork, Error> {
+        let config = NetworkConfig::with_sizes(input_size, history_size, hidden_size, output_size);
+        config.validate()?;
+        Ok(Self::with_rng(&config, ChaCha8Rng::seed_from_u64(seed)))
     }
 
-    #[wasm_bindgen]
-    pub fn forward(&mut self, input: &[f32]) {
+    /// Creates a network with one hidden layer per entry of `hidden_sizes`,
+    /// from the input side to the output side. Fails like
+    /// `NetworkConfig::build()` if there is no hidden layer or a size is 0.
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn with_hidden_sizes(
+        input_size: usize,
+        history_size: usize,
+        hidden_sizes: &[usize],
+        output_size: usize,
+    ) -> Result<RPSNetwork, Error> {
+        NetworkConfig::with_sizes(input_size, history_size, 0, output_size)
+            .hidden_sizes(hidden_sizes)
+            .build()
+    }
+
+    /// Adds the moves of a new round, which must hold `input_size` values, to
+    /// the history and predicts the next one.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        check_input(input, self.input_size)?;
+
         // Shift history items and add new item
-        let past = self.history.slice(s![1.., ..]).to_owned();
-        self.history.slice_mut(s![..-1, ..]).assign(&past);
-        self.history.slice_mut(s![-1.., ..]).assign(&arr1(input));
+        for slot in 1..self.history_size {
+            let (mut older, newer) = self
+                .history
+                .multi_slice_mut((s![slot - 1, ..], s![slot, ..]));
+            older.assign(&newer);
+        }
+        self.history.slice_mut(s![-1, ..]).assign(&aview1(input));
+
+        if let Some(recurrent) = &mut self.recurrent {
+            recurrent.step(aview1(input));
+        }
+        self.evaluate_with_uncertainty();
+        Ok(())
+    }
 
-        // Compute hidden layer activations
-        let a = &self.history.dot(&self.w1);
-        let hidden = (a + &self.b1).mapv(|v| v.tanh());
-        self.hidden = hidden;
+    /// Evaluates `n` history windows at once, without changing the history.
+    /// `inputs` holds the windows one after the other, each flattened like
+    /// `history()`, and the result holds the `n` output distributions one
+    /// after the other, as `probs()` would return them. Not supported by
+    /// recurrent networks, which need the rounds one at a time.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn forward_batch(&self, inputs: &[Real], n: usize) -> Result<Vec<Real>, Error> {
+        if self.recurrent.is_some() {
+            return Err(Error::Unsupported(
+                "recurrent networks can't evaluate windows in a batch",
+            ));
+        }
+        let window = self.history_size * self.input_size;
+        if inputs.len() != n * window {
+            return Err(Error::ShapeMismatch {
+                name: "inputs",
+                expected: vec![n * window],
+                found: vec![inputs.len()],
+            });
+        }
 
-        // Compute output probabilities
-        self.probs = &self.hidden * &self.w2 + &self.b2;
+        let mut x = aview1(inputs).into_shape((n, window)).unwrap().to_owned();
+        let mut attention = self.attention.clone();
+        if attention.is_some() || self.history_decay != 1.0 {
+            for mut row in x.rows_mut() {
+                let history = self.decay(
+                    row.view()
+                        .into_shape((self.history_size, self.input_size))
+                        .unwrap(),
+                );
+                let weighted = match &mut attention {
+                    Some(attention) => {
+                        attention.forward(history.view());
+                        attention.apply(history.view())
+                    }
+                    None => Array1::from(history.into_raw_vec()),
+                };
+                row.assign(&weighted);
+            }
+        }
+        for layer in &self.layers {
+            x = layer.forward_batch(x.view());
+        }
 
-        // Apply softmax to output probabilities
-        let max_probs = self.probs.max().unwrap().to_owned();
-        self.probs.mapv_inplace(|v| (v - max_probs).exp());
-        self.probs /= self.probs.sum();
+        // Apply softmax to each row, then mix in the exploration
+        let floor = self.exploration / self.output_size as Real;
+        for mut row in x.rows_mut() {
+            let max = row.fold(Real::NEG_INFINITY, |a, &b| a.max(b));
+            row.mapv_inplace(|v| ((v - max) / self.temperature).exp());
+            let sum = row.sum();
+            row.mapv_inplace(|p| (1.0 - self.exploration) * p / sum + floor);
+        }
+        Ok(x.into_raw_vec())
     }
 
-    #[wasm_bindgen]
-    pub fn backward(&mut self, label: usize, learning_rate: f32) {
-        // Compute the error between the predicted and actual output
-        let mut dprobs = self.probs.clone();
-        dprobs[(label, 1)] -= 1.0;
+    /// Trains the network towards `label` for the last input. Without an
+    /// explicit `learning_rate`, the rate comes from the network's schedule.
+    /// `label` must be below `output_size`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn backward(&mut self, label: usize, learning_rate: Option<Real>) -> Result<(), Error> {
+        self.learn(label, None, learning_rate)
+    }
 
-        // Compute the hidden layer gradient
-        let dhidden = &dprobs * &self.w2 * (1.0 - &self.hidden * &self.hidden);
+    /// Like `backward()`, also training the outcome head towards
+    /// `outcome`, the result of the round for the side that played against
+    /// the predicted move. See `NetworkConfig::outcome_head()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn backward_with_outcome(
+        &mut self,
+        label: usize,
+        outcome: Outcome,
+        learning_rate: Option<Real>,
+    ) -> Result<(), Error> {
+        if self.outcome.is_none() {
+            return Err(Error::Unsupported("the network has no outcome head"));
+        }
+        self.learn(label, Some(outcome), learning_rate)
+    }
 
-        // Update the weights and biases
-        self.w2 = &self.w2 - &self.hidden * &dprobs;
-        self.b2 = &self.b2 - learning_rate * &dprobs;
-        self.w1 = &self.w1 - learning_rate * &self.history * &dhidden;
-        self.b1 = &self.b1 - learning_rate * &dhidden;
+    /// Calls `callback` after every `backward()`, and so every
+    /// `train_step()`, with the number of the training step, the loss of
+    /// the prediction trained on, the recent accuracy and the learning rate
+    /// used, so that a UI can react to training without polling. Passing
+    /// `undefined` removes it. Exceptions thrown by the callback are ignored,
+    /// and the callback isn't serialized.
+    #[cfg(feature = "wasm")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn on_train_step(&mut self, callback: Option<js_sys::Function>) {
+        self.on_train_step = callback.map(TrainCallback);
     }
 
-    #[wasm_bindgen]
-    pub fn probs(&mut self) -> Vec<f32> {
-        self.probs.row(0).to_vec()
+    /// Trains the network on `n` rounds drawn at random from the replay
+    /// buffer, see `NetworkConfig::replay_capacity()`, and returns their
+    /// mean cross-entropy loss. Without an explicit `learning_rate`, each
+    /// round advances the schedule. Afterwards, `probs()` reflects the
+    /// updated weights for the current history. Does nothing and returns 0
+    /// when the buffer is empty.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn train_from_replay(&mut self, n: usize, learning_rate: Option<Real>) -> Real {
+        let (rounds, loss) = self.replay_while(|i| i < n, learning_rate);
+        if rounds == 0 {
+            0.0
+        } else {
+            loss / rounds as Real
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Trains the network on rounds drawn at random from the replay buffer,
+    /// like `train_from_replay()`, until `ms` milliseconds have passed, and
+    /// returns the number of rounds trained on. The time is measured with
+    /// `performance.now()` in the browser, so that learning can be
+    /// scheduled in idle frames without holding up the UI; the round in
+    /// progress when the budget runs out is finished.
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn train_for(&mut self, ms: f64, learning_rate: Option<Real>) -> u32 {
+        let stopwatch = clock::Stopwatch::start();
+        let (rounds, _) = self.replay_while(|_| stopwatch.elapsed_ms() < ms, learning_rate);
+        rounds as u32
+    }
 
-    const INPUT_SIZE: usize = 3;
-    const HISTORY_SIZE: usize = 3;
-    const HIDDEN_SIZE: usize = 8;
-    const OUTPUT_SIZE: usize = 3;
+    /// Trains the network `epochs` times over a recorded game, so that a
+    /// model can start a session from past games instead of from scratch,
+    /// and returns the mean cross-entropy loss of the last pass. `moves`
+    /// holds the opponent's moves in the order played when the network has
+    /// as many inputs as outputs, or the opponent's and the bot's move of
+    /// each round in turn when it has twice as many, like
+    /// `Encoding::BothMoves`. Each pass starts from an empty history and
+    /// trains the network to predict every opponent move from the rounds
+    /// before it, as `train_step()` would during the game; without an
+    /// explicit `learning_rate`, each round advances the schedule. The
+    /// history is cleared afterwards, and `undo_backward()` is no longer
+    /// possible. Nothing changes when a move is invalid.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn train_on_sequence(
+        &mut self,
+        moves: &[u8],
+        epochs: usize,
+        learning_rate: Option<Real>,
+    ) -> Result<Real, Error> {
+        let rounds = self.sequence_rounds(moves)?;
+        let mut loss = 0.0;
+        for _ in 0..epochs {
+            self.reset_history();
+            loss = self.sequence_pass(&rounds, learning_rate)?;
+        }
+        self.reset_history();
+        self.undo = None;
+        Ok(loss)
+    }
 
-    #[test]
-    fn init_network() {
-        let network = RPSNetwork::new(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE);
+    /// Number of rounds in the replay buffer.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn replay_len(&self) -> usize {
+        self.replay.len()
+    }
 
-        assert_eq!(network.input_size, INPUT_SIZE);
-        assert_eq!(network.history_size, HISTORY_SIZE);
-        assert_eq!(network.hidden_size, HIDDEN_SIZE);
-        assert_eq!(network.output_size, OUTPUT_SIZE);
-        assert_eq!(network.w1.shape(), vec![HISTORY_SIZE, HIDDEN_SIZE]);
-        assert_eq!(network.b1.shape(), vec![HIDDEN_SIZE, 1]);
-        assert_eq!(network.history.shape(), vec![INPUT_SIZE, HISTORY_SIZE]);
-        assert_eq!(network.hidden.shape(), vec![HIDDEN_SIZE, 1]);
-        assert_eq!(network.w2.shape(), vec![HIDDEN_SIZE, OUTPUT_SIZE]);
-        assert_eq!(network.b2.shape(), vec![OUTPUT_SIZE, 1]);
-        assert_eq!(network.probs.shape(), vec![OUTPUT_SIZE, 1]);
+    /// Cross-entropy loss of the last `forward()` against `label`, ignoring
+    /// exploration, without changing the network.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn loss(&self, label: usize) -> Result<Real, Error> {
+        check_label(label, self.output_size)?;
+        Ok(cross_entropy(self.probs[label]))
     }
 
-    #[test]
-    fn history() {
-        let mut network = RPSNetwork::new(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE);
+    /// Runs `forward()` on `input` then `backward()` towards `label`, and
+    /// returns the cross-entropy loss of the prediction made before the
+    /// update.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn train_step(
+        &mut self,
+        input: &[Real],
+        label: usize,
+        learning_rate: Option<Real>,
+    ) -> Result<Real, Error> {
+        check_label(label, self.output_size)?;
+        self.forward(input)?;
+        let loss = self.loss(label)?;
+        self.backward(label, learning_rate)?;
+        Ok(loss)
+    }
 
-        let input = vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+    /// Reverts the weight update of the last `backward()`, along with its
+    /// effect on the optimizer, schedule and metrics, e.g. after passing the
+    /// wrong label. Only one update can be undone; returns whether there was
+    /// one.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn undo_backward(&mut self) -> bool {
+        let Some(undo) = self.undo.take() else {
+            return false;
+        };
+        let Undo {
+            params,
+            optim,
+            schedule,
+            clip_count,
+            metrics,
+            drift,
+            evicted,
+            logged,
+        } = *undo;
+        for (mut param, saved) in self.params_mut().into_iter().zip(params) {
+            param.assign(&saved);
+        }
+        self.optim = optim;
+        self.schedule = schedule;
+        self.clip_count = clip_count;
+        self.metrics = metrics;
+        self.drift = drift;
+        self.replay.unpush(evicted);
+        self.calibration.unrecord(logged);
+        true
+    }
+
+    /// Output distribution of the last `forward()`, mixed with the uniform
+    /// distribution according to the exploration setting.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn probs(&self) -> Vec<Real> {
+        self.mixed_probs.clone()
+    }
+
+    /// Logits of the output layer in the last `forward()`, before the
+    /// temperature, softmax and exploration behind `probs()`, e.g. to apply
+    /// another temperature, measure the margin between the top two moves, or
+    /// average several networks before the softmax.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn logits(&self) -> Vec<Real> {
+        self.layers[self.layers.len() - 1].output.to_vec()
+    }
+
+    /// Pointer to the `output_size` values of `probs()` in wasm linear
+    /// memory, so that JS can read them through a typed array over
+    /// `memory.buffer` without allocating. The values are updated in place
+    /// by `forward()` and `set_exploration()`; the view must be recreated
+    /// whenever the memory grows.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn probs_ptr(&self) -> *const Real {
+        self.mixed_probs.as_ptr()
+    }
+
+    /// Shannon entropy of `probs()` in nats: 0 when the network is certain of
+    /// the next move, ln(output_size) when it has no idea.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn entropy(&self) -> Real {
+        entropy(&self.mixed_probs)
+    }
+
+    /// Most likely opponent move according to `probs()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn predict(&self) -> Prediction {
+        Prediction::argmax(&self.mixed_probs)
+    }
+
+    /// Distribution of the outcome head over the next round, indexed like
+    /// `Outcome::index()`, or `None` without an outcome head. See
+    /// `NetworkConfig::outcome_head()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn outcome_probs(&self) -> Option<Vec<Real>> {
+        self.outcome.as_ref().map(|_| self.outcome_probs.to_vec())
+    }
+
+    /// Most likely outcome of the next round for the side playing against
+    /// the predicted moves, e.g. for a "bot expects to win" indicator, or
+    /// `None` without an outcome head.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn expected_outcome(&self) -> Option<Outcome> {
+        self.outcome_probs()
+            .map(|probs| Outcome::ALL[Prediction::argmax(&probs).index])
+    }
+
+    /// Move that beats the predicted one according to `beats`, which must
+    /// cover every output. When several moves beat it, picks the one doing
+    /// best against the whole of `probs()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn counter_move(&self, beats: &Beats) -> Result<usize, Error> {
+        beats.counter(&self.mixed_probs, self.output_size)
+    }
+
+    /// `probs()`, the predicted move and the move countering it according
+    /// to `beats`, as for `counter_move()`, in one object.
+    #[cfg_attr(
+        feature = "wasm",
+        wasm_bindgen(unchecked_return_type = "PredictionResult")
+    )]
+    pub fn prediction_result(&self, beats: &Beats) -> Result<PredictionResult, Error> {
+        Ok(PredictionResult::new(
+            self.probs(),
+            self.counter_move(beats)?,
+        ))
+    }
+
+    /// Draws a move index from `probs()` with the network's RNG, so that a
+    /// seeded network samples the same moves on every run.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn sample_move(&mut self) -> usize {
+        let probs = self.mixed_probs.clone();
+        self.sample(&probs)
+    }
+
+    /// Fraction of the moves predicted correctly by `forward()`, as revealed
+    /// by the labels passed to `backward()`, since the network was created.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn accuracy(&self) -> Real {
+        self.metrics.accuracy()
+    }
+
+    /// Same as `accuracy()`, over the last rounds only. The window length is
+    /// set with `NetworkConfig::accuracy_window()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn recent_accuracy(&self) -> Real {
+        self.metrics.recent_accuracy()
+    }
+
+    /// How far the accuracy has dropped lately, from 0 while it holds: a
+    /// drift is detected when the score exceeds the threshold set with
+    /// `set_drift_detection()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn drift_score(&self) -> Real {
+        self.drift.score()
+    }
 
-        network.forward(&input[..3]);
-        network.forward(&input[3..6]);
-        network.forward(&input[6..]);
+    /// Whether the last `backward()` detected that the opponent changed
+    /// strategy, after which the drift score starts over.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn drift_detected(&self) -> bool {
+        self.drift.detected
+    }
+
+    /// Number of drifts detected since the network was created.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn drift_count(&self) -> u32 {
+        self.drift.detections
+    }
+
+    /// Fits the temperature of `calibrated_probs()` on the predictions of
+    /// the last 100 `backward()` calls, so that their confidence matches how
+    /// often they turned out right, and returns it. Above 1, the network was
+    /// overconfident. Keeps the previous temperature, initially 1, before
+    /// the first `backward()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn calibrate(&mut self) -> Real {
+        self.calibration.fit()
+    }
+
+    /// Temperature fitted by the last `calibrate()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn calibration_temperature(&self) -> Real {
+        self.calibration.temperature
+    }
+
+    /// Output distribution of the last `forward()` at the temperature
+    /// fitted by `calibrate()` instead of the one set with
+    /// `set_temperature()`, and without exploration, for displaying
+    /// confidence levels that match the observed accuracy.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn calibrated_probs(&self) -> Vec<Real> {
+        let logits = &self.layers[self.layers.len() - 1].output;
+        self.calibration.probs(logits.as_slice().unwrap())
+    }
+
+    /// Cross-entropy losses of the predictions trained on by the last
+    /// `backward()` calls, oldest first, for plotting a learning curve. The
+    /// number kept is set with `NetworkConfig::loss_history_size()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn loss_history(&self) -> Vec<Real> {
+        self.metrics.losses().iter().copied().collect()
+    }
+
+    /// Number of rounds per actual move (rows) and move predicted by
+    /// `forward()` (columns), as an `output_size` x `output_size` matrix
+    /// flattened row by row.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn confusion_matrix(&self) -> Vec<u32> {
+        self.metrics.confusion_matrix().to_vec()
+    }
+
+    /// Inputs of the last `history_size` rounds, from the oldest to the
+    /// newest, flattened round by round.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn history(&self) -> Vec<Real> {
+        self.history.iter().cloned().collect()
+    }
 
-        for i in 0..9 {
-            assert_eq!(input[i], network.history[(i, 0)]);
+    /// Replaces the history with `history`, flattened like `history()`
+    /// returns it, e.g. to resume a game without replaying its rounds. Not
+    /// supported by recurrent networks, whose state can't be recovered from
+    /// the history window.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn seed_history(&mut self, history: &[Real]) -> Result<(), Error> {
+        if self.recurrent.is_some() {
+            return Err(Error::Unsupported(
+                "recurrent networks can't be seeded with a history",
+            ));
+        }
+        if history.len() != self.history.len() {
+            return Err(Error::ShapeMismatch {
+                name: "history",
+                expected: vec![self.history_size * self.input_size],
+                found: vec![history.len()],
+            });
         }
+        for (slot, value) in self.history.iter_mut().zip(history) {
+            *slot = *value;
+        }
+        Ok(())
     }
 
-    #[test]
-    fn forward_pass() {
-        let mut network = RPSNetwork::new(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE);
+    /// Changes the history window to `history_size` rounds, keeping the
+    /// most recent ones. Growing the window adds empty older slots whose
+    /// first-layer weights start at zero, so the prediction doesn't change
+    /// until training uses them; shrinking it drops the oldest slots along
+    /// with their weights. The replay buffer and optimizer state follow, and
+    /// `undo_backward()` is no longer possible. Recurrent networks, which
+    /// don't read the window, can't be resized.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn resize_history(&mut self, history_size: usize) -> Result<(), Error> {
+        if self.recurrent.is_some() {
+            return Err(Error::Unsupported(
+                "recurrent networks don't have a history window to resize",
+            ));
+        }
+        if history_size == 0 {
+            return Err(Error::InvalidSize {
+                name: "history_size",
+                value: 0,
+                min: 1,
+            });
+        }
+
+        let weights = &mut self.layers[0].weights;
+        *weights = keep_last_rows(weights.view(), history_size * self.input_size);
+        self.optim
+            .resize_front(0, history_size * self.input_size * weights.ncols());
+        if let Some(attention) = &mut self.attention {
+            let position = attention.position.view().insert_axis(Axis(1));
+            attention.position = keep_last_rows(position, history_size).remove_axis(Axis(1));
+            attention.weights = Array1::from_elem(history_size, 1.0 / history_size as Real);
+            // The position scores come right after the query
+            self.optim
+                .resize_front(2 * self.layers.len() + 1, history_size);
+        }
+        self.history = keep_last_rows(self.history.view(), history_size);
+        self.replay.resize_windows(history_size);
+        self.undo = None;
+        self.history_size = history_size;
+        self.config.history_size = history_size;
+        self.evaluate();
+        Ok(())
+    }
 
-        let input: Vec<f32> = vec![1.0, 0.0, 0.0];
+    /// Changes the first hidden layer to `hidden_size` units, keeping the
+    /// learned weights of the first units. New units get random incoming
+    /// weights and zero outgoing ones, so the prediction doesn't change
+    /// until training uses them; removed units take their weights with them.
+    /// The optimizer state follows, and `undo_backward()` is no longer
+    /// possible. Recurrent layers can't be resized.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn resize_hidden(&mut self, hidden_size: usize) -> Result<(), Error> {
+        if self.recurrent.is_some() {
+            return Err(Error::Unsupported("recurrent layers can't be resized"));
+        }
+        if hidden_size == 0 {
+            return Err(Error::InvalidSize {
+                name: "hidden_size",
+                value: 0,
+                min: 1,
+            });
+        }
 
-        network.forward(&input);
+        let inputs = self.layers[0].weights.nrows();
+        let outputs = self.layers[1].outputs();
+        let init = self.config.init.unwrap_or(Init::StandardNormal);
+        self.layers[0].resize_outputs(hidden_size, init, &mut self.rng);
+        self.layers[1].resize_inputs(hidden_size);
+        // Weights, bias, then the weights of the next layer
+        for (index, rows, cols, new_rows, new_cols) in [
+            (0, inputs, self.hidden_size, inputs, hidden_size),
+            (1, 1, self.hidden_size, 1, hidden_size),
+            (2, self.hidden_size, outputs, hidden_size, outputs),
+        ] {
+            self.optim.map_moments(index, |buffer| {
+                let buffer = aview1(buffer).into_shape((rows, cols)).unwrap();
+                keep_first(buffer, new_rows, new_cols).into_raw_vec()
+            });
+        }
+        // With a single hidden layer, the outcome head reads it too
+        if self.layers.len() == 2 {
+            let index = self.params().len() - 2;
+            if let Some(head) = &mut self.outcome {
+                head.resize_inputs(hidden_size);
+                let outcomes = Outcome::ALL.len();
+                self.optim.map_moments(index, |buffer| {
+                    let buffer = aview1(buffer)
+                        .into_shape((self.hidden_size, outcomes))
+                        .unwrap();
+                    keep_first(buffer, hidden_size, outcomes).into_raw_vec()
+                });
+            }
+        }
+        self.undo = None;
+        self.hidden_size = hidden_size;
+        self.config.hidden_sizes[0] = hidden_size;
+        self.evaluate();
+        Ok(())
+    }
 
-        assert!(network.probs.iter().all(|v| v.to_owned() != 0.0));
+    /// Draws new weights and forgets everything learned, including the
+    /// optimizer state and the metrics, as if the network had just been
+    /// built from the same configuration. The schedule starts over, and the
+    /// temperature, exploration, history decay, drift detection and training
+    /// settings are kept, along with the `on_train_step()` callback.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn reset(&mut self) {
+        let mut network = Self::with_rng(&self.config, self.rng.clone());
+        network.schedule = self.schedule.restarted();
+        network.temperature = self.temperature;
+        network.exploration = self.exploration;
+        network.history_decay = self.history_decay;
+        network.training = self.training;
+        network.drift = DriftDetector::new(self.drift.threshold, self.drift.response);
+        #[cfg(feature = "wasm")]
+        {
+            network.on_train_step = self.on_train_step.take();
+        }
+        *self = network;
     }
 
-    #[test]
-    fn backward_pass_success() {
-        let mut network = RPSNetwork::new(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE);
+    /// Captures the whole state of the network, so that training can be
+    /// rolled back with `restore()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn snapshot(&self) -> Checkpoint {
+        Checkpoint {
+            network: self.clone(),
+        }
+    }
 
-        let input: Vec<f32> = vec![1.0, 0.0, 0.0];
+    /// Returns to the state captured by `snapshot()`, including the weights,
+    /// optimizer state, history and settings at the time.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn restore(&mut self, checkpoint: &Checkpoint) {
+        *self = checkpoint.network.clone();
+    }
 
-        network.forward(&input);
+    /// Creates an inference-only copy of the network with int8 weights,
+    /// starting from the current history, with the history decay folded into
+    /// the first layer. Only feedforward networks without attention can be
+    /// quantized.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn quantize(&self) -> Result<QuantizedNetwork, Error> {
+        if self.recurrent.is_some() || self.attention.is_some() {
+            return Err(Error::Unsupported(
+                "only feedforward networks without attention can be quantized",
+            ));
+        }
+        let mut layers = self.layers.clone();
+        for (i, mut row) in layers[0].weights.rows_mut().into_iter().enumerate() {
+            row *= self.slot_decay(i / self.input_size);
+        }
+        Ok(QuantizedNetwork::new(
+            &layers,
+            &self.history,
+            &self.probs,
+            self.temperature,
+            self.exploration,
+        ))
+    }
 
-        let paper_prob = network.probs()[1];
+    /// Clears the history, and the state of a recurrent layer, keeping the
+    /// learned weights.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn reset_history(&mut self) {
+        self.history.fill(0.0);
+        if let Some(recurrent) = &mut self.recurrent {
+            recurrent.reset_state();
+        }
+    }
 
-        for _ in 0..100 {
-            network.backward(1, 0.01);
-            network.forward(&input);
+    /// Contribution of each history slot, from the oldest to the newest, to
+    /// the logit of the predicted move in the last `forward()`, measured as
+    /// gradient times input: positive slots pushed towards the prediction,
+    /// negative ones away from it. Attention and history decay are included,
+    /// as they scale the slots before the first layer. Not supported by
+    /// recurrent networks, which don't see the slots separately.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn explain(&self) -> Result<Vec<Real>, Error> {
+        if self.recurrent.is_some() {
+            return Err(Error::Unsupported(
+                "recurrent networks can't attribute their prediction to history slots",
+            ));
         }
+        let predicted = Prediction::argmax(self.probs.as_slice().unwrap()).index;
+        let mut delta = Array1::zeros(self.output_size);
+        delta[predicted] = 1.0;
+        for i in (1..self.layers.len()).rev() {
+            delta =
+                self.layers[i - 1].backprop(mat_vec(self.layers[i].weights.view(), delta.view()));
+        }
+        let gradient = mat_vec(self.layers[0].weights.view(), delta.view());
+        let input = self.layer_input(0);
+        Ok((&gradient * &input)
+            .exact_chunks(self.input_size)
+            .into_iter()
+            .map(|slot| slot.sum())
+            .collect())
+    }
 
-        assert!(paper_prob < network.probs()[1]);
+    /// Sizes of the hidden layers, from the input side to the output side.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn hidden_sizes(&self) -> Vec<usize> {
+        let (_, hidden) = self.layers.split_last().unwrap();
+        self.recurrent
+            .iter()
+            .map(Recurrent::hidden_size)
+            .chain(hidden.iter().map(Layer::outputs))
+            .collect()
     }
 
-    #[test]
-    fn backward_pass_fail() {
-        let mut network = RPSNetwork::new(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE);
+    /// Activations of the hidden layer number `layer`, counting from the
+    /// input side, during the last `forward()`. Dropout isn't applied. For a
+    /// recurrent layer, this is its state.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn hidden(&self, layer: usize) -> Result<Vec<Real>, Error> {
+        self.check_hidden_layer(layer)?;
+        match &self.recurrent {
+            Some(recurrent) if layer == 0 => Ok(recurrent.state().to_vec()),
+            Some(_) => Ok(self.layers[layer - 1].output.to_vec()),
+            None => Ok(self.layers[layer].output.to_vec()),
+        }
+    }
 
-        let input: Vec<f32> = vec![1.0, 0.0, 0.0];
+    /// Values of the hidden layer number `layer` before its activation,
+    /// during the last `forward()`. Not available for a recurrent layer,
+    /// whose gates each have their own.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn pre_activation(&self, layer: usize) -> Result<Vec<Real>, Error> {
+        self.check_hidden_layer(layer)?;
+        match &self.recurrent {
+            Some(_) if layer == 0 => Err(Error::Unsupported(
+                "recurrent layers have no single pre-activation",
+            )),
+            Some(_) => Ok(self.layers[layer - 1].pre_activation.to_vec()),
+            None => Ok(self.layers[layer].pre_activation.to_vec()),
+        }
+    }
 
-        network.forward(&input);
+    /// L2 norms of the weight matrices, from the input layer to the output
+    /// layer. A recurrent layer contributes its input and recurrent weights,
+    /// in that order.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn weight_norms(&self) -> Vec<Real> {
+        self.recurrent
+            .iter()
+            .flat_map(Recurrent::weights)
+            .chain(self.layers.iter().map(|layer| &layer.weights))
+            .map(|weights| weights.iter().map(|w| w * w).sum::<Real>().sqrt())
+            .collect()
+    }
 
-        let scissors_prob = network.probs()[2];
+    /// Number of parameter tensors, see `get_weights()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn parameter_count(&self) -> usize {
+        self.params().len()
+    }
 
-        for _ in 0..100 {
-            network.backward(1, 0.01);
-            network.forward(&input);
+    /// Number of learned values across every parameter tensor, where
+    /// `parameter_count()` counts the tensors themselves.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn param_count(&self) -> usize {
+        self.params().iter().map(|param| param.len()).sum()
+    }
+
+    /// Approximate number of bytes held by the network: the parameters,
+    /// optimizer state, cached activations and history, plus the replay
+    /// buffer, calibration log and undo state as filled so far. Compare
+    /// configurations after a few rounds of training, once the optimizer
+    /// has allocated its state.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn memory_bytes(&self) -> usize {
+        use core::mem::size_of;
+
+        let params = self.param_count();
+        let activations: usize = self
+            .layers
+            .iter()
+            .chain(&self.outcome)
+            .map(|layer| 3 * layer.outputs())
+            .sum();
+        let undo = match &self.undo {
+            Some(undo) => params + undo.optim.len() + self.output_size,
+            None => 0,
+        };
+        let reals = params
+            + self.optim.len()
+            + activations
+            + self.history.len()
+            + self.probs.len()
+            + self.mixed_probs.len()
+            + self.outcome_probs.len()
+            + self
+                .attention
+                .as_ref()
+                .map_or(0, |attention| attention.weights.len())
+            + self.recurrent.as_ref().map_or(0, Recurrent::cache_len)
+            + self.replay.len() * self.history.len()
+            + self.calibration.len() * self.output_size
+            + self.metrics.losses().len()
+            + undo;
+        let labels = self.replay.len() + self.calibration.len();
+        let metrics = self.output_size * self.output_size * size_of::<u32>()
+            + self.config.accuracy_window * size_of::<bool>();
+        size_of::<Self>() + reals * size_of::<Real>() + labels * size_of::<usize>() + metrics
+    }
+
+    /// Shape of the parameter tensor number `index`, see `get_weights()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn weight_shape(&self, index: usize) -> Result<Vec<usize>, Error> {
+        Ok(self.param(index)?.shape().to_vec())
+    }
+
+    /// Values of the parameter tensor number `index`, flattened row by row.
+    /// Each dense layer, from the input side to the output side, has its
+    /// `(inputs, outputs)` weights then its bias. They are followed by the
+    /// input weights, recurrent weights and bias of a recurrent layer, or by
+    /// the query and position scores of the attention.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn get_weights(&self, index: usize) -> Result<Vec<Real>, Error> {
+        Ok(self.param(index)?.iter().cloned().collect())
+    }
+
+    /// Overwrites the parameter tensor number `index` with `values`,
+    /// flattened like `get_weights()` returns them.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_weights(&mut self, index: usize, values: &[Real]) -> Result<(), Error> {
+        let shape = self.param(index)?.shape().to_vec();
+        if shape.iter().product::<usize>() != values.len() {
+            return Err(Error::ShapeMismatch {
+                name: "weights",
+                expected: shape,
+                found: vec![values.len()],
+            });
         }
+        let mut params = self.params_mut();
+        for (param, value) in params[index].iter_mut().zip(values) {
+            *param = *value;
+        }
+        Ok(())
+    }
 
-        assert!(scissors_prob > network.probs()[2]);
+    /// Moves every parameter a share `alpha` of the way to the same
+    /// parameter of `other`, as in federated averaging, so that networks
+    /// trained from the same configuration in separate sessions or on
+    /// separate devices can be combined: 0.5 averages them, 0 leaves this
+    /// network as is and 1 copies the weights of `other`. Both networks must
+    /// have the same sizes, architecture, activations and history decay, so
+    /// that each parameter means the same in both. The optimizer state,
+    /// metrics and history are kept, `undo_backward()` is no longer possible,
+    /// and `probs()` reflects the merged weights for the current history.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn merge(&mut self, other: &RPSNetwork, alpha: Real) -> Result<(), Error> {
+        if !(0.0..=1.0).contains(&alpha) {
+            return Err(Error::OutOfRange {
+                name: "alpha",
+                value: alpha,
+                min: 0.0,
+                max: 1.0,
+            });
+        }
+        for (name, expected, found) in [
+            ("input_size", self.input_size, other.input_size),
+            ("history_size", self.history_size, other.history_size),
+            ("output_size", self.output_size, other.output_size),
+        ] {
+            if expected != found {
+                return Err(Error::ShapeMismatch {
+                    name,
+                    expected: vec![expected],
+                    found: vec![found],
+                });
+            }
+        }
+        if self.config.architecture != other.config.architecture
+            || self.attention.is_some() != other.attention.is_some()
+            || self.outcome.is_some() != other.outcome.is_some()
+        {
+            return Err(Error::Unsupported(
+                "the merged network has a different architecture",
+            ));
+        }
+        let activations = |network: &RPSNetwork| -> Vec<Option<Activation>> {
+            network
+                .layers
+                .iter()
+                .map(|layer| layer.activation)
+                .collect()
+        };
+        if activations(self) != activations(other) {
+            return Err(Error::Unsupported(
+                "the merged network has different activations",
+            ));
+        }
+        if self.history_decay != other.history_decay {
+            return Err(Error::Unsupported(
+                "the merged network has a different history decay",
+            ));
+        }
+        let (params, theirs) = (self.params(), other.params());
+        if let Some(index) = (0..params.len().max(theirs.len()))
+            .find(|&i| params.get(i).map(|p| p.shape()) != theirs.get(i).map(|p| p.shape()))
+        {
+            let shape = |params: &[ArrayViewD<Real>]| {
+                params
+                    .get(index)
+                    .map_or_else(Vec::new, |p| p.shape().to_vec())
+            };
+            return Err(Error::ParameterMismatch {
+                index,
+                expected: shape(&params),
+                found: shape(&theirs),
+            });
+        }
+        drop(params);
+
+        for (mut param, theirs) in self.params_mut().into_iter().zip(theirs) {
+            param.zip_mut_with(&theirs, |mine, theirs| {
+                *mine = (1.0 - alpha) * *mine + alpha * theirs;
+            });
+        }
+        self.undo = None;
+        self.evaluate();
+        Ok(())
+    }
+
+    /// Attention weights of the history slots during the last `forward()`,
+    /// from the oldest round to the newest. Empty without attention.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn attention_weights(&self) -> Vec<Real> {
+        self.attention
+            .as_ref()
+            .map_or_else(Vec::new, |attention| attention.weights.to_vec())
+    }
+
+    /// Number of `backward()` calls whose gradients were clipped.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn clip_count(&self) -> u32 {
+        self.clip_count
+    }
+
+    /// Sets the softmax temperature of the next `forward()`, which must be
+    /// positive. Defaults to 1.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_temperature(&mut self, temperature: Real) -> Result<(), Error> {
+        if !(temperature > 0.0 && temperature.is_finite()) {
+            return Err(Error::OutOfRange {
+                name: "temperature",
+                value: temperature,
+                min: Real::MIN_POSITIVE,
+                max: Real::INFINITY,
+            });
+        }
+        self.temperature = temperature;
+        Ok(())
+    }
+
+    /// Mixes `probs()` with the uniform distribution, with weight `epsilon`
+    /// between 0 (off, the default) and 1, so that every move keeps a
+    /// probability of at least `epsilon / output_size`. Training is
+    /// unaffected, so this can be changed at any round.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_exploration(&mut self, epsilon: Real) -> Result<(), Error> {
+        if !(0.0..=1.0).contains(&epsilon) {
+            return Err(Error::OutOfRange {
+                name: "exploration",
+                value: epsilon,
+                min: 0.0,
+                max: 1.0,
+            });
+        }
+        self.exploration = epsilon;
+        self.mix_probs();
+        Ok(())
+    }
+
+    /// Scales each history slot by `decay` to the power of its age in rounds
+    /// before the next `forward()`, so that the most recent rounds dominate
+    /// the prediction. `decay` must be between 0 and 1 (off, the default).
+    /// Recurrent networks, which don't read the history window, ignore it.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_history_decay(&mut self, decay: Real) -> Result<(), Error> {
+        if !(0.0..=1.0).contains(&decay) {
+            return Err(Error::OutOfRange {
+                name: "history_decay",
+                value: decay,
+                min: 0.0,
+                max: 1.0,
+            });
+        }
+        self.history_decay = decay;
+        Ok(())
+    }
+
+    /// Detects a drift when the drift score exceeds `threshold`, which must
+    /// be positive (5 by default), and then applies `response` (only
+    /// `DriftResponse::Signal` by default). Lower thresholds react faster to
+    /// a change of strategy, but raise more false alarms.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_drift_detection(
+        &mut self,
+        threshold: Real,
+        response: DriftResponse,
+    ) -> Result<(), Error> {
+        if !(threshold > 0.0 && threshold.is_finite()) {
+            return Err(Error::OutOfRange {
+                name: "threshold",
+                value: threshold,
+                min: Real::MIN_POSITIVE,
+                max: Real::INFINITY,
+            });
+        }
+        self.drift.threshold = threshold;
+        self.drift.response = response;
+        Ok(())
+    }
+
+    /// Enables dropout in `forward()`. Leave it off when only predicting.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    /// Replaces the learning-rate schedule, starting it from its first step.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_schedule(&mut self, schedule: &Schedule) {
+        self.schedule = *schedule;
+    }
+
+    /// Learning rate that the next scheduled `backward()` will use.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn learning_rate(&self) -> Real {
+        self.schedule.current()
+    }
+
+    /// Serializes the network, including its learned weights and current
+    /// history, to a JSON string.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn to_json(&self) -> Result<String, Error> {
+        migration::to_json(Saving::Network(self))
+    }
+
+    /// Restores a network previously serialized with `to_json()`, or with
+    /// `LegacyRPSNetwork::to_json()`, see `from_legacy()`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn from_json(json: &str) -> Result<RPSNetwork, Error> {
+        let mut network = migration::from_json(json, Saved::unversioned_json)?.into_network()?;
+        network.validate()?;
+        network.mix_probs();
+        Ok(network)
+    }
+
+    /// Serializes the network to a compact binary encoding, better suited
+    /// than JSON to size-limited storage such as `localStorage`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        migration::to_bytes(Saving::Network(self))
+    }
+
+    /// Restores a network previously serialized with `to_bytes()`, or with
+    /// `LegacyRPSNetwork::to_bytes()`, see `from_legacy()`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<RPSNetwork, Error> {
+        let mut network = migration::from_bytes(bytes, Saved::unversioned_bytes)?.into_network()?;
+        network.validate()?;
+        network.mix_probs();
+        Ok(network)
+    }
+
+    /// Builds a network from `config` and copies into it the weights of a
+    /// network saved with `to_bytes()`, so that a model can move to larger
+    /// or smaller hidden layers or history window without losing what it
+    /// learned. Weights are matched like `resize_hidden()` and
+    /// `resize_history()` match them: the first units of each layer and the
+    /// most recent history slots are kept, new units get random incoming
+    /// weights and zero outgoing ones, and new history slots zero weights.
+    /// Everything else, including the optimizer state and the history,
+    /// starts fresh. Both networks must have the same input and output
+    /// sizes, architecture and number of hidden layers, and recurrent layers
+    /// the same size.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn from_pretrained(bytes: &[u8], config: &NetworkConfig) -> Result<RPSNetwork, Error> {
+        let pretrained = Self::from_bytes(bytes)?;
+        let mut network = config.build()?;
+        network.transfer(&pretrained)?;
+        network.evaluate();
+        Ok(network)
+    }
+}
+
+impl RPSNetwork {
+    pub(crate) fn with_rng(config: &NetworkConfig, mut rng: ChaCha8Rng) -> Self {
+        let NetworkConfig {
+            input_size,
+            history_size,
+            output_size,
+            ..
+        } = *config;
+
+        let init = config.init.unwrap_or(Init::StandardNormal);
+        let recurrent = Recurrent::new(
+            config.architecture,
+            input_size,
+            config.hidden_sizes[0],
+            config.layer_activation_or_default(0),
+            config.bptt_steps.unwrap_or(history_size),
+            init,
+            &mut rng,
+        );
+        let skip = recurrent.is_some() as usize;
+        let mut layers = Vec::with_capacity(config.hidden_sizes.len() + 1 - skip);
+        let mut inputs = match &recurrent {
+            Some(recurrent) => recurrent.hidden_size(),
+            None => history_size * input_size,
+        };
+        for (i, &outputs) in config.hidden_sizes.iter().enumerate().skip(skip) {
+            let activation = config.layer_activation_or_default(i);
+            layers.push(Layer::new(
+                inputs,
+                outputs,
+                Some(activation),
+                init,
+                &mut rng,
+            ));
+            inputs = outputs;
+        }
+        layers.push(Layer::new(inputs, output_size, None, init, &mut rng));
+        let outcome = config
+            .outcome_head
+            .then(|| Layer::new(inputs, Outcome::ALL.len(), None, init, &mut rng));
+        let history = Array2::zeros((history_size, input_size));
+        let probs = Array1::from_elem(output_size, 1.0 / (output_size as Real));
+
+        Self {
+            input_size,
+            history_size,
+            hidden_size: config.hidden_sizes[0],
+            output_size,
+            history,
+            recurrent,
+            attention: config
+                .attention
+                .then(|| Attention::new(input_size, history_size)),
+            layers,
+            outcome,
+            outcome_probs: Array1::from_elem(Outcome::ALL.len(), 1.0 / 3.0),
+            mixed_probs: probs.to_vec(),
+            probs,
+            optim: OptimizerState::new(config.optimizer),
+            schedule: Schedule::default(),
+            dropout: config.dropout,
+            weight_decay: config.weight_decay,
+            max_grad_norm: config.max_grad_norm,
+            clip_count: 0,
+            temperature: 1.0,
+            exploration: 0.0,
+            history_decay: 1.0,
+            metrics: Metrics::new(
+                config.accuracy_window,
+                output_size,
+                config.loss_history_size,
+            ),
+            drift: DriftDetector::new(drift::DEFAULT_THRESHOLD, DriftResponse::Signal),
+            calibration: Calibration::new(),
+            replay: ReplayBuffer::new(config.replay_capacity),
+            undo: None,
+            scratch: Scratch::default(),
+            uncertainty: None,
+            config: config.clone(),
+            training: false,
+            rng,
+            #[cfg(feature = "wasm")]
+            on_train_step: None,
+        }
+    }
+
+    /// Parameter tensors in the order used by the optimizer.
+    fn params(&self) -> Vec<ArrayViewD<'_, Real>> {
+        let mut params: Vec<_> = self
+            .layers
+            .iter()
+            .flat_map(|layer| {
+                [
+                    layer.weights.view().into_dyn(),
+                    layer.bias.view().into_dyn(),
+                ]
+            })
+            .collect();
+        match (&self.recurrent, &self.attention) {
+            (Some(recurrent), _) => params.extend(recurrent.params()),
+            (None, Some(attention)) => params.extend(attention.params()),
+            (None, None) => {}
+        }
+        if let Some(head) = &self.outcome {
+            params.extend([head.weights.view().into_dyn(), head.bias.view().into_dyn()]);
+        }
+        params
+    }
+
+    fn params_mut(&mut self) -> Vec<ArrayViewMutD<'_, Real>> {
+        let mut params: Vec<_> = self
+            .layers
+            .iter_mut()
+            .flat_map(|layer| {
+                [
+                    layer.weights.view_mut().into_dyn(),
+                    layer.bias.view_mut().into_dyn(),
+                ]
+            })
+            .collect();
+        match (&mut self.recurrent, &mut self.attention) {
+            (Some(recurrent), _) => params.extend(recurrent.params_mut()),
+            (None, Some(attention)) => params.extend(attention.params_mut()),
+            (None, None) => {}
+        }
+        if let Some(head) = &mut self.outcome {
+            params.extend([
+                head.weights.view_mut().into_dyn(),
+                head.bias.view_mut().into_dyn(),
+            ]);
+        }
+        params
+    }
+
+    fn param(&self, index: usize) -> Result<ArrayViewD<'_, Real>, Error> {
+        let mut params = self.params();
+        let parameters = params.len();
+        if index < parameters {
+            Ok(params.swap_remove(index))
+        } else {
+            Err(Error::InvalidParameter { index, parameters })
+        }
+    }
+
+    fn check_hidden_layer(&self, layer: usize) -> Result<(), Error> {
+        let layers = self.layers.len() - 1 + self.recurrent.is_some() as usize;
+        if layer < layers {
+            Ok(())
+        } else {
+            Err(Error::InvalidLayer { layer, layers })
+        }
+    }
+
+    /// Draws an index from the distribution `probs` with the network's RNG.
+    pub(crate) fn sample(&mut self, probs: &[Real]) -> usize {
+        let mut r = self.rng.gen::<Real>();
+        for (i, p) in probs.iter().enumerate() {
+            if r < *p {
+                return i;
+            }
+            r -= p;
+        }
+        // Rounding can leave a sliver past the last move
+        probs.len() - 1
+    }
+
+    /// Draws new weights for the output layer and forgets its optimizer
+    /// state.
+    fn reset_output_layer(&mut self) {
+        let init = self.config.init.unwrap_or(Init::StandardNormal);
+        let index = self.layers.len() - 1;
+        let inputs = self.layers[index].weights.nrows();
+        self.layers[index] = Layer::new(inputs, self.output_size, None, init, &mut self.rng);
+        for param in [2 * index, 2 * index + 1] {
+            self.optim
+                .map_moments(param, |buffer| vec![0.0; buffer.len()]);
+        }
+    }
+
+    /// Trains the network on rounds drawn at random from the replay buffer
+    /// for as long as `more` of the number of rounds trained on so far
+    /// holds, then evaluates the current history again. Returns the number
+    /// of rounds and their total loss.
+    fn replay_while(
+        &mut self,
+        mut more: impl FnMut(usize) -> bool,
+        learning_rate: Option<Real>,
+    ) -> (usize, Real) {
+        if self.replay.len() == 0 || !more(0) {
+            return (0, 0.0);
+        }
+        // The replayed updates can't be undone one by one
+        self.undo = None;
+
+        let history = self.history.clone();
+        let mut rounds = 0;
+        let mut loss = 0.0;
+        loop {
+            let (window, label) = self.replay.sample(&mut self.rng).unwrap().clone();
+            self.history = window;
+            self.evaluate();
+            // Labels were checked when added to the buffer
+            loss += cross_entropy(self.probs[label]);
+            self.update(label, None, learning_rate);
+            rounds += 1;
+            if !more(rounds) {
+                break;
+            }
+        }
+        self.history = history;
+        self.evaluate();
+        (rounds, loss)
+    }
+
+    /// Inputs and opponent moves of the rounds of a recorded game, see
+    /// `train_on_sequence()`.
+    fn sequence_rounds(&self, moves: &[u8]) -> Result<Vec<(Vec<Real>, usize)>, Error> {
+        let per_round = if self.input_size == self.output_size {
+            1
+        } else if self.input_size == 2 * self.output_size {
+            2
+        } else {
+            return Err(Error::Unsupported(
+                "recorded games need as many inputs as outputs, or twice as many",
+            ));
+        };
+        if !moves.len().is_multiple_of(per_round) {
+            return Err(Error::ShapeMismatch {
+                name: "moves",
+                expected: vec![moves.len() - moves.len() % per_round],
+                found: vec![moves.len()],
+            });
+        }
+        moves
+            .chunks(per_round)
+            .map(|round| {
+                let mut input = vec![0.0; self.input_size];
+                for (i, &m) in round.iter().enumerate() {
+                    check_label(m as usize, self.output_size)?;
+                    input[i * self.output_size + m as usize] = 1.0;
+                }
+                Ok((input, round[0] as usize))
+            })
+            .collect()
+    }
+
+    /// Trains the network once over `rounds` from the current history, as
+    /// returned by `sequence_rounds()`, and returns the mean loss of the
+    /// predictions, or 0 for fewer than 2 rounds.
+    fn sequence_pass(
+        &mut self,
+        rounds: &[(Vec<Real>, usize)],
+        learning_rate: Option<Real>,
+    ) -> Result<Real, Error> {
+        let mut loss = 0.0;
+        for pair in rounds.windows(2) {
+            loss += self.train_step(&pair[0].0, pair[1].1, learning_rate)?;
+        }
+        Ok(if rounds.len() < 2 {
+            0.0
+        } else {
+            loss / (rounds.len() - 1) as Real
+        })
+    }
+
+    /// Trains the network towards `label`, and the outcome head towards
+    /// `outcome` if given, for the last input.
+    fn learn(
+        &mut self,
+        label: usize,
+        outcome: Option<Outcome>,
+        learning_rate: Option<Real>,
+    ) -> Result<(), Error> {
+        check_label(label, self.output_size)?;
+        // Save the state in the buffers of the previous undo state, whose
+        // dropped rounds are gone for good
+        let mut undo = match self.undo.take() {
+            Some(mut undo) => {
+                undo.optim.clone_from(&self.optim);
+                undo.metrics.clone_from(&self.metrics);
+                undo
+            }
+            None => Box::new(Undo {
+                params: Vec::new(),
+                optim: self.optim.clone(),
+                schedule: self.schedule,
+                clip_count: self.clip_count,
+                metrics: self.metrics.clone(),
+                drift: self.drift.clone(),
+                evicted: None,
+                logged: None,
+            }),
+        };
+        let current = self.params();
+        undo.params.truncate(current.len());
+        for (i, param) in current.iter().enumerate() {
+            match undo.params.get_mut(i) {
+                Some(saved) if saved.shape() == param.shape() => saved.assign(param),
+                Some(saved) => *saved = param.to_owned(),
+                None => undo.params.push(param.to_owned()),
+            }
+        }
+        undo.schedule = self.schedule;
+        undo.clip_count = self.clip_count;
+        undo.drift.clone_from(&self.drift);
+        let predicted = Prediction::argmax(self.probs.as_slice().unwrap()).index;
+        self.metrics.record(predicted, label);
+        let loss = self.loss(label)?;
+        self.metrics.record_loss(loss);
+        let evicted = undo.evicted.take();
+        if self.replay.capacity() > 0 {
+            let window = match evicted {
+                Some((mut window, _)) if window.dim() == self.history.dim() => {
+                    window.assign(&self.history);
+                    window
+                }
+                _ => self.history.clone(),
+            };
+            undo.evicted = self.replay.push(window, label);
+        }
+        let output = &self.layers[self.layers.len() - 1].output;
+        let mut logits = undo
+            .logged
+            .take()
+            .map_or_else(Vec::new, |(logits, _)| logits);
+        logits.clear();
+        logits.extend(output.iter().copied());
+        undo.logged = self.calibration.record(logits, label);
+        self.undo = Some(undo);
+        self.schedule
+            .observe_accuracy(self.metrics.recent_accuracy());
+
+        if self.drift.record(predicted == label) {
+            match self.drift.response {
+                DriftResponse::Signal => {}
+                DriftResponse::ResetOutput => self.reset_output_layer(),
+                DriftResponse::BoostLearningRate => {
+                    self.drift.boost_rounds = self.config.accuracy_window;
+                }
+            }
+        }
+        let mut learning_rate = learning_rate.unwrap_or_else(|| self.schedule.next());
+        if self.drift.boost_rounds > 0 {
+            self.drift.boost_rounds -= 1;
+            learning_rate *= drift::BOOST;
+        }
+        self.update(label, outcome, Some(learning_rate));
+
+        #[cfg(feature = "wasm")]
+        if let Some(TrainCallback(callback)) = &self.on_train_step {
+            // The update is done, so an exception can't undo it
+            let _ = callback.apply(
+                &JsValue::NULL,
+                &js_sys::Array::of4(
+                    &self.metrics.total().into(),
+                    &loss.into(),
+                    &self.metrics.recent_accuracy().into(),
+                    &learning_rate.into(),
+                ),
+            );
+        }
+        Ok(())
+    }
+
+    /// Moves the weights towards `label`, smoothed according to
+    /// `NetworkConfig::label_smoothing()`, for the last evaluated input, and
+    /// the outcome head towards `outcome` if given.
+    fn update(&mut self, label: usize, outcome: Option<Outcome>, learning_rate: Option<Real>) {
+        let smoothing = self.config.label_smoothing;
+        let mut target = core::mem::take(&mut self.scratch.target);
+        fit(&mut target, Ix1(self.output_size));
+        target.fill(smoothing / self.output_size as Real);
+        target[label] += 1.0 - smoothing;
+        self.update_towards(target.view(), outcome, learning_rate);
+        self.scratch.target = target;
+    }
+
+    /// Moves the weights towards the `target` distribution for the last
+    /// evaluated input, minimizing the cross-entropy, and the outcome head
+    /// towards `outcome` if given. The gradients of the dense layers are
+    /// computed in place in `scratch`.
+    pub(crate) fn update_towards(
+        &mut self,
+        target: ArrayView1<Real>,
+        outcome: Option<Outcome>,
+        learning_rate: Option<Real>,
+    ) {
+        let learning_rate = learning_rate.unwrap_or_else(|| self.schedule.next());
+        let mut scratch = core::mem::take(&mut self.scratch);
+        self.layer_inputs_into(&mut scratch);
+        let Scratch {
+            input,
+            hidden,
+            grads,
+            dinput,
+            head_grads,
+            head_dinput,
+            ..
+        } = &mut scratch;
+        grads.resize_with(self.layers.len(), Default::default);
+        for (layer, (dweights, dbias)) in self.layers.iter().zip(grads.iter_mut()) {
+            fit(dweights, layer.weights.raw_dim());
+            fit(dbias, layer.bias.raw_dim());
+        }
+        let first = match &self.recurrent {
+            Some(recurrent) => recurrent.state().view(),
+            None => input.view(),
+        };
+        let layer_input = |i: usize| match i {
+            0 => first.view(),
+            _ => hidden[i - 1].view(),
+        };
+
+        // Compute the error between the predicted and target output, which
+        // is the gradient of the output bias
+        let last = self.layers.len() - 1;
+        Zip::from(&mut grads[last].1)
+            .and(&self.probs)
+            .and(&target)
+            .for_each(|delta, &p, &t| *delta = (p - t) / self.temperature);
+
+        // The outcome head reads the input of the output layer, so its
+        // gradient joins the one flowing back from there
+        let head = self.outcome.as_ref().zip(outcome);
+        if let Some((head, outcome)) = head {
+            let (dweights, delta) = head_grads;
+            fit(dweights, head.weights.raw_dim());
+            fit(delta, head.bias.raw_dim());
+            delta.assign(&self.outcome_probs);
+            delta[outcome.index()] -= 1.0;
+            outer_into(layer_input(last), delta.view(), dweights.view_mut());
+            dweights.scaled_add(self.weight_decay, &head.weights);
+            fit(head_dinput, Ix1(head.weights.nrows()));
+            mat_vec_into(head.weights.view(), delta.view(), head_dinput.view_mut());
+        }
+
+        // Backpropagate through the layers, from the output to the input,
+        // decaying the weights towards zero. The gradient of the input of a
+        // layer becomes the delta of the layer before it.
+        // Gradients of the recurrent or attention parameters, which come
+        // before the first dense layer
+        let mut input_grads = Vec::new();
+        for i in (0..self.layers.len()).rev() {
+            let layer = &self.layers[i];
+            let (before, rest) = grads.split_at_mut(i);
+            let (dweights, delta) = &mut rest[0];
+            outer_into(layer_input(i), delta.view(), dweights.view_mut());
+            dweights.scaled_add(self.weight_decay, &layer.weights);
+            let dinput = match before.last_mut() {
+                Some((_, next_delta)) => next_delta,
+                None => {
+                    fit(dinput, Ix1(layer.weights.nrows()));
+                    &mut *dinput
+                }
+            };
+            mat_vec_into(layer.weights.view(), delta.view(), dinput.view_mut());
+            if i == last && head.is_some() {
+                *dinput += &*head_dinput;
+            }
+            if i > 0 {
+                self.layers[i - 1].backprop_in_place(dinput.view_mut());
+            } else if let Some(recurrent) = &self.recurrent {
+                input_grads = recurrent.backward(dinput.clone(), self.weight_decay);
+            } else if let Some(attention) = &self.attention {
+                let dinput = dinput
+                    .view()
+                    .into_shape((self.history_size, self.input_size))
+                    .unwrap();
+                let history = self.decay(self.history.view());
+                input_grads = attention.backward(history.view(), dinput);
+            }
+        }
+        let mut head_grads = head.map(|_| head_grads);
+
+        // Clip the gradients to a maximum global norm
+        if let Some(max_grad_norm) = self.max_grad_norm {
+            let norm = grads
+                .iter()
+                .flat_map(|(dweights, dbias)| dweights.iter().chain(dbias))
+                .chain(input_grads.iter().flatten())
+                .chain(
+                    head_grads
+                        .iter()
+                        .flat_map(|(dweights, dbias)| dweights.iter().chain(dbias)),
+                )
+                .map(|g| g * g)
+                .sum::<Real>()
+                .sqrt();
+            if norm > max_grad_norm {
+                let scale = max_grad_norm / norm;
+                for (dweights, dbias) in grads.iter_mut() {
+                    *dweights *= scale;
+                    *dbias *= scale;
+                }
+                for grad in input_grads.iter_mut() {
+                    *grad *= scale;
+                }
+                if let Some((dweights, dbias)) = &mut head_grads {
+                    *dweights *= scale;
+                    *dbias *= scale;
+                }
+                self.clip_count += 1;
+            }
+        }
+
+        // Update the weights and biases
+        self.optim.begin_step();
+        for (i, (layer, (dweights, dbias))) in self.layers.iter_mut().zip(grads.iter()).enumerate()
+        {
+            self.optim.update(
+                2 * i,
+                layer.weights.view_mut(),
+                dweights.view(),
+                learning_rate,
+            );
+            self.optim.update(
+                2 * i + 1,
+                layer.bias.view_mut(),
+                dbias.view(),
+                learning_rate,
+            );
+        }
+        let params = match (&mut self.recurrent, &mut self.attention) {
+            (Some(recurrent), _) => recurrent.params_mut(),
+            (None, Some(attention)) => attention.params_mut(),
+            (None, None) => Vec::new(),
+        };
+        let offset = 2 * self.layers.len();
+        // The outcome head comes last
+        let head_index = offset + params.len();
+        for (j, (param, grad)) in params.into_iter().zip(&input_grads).enumerate() {
+            self.optim
+                .update(offset + j, param, grad.view(), learning_rate);
+        }
+        if let (Some(head), Some((dweights, dbias))) = (&mut self.outcome, &head_grads) {
+            self.optim.update(
+                head_index,
+                head.weights.view_mut(),
+                dweights.view(),
+                learning_rate,
+            );
+            self.optim.update(
+                head_index + 1,
+                head.bias.view_mut(),
+                dbias.view(),
+                learning_rate,
+            );
+        }
+        self.scratch = scratch;
+    }
+
+    /// Computes the output distribution for the current history and
+    /// recurrent state. Feedforward networks work in `scratch` and the
+    /// layers' own buffers, so that predicting a round doesn't allocate once
+    /// the buffers have their sizes; recurrent layers still cache each step
+    /// for backpropagation through time.
+    fn evaluate(&mut self) {
+        let mut scratch = core::mem::take(&mut self.scratch);
+        scratch
+            .hidden
+            .resize_with(self.layers.len() - 1, Default::default);
+        if self.recurrent.is_none() {
+            self.decay_into(&mut scratch.input);
+            let mut history = scratch
+                .input
+                .view_mut()
+                .into_shape((self.history_size, self.input_size))
+                .unwrap();
+            if let Some(attention) = &mut self.attention {
+                attention.forward(history.view());
+                attention.apply_in_place(history.view_mut());
+            }
+        }
+
+        // Compute hidden layer activations, dropping units at random while
+        // training
+        let dropout = if self.training { self.dropout } else { 0.0 };
+        let first = match &self.recurrent {
+            Some(recurrent) => recurrent.state().view(),
+            None => scratch.input.view(),
+        };
+        let (output, hidden) = self.layers.split_last_mut().unwrap();
+        for (i, layer) in hidden.iter_mut().enumerate() {
+            let (previous, next) = scratch.hidden.split_at_mut(i);
+            let x = previous.last().map_or(first.view(), |x| x.view());
+            layer.forward(x, dropout, &mut self.rng);
+            layer.dropped_output_into(&mut next[0]);
+        }
+        let x = scratch.hidden.last().map_or(first, |x| x.view());
+
+        // Compute output probabilities
+        output.forward(x.view(), 0.0, &mut self.rng);
+        self.probs.assign(&output.output);
+        if let Some(head) = &mut self.outcome {
+            head.forward(x.view(), 0.0, &mut self.rng);
+            self.outcome_probs.assign(&head.output);
+            softmax(&mut self.outcome_probs);
+        }
+        self.scratch = scratch;
+
+        // Apply softmax to output probabilities
+        let max_probs = self.probs.fold(Real::NEG_INFINITY, |a, &b| a.max(b));
+        let temperature = self.temperature;
+        self.probs
+            .mapv_inplace(|v| ((v - max_probs) / temperature).exp());
+        let sum = self.probs.sum();
+        self.probs /= sum;
+        self.mix_probs();
+    }
+
+    /// Updates `mixed_probs` from the output distribution and the
+    /// exploration setting, in place so that `probs_ptr()` stays valid.
+    fn mix_probs(&mut self) {
+        let floor = self.exploration / self.output_size as Real;
+        self.mixed_probs.resize(self.output_size, 0.0);
+        for (mixed, p) in self.mixed_probs.iter_mut().zip(&self.probs) {
+            *mixed = (1.0 - self.exploration) * p + floor;
+        }
+    }
+
+    /// Input seen by layer `i` during the last `forward()`.
+    fn layer_input(&self, i: usize) -> Array1<Real> {
+        match (i, &self.recurrent, &self.attention) {
+            (0, Some(recurrent), _) => recurrent.state().clone(),
+            (0, None, Some(attention)) => attention.apply(self.decay(self.history.view()).view()),
+            (0, None, None) => Array1::from(self.decay(self.history.view()).into_raw_vec()),
+            _ => self.layers[i - 1].dropped_output(),
+        }
+    }
+
+    /// Writes the inputs of the layers seen by the last `evaluate()` to
+    /// `scratch`, like `layer_input()`.
+    fn layer_inputs_into(&self, scratch: &mut Scratch) {
+        if self.recurrent.is_none() {
+            self.decay_into(&mut scratch.input);
+            if let Some(attention) = &self.attention {
+                let history = scratch
+                    .input
+                    .view_mut()
+                    .into_shape((self.history_size, self.input_size))
+                    .unwrap();
+                attention.apply_in_place(history);
+            }
+        }
+        scratch
+            .hidden
+            .resize_with(self.layers.len() - 1, Default::default);
+        for (layer, out) in self.layers.iter().zip(&mut scratch.hidden) {
+            layer.dropped_output_into(out);
+        }
+    }
+
+    /// Writes `decay()` of the history to `out`, flattened slot by slot,
+    /// which only allocates if `out` doesn't have the size of the window yet.
+    fn decay_into(&self, out: &mut Array1<Real>) {
+        fit(out, Ix1(self.history.len()));
+        for (slot, (mut values, history)) in out
+            .exact_chunks_mut(self.input_size)
+            .into_iter()
+            .zip(self.history.rows())
+            .enumerate()
+        {
+            values.assign(&history);
+            if self.history_decay != 1.0 {
+                values *= self.slot_decay(slot);
+            }
+        }
+    }
+
+    /// `history`, a window of `history_size` slots from the oldest to the
+    /// newest, with each slot scaled by its `slot_decay()`.
+    fn decay(&self, history: ArrayView2<Real>) -> Array2<Real> {
+        let mut decayed = history.to_owned();
+        if self.history_decay != 1.0 {
+            for (slot, mut values) in decayed.rows_mut().into_iter().enumerate() {
+                values *= self.slot_decay(slot);
+            }
+        }
+        decayed
+    }
+
+    /// Scale of history slot `slot`, the newest slot being the last one.
+    fn slot_decay(&self, slot: usize) -> Real {
+        self.history_decay
+            .powi((self.history_size - 1 - slot) as i32)
+    }
+
+    /// Checks that every tensor has the shape implied by the network sizes,
+    /// so that a corrupted or mismatched model is rejected on load instead of
+    /// panicking on the next `forward()`.
+    #[cfg(feature = "serde")]
+    fn validate(&self) -> Result<(), Error> {
+        let check = |name, expected: &[usize], found: &[usize]| {
+            if expected == found {
+                Ok(())
+            } else {
+                Err(Error::ShapeMismatch {
+                    name,
+                    expected: expected.to_vec(),
+                    found: found.to_vec(),
+                })
+            }
+        };
+
+        check(
+            "history",
+            &[self.history_size, self.input_size],
+            self.history.shape(),
+        )?;
+        // The recurrent layer, if any, stands in for the first dense layer
+        let min_layers = if self.recurrent.is_some() { 1 } else { 2 };
+        if self.layers.len() < min_layers {
+            return Err(Error::ShapeMismatch {
+                name: "layers",
+                expected: vec![min_layers],
+                found: vec![self.layers.len()],
+            });
+        }
+        let mut inputs = self.history_size * self.input_size;
+        if let Some(recurrent) = &self.recurrent {
+            inputs = recurrent.hidden_size();
+            recurrent.validate(self.input_size, inputs)?;
+        }
+        if let Some(attention) = &self.attention {
+            check("query", &[self.input_size], attention.query.shape())?;
+            check("position", &[self.history_size], attention.position.shape())?;
+            check("attention", &[self.history_size], attention.weights.shape())?;
+        }
+        check("layers", &[self.hidden_size], &[self.hidden_sizes()[0]])?;
+        for (i, layer) in self.layers.iter().enumerate() {
+            let outputs = if i + 1 == self.layers.len() {
+                self.output_size
+            } else {
+                layer.outputs()
+            };
+            check("weights", &[inputs, outputs], layer.weights.shape())?;
+            check("bias", &[outputs], layer.bias.shape())?;
+            check("pre_activation", &[outputs], layer.pre_activation.shape())?;
+            check("output", &[outputs], layer.output.shape())?;
+            check("dropout_mask", &[outputs], layer.dropout_mask.shape())?;
+            inputs = outputs;
+        }
+        check("probs", &[self.output_size], self.probs.shape())?;
+        if let Some(head) = &self.outcome {
+            let inputs = self.layers[self.layers.len() - 1].weights.nrows();
+            let outcomes = Outcome::ALL.len();
+            check("outcome", &[inputs, outcomes], head.weights.shape())?;
+            check("outcome_bias", &[outcomes], head.bias.shape())?;
+            check("outcome_probs", &[outcomes], self.outcome_probs.shape())?;
+        }
+
+        // The configuration must describe the layers, as `reset()` rebuilds
+        // the network from it
+        let config = &self.config;
+        check(
+            "config",
+            &[self.input_size, self.history_size, self.output_size],
+            &[config.input_size, config.history_size, config.output_size],
+        )?;
+        check("hidden_sizes", &self.hidden_sizes(), &config.hidden_sizes)?;
+        let architecture = self
+            .recurrent
+            .as_ref()
+            .map_or(Architecture::Feedforward, Recurrent::architecture);
+        let skip = self.recurrent.is_some() as usize;
+        let activations = self.layers.iter().enumerate().all(|(i, layer)| {
+            let expected =
+                (i + 1 < self.layers.len()).then(|| config.layer_activation_or_default(i + skip));
+            layer.activation == expected
+        });
+        if config.architecture != architecture
+            || config.attention != self.attention.is_some()
+            || config.outcome_head != self.outcome.is_some()
+            || !activations
+        {
+            return Err(Error::Unsupported(
+                "the configuration doesn't match the layers",
+            ));
+        }
+
+        let lens: Vec<usize> = self.params().iter().map(|param| param.len()).collect();
+        self.optim.validate(&lens)?;
+        self.metrics.validate(
+            self.output_size,
+            config.accuracy_window,
+            config.loss_history_size,
+        )?;
+        self.replay.validate(
+            config.replay_capacity,
+            self.history_size,
+            self.input_size,
+            self.output_size,
+        )?;
+        self.calibration.validate(self.output_size)?;
+        Ok(())
+    }
+
+    /// Copies the weights of `pretrained` where its tensors overlap with
+    /// this network's, see `from_pretrained()`.
+    #[cfg(feature = "serde")]
+    fn transfer(&mut self, pretrained: &RPSNetwork) -> Result<(), Error> {
+        for (name, expected, found) in [
+            ("input_size", self.input_size, pretrained.input_size),
+            ("output_size", self.output_size, pretrained.output_size),
+        ] {
+            if expected != found {
+                return Err(Error::ShapeMismatch {
+                    name,
+                    expected: vec![expected],
+                    found: vec![found],
+                });
+            }
+        }
+        if self.config.architecture != pretrained.config.architecture
+            || self.attention.is_some() != pretrained.attention.is_some()
+            || self.outcome.is_some() != pretrained.outcome.is_some()
+            || self.layers.len() != pretrained.layers.len()
+        {
+            return Err(Error::Unsupported(
+                "the pretrained network has a different architecture",
+            ));
+        }
+        if self.recurrent.is_some() && self.hidden_size != pretrained.hidden_size {
+            return Err(Error::Unsupported("recurrent layers can't be resized"));
+        }
+
+        let layers = self.layers.len();
+        let feedforward = self.recurrent.is_none();
+        let attention = self.attention.is_some();
+        let outcome = self.outcome.is_some();
+        let params = self.params_mut();
+        // The outcome head, if any, comes last
+        let head = outcome.then(|| params.len() - 2);
+        for (index, (mut param, saved)) in params.into_iter().zip(pretrained.params()).enumerate() {
+            // The first layer weights and the position scores go by history
+            // slot, matched from the most recent one
+            let from_end = feedforward && (index == 0 || attention && index == 2 * layers + 1);
+            let kept: Vec<usize> = param
+                .shape()
+                .iter()
+                .zip(saved.shape())
+                .map(|(len, saved)| *len.min(saved))
+                .collect();
+            let range = |axis: usize, len: usize| {
+                if from_end && axis == 0 {
+                    len - kept[0]..len
+                } else {
+                    0..kept[axis]
+                }
+            };
+            // Weights from new units or slots start at zero
+            if index < 2 * layers && index % 2 == 0 || head == Some(index) {
+                let rows = range(0, param.shape()[0]);
+                for (i, mut row) in param.axis_iter_mut(Axis(0)).enumerate() {
+                    if !rows.contains(&i) {
+                        row.fill(0.0);
+                    }
+                }
+            }
+            param
+                .slice_each_axis_mut(|axis| {
+                    ndarray::Slice::from(range(axis.axis.index(), axis.len))
+                })
+                .assign(&saved.slice_each_axis(|axis| {
+                    ndarray::Slice::from(range(axis.axis.index(), axis.len))
+                }));
+        }
+        Ok(())
+    }
+}
+
+/// Buffers reused by every `evaluate()`, sized on first use.
+#[derive(Clone, Default)]
+struct Scratch {
+    /// Input of the first layer of feedforward networks: the history
+    /// window, decayed and weighted by the attention if any.
+    input: Array1<Real>,
+    /// Output of each hidden layer as seen by the next one.
+    hidden: Vec<Array1<Real>>,
+    /// Target distribution of `update()`.
+    target: Array1<Real>,
+    /// Gradients of the weights and bias of each layer. The bias gradient
+    /// is also the delta backpropagated to the layer before.
+    grads: Vec<(Array2<Real>, Array1<Real>)>,
+    /// Gradient of the input of the first layer.
+    dinput: Array1<Real>,
+    /// Gradients of the weights and bias of the outcome head.
+    head_grads: (Array2<Real>, Array1<Real>),
+    /// Gradient of the input of the outcome head.
+    head_dinput: Array1<Real>,
+}
+
+/// Gives `buffer` the shape `dim`, which only allocates if it has another
+/// shape. The values are left unspecified.
+pub(crate) fn fit<D: Dimension>(buffer: &mut Array<Real, D>, dim: D) {
+    if buffer.raw_dim() != dim {
+        *buffer = Array::zeros(dim);
+    }
+}
+
+/// Training state saved by `backward()` for `undo_backward()`.
+#[derive(Clone)]
+struct Undo {
+    params: Vec<ArrayD<Real>>,
+    optim: OptimizerState,
+    schedule: Schedule,
+    clip_count: u32,
+    metrics: Metrics,
+    drift: DriftDetector,
+    /// Round dropped from the replay buffer by the last `backward()`.
+    evicted: Option<(Array2<Real>, usize)>,
+    /// Round dropped from the calibration log by the last `backward()`.
+    logged: Option<(Vec<Real>, usize)>,
+}
+
+/// Last `rows` rows of `a`, after rows of zeros if `a` has fewer.
+pub(crate) fn keep_last_rows(a: ArrayView2<Real>, rows: usize) -> Array2<Real> {
+    let kept = rows.min(a.nrows());
+    let mut resized = Array2::zeros((rows, a.ncols()));
+    resized
+        .slice_mut(s![rows - kept.., ..])
+        .assign(&a.slice(s![a.nrows() - kept.., ..]));
+    resized
+}
+
+/// First `rows` rows and `cols` columns of `a`, padded with zeros where `a`
+/// is smaller.
+pub(crate) fn keep_first(a: ArrayView2<Real>, rows: usize, cols: usize) -> Array2<Real> {
+    let (kept_rows, kept_cols) = (rows.min(a.nrows()), cols.min(a.ncols()));
+    let mut resized = Array2::zeros((rows, cols));
+    resized
+        .slice_mut(s![..kept_rows, ..kept_cols])
+        .assign(&a.slice(s![..kept_rows, ..kept_cols]));
+    resized
+}
+
+pub(crate) fn check_input(input: &[Real], input_size: usize) -> Result<(), Error> {
+    if input.len() == input_size {
+        Ok(())
+    } else {
+        Err(Error::ShapeMismatch {
+            name: "input",
+            expected: vec![input_size],
+            found: vec![input.len()],
+        })
+    }
+}
+
+pub(crate) fn check_label(label: usize, output_size: usize) -> Result<(), Error> {
+    if label < output_size {
+        Ok(())
+    } else {
+        Err(Error::InvalidMove {
+            index: label,
+            moves: output_size,
+        })
+    }
+}
+
+/// Turns `logits` into the distribution they give, in place.
+fn softmax(logits: &mut Array1<Real>) {
+    let max = logits.fold(Real::NEG_INFINITY, |a, &b| a.max(b));
+    logits.mapv_inplace(|v| (v - max).exp());
+    let sum = logits.sum();
+    *logits /= sum;
+}
+
+/// Cross-entropy loss of a prediction giving probability `p` to the label,
+/// kept finite when `p` underflows to zero.
+pub(crate) fn cross_entropy(p: Real) -> Real {
+    -p.max(Real::MIN_POSITIVE).ln()
+}
+
+/// Shannon entropy of `probs` in nats, from 0 for a certain prediction up to
+/// ln(n) for a uniform distribution over n moves.
+pub(crate) fn entropy(probs: &[Real]) -> Real {
+    -probs
+        .iter()
+        .filter(|&&p| p > 0.0)
+        .map(|p| p * p.ln())
+        .sum::<Real>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT_SIZE: usize = 3;
+    const HISTORY_SIZE: usize = 3;
+    const HIDDEN_SIZE: usize = 8;
+    const OUTPUT_SIZE: usize = 3;
+    const SEED: u64 = 0;
+
+    #[test]
+    fn init_network() {
+        let network = RPSNetwork::new(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE).unwrap();
+
+        assert_eq!(network.input_size, INPUT_SIZE);
+        assert_eq!(network.history_size, HISTORY_SIZE);
+        assert_eq!(network.hidden_size, HIDDEN_SIZE);
+        assert_eq!(network.output_size, OUTPUT_SIZE);
+        assert_eq!(network.hidden_sizes(), vec![HIDDEN_SIZE]);
+        assert_eq!(network.layers.len(), 2);
+        assert_eq!(
+            network.layers[0].weights.shape(),
+            vec![HISTORY_SIZE * INPUT_SIZE, HIDDEN_SIZE]
+        );
+        assert_eq!(network.layers[0].bias.shape(), vec![HIDDEN_SIZE]);
+        assert_eq!(network.history.shape(), vec![HISTORY_SIZE, INPUT_SIZE]);
+        assert_eq!(network.layers[0].output.shape(), vec![HIDDEN_SIZE]);
+        assert_eq!(
+            network.layers[1].weights.shape(),
+            vec![HIDDEN_SIZE, OUTPUT_SIZE]
+        );
+        assert_eq!(network.layers[1].bias.shape(), vec![OUTPUT_SIZE]);
+        assert_eq!(network.probs.shape(), vec![OUTPUT_SIZE]);
+    }
+
+    #[test]
+    fn history() {
+        let mut network =
+            RPSNetwork::new(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE).unwrap();
+
+        let input = vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+
+        network.forward(&input[..3]).unwrap();
+        network.forward(&input[3..6]).unwrap();
+        network.forward(&input[6..]).unwrap();
+
+        for (i, v) in input.iter().enumerate() {
+            assert_eq!(*v, network.history[(i / INPUT_SIZE, i % INPUT_SIZE)]);
+        }
+    }
+
+    #[test]
+    fn forward_pass() {
+        let mut network =
+            RPSNetwork::new(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE).unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        network.forward(&input).unwrap();
+
+        assert!(network.probs.iter().all(|v| *v != 0.0));
+    }
+
+    #[test]
+    fn backward_pass_success() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        network.forward(&input).unwrap();
+
+        let paper_prob = network.probs()[1];
+
+        for _ in 0..100 {
+            network.backward(1, Some(0.01)).unwrap();
+            network.forward(&input).unwrap();
+        }
+
+        assert!(paper_prob < network.probs()[1]);
+    }
+
+    #[test]
+    fn backward_pass_fail() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        network.forward(&input).unwrap();
+
+        let scissors_prob = network.probs()[2];
+
+        for _ in 0..100 {
+            network.backward(1, Some(0.01)).unwrap();
+            network.forward(&input).unwrap();
+        }
+
+        assert!(scissors_prob > network.probs()[2]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip() {
+        let mut network =
+            RPSNetwork::new(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE).unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        network.forward(&input).unwrap();
+        network.backward(1, Some(0.01)).unwrap();
+        network.forward(&input).unwrap();
+
+        let json = network.to_json().unwrap();
+        let mut restored = RPSNetwork::from_json(&json).unwrap();
+
+        assert_eq!(restored.history_size, HISTORY_SIZE);
+        assert_eq!(restored.layers, network.layers);
+        assert_eq!(restored.history, network.history);
+        assert_eq!(restored.probs(), network.probs());
+
+        network.forward(&input).unwrap();
+        restored.forward(&input).unwrap();
+
+        assert_eq!(restored.probs(), network.probs());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn bytes_round_trip() {
+        let mut network =
+            RPSNetwork::new(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE).unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        network.forward(&input).unwrap();
+        network.backward(1, Some(0.01)).unwrap();
+        network.forward(&input).unwrap();
+
+        let bytes = network.to_bytes().unwrap();
+        let restored = RPSNetwork::from_bytes(&bytes).unwrap();
+
+        assert!(bytes.len() < network.to_json().unwrap().len());
+        assert_eq!(restored.layers, network.layers);
+        assert_eq!(restored.history, network.history);
+        assert_eq!(restored.probs, network.probs);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn load_rejects_mismatched_shapes() {
+        let mut network =
+            RPSNetwork::new(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE).unwrap();
+        network.hidden_size += 1;
+
+        let bytes = network.to_bytes().unwrap();
+
+        assert!(matches!(
+            RPSNetwork::from_bytes(&bytes),
+            Err(Error::ShapeMismatch { name: "layers", .. })
+        ));
+        assert!(RPSNetwork::from_bytes(&bytes[..bytes.len() / 2]).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn load_rejects_mismatched_state() {
+        let config = NetworkConfig::with_sizes(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE)
+            .optimizer(Optimizer::Adam)
+            .replay_capacity(4)
+            .seed(SEED);
+        let train = |network: &mut RPSNetwork| {
+            network.forward(&[1.0, 0.0, 0.0]).unwrap();
+            network.backward(1, Some(0.01)).unwrap();
+        };
+        let mut network = config.build().unwrap();
+        train(&mut network);
+        let mut wider = config
+            .clone()
+            .history_size(HISTORY_SIZE + 1)
+            .hidden_size(HIDDEN_SIZE + 1)
+            .build()
+            .unwrap();
+        train(&mut wider);
+        let load = |network: &RPSNetwork| RPSNetwork::from_bytes(&network.to_bytes().unwrap());
+        assert!(load(&network).is_ok());
+
+        let mut tampered = network.clone();
+        tampered.optim = wider.optim.clone();
+        assert!(matches!(
+            load(&tampered),
+            Err(Error::ShapeMismatch { name: "m", .. })
+        ));
+
+        let mut tampered = network.clone();
+        tampered.metrics = Metrics::new(20, OUTPUT_SIZE + 1, 100);
+        assert!(matches!(
+            load(&tampered),
+            Err(Error::ShapeMismatch {
+                name: "classes",
+                ..
+            })
+        ));
+
+        let mut tampered = network.clone();
+        tampered.replay = wider.replay.clone();
+        assert!(matches!(
+            load(&tampered),
+            Err(Error::ShapeMismatch { name: "replay", .. })
+        ));
+
+        let mut tampered = network.clone();
+        tampered.calibration.record(vec![0.0; OUTPUT_SIZE + 1], 0);
+        assert!(matches!(
+            load(&tampered),
+            Err(Error::ShapeMismatch { name: "logits", .. })
+        ));
+
+        let mut tampered = network.clone();
+        tampered.config.hidden_sizes = vec![HIDDEN_SIZE + 1];
+        assert!(matches!(
+            load(&tampered),
+            Err(Error::ShapeMismatch {
+                name: "hidden_sizes",
+                ..
+            })
+        ));
+
+        let mut tampered = network.clone();
+        tampered.config = tampered.config.activation(Activation::ReLU);
+        assert!(matches!(load(&tampered), Err(Error::Unsupported(_))));
+    }
+
+    #[test]
+    fn seeded_init_is_reproducible() {
+        let mut a =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, 42)
+                .unwrap();
+        let mut b =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, 42)
+                .unwrap();
+        let c = RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, 43)
+            .unwrap();
+
+        assert_eq!(a.layers, b.layers);
+        assert_ne!(a.layers, c.layers);
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        for _ in 0..10 {
+            a.forward(&input).unwrap();
+            b.forward(&input).unwrap();
+            a.backward(2, Some(0.1)).unwrap();
+            b.backward(2, Some(0.1)).unwrap();
+        }
+
+        assert_eq!(a.layers, b.layers);
+        assert_eq!(a.probs(), b.probs());
+    }
+
+    #[test]
+    fn adam_backward_pass() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .optimizer(Optimizer::Adam)
+            .seed(SEED)
+            .build()
+            .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        network.forward(&input).unwrap();
+
+        let paper_prob = network.probs()[1];
+
+        for _ in 0..20 {
+            network.backward(1, Some(0.01)).unwrap();
+            network.forward(&input).unwrap();
+        }
+
+        assert!(paper_prob < network.probs()[1]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn momentum_survives_serialization() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .optimizer(Optimizer::Momentum)
+            .seed(SEED)
+            .build()
+            .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        for _ in 0..5 {
+            network.forward(&input).unwrap();
+            network.backward(1, Some(0.01)).unwrap();
+        }
+
+        let mut restored = RPSNetwork::from_bytes(&network.to_bytes().unwrap()).unwrap();
+
+        assert_eq!(restored.optim, network.optim);
+
+        network.forward(&input).unwrap();
+        network.backward(1, Some(0.01)).unwrap();
+        restored.forward(&input).unwrap();
+        restored.backward(1, Some(0.01)).unwrap();
+
+        assert_eq!(restored.layers, network.layers);
+    }
+
+    #[test]
+    fn backward_follows_schedule() {
+        let mut network =
+            RPSNetwork::new(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE).unwrap();
+
+        assert_eq!(network.learning_rate(), DEFAULT_LEARNING_RATE);
+
+        network.set_schedule(&Schedule::exponential_decay(0.1, 0.5).unwrap());
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        network.forward(&input).unwrap();
+        network.backward(1, None).unwrap();
+        network.backward(1, Some(1.0)).unwrap();
+
+        assert_eq!(network.learning_rate(), 0.05);
+    }
+
+    #[test]
+    fn accuracy_adaptive_schedule() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+        network.set_schedule(&Schedule::accuracy_adaptive(0.01, 0.5).unwrap());
+        assert_eq!(network.learning_rate(), 0.5);
+
+        // The learning rate drops as the network learns a constant opponent
+        network.forward(&[1.0, 0.0, 0.0]).unwrap();
+        for _ in 0..30 {
+            network.backward(0, None).unwrap();
+            network.forward(&[1.0, 0.0, 0.0]).unwrap();
+        }
+        assert_eq!(network.recent_accuracy(), 1.0);
+        assert_eq!(network.learning_rate(), 0.01);
+
+        // And rises when it stops predicting right
+        network.backward(1, None).unwrap();
+        assert!(network.learning_rate() > 0.01);
+        network.undo_backward();
+        assert_eq!(network.learning_rate(), 0.01);
+    }
+
+    #[test]
+    fn dropout_only_while_training() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .dropout(0.5)
+            .seed(SEED)
+            .build()
+            .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        network.set_training(true);
+        network.forward(&input).unwrap();
+
+        let mask = &network.layers[0].dropout_mask;
+        assert!(mask.iter().any(|m| *m == 0.0));
+        assert!(mask.iter().all(|m| *m == 0.0 || *m == 2.0));
+
+        network.backward(1, Some(0.1)).unwrap();
+        network.set_training(false);
+        network.forward(&input).unwrap();
+
+        assert!(network.layers[0].dropout_mask.iter().all(|m| *m == 1.0));
+    }
+
+    #[test]
+    fn weight_decay_shrinks_weights() {
+        let config = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .seed(SEED);
+        let mut plain = config.build().unwrap();
+        let mut decayed = config.weight_decay(0.1).build().unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        for _ in 0..20 {
+            plain.forward(&input).unwrap();
+            plain.backward(1, Some(0.1)).unwrap();
+            decayed.forward(&input).unwrap();
+            decayed.backward(1, Some(0.1)).unwrap();
+        }
+
+        let plain_norms = plain.weight_norms();
+        let decayed_norms = decayed.weight_norms();
+
+        assert_eq!(decayed_norms.len(), 2);
+        assert!(decayed_norms[0] < plain_norms[0]);
+        assert!(decayed_norms[1] < plain_norms[1]);
+    }
+
+    #[test]
+    fn calibration() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+        assert_eq!(network.calibrate(), 1.0);
+        assert_eq!(network.calibrated_probs(), network.probs());
+
+        // A large learning rate makes the network overconfident against an
+        // opponent with no pattern
+        let mut rng = ChaCha8Rng::seed_from_u64(SEED);
+        network.forward(&[1.0, 0.0, 0.0]).unwrap();
+        for _ in 0..150 {
+            let m = rng.gen_range(0..OUTPUT_SIZE);
+            network.backward(m, Some(0.5)).unwrap();
+            network.forward(&moves::one_hot(m, INPUT_SIZE)).unwrap();
+        }
+        let temperature = network.calibrate();
+        assert!(temperature > 1.0, "{}", temperature);
+        assert_eq!(network.calibration_temperature(), temperature);
+        let max = |probs: Vec<Real>| probs.into_iter().fold(0.0, Real::max);
+        assert!(max(network.calibrated_probs()) < max(network.probs()));
+
+        network.backward(0, None).unwrap();
+        assert!(network.undo_backward());
+        assert_eq!(network.calibrate(), temperature);
+    }
+
+    #[test]
+    fn explain() {
+        for attention in [false, true] {
+            let mut network = NetworkConfig::new()
+                .input_size(INPUT_SIZE)
+                .history_size(HISTORY_SIZE)
+                .hidden_size(HIDDEN_SIZE)
+                .output_size(OUTPUT_SIZE)
+                .attention(attention)
+                .seed(SEED)
+                .build()
+                .unwrap();
+            assert_eq!(network.explain().unwrap(), vec![0.0; HISTORY_SIZE]);
+
+            // The label is always the newest input, the older ones are noise
+            let mut rng = ChaCha8Rng::seed_from_u64(SEED);
+            for _ in 0..300 {
+                let m = rng.gen_range(0..OUTPUT_SIZE);
+                network.forward(&moves::one_hot(m, INPUT_SIZE)).unwrap();
+                network.backward(m, Some(0.1)).unwrap();
+            }
+            let contributions = network.explain().unwrap();
+            assert_eq!(contributions.len(), HISTORY_SIZE);
+            let newest = contributions[HISTORY_SIZE - 1];
+            assert!(
+                contributions[..HISTORY_SIZE - 1]
+                    .iter()
+                    .all(|c| c.abs() < newest),
+                "{:?}",
+                contributions
+            );
+        }
+
+        let recurrent = NetworkConfig::new()
+            .architecture(Architecture::Gru)
+            .build()
+            .unwrap();
+        assert!(recurrent.explain().is_err());
+    }
+
+    #[test]
+    fn memory_footprint() {
+        let config = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .optimizer(Optimizer::Adam)
+            .seed(SEED);
+        let mut network = config.build().unwrap();
+        // (9 x 8 + 8) + (8 x 3 + 3)
+        assert_eq!(network.param_count(), 107);
+        let fresh = network.memory_bytes();
+        assert!(fresh > 107 * core::mem::size_of::<Real>());
+
+        // Adam allocates two moments per parameter
+        train_on_pattern(&mut network, &[0, 1, 2], 5);
+        let trained = network.memory_bytes();
+        assert!(trained >= fresh + 2 * 107 * core::mem::size_of::<Real>());
+
+        let mut larger = config.hidden_size(2 * HIDDEN_SIZE).build().unwrap();
+        assert_eq!(larger.param_count(), 211);
+        train_on_pattern(&mut larger, &[0, 1, 2], 5);
+        assert!(larger.memory_bytes() > trained);
+    }
+
+    #[test]
+    fn train_for() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .replay_capacity(20)
+            .seed(SEED)
+            .build()
+            .unwrap();
+        assert_eq!(network.train_for(5.0, Some(0.1)), 0);
+
+        train_on_pattern(&mut network, &[0, 1, 2], 10);
+        let probs = network.probs();
+        assert_eq!(network.train_for(0.0, Some(0.1)), 0);
+        assert_eq!(network.probs(), probs);
+        let rounds = network.train_for(5.0, Some(0.1));
+        assert!(rounds > 0);
+        assert_ne!(network.probs(), probs);
+        assert!(!network.undo_backward());
+    }
+
+    #[test]
+    fn label_smoothing() {
+        let config = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .seed(SEED);
+        let mut plain = config.build().unwrap();
+        let mut smoothed = config.label_smoothing(0.3).build().unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+        for _ in 0..200 {
+            plain.forward(&input).unwrap();
+            plain.backward(0, Some(0.5)).unwrap();
+            smoothed.forward(&input).unwrap();
+            smoothed.backward(0, Some(0.5)).unwrap();
+        }
+        plain.forward(&input).unwrap();
+        smoothed.forward(&input).unwrap();
+
+        // The smoothed target gives the label 0.7 + 0.3 / 3
+        assert!(plain.probs()[0] > 0.99);
+        assert!(
+            (smoothed.probs()[0] - 0.8).abs() < 0.02,
+            "{:?}",
+            smoothed.probs()
+        );
+        assert!(smoothed.probs()[1] > 0.05 && smoothed.probs()[2] > 0.05);
+    }
+
+    /// Compares the updates of `backward()` on a network with two hidden
+    /// layers with finite differences of the loss, for every parameter.
+    #[test]
+    fn dense_gradients() {
+        let mut fresh = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_sizes(&[HIDDEN_SIZE, 4])
+            .output_size(OUTPUT_SIZE)
+            .init(Init::Xavier)
+            .seed(SEED)
+            .build()
+            .unwrap();
+        fresh
+            .seed_history(&[0.5, -1.0, 0.0, 1.0, 0.0, 0.3, -0.2, 0.0, 1.0])
+            .unwrap();
+        for i in 0..fresh.parameter_count() {
+            // Non-zero biases, so that they don't hide mistakes
+            if fresh.weight_shape(i).unwrap().len() == 1 {
+                let bias: Vec<Real> = (0..fresh.get_weights(i).unwrap().len())
+                    .map(|j| 0.1 * j as Real - 0.2)
+                    .collect();
+                fresh.set_weights(i, &bias).unwrap();
+            }
+        }
+        let input: Vec<Real> = vec![0.0, 1.0, 0.0];
+        let label = 2;
+        let loss = |network: &mut RPSNetwork| {
+            network.forward(&input).unwrap();
+            network.loss(label).unwrap()
+        };
+
+        let learning_rate = 1e-3;
+        let mut network = fresh.clone();
+        loss(&mut network);
+        network.backward(label, Some(learning_rate)).unwrap();
+
+        let eps = 1e-2;
+        for k in 0..fresh.parameter_count() {
+            let before = fresh.get_weights(k).unwrap();
+            let after = network.get_weights(k).unwrap();
+            for j in 0..before.len() {
+                let analytical = (before[j] - after[j]) / learning_rate;
+                let perturbed = |delta| {
+                    let mut network = fresh.clone();
+                    let mut values = before.clone();
+                    values[j] += delta;
+                    network.set_weights(k, &values).unwrap();
+                    loss(&mut network)
+                };
+                let numerical = (perturbed(eps) - perturbed(-eps)) / (2.0 * eps);
+
+                assert!(
+                    (numerical - analytical).abs() < 1e-2,
+                    "param {}[{}]: {}, expected {}",
+                    k,
+                    j,
+                    analytical,
+                    numerical
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn gradient_clipping() {
+        let config = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .seed(SEED);
+        let mut network = config.clone().max_grad_norm(1e-3).build().unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        network.forward(&input).unwrap();
+        let w2 = network.layers[1].weights.clone();
+        network.backward(1, Some(1.0)).unwrap();
+
+        assert_eq!(network.clip_count(), 1);
+
+        // The whole update is bounded by the learning rate times the max norm.
+        let delta = (&network.layers[1].weights - &w2)
+            .iter()
+            .map(|d| d * d)
+            .sum::<Real>()
+            .sqrt();
+        assert!(delta <= 1e-3 + 1e-6);
+
+        let mut network = config.max_grad_norm(1e6).build().unwrap();
+
+        network.forward(&input).unwrap();
+        network.backward(1, Some(1.0)).unwrap();
+
+        assert_eq!(network.clip_count(), 0);
+    }
+
+    #[test]
+    fn backward_pass_with_activations() {
+        for activation in [Activation::ReLU, Activation::LeakyReLU, Activation::Sigmoid] {
+            let mut network = NetworkConfig::new()
+                .input_size(INPUT_SIZE)
+                .history_size(HISTORY_SIZE)
+                .hidden_size(HIDDEN_SIZE)
+                .output_size(OUTPUT_SIZE)
+                .activation(activation)
+                .init(Init::He)
+                .seed(SEED)
+                .build()
+                .unwrap();
+
+            let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+            network.forward(&input).unwrap();
+
+            let paper_prob = network.probs()[1];
+
+            for _ in 0..100 {
+                network.backward(1, Some(0.01)).unwrap();
+                network.forward(&input).unwrap();
+            }
+
+            assert!(paper_prob < network.probs()[1], "{:?}", activation);
+        }
+    }
+
+    #[test]
+    fn deep_network() {
+        let hidden_sizes = [HIDDEN_SIZE, 6, 4];
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_sizes(&hidden_sizes)
+            .output_size(OUTPUT_SIZE)
+            .layer_activation(1, Activation::ReLU)
+            .init(Init::Xavier)
+            .seed(SEED)
+            .build()
+            .unwrap();
+
+        assert_eq!(network.hidden_size, HIDDEN_SIZE);
+        assert_eq!(network.hidden_sizes(), hidden_sizes.to_vec());
+        assert_eq!(network.weight_norms().len(), 4);
+        assert_eq!(network.layers[1].activation, Some(Activation::ReLU));
+        assert_eq!(network.layers[3].activation, None);
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        network.forward(&input).unwrap();
+
+        let paper_prob = network.probs()[1];
+
+        for _ in 0..100 {
+            network.backward(1, Some(0.01)).unwrap();
+            network.forward(&input).unwrap();
+        }
+
+        assert!(paper_prob < network.probs()[1]);
+
+        let network =
+            RPSNetwork::with_hidden_sizes(INPUT_SIZE, HISTORY_SIZE, &hidden_sizes, OUTPUT_SIZE)
+                .unwrap();
+
+        assert_eq!(network.hidden_sizes(), hidden_sizes.to_vec());
+        assert!(RPSNetwork::with_hidden_sizes(INPUT_SIZE, HISTORY_SIZE, &[], OUTPUT_SIZE).is_err());
+        assert!(
+            RPSNetwork::with_hidden_sizes(INPUT_SIZE, HISTORY_SIZE, &[4, 0], OUTPUT_SIZE).is_err()
+        );
+    }
+
+    /// Plays the repeating moves `pattern`, training the network to predict
+    /// each next move from the last one, and returns how many of the moves of
+    /// a final cycle were predicted correctly.
+    fn train_on_pattern(network: &mut RPSNetwork, pattern: &[usize], rounds: usize) -> usize {
+        let one_hot = |m: usize| {
+            let mut input = vec![0.0; OUTPUT_SIZE];
+            input[m] = 1.0;
+            input
+        };
+
+        for round in 0..rounds {
+            network
+                .forward(&one_hot(pattern[round % pattern.len()]))
+                .unwrap();
+            network
+                .backward(pattern[(round + 1) % pattern.len()], Some(0.05))
+                .unwrap();
+        }
+
+        (rounds..rounds + pattern.len())
+            .filter(|&round| {
+                network
+                    .forward(&one_hot(pattern[round % pattern.len()]))
+                    .unwrap();
+                network.predict().index == pattern[(round + 1) % pattern.len()]
+            })
+            .count()
+    }
+
+    #[test]
+    fn recurrent_network_sees_past_history() {
+        // After a rock, the next move depends on the move before it, which a
+        // one-round history window can't see.
+        let pattern = [0, 0, 1, 2];
+        let config = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(1)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .init(Init::Xavier)
+            .seed(SEED);
+        let mut feedforward = config.build().unwrap();
+        let mut elman = config
+            .architecture(Architecture::Elman)
+            .bptt_steps(4)
+            .build()
+            .unwrap();
+
+        assert_eq!(elman.hidden_sizes(), vec![HIDDEN_SIZE]);
+        assert_eq!(elman.weight_norms().len(), 3);
+        assert!(train_on_pattern(&mut feedforward, &pattern, 2000) < pattern.len());
+        assert_eq!(train_on_pattern(&mut elman, &pattern, 2000), pattern.len());
+    }
+
+    #[test]
+    fn gated_networks_learn_long_cycle() {
+        // A human cycling through 7 moves, where the last move alone says
+        // little about the next one
+        let pattern = [0, 0, 1, 0, 2, 2, 1];
+
+        for architecture in [Architecture::Lstm, Architecture::Gru] {
+            let mut network = NetworkConfig::new()
+                .input_size(INPUT_SIZE)
+                .history_size(1)
+                .hidden_size(HIDDEN_SIZE)
+                .output_size(OUTPUT_SIZE)
+                .architecture(architecture)
+                .bptt_steps(pattern.len())
+                .init(Init::Xavier)
+                .optimizer(Optimizer::Adam)
+                .seed(SEED)
+                .build()
+                .unwrap();
+
+            assert_eq!(network.hidden_sizes(), vec![HIDDEN_SIZE]);
+            assert_eq!(
+                train_on_pattern(&mut network, &pattern, 3000),
+                pattern.len(),
+                "{:?}",
+                architecture
+            );
+        }
+    }
+
+    #[test]
+    fn attention_over_history() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .attention(true)
+            .seed(SEED)
+            .build()
+            .unwrap();
+
+        assert!(
+            RPSNetwork::new(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE)
+                .unwrap()
+                .attention_weights()
+                .is_empty()
+        );
+        assert_eq!(network.attention_weights(), vec![1.0 / 3.0; HISTORY_SIZE]);
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        network.forward(&input).unwrap();
+
+        let paper_prob = network.probs()[1];
+
+        for _ in 0..100 {
+            network.backward(1, Some(0.01)).unwrap();
+            network.forward(&input).unwrap();
+        }
+
+        let weights = network.attention_weights();
+
+        assert!(paper_prob < network.probs()[1]);
+        assert_ne!(weights, vec![1.0 / 3.0; HISTORY_SIZE]);
+        assert!((weights.iter().sum::<Real>() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn temperature() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        // Fill the history, so that only the temperature changes below
+        for _ in 0..HISTORY_SIZE {
+            network.forward(&input).unwrap();
+        }
+        let probs = network.probs();
+        let max = |probs: &[Real]| probs.iter().cloned().fold(0.0, Real::max);
+
+        network.set_temperature(0.5).unwrap();
+        network.forward(&input).unwrap();
+        assert!(max(&network.probs()) > max(&probs));
+
+        network.set_temperature(2.0).unwrap();
+        network.forward(&input).unwrap();
+        assert!(max(&network.probs()) < max(&probs));
+
+        assert!(network.set_temperature(0.0).is_err());
+        assert!(network.set_temperature(Real::NAN).is_err());
+        assert_eq!(network.temperature, 2.0);
+    }
+
+    #[test]
+    fn merge() {
+        let config = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .seed(SEED);
+        let mut a = config.build().unwrap();
+        let mut b = config.build().unwrap();
+        train_on_pattern(&mut a, &[0, 1, 2], 20);
+        train_on_pattern(&mut b, &[2, 2, 1], 20);
+
+        let mut merged = a.clone();
+        merged.merge(&b, 0.5).unwrap();
+        for index in 0..merged.parameter_count() {
+            let (a, b) = (a.get_weights(index).unwrap(), b.get_weights(index).unwrap());
+            for ((m, a), b) in merged.get_weights(index).unwrap().iter().zip(a).zip(b) {
+                assert!((m - (a + b) / 2.0).abs() < 1e-6);
+            }
+        }
+        assert_ne!(merged.probs(), a.probs());
+
+        let mut copy = a.clone();
+        copy.merge(&b, 1.0).unwrap();
+        assert_eq!(copy.get_weights(0), b.get_weights(0));
+        let mut kept = a.clone();
+        kept.merge(&b, 0.0).unwrap();
+        assert_eq!(kept.probs(), a.probs());
+
+        assert!(a.merge(&b, 1.5).is_err());
+        assert!(a.merge(&b, Real::NAN).is_err());
+        let larger = config.clone().hidden_size(HIDDEN_SIZE + 1).build().unwrap();
+        assert_eq!(
+            a.merge(&larger, 0.5),
+            Err(Error::ParameterMismatch {
+                index: 0,
+                expected: vec![HISTORY_SIZE * INPUT_SIZE, HIDDEN_SIZE],
+                found: vec![HISTORY_SIZE * INPUT_SIZE, HIDDEN_SIZE + 1],
+            })
+        );
+        let longer = config
+            .clone()
+            .history_size(HISTORY_SIZE + 1)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            a.merge(&longer, 0.5),
+            Err(Error::ShapeMismatch {
+                name: "history_size",
+                ..
+            })
+        ));
+
+        // Networks whose parameters have the same shapes but another meaning
+        let relu = config.clone().activation(Activation::ReLU).build().unwrap();
+        let mut decayed = config.clone().build().unwrap();
+        decayed.set_history_decay(0.5).unwrap();
+        let recurrent = config
+            .clone()
+            .architecture(Architecture::Elman)
+            .build()
+            .unwrap();
+        for other in [relu, decayed, recurrent] {
+            assert!(matches!(a.merge(&other, 0.5), Err(Error::Unsupported(_))));
+        }
+    }
+
+    #[test]
+    fn logits() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+        network.set_temperature(2.0).unwrap();
+        network.forward(&[1.0, 0.0, 0.0]).unwrap();
+
+        // The softmax of the logits over the temperature gives the probs
+        let logits = network.logits();
+        assert_eq!(logits.len(), OUTPUT_SIZE);
+        let mut probs = Array1::from(logits).mapv(|l| l / 2.0);
+        softmax(&mut probs);
+        for (p, expected) in probs.iter().zip(network.probs()) {
+            assert!((p - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn history_decay() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+        network.set_history_decay(0.0).unwrap();
+        let mut other = network.clone();
+
+        // Only the newest slot is left, so older rounds don't matter
+        network.forward(&[1.0, 0.0, 0.0]).unwrap();
+        other.forward(&[0.0, 0.0, 1.0]).unwrap();
+        for input in [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0]] {
+            network.forward(&input).unwrap();
+            other.forward(&input).unwrap();
+            assert_eq!(network.probs(), other.probs());
+        }
+        let batch = network.forward_batch(&network.history(), 1).unwrap();
+        for (b, p) in batch.iter().zip(network.probs()) {
+            assert!((b - p).abs() < 1e-5);
+        }
+
+        network.set_history_decay(0.5).unwrap();
+        network.forward(&[0.0, 1.0, 0.0]).unwrap();
+        let quantized = network.quantize().unwrap();
+        for (q, p) in quantized.probs().iter().zip(network.probs()) {
+            assert!((q - p).abs() < 0.05);
+        }
+
+        #[cfg(feature = "serde")]
+        {
+            let restored = RPSNetwork::from_json(&network.to_json().unwrap()).unwrap();
+            assert_eq!(restored.history_decay, 0.5);
+        }
+        assert!(network.set_history_decay(1.5).is_err());
+        assert!(network.set_history_decay(Real::NAN).is_err());
+    }
+
+    #[test]
+    fn drift_detection() {
+        for response in [
+            DriftResponse::Signal,
+            DriftResponse::ResetOutput,
+            DriftResponse::BoostLearningRate,
+        ] {
+            let mut network =
+                RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                    .unwrap();
+            network.set_drift_detection(1.5, response).unwrap();
+            let play = |network: &mut RPSNetwork, label: usize| {
+                network.backward(label, Some(0.1)).unwrap();
+                network.forward(&moves::one_hot(label, 3)).unwrap();
+                network.drift_detected()
+            };
+
+            // The opponent always plays rock, then switches to paper
+            network.forward(&[1.0, 0.0, 0.0]).unwrap();
+            for _ in 0..50 {
+                play(&mut network, 0);
+            }
+            assert_eq!(network.drift_count(), 0);
+            assert_eq!(network.drift_score(), 0.0);
+            let output = network.get_weights(2).unwrap();
+            let detected = (0..20).position(|_| play(&mut network, 1)).unwrap();
+            assert!(detected < 5, "{:?}: {}", response, detected);
+            assert_eq!(network.drift_count(), 1);
+            assert_eq!(
+                network.drift.boost_rounds > 0,
+                response == DriftResponse::BoostLearningRate
+            );
+
+            // Undoing the update of the detection takes it back
+            network.undo_backward();
+            assert_eq!(network.drift_count(), 0);
+            assert!(network.drift_score() > 0.0);
+            assert_eq!(network.drift.boost_rounds, 0);
+            if response == DriftResponse::ResetOutput {
+                network.backward(1, Some(0.1)).unwrap();
+                let reset = network.get_weights(2).unwrap();
+                assert!(reset.iter().zip(&output).all(|(r, o)| (r - o).abs() > 1e-3));
+            }
+        }
+
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+        assert!(network
+            .set_drift_detection(0.0, DriftResponse::Signal)
+            .is_err());
+    }
+
+    #[test]
+    fn resize_history() {
+        for attention in [false, true] {
+            let mut network = NetworkConfig::new()
+                .input_size(INPUT_SIZE)
+                .history_size(HISTORY_SIZE)
+                .hidden_size(HIDDEN_SIZE)
+                .output_size(OUTPUT_SIZE)
+                .attention(attention)
+                .optimizer(Optimizer::Adam)
+                .replay_capacity(10)
+                .seed(SEED)
+                .build()
+                .unwrap();
+            train_on_pattern(&mut network, &[0, 1, 2], 20);
+            let history = network.history();
+            let probs = network.probs();
+
+            // Empty older slots with zero weights leave the prediction as is
+            network.resize_history(5).unwrap();
+            assert_eq!(network.history_size, 5);
+            assert_eq!(network.history()[..6], [0.0; 6]);
+            assert_eq!(network.history()[6..], history[..]);
+            if !attention {
+                assert_eq!(network.probs(), probs);
+            }
+
+            network.resize_history(2).unwrap();
+            assert_eq!(network.history(), history[3..]);
+            assert_eq!(
+                network.weight_shape(0),
+                Ok(vec![2 * INPUT_SIZE, HIDDEN_SIZE])
+            );
+
+            train_on_pattern(&mut network, &[0, 1, 2], 20);
+            network.train_from_replay(5, None);
+            #[cfg(feature = "serde")]
+            {
+                let restored = RPSNetwork::from_json(&network.to_json().unwrap()).unwrap();
+                assert_eq!(restored.history_size, 2);
+            }
+            network.reset();
+            assert_eq!(network.history_size, 2);
+        }
+
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+        assert!(network.resize_history(0).is_err());
+        let mut recurrent = NetworkConfig::new()
+            .architecture(Architecture::Elman)
+            .build()
+            .unwrap();
+        assert!(recurrent.resize_history(5).is_err());
+    }
+
+    #[test]
+    fn resize_hidden() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_sizes(&[HIDDEN_SIZE, 4])
+            .output_size(OUTPUT_SIZE)
+            .optimizer(Optimizer::Adam)
+            .seed(SEED)
+            .build()
+            .unwrap();
+        train_on_pattern(&mut network, &[0, 1, 2], 20);
+        let probs = network.probs();
+        let weights = network.get_weights(0).unwrap();
+
+        // New units don't reach the next layer yet
+        network.resize_hidden(12).unwrap();
+        assert_eq!(network.hidden_sizes(), vec![12, 4]);
+        assert_eq!(network.probs(), probs);
+        let grown = network.get_weights(0).unwrap();
+        for (row, old) in grown.chunks(12).zip(weights.chunks(HIDDEN_SIZE)) {
+            assert_eq!(row[..HIDDEN_SIZE], *old);
+        }
+        assert_eq!(
+            network.get_weights(2).unwrap()[HIDDEN_SIZE * 4..],
+            [0.0; 16]
+        );
+
+        assert_eq!(train_on_pattern(&mut network, &[0, 1, 2], 100), 3);
+        network.resize_hidden(2).unwrap();
+        assert_eq!(network.weight_shape(2), Ok(vec![2, 4]));
+        train_on_pattern(&mut network, &[0, 1, 2], 10);
+        #[cfg(feature = "serde")]
+        {
+            let restored = RPSNetwork::from_json(&network.to_json().unwrap()).unwrap();
+            assert_eq!(restored.hidden_size, 2);
+        }
+
+        assert!(network.resize_hidden(0).is_err());
+        let mut recurrent = NetworkConfig::new()
+            .architecture(Architecture::Lstm)
+            .build()
+            .unwrap();
+        assert!(recurrent.resize_hidden(12).is_err());
+    }
+
+    #[test]
+    fn outcome_head() {
+        let config = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .outcome_head(true)
+            .max_grad_norm(5.0)
+            .seed(SEED);
+        let mut network = config.build().unwrap();
+        assert_eq!(network.outcome_probs(), Some(vec![1.0 / 3.0; 3]));
+
+        // The opponent sticks to rock and loses to paper every round
+        let rock = moves::one_hot(0, INPUT_SIZE);
+        network.forward(&rock).unwrap();
+        for _ in 0..50 {
+            network
+                .backward_with_outcome(0, Outcome::Win, Some(0.1))
+                .unwrap();
+            network.forward(&rock).unwrap();
+        }
+        assert_eq!(network.predict().index, 0);
+        assert_eq!(network.expected_outcome(), Some(Outcome::Win));
+        assert!(network.outcome_probs().unwrap()[Outcome::Win.index()] > 0.9);
+
+        // Training the moves alone still moves the shared hidden layer
+        let outcome_probs = network.outcome_probs();
+        network.backward(0, Some(0.1)).unwrap();
+        network.evaluate();
+        assert_ne!(network.outcome_probs(), outcome_probs);
+        assert!(network.undo_backward());
+        network.evaluate();
+        assert_eq!(network.outcome_probs(), outcome_probs);
+
+        network.resize_hidden(HIDDEN_SIZE + 4).unwrap();
+        assert_eq!(network.outcome_probs(), outcome_probs);
+        network
+            .backward_with_outcome(0, Outcome::Win, Some(0.1))
+            .unwrap();
+        #[cfg(feature = "serde")]
+        {
+            let restored = RPSNetwork::from_json(&network.to_json().unwrap()).unwrap();
+            assert_eq!(restored.outcome_probs(), network.outcome_probs());
+        }
+
+        let mut plain = config.outcome_head(false).build().unwrap();
+        assert_eq!(plain.outcome_probs(), None);
+        assert_eq!(plain.expected_outcome(), None);
+        assert!(plain.backward_with_outcome(0, Outcome::Win, None).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn from_pretrained() {
+        let config = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_sizes(&[HIDDEN_SIZE, 4])
+            .output_size(OUTPUT_SIZE)
+            .seed(SEED);
+        let mut network = config.build().unwrap();
+        train_on_pattern(&mut network, &[0, 1, 2], 20);
+        let bytes = network.to_bytes().unwrap();
+        let play = |network: &mut RPSNetwork| {
+            network.reset_history();
+            for m in [2, 0, 1] {
+                network.forward(&moves::one_hot(m, INPUT_SIZE)).unwrap();
+            }
+            network.probs()
+        };
+        let probs = play(&mut network);
+
+        let same = RPSNetwork::from_pretrained(&bytes, &config).unwrap();
+        assert_eq!(same.history(), vec![0.0; HISTORY_SIZE * INPUT_SIZE]);
+        for index in 0..same.params().len() {
+            assert_eq!(same.param(index), network.param(index));
+        }
+
+        // New units and older slots start without influence
+        let grown = config
+            .clone()
+            .history_size(HISTORY_SIZE + 2)
+            .hidden_sizes(&[HIDDEN_SIZE + 4, 6]);
+        let mut larger = RPSNetwork::from_pretrained(&bytes, &grown).unwrap();
+        assert_eq!(larger.hidden_sizes(), vec![HIDDEN_SIZE + 4, 6]);
+        assert_eq!(
+            larger.get_weights(0).unwrap()[..2 * INPUT_SIZE * (HIDDEN_SIZE + 4)],
+            vec![0.0; 2 * INPUT_SIZE * (HIDDEN_SIZE + 4)][..]
+        );
+        let larger_probs = play(&mut larger);
+        for (a, b) in larger_probs.iter().zip(&probs) {
+            assert!((a - b).abs() < 1e-6, "{:?} {:?}", larger_probs, probs);
+        }
+
+        let shrunk = config.clone().history_size(1).hidden_sizes(&[2, 2]);
+        let mut smaller = RPSNetwork::from_pretrained(&bytes, &shrunk).unwrap();
+        assert_eq!(smaller.weight_shape(0), Ok(vec![INPUT_SIZE, 2]));
+        train_on_pattern(&mut smaller, &[0, 1, 2], 5);
+
+        for config in [
+            config.clone().output_size(OUTPUT_SIZE + 1),
+            config.clone().attention(true),
+            config.clone().hidden_size(HIDDEN_SIZE),
+        ] {
+            assert!(RPSNetwork::from_pretrained(&bytes, &config).is_err());
+        }
+        assert!(RPSNetwork::from_pretrained(&bytes[1..], &config).is_err());
+    }
+
+    #[test]
+    fn exploration() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        network.forward(&input).unwrap();
+        let greedy = network.probs();
+
+        network.set_exploration(0.3).unwrap();
+        let mixed = network.probs();
+
+        assert!(mixed.iter().all(|p| *p >= 0.1));
+        assert!((mixed.iter().sum::<Real>() - 1.0).abs() < 1e-6);
+        for (m, g) in mixed.iter().zip(&greedy) {
+            assert!((m - (0.7 * g + 0.1)).abs() < 1e-6);
+        }
+
+        network.set_exploration(1.0).unwrap();
+        assert_eq!(network.probs(), vec![1.0 / 3.0; OUTPUT_SIZE]);
+
+        assert!(network.set_exploration(1.5).is_err());
+        network.set_exploration(0.0).unwrap();
+        assert_eq!(network.probs(), greedy);
+    }
+
+    #[test]
+    fn probs_ptr() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+        let ptr = network.probs_ptr();
+        let view = |ptr: *const Real| unsafe { core::slice::from_raw_parts(ptr, OUTPUT_SIZE) };
+        assert_eq!(view(ptr), network.probs().as_slice());
+
+        network.forward(&[1.0, 0.0, 0.0]).unwrap();
+        network.set_exploration(0.3).unwrap();
+
+        // Updated in place
+        assert_eq!(network.probs_ptr(), ptr);
+        assert_eq!(view(ptr), network.probs().as_slice());
+    }
+
+    #[test]
+    fn predict() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        for _ in 0..50 {
+            network.forward(&input).unwrap();
+            network.backward(1, Some(0.1)).unwrap();
+        }
+        network.forward(&input).unwrap();
+
+        let prediction = network.predict();
+        assert_eq!(prediction.index, 1);
+        assert_eq!(prediction.probability, network.probs()[1]);
+
+        network.set_exploration(0.5).unwrap();
+        assert_eq!(network.predict().index, 1);
+        assert!(network.predict().probability < prediction.probability);
+    }
+
+    #[test]
+    fn sample_move() {
+        let mut a =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+        let mut b =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+        a.forward(&input).unwrap();
+        b.forward(&input).unwrap();
+
+        let moves: Vec<usize> = (0..100).map(|_| a.sample_move()).collect();
+        assert!(moves.iter().all(|&m| m < OUTPUT_SIZE));
+        assert!((0..OUTPUT_SIZE).all(|m| moves.contains(&m)));
+        assert_eq!(moves, (0..100).map(|_| b.sample_move()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn counter_move() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        for _ in 0..50 {
+            network.forward(&input).unwrap();
+            network.backward(2, Some(0.1)).unwrap();
+        }
+        network.forward(&input).unwrap();
+
+        assert_eq!(network.counter_move(&Beats::rock_paper_scissors()), Ok(0));
+        assert_eq!(
+            network.counter_move(&Beats::new(&[0, 1, 2]).unwrap()),
+            Ok(2)
+        );
+        assert!(network.counter_move(&Beats::new(&[1, 0]).unwrap()).is_err());
+
+        let result = network
+            .prediction_result(&Beats::rock_paper_scissors())
+            .unwrap();
+        assert_eq!(result.probs, network.probs());
+        assert_eq!(result.predicted, 2);
+        assert_eq!(result.confidence, network.probs()[2]);
+        assert_eq!(result.counter_move, 0);
+    }
+
+    #[test]
+    fn accuracy() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .accuracy_window(5)
+            .seed(SEED)
+            .build()
+            .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        let mut hits = Vec::new();
+        for round in 0..30 {
+            let label = round % 2;
+            network.forward(&input).unwrap();
+            hits.push(network.predict().index == label);
+            network.backward(label, Some(0.1)).unwrap();
+        }
+
+        let rate = |hits: &[bool]| hits.iter().filter(|&&h| h).count() as Real / hits.len() as Real;
+        assert_eq!(network.accuracy(), rate(&hits));
+        assert_eq!(network.recent_accuracy(), rate(&hits[25..]));
+
+        let confusion = network.confusion_matrix();
+        assert_eq!(confusion.len(), OUTPUT_SIZE * OUTPUT_SIZE);
+        assert_eq!(confusion.iter().sum::<u32>(), 30);
+        assert_eq!(confusion[2 * OUTPUT_SIZE..], [0, 0, 0]);
+        let diagonal: u32 = (0..OUTPUT_SIZE)
+            .map(|i| confusion[i * OUTPUT_SIZE + i])
+            .sum();
+        assert_eq!(diagonal as usize, hits.iter().filter(|&&h| h).count());
+    }
+
+    #[test]
+    fn entropy() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        let uniform = (OUTPUT_SIZE as Real).ln();
+        assert!((network.entropy() - uniform).abs() < 1e-6);
+
+        for _ in 0..50 {
+            network.forward(&input).unwrap();
+            network.backward(1, Some(0.1)).unwrap();
+        }
+        network.forward(&input).unwrap();
+        let confident = network.entropy();
+        assert!(confident < 0.5 * uniform);
+
+        network.set_exploration(0.5).unwrap();
+        assert!(network.entropy() > confident);
+
+        assert_eq!(super::entropy(&[0.0, 1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn hidden_activations() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_sizes(&[HIDDEN_SIZE, 4])
+            .output_size(OUTPUT_SIZE)
+            .activation(Activation::ReLU)
+            .seed(SEED)
+            .build()
+            .unwrap();
+
+        network.forward(&[1.0, 0.0, 0.0]).unwrap();
+
+        for (layer, size) in [HIDDEN_SIZE, 4].into_iter().enumerate() {
+            let hidden = network.hidden(layer).unwrap();
+            let pre_activation = network.pre_activation(layer).unwrap();
+            assert_eq!(hidden.len(), size);
+            for (h, a) in hidden.iter().zip(&pre_activation) {
+                assert_eq!(*h, a.max(0.0));
+            }
+        }
+        assert_eq!(
+            network.hidden(2),
+            Err(Error::InvalidLayer {
+                layer: 2,
+                layers: 2
+            })
+        );
+
+        let mut recurrent = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_sizes(&[HIDDEN_SIZE, 4])
+            .output_size(OUTPUT_SIZE)
+            .architecture(Architecture::Gru)
+            .seed(SEED)
+            .build()
+            .unwrap();
+
+        recurrent.forward(&[1.0, 0.0, 0.0]).unwrap();
+
+        assert_eq!(
+            recurrent.hidden(0).unwrap(),
+            recurrent.recurrent.as_ref().unwrap().state().to_vec()
+        );
+        assert!(recurrent.pre_activation(0).is_err());
+        assert_eq!(recurrent.pre_activation(1).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn weights_round_trip() {
+        let mut a = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .architecture(Architecture::Elman)
+            .seed(SEED)
+            .build()
+            .unwrap();
+        let mut b = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .architecture(Architecture::Elman)
+            .seed(SEED + 1)
+            .build()
+            .unwrap();
+
+        assert_eq!(a.parameter_count(), 5);
+        assert_eq!(a.weight_shape(0), Ok(vec![HIDDEN_SIZE, OUTPUT_SIZE]));
+        assert_eq!(a.weight_shape(3), Ok(vec![HIDDEN_SIZE, HIDDEN_SIZE]));
+        assert_eq!(
+            a.get_weights(5),
+            Err(Error::InvalidParameter {
+                index: 5,
+                parameters: 5
+            })
+        );
+        assert!(b.set_weights(0, &[0.0; 3]).is_err());
+
+        for i in 0..a.parameter_count() {
+            b.set_weights(i, &a.get_weights(i).unwrap()).unwrap();
+        }
+        assert_eq!(a.layers, b.layers);
+        assert_eq!(a.recurrent, b.recurrent);
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+        a.forward(&input).unwrap();
+        b.forward(&input).unwrap();
+        assert_eq!(a.probs(), b.probs());
+    }
+
+    #[test]
+    fn seed_history() {
+        let mut played =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+        let mut seeded =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+
+        played.forward(&[1.0, 0.0, 0.0]).unwrap();
+        played.forward(&[0.0, 1.0, 0.0]).unwrap();
+        assert_eq!(
+            played.history(),
+            vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+        );
+
+        seeded.seed_history(&played.history()).unwrap();
+        assert_eq!(seeded.history, played.history);
+
+        played.forward(&[0.0, 0.0, 1.0]).unwrap();
+        seeded.forward(&[0.0, 0.0, 1.0]).unwrap();
+        assert_eq!(seeded.probs(), played.probs());
+
+        assert!(seeded.seed_history(&[0.0; 3]).is_err());
+        let mut recurrent = NetworkConfig::new()
+            .architecture(Architecture::Elman)
+            .build()
+            .unwrap();
+        assert!(recurrent.seed_history(&recurrent.history()).is_err());
+    }
+
+    #[test]
+    fn reset() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .optimizer(Optimizer::Adam)
+            .seed(SEED)
+            .build()
+            .unwrap();
+        network.set_schedule(&Schedule::warmup(0.1, 5).unwrap());
+        network.set_exploration(0.2).unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+        for _ in 0..5 {
+            network.forward(&input).unwrap();
+            network.backward(1, None).unwrap();
+        }
+        let trained = network.layers.clone();
+
+        network.reset();
+
+        assert_ne!(network.layers, trained);
+        assert_eq!(network.history(), vec![0.0; HISTORY_SIZE * INPUT_SIZE]);
+        assert_eq!(network.optim, OptimizerState::new(Optimizer::Adam));
+        assert_eq!(network.accuracy(), 0.0);
+        assert_eq!(network.schedule, Schedule::warmup(0.1, 5).unwrap());
+        assert_eq!(network.exploration, 0.2);
+    }
+
+    #[test]
+    fn snapshot_restore() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .optimizer(Optimizer::Momentum)
+            .seed(SEED)
+            .build()
+            .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+        network.forward(&input).unwrap();
+        network.backward(1, Some(0.1)).unwrap();
+
+        let checkpoint = network.snapshot();
+        let layers = network.layers.clone();
+        let optim = network.optim.clone();
+
+        for _ in 0..5 {
+            network.forward(&input).unwrap();
+            network.backward(2, Some(10.0)).unwrap();
+        }
+        assert_ne!(network.layers, layers);
+
+        network.restore(&checkpoint);
+        assert_eq!(network.layers, layers);
+        assert_eq!(network.optim, optim);
+
+        network.forward(&input).unwrap();
+        network.backward(2, Some(10.0)).unwrap();
+        network.restore(&checkpoint);
+        assert_eq!(network.layers, layers);
+    }
+
+    #[test]
+    fn undo_backward() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .architecture(Architecture::Gru)
+            .optimizer(Optimizer::Adam)
+            .seed(SEED)
+            .build()
+            .unwrap();
+        let mut reference = network.clone();
+
+        assert!(!network.undo_backward());
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+        for network in [&mut network, &mut reference] {
+            network.forward(&input).unwrap();
+            network.backward(1, None).unwrap();
+            network.forward(&input).unwrap();
+        }
+
+        network.backward(2, None).unwrap();
+        assert!(network.undo_backward());
+        assert!(!network.undo_backward());
+        network.backward(0, None).unwrap();
+        reference.backward(0, None).unwrap();
+
+        assert_eq!(network.layers, reference.layers);
+        assert_eq!(network.recurrent, reference.recurrent);
+        assert_eq!(network.optim, reference.optim);
+        assert_eq!(network.schedule, reference.schedule);
+        assert_eq!(network.confusion_matrix(), reference.confusion_matrix());
+    }
+
+    #[test]
+    fn forward_batch() {
+        for attention in [false, true] {
+            let mut network = NetworkConfig::new()
+                .input_size(INPUT_SIZE)
+                .history_size(HISTORY_SIZE)
+                .hidden_sizes(&[HIDDEN_SIZE, 4])
+                .output_size(OUTPUT_SIZE)
+                .attention(attention)
+                .seed(SEED)
+                .build()
+                .unwrap();
+            network.set_temperature(0.5).unwrap();
+            network.set_exploration(0.1).unwrap();
+
+            let mut windows = Vec::new();
+            let mut expected = Vec::new();
+            for round in 0..5 {
+                let mut input = vec![0.0; INPUT_SIZE];
+                input[round % INPUT_SIZE] = 1.0;
+                network.forward(&input).unwrap();
+                windows.extend(network.history());
+                expected.extend(network.probs());
+            }
+            let history = network.history();
+
+            let probs = network.forward_batch(&windows, 5).unwrap();
+
+            assert_eq!(probs.len(), expected.len());
+            for (p, e) in probs.iter().zip(&expected) {
+                assert!((p - e).abs() < 1e-6);
+            }
+            assert_eq!(network.history(), history);
+            assert!(network.forward_batch(&windows, 4).is_err());
+        }
+
+        let recurrent = NetworkConfig::new()
+            .architecture(Architecture::Elman)
+            .build()
+            .unwrap();
+        assert!(recurrent.forward_batch(&[], 0).is_err());
+    }
+
+    #[test]
+    fn forward_reuses_buffers() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_sizes(&[HIDDEN_SIZE, 4])
+            .output_size(OUTPUT_SIZE)
+            .attention(true)
+            .outcome_head(true)
+            .seed(SEED)
+            .build()
+            .unwrap();
+        network.set_history_decay(0.9).unwrap();
+        let buffers = |network: &RPSNetwork| {
+            let mut buffers = vec![
+                network.history.as_ptr(),
+                network.probs.as_ptr(),
+                network.outcome_probs.as_ptr(),
+                network.mixed_probs.as_ptr(),
+                network.scratch.input.as_ptr(),
+            ];
+            buffers.extend(network.scratch.hidden.iter().map(|x| x.as_ptr()));
+            for layer in &network.layers {
+                buffers.extend([layer.pre_activation.as_ptr(), layer.output.as_ptr()]);
+            }
+            buffers
+        };
+        network.forward(&[1.0, 0.0, 0.0]).unwrap();
+        let expected = buffers(&network);
+        for round in 0..10 {
+            let mut input = [0.0; INPUT_SIZE];
+            input[round % INPUT_SIZE] = 1.0;
+            network.forward(&input).unwrap();
+            assert_eq!(buffers(&network), expected);
+        }
+
+        // The buffers hold the same values as the allocating path
+        assert_eq!(network.scratch.input, network.layer_input(0));
+        assert_eq!(network.scratch.hidden[0], network.layer_input(1));
+    }
+
+    #[test]
+    fn backward_reuses_buffers() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_sizes(&[HIDDEN_SIZE, 4])
+            .output_size(OUTPUT_SIZE)
+            .attention(true)
+            .outcome_head(true)
+            .max_grad_norm(0.1)
+            .seed(SEED)
+            .build()
+            .unwrap();
+        let buffers = |network: &RPSNetwork| {
+            let scratch = &network.scratch;
+            let mut buffers = vec![
+                scratch.target.as_ptr(),
+                scratch.dinput.as_ptr(),
+                scratch.head_grads.0.as_ptr(),
+                scratch.head_grads.1.as_ptr(),
+                scratch.head_dinput.as_ptr(),
+            ];
+            for (dweights, dbias) in &scratch.grads {
+                buffers.extend([dweights.as_ptr(), dbias.as_ptr()]);
+            }
+            let undo = network.undo.as_ref().unwrap();
+            buffers.extend(undo.params.iter().map(|param| param.as_ptr()));
+            buffers
+        };
+        network.forward(&[1.0, 0.0, 0.0]).unwrap();
+        network
+            .backward_with_outcome(0, Outcome::Win, None)
+            .unwrap();
+        let expected = buffers(&network);
+        for round in 0..10 {
+            let mut input = [0.0; INPUT_SIZE];
+            input[round % INPUT_SIZE] = 1.0;
+            network.forward(&input).unwrap();
+            network
+                .backward_with_outcome(round % OUTPUT_SIZE, Outcome::Lose, None)
+                .unwrap();
+            assert_eq!(buffers(&network), expected);
+        }
+        assert!(network.clip_count() > 0);
+        assert!(network.undo_backward());
+    }
+
+    #[test]
+    fn train_from_replay() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .replay_capacity(4)
+            .seed(SEED)
+            .build()
+            .unwrap();
+
+        assert_eq!(network.train_from_replay(10, Some(0.1)), 0.0);
+
+        // Early game: rock is always followed by paper
+        let rock: Vec<Real> = vec![1.0, 0.0, 0.0];
+        network.forward(&rock).unwrap();
+        network.backward(1, Some(0.1)).unwrap();
+        let paper_prob = network.probs()[1];
+
+        for _ in 0..5 {
+            network.forward(&rock).unwrap();
+            network.backward(1, Some(0.01)).unwrap();
+        }
+        assert_eq!(network.replay_len(), 4);
+
+        let history = network.history();
+        let loss = network.train_from_replay(50, Some(0.1));
+
+        assert!(loss > 0.0);
+        assert_eq!(network.history(), history);
+        assert!(network.probs()[1] > paper_prob);
+        assert!(!network.undo_backward());
+    }
+
+    #[test]
+    fn train_on_sequence() {
+        let config = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .seed(SEED);
+        let mut network = config.build().unwrap();
+        let game: Vec<u8> = (0..30).map(|round| [0, 0, 1, 2][round % 4]).collect();
+
+        let first = network.train_on_sequence(&game, 1, Some(0.1)).unwrap();
+        let last = network.train_on_sequence(&game, 20, Some(0.1)).unwrap();
+        assert!(last < first, "{} {}", last, first);
+        assert_eq!(network.history(), vec![0.0; HISTORY_SIZE * INPUT_SIZE]);
+        assert!(!network.undo_backward());
+
+        // The network plays the game on from what it learned
+        for &m in &game[..3] {
+            network
+                .forward(&moves::one_hot(m as usize, INPUT_SIZE))
+                .unwrap();
+        }
+        assert_eq!(network.predict().index, 2);
+
+        let before = network.get_weights(0).unwrap();
+        assert!(network.train_on_sequence(&[0, 3, 1], 1, None).is_err());
+        assert_eq!(network.get_weights(0).unwrap(), before);
+        assert_eq!(network.train_on_sequence(&[1], 5, None), Ok(0.0));
+
+        // With both moves, the bot's moves follow the opponent's
+        let mut both = config.input_size(2 * OUTPUT_SIZE).build().unwrap();
+        let game: Vec<u8> = (0..40).map(|i| [0, 2, 1, 0][i % 4]).collect();
+        let first = both.train_on_sequence(&game, 1, Some(0.1)).unwrap();
+        assert!(both.train_on_sequence(&game, 20, Some(0.1)).unwrap() < first);
+        assert!(both.train_on_sequence(&game[1..], 1, None).is_err());
+        assert!(NetworkConfig::new()
+            .input_size(5)
+            .build()
+            .unwrap()
+            .train_on_sequence(&game, 1, None)
+            .is_err());
+    }
+
+    #[test]
+    fn reset_history() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_size(HIDDEN_SIZE)
+            .output_size(OUTPUT_SIZE)
+            .architecture(Architecture::Lstm)
+            .seed(SEED)
+            .build()
+            .unwrap();
+        let fresh = network.recurrent.clone();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+        network.forward(&input).unwrap();
+        let layers = network.layers.clone();
+
+        network.reset_history();
+
+        assert_eq!(network.history(), vec![0.0; HISTORY_SIZE * INPUT_SIZE]);
+        assert_eq!(network.recurrent, fresh);
+        assert_eq!(network.layers, layers);
+    }
+
+    #[test]
+    fn rejects_invalid_rounds() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+
+        assert_eq!(
+            network.forward(&[1.0, 0.0]),
+            Err(Error::ShapeMismatch {
+                name: "input",
+                expected: vec![INPUT_SIZE],
+                found: vec![2],
+            })
+        );
+        assert_eq!(network.history(), vec![0.0; HISTORY_SIZE * INPUT_SIZE]);
+
+        network.forward(&[1.0, 0.0, 0.0]).unwrap();
+        assert_eq!(
+            network.backward(OUTPUT_SIZE, None),
+            Err(Error::InvalidMove {
+                index: OUTPUT_SIZE,
+                moves: OUTPUT_SIZE,
+            })
+        );
+        assert!(!network.undo_backward());
+        assert!(network.train_step(&[1.0, 0.0, 0.0], 5, None).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        for (input_size, history_size, hidden_size, output_size, name) in [
+            (0, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, "input_size"),
+            (INPUT_SIZE, 0, HIDDEN_SIZE, OUTPUT_SIZE, "history_size"),
+            (INPUT_SIZE, HISTORY_SIZE, 0, OUTPUT_SIZE, "hidden_size"),
+            (INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, 0, "output_size"),
+        ] {
+            let network =
+                RPSNetwork::new_with_seed(input_size, history_size, hidden_size, output_size, SEED);
+            assert!(
+                matches!(network, Err(Error::InvalidSize { name: n, .. }) if n == name),
+                "{}",
+                name
+            );
+            assert!(RPSNetwork::new(input_size, history_size, hidden_size, output_size).is_err());
+        }
+    }
+
+    #[test]
+    fn loss() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        // Before any forward(), the distribution is uniform
+        assert!((network.loss(0).unwrap() - (OUTPUT_SIZE as Real).ln()).abs() < 1e-6);
+
+        network.forward(&input).unwrap();
+        let loss = network.loss(2).unwrap();
+        network.set_exploration(0.5).unwrap();
+        assert_eq!(network.loss(2).unwrap(), loss);
+
+        network.probs[2] = 0.0;
+        assert!(network.loss(2).unwrap().is_finite());
+        assert!(network.loss(OUTPUT_SIZE).is_err());
+    }
+
+    #[test]
+    fn train_step() {
+        let mut network =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+        let mut reference =
+            RPSNetwork::new_with_seed(INPUT_SIZE, HISTORY_SIZE, HIDDEN_SIZE, OUTPUT_SIZE, SEED)
+                .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        let first = network.train_step(&input, 1, Some(0.1)).unwrap();
+        reference.forward(&input).unwrap();
+        assert_eq!(first, reference.loss(1).unwrap());
+        assert_eq!(first, -reference.probs()[1].ln());
+        reference.backward(1, Some(0.1)).unwrap();
+        assert_eq!(network.layers, reference.layers);
+
+        let mut last = first;
+        let mut losses = vec![first];
+        for _ in 0..20 {
+            last = network.train_step(&input, 1, Some(0.1)).unwrap();
+            losses.push(last);
+        }
+        assert!(last < first);
+        assert_eq!(network.loss_history(), losses);
+
+        network.forward(&input).unwrap();
+        network.backward(1, None).unwrap();
+        assert_eq!(network.loss_history().len(), 22);
+        assert!(network.undo_backward());
+        assert_eq!(network.loss_history(), losses);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn recurrent_state_survives_serialization() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_sizes(&[HIDDEN_SIZE, 4])
+            .output_size(OUTPUT_SIZE)
+            .architecture(Architecture::Elman)
+            .seed(SEED)
+            .build()
+            .unwrap();
+
+        let input: Vec<Real> = vec![1.0, 0.0, 0.0];
+
+        for _ in 0..5 {
+            network.forward(&input).unwrap();
+            network.backward(1, Some(0.01)).unwrap();
+        }
+
+        let mut restored = RPSNetwork::from_bytes(&network.to_bytes().unwrap()).unwrap();
+
+        assert_eq!(restored.recurrent, network.recurrent);
+
+        network.forward(&input).unwrap();
+        network.backward(1, Some(0.01)).unwrap();
+        restored.forward(&input).unwrap();
+        restored.backward(1, Some(0.01)).unwrap();
+
+        assert_eq!(restored.recurrent, network.recurrent);
+        assert_eq!(restored.probs(), network.probs());
+    }
+
+    #[test]
+    fn quantize() {
+        let mut network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_sizes(&[HIDDEN_SIZE, 4])
+            .output_size(OUTPUT_SIZE)
+            .seed(SEED)
+            .build()
+            .unwrap();
+        train_on_pattern(&mut network, &[0, 1, 2], 100);
+
+        let mut quantized = network.quantize().unwrap();
+        assert_eq!(quantized.history(), network.history());
+
+        for m in [0, 1, 2, 2, 0, 1] {
+            let mut input = vec![0.0; INPUT_SIZE];
+            input[m] = 1.0;
+            network.forward(&input).unwrap();
+            quantized.forward(&input).unwrap();
+
+            assert_eq!(quantized.predict().index, network.predict().index);
+            for (q, p) in quantized.probs().iter().zip(network.probs()) {
+                assert!((q - p).abs() < 0.05);
+            }
+        }
+
+        let recurrent = NetworkConfig::new()
+            .architecture(Architecture::Elman)
+            .seed(SEED)
+            .build()
+            .unwrap();
+        assert!(matches!(recurrent.quantize(), Err(Error::Unsupported(_))));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn quantized_bytes_round_trip() {
+        let network = NetworkConfig::new()
+            .input_size(INPUT_SIZE)
+            .history_size(HISTORY_SIZE)
+            .hidden_sizes(&[32])
+            .output_size(OUTPUT_SIZE)
+            .seed(SEED)
+            .build()
+            .unwrap();
+        let quantized = network.quantize().unwrap();
+
+        let bytes = quantized.to_bytes().unwrap();
+        assert!(bytes.len() < network.to_bytes().unwrap().len() / 2);
+        assert_eq!(QuantizedNetwork::from_bytes(&bytes).unwrap(), quantized);
+
+        // The header tells the models apart and is checked on load
+        assert!(bytes.starts_with(b"RPSN"));
+        assert!(RPSNetwork::from_bytes(&bytes).is_err());
+        assert!(QuantizedNetwork::from_bytes(&network.to_bytes().unwrap()).is_err());
+        let mut tampered = bytes.clone();
+        tampered[8] ^= 1;
+        assert!(matches!(
+            QuantizedNetwork::from_bytes(&tampered),
+            Err(Error::ConfigMismatch { .. })
+        ));
+        let unversioned = bincode::serialize(&quantized).unwrap();
+        assert_eq!(
+            QuantizedNetwork::from_bytes(&unversioned).unwrap(),
+            quantized
+        );
     }
 }
@@ -0,0 +1,252 @@
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::moves::one_hot;
+use crate::{Beats, Encoding, Error, Real, RpsPredictor};
+
+/// One of the six strategies weighed by `MetaStrategy`: whose next move is
+/// predicted, and how many more times the answer is rotated to the move
+/// beating it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Strategy {
+    /// Predicts the bot's own next move instead of the opponent's, assuming
+    /// the opponent predicts it too and plays what beats it.
+    pub own: bool,
+    /// Extra rotations, from 0 to 2, against an opponent second-guessing
+    /// the bot.
+    pub rotation: usize,
+}
+
+impl Strategy {
+    /// Every strategy, in the order of `MetaStrategy::scores()`.
+    pub const ALL: [Strategy; 6] = [
+        Strategy::new(false, 0),
+        Strategy::new(false, 1),
+        Strategy::new(false, 2),
+        Strategy::new(true, 0),
+        Strategy::new(true, 1),
+        Strategy::new(true, 2),
+    ];
+
+    const fn new(own: bool, rotation: usize) -> Self {
+        Self { own, rotation }
+    }
+}
+
+/// Iocaine Powder-style meta-strategy over a base predictor. One copy of the
+/// predictor follows the opponent's moves, another the bot's own moves from
+/// the opponent's point of view, and each `Strategy` turns their
+/// predictions into a move. The bot plays the move of the strategy that
+/// scored best over the last rounds, so that an opponent anticipating the
+/// base predictor is anticipated in turn.
+#[derive(Debug, Clone)]
+pub struct MetaStrategy<P> {
+    rules: Beats,
+    encoding: Encoding,
+    /// Predicts the opponent's next move.
+    opponent: P,
+    /// Predicts the bot's next move, fed the rounds with both players
+    /// swapped.
+    own: P,
+    window: usize,
+    /// Move each strategy plays in the coming round.
+    moves: Vec<usize>,
+    /// Payoffs of each strategy over the last `window` rounds, by strategy.
+    payoffs: Vec<VecDeque<Real>>,
+    rounds: u32,
+    /// Moves of the opponent and the bot in the last round.
+    previous: Option<(usize, usize)>,
+}
+
+impl<P: RpsPredictor> MetaStrategy<P> {
+    /// Plays the game described by `rules`, feeding rounds encoded by
+    /// `encoding` to the `opponent` and `own` predictors, and judging
+    /// strategies over the last `window` rounds.
+    pub fn new(
+        opponent: P,
+        own: P,
+        rules: &Beats,
+        encoding: Encoding,
+        window: usize,
+    ) -> Result<Self, Error> {
+        if window == 0 {
+            return Err(Error::InvalidSize {
+                name: "window",
+                value: 0,
+                min: 1,
+            });
+        }
+        let mut meta = Self {
+            rules: rules.clone(),
+            encoding,
+            opponent,
+            own,
+            window,
+            moves: Vec::new(),
+            payoffs: vec![VecDeque::with_capacity(window + 1); Strategy::ALL.len()],
+            rounds: 0,
+            previous: None,
+        };
+        meta.moves = meta.strategy_moves()?;
+        Ok(meta)
+    }
+
+    /// Move to play next: that of the best scoring strategy.
+    pub fn bot_move(&self) -> usize {
+        self.moves[self.best()]
+    }
+
+    /// Strategy behind `bot_move()`.
+    pub fn strategy(&self) -> Strategy {
+        Strategy::ALL[self.best()]
+    }
+
+    /// Total payoff of each strategy over the last rounds, in the order of
+    /// `Strategy::ALL`.
+    pub fn scores(&self) -> Vec<Real> {
+        self.payoffs.iter().map(|p| p.iter().sum()).collect()
+    }
+
+    /// Records a round where the opponent played `opponent` and the bot
+    /// `bot`: scores every strategy, trains both predictors with
+    /// `learning_rate`, and prepares the moves of the next round.
+    pub fn play(&mut self, opponent: usize, bot: usize, learning_rate: Real) -> Result<(), Error> {
+        let outcome = self.rules.judge(opponent, bot)?;
+        let reversed = self.rules.judge(bot, opponent)?;
+        for (payoffs, &m) in self.payoffs.iter_mut().zip(&self.moves) {
+            payoffs.push_back(self.rules.payoff(m, opponent));
+            if payoffs.len() > self.window {
+                payoffs.pop_front();
+            }
+        }
+
+        // The first round has no prediction to learn from
+        if self.rounds > 0 {
+            self.opponent.backward(opponent, learning_rate)?;
+            self.own.backward(bot, learning_rate)?;
+        }
+        let moves = self.rules.moves();
+        let previous_bot = self.previous.map(|(_, bot)| bot);
+        let previous_opponent = self.previous.map(|(opponent, _)| opponent);
+        self.opponent.forward(&self.encoding.encode_after(
+            opponent,
+            bot,
+            outcome,
+            previous_bot,
+            moves,
+        )?)?;
+        self.own.forward(&self.encoding.encode_after(
+            bot,
+            opponent,
+            reversed,
+            previous_opponent,
+            moves,
+        )?)?;
+        self.previous = Some((opponent, bot));
+        self.rounds += 1;
+        self.moves = self.strategy_moves()?;
+        Ok(())
+    }
+
+    /// Predictor of the opponent's moves.
+    pub fn opponent_predictor(&self) -> &P {
+        &self.opponent
+    }
+
+    /// Predictor of the bot's own moves.
+    pub fn own_predictor(&self) -> &P {
+        &self.own
+    }
+
+    /// Moves of every strategy given the current predictions.
+    fn strategy_moves(&self) -> Result<Vec<usize>, Error> {
+        let moves = self.rules.moves();
+        let beat = |m| self.rules.counter(&one_hot(m, moves), moves);
+        // Against the opponent's predicted move, or against the move
+        // beating the bot's predicted one
+        let opponent = self.opponent.predict().index;
+        let own = beat(self.own.predict().index)?;
+        Strategy::ALL
+            .iter()
+            .map(|strategy| {
+                let mut m = if strategy.own { own } else { opponent };
+                for _ in 0..=strategy.rotation {
+                    m = beat(m)?;
+                }
+                Ok(m)
+            })
+            .collect()
+    }
+
+    /// Index of the strategy with the best score, preferring the first on
+    /// ties.
+    fn best(&self) -> usize {
+        let scores = self.scores();
+        let mut best = 0;
+        for (i, &score) in scores.iter().enumerate().skip(1) {
+            if score > scores[best] {
+                best = i;
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MarkovPredictor, Move, Outcome};
+
+    fn meta<P: RpsPredictor>(opponent: P, own: P) -> MetaStrategy<P> {
+        let rules = Beats::rock_paper_scissors();
+        MetaStrategy::new(opponent, own, &rules, Encoding::BothMoves, 20).unwrap()
+    }
+
+    #[test]
+    fn beats_predictable_opponent() {
+        let mut meta = meta(
+            MarkovPredictor::new(1, 3).unwrap(),
+            MarkovPredictor::new(1, 3).unwrap(),
+        );
+        let mut bot_wins = 0;
+        for round in 0..60 {
+            let opponent = Move::ALL[round % 3];
+            let bot = Move::ALL[meta.bot_move()];
+            if opponent.against(bot) == Outcome::Lose && round >= 30 {
+                bot_wins += 1;
+            }
+            meta.play(opponent.index(), bot.index(), 0.1).unwrap();
+        }
+        assert_eq!(bot_wins, 30);
+        assert_eq!(meta.strategy(), Strategy::new(false, 0));
+    }
+
+    #[test]
+    fn anticipates_second_guessing() {
+        // The opponent runs the same predictor over the bot's moves and plays
+        // what beats its prediction, which the bot's own predictor sees
+        // coming
+        let mut meta = meta(
+            MarkovPredictor::new(1, 3).unwrap(),
+            MarkovPredictor::new(1, 3).unwrap(),
+        );
+        let mut second_guesser = MarkovPredictor::new(1, 3).unwrap();
+        let mut bot_wins = 0;
+        for round in 0..100 {
+            let opponent = Move::ALL[second_guesser.predict().index].beaten_by();
+            let bot = Move::ALL[meta.bot_move()];
+            if opponent.against(bot) == Outcome::Lose && round >= 50 {
+                bot_wins += 1;
+            }
+            meta.play(opponent.index(), bot.index(), 0.1).unwrap();
+            if round > 0 {
+                second_guesser.backward(bot.index()).unwrap();
+            }
+            second_guesser.forward(&bot.one_hot()).unwrap();
+        }
+        assert_eq!(bot_wins, 50);
+        assert_eq!(meta.strategy(), Strategy::new(true, 0));
+        assert_eq!(meta.own_predictor(), &second_guesser);
+    }
+}
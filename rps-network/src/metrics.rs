@@ -0,0 +1,235 @@
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::Real;
+
+#[cfg(feature = "serde")]
+use crate::Error;
+
+/// Prediction statistics of a network, recorded on every `backward()` call
+/// once the actual move is known.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq)]
+pub(crate) struct Metrics {
+    total: u32,
+    correct: u32,
+    /// Outcomes of the last `window_size` predictions, oldest first.
+    recent: VecDeque<bool>,
+    window_size: usize,
+    /// Counts of predictions per actual move (rows) and predicted move
+    /// (columns), flattened row by row.
+    confusion: Vec<u32>,
+    classes: usize,
+    /// Losses of the last `loss_history_size` predictions, oldest first.
+    losses: VecDeque<Real>,
+    loss_history_size: usize,
+}
+
+/// `clone_from()` reuses the buffers, as `backward()` saves the metrics for
+/// `undo_backward()` on every step.
+impl Clone for Metrics {
+    fn clone(&self) -> Self {
+        Self {
+            total: self.total,
+            correct: self.correct,
+            recent: self.recent.clone(),
+            window_size: self.window_size,
+            confusion: self.confusion.clone(),
+            classes: self.classes,
+            losses: self.losses.clone(),
+            loss_history_size: self.loss_history_size,
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.total = source.total;
+        self.correct = source.correct;
+        self.recent.clone_from(&source.recent);
+        self.window_size = source.window_size;
+        self.confusion.clone_from(&source.confusion);
+        self.classes = source.classes;
+        self.losses.clone_from(&source.losses);
+        self.loss_history_size = source.loss_history_size;
+    }
+}
+
+impl Metrics {
+    pub(crate) fn new(window_size: usize, classes: usize, loss_history_size: usize) -> Self {
+        Self {
+            total: 0,
+            correct: 0,
+            recent: VecDeque::with_capacity(window_size),
+            window_size,
+            confusion: vec![0; classes * classes],
+            classes,
+            losses: VecDeque::with_capacity(loss_history_size),
+            loss_history_size,
+        }
+    }
+
+    /// Checks the sizes against the network's number of moves and the
+    /// configured window and loss history sizes.
+    #[cfg(feature = "serde")]
+    pub(crate) fn validate(
+        &self,
+        classes: usize,
+        window_size: usize,
+        loss_history_size: usize,
+    ) -> Result<(), Error> {
+        for (name, expected, found) in [
+            ("classes", classes, self.classes),
+            ("confusion", classes * classes, self.confusion.len()),
+            ("accuracy_window", window_size, self.window_size),
+            (
+                "recent",
+                window_size.min(self.recent.len()),
+                self.recent.len(),
+            ),
+            (
+                "loss_history_size",
+                loss_history_size,
+                self.loss_history_size,
+            ),
+            (
+                "losses",
+                loss_history_size.min(self.losses.len()),
+                self.losses.len(),
+            ),
+        ] {
+            if expected != found {
+                return Err(Error::ShapeMismatch {
+                    name,
+                    expected: vec![expected],
+                    found: vec![found],
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Records whether the move `predicted` matched the `actual` one.
+    pub(crate) fn record(&mut self, predicted: usize, actual: usize) {
+        let hit = predicted == actual;
+        self.total += 1;
+        self.correct += hit as u32;
+        if self.recent.len() == self.window_size {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(hit);
+        self.confusion[actual * self.classes + predicted] += 1;
+    }
+
+    /// Records the loss of a prediction, dropping the oldest one once the
+    /// history is full.
+    pub(crate) fn record_loss(&mut self, loss: Real) {
+        if self.loss_history_size == 0 {
+            return;
+        }
+        if self.losses.len() == self.loss_history_size {
+            self.losses.pop_front();
+        }
+        self.losses.push_back(loss);
+    }
+
+    pub(crate) fn losses(&self) -> &VecDeque<Real> {
+        &self.losses
+    }
+
+    /// Number of predictions recorded.
+    #[cfg(feature = "wasm")]
+    pub(crate) fn total(&self) -> u32 {
+        self.total
+    }
+
+    /// Fraction of correct predictions since the network was created, or 0
+    /// before the first one.
+    pub(crate) fn accuracy(&self) -> Real {
+        ratio(self.correct as usize, self.total as usize)
+    }
+
+    /// Fraction of correct predictions over the sliding window.
+    pub(crate) fn recent_accuracy(&self) -> Real {
+        ratio(
+            self.recent.iter().filter(|&&hit| hit).count(),
+            self.recent.len(),
+        )
+    }
+
+    pub(crate) fn confusion_matrix(&self) -> &[u32] {
+        &self.confusion
+    }
+}
+
+fn ratio(n: usize, total: usize) -> Real {
+    if total == 0 {
+        0.0
+    } else {
+        n as Real / total as Real
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sliding_window() {
+        let mut metrics = Metrics::new(2, 3, 0);
+        assert_eq!(metrics.accuracy(), 0.0);
+        assert_eq!(metrics.recent_accuracy(), 0.0);
+
+        metrics.record(0, 0);
+        metrics.record(1, 2);
+        metrics.record(2, 2);
+        metrics.record(2, 2);
+
+        assert_eq!(metrics.accuracy(), 0.75);
+        assert_eq!(metrics.recent_accuracy(), 1.0);
+
+        metrics.record(0, 1);
+        assert_eq!(metrics.recent_accuracy(), 0.5);
+    }
+
+    #[test]
+    fn confusion_matrix() {
+        let mut metrics = Metrics::new(2, 3, 0);
+        metrics.record(0, 0);
+        metrics.record(1, 2);
+        metrics.record(1, 2);
+        metrics.record(2, 1);
+
+        assert_eq!(metrics.confusion_matrix(), &[1, 0, 0, 0, 0, 1, 0, 2, 0]);
+    }
+
+    #[test]
+    fn loss_history() {
+        let mut metrics = Metrics::new(2, 3, 3);
+        for loss in [4.0, 3.0, 2.0, 1.0] {
+            metrics.record_loss(loss);
+        }
+        assert_eq!(metrics.losses(), &[3.0, 2.0, 1.0]);
+
+        let mut disabled = Metrics::new(2, 3, 0);
+        disabled.record_loss(1.0);
+        assert!(disabled.losses().is_empty());
+    }
+
+    #[test]
+    fn clone_from_reuses_buffers() {
+        let mut metrics = Metrics::new(2, 3, 3);
+        metrics.record(0, 0);
+        metrics.record_loss(1.0);
+        let mut saved = metrics.clone();
+        let confusion = saved.confusion.as_ptr();
+
+        metrics.record(1, 2);
+        metrics.record_loss(2.0);
+        saved.clone_from(&metrics);
+        assert_eq!(saved, metrics);
+        assert_eq!(saved.confusion.as_ptr(), confusion);
+    }
+}
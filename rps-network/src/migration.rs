@@ -0,0 +1,583 @@
+#[cfg(feature = "serde")]
+use alloc::boxed::Box;
+#[cfg(feature = "serde")]
+use alloc::string::String;
+use alloc::vec;
+#[cfg(feature = "serde")]
+use alloc::vec::Vec;
+use ndarray::{ArrayView1, ArrayView2};
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "serde")]
+use crate::{Activation, ModelStore, QuantizedNetwork};
+use crate::{Error, LegacyRPSNetwork, NetworkConfig, RPSNetwork};
+
+/// Version of the format written by `to_json()` and `to_bytes()`:
+///
+/// 1. The model next to the version, after the `RPSN` magic in binary.
+/// 2. Adds the magic to JSON, and a hash of the model's sizes and parameter
+///    shapes, checked against the model on load so that a model that doesn't
+///    match its header fails with `Error::ConfigMismatch` instead of loading
+///    garbage weights.
+/// 3. Extends the hash to the activations, architecture and optimizer, and
+///    saves `ModelStore`s and `QuantizedNetwork`s in the same envelope.
+///
+/// Models saved in any earlier version, or before the format had a version,
+/// still load, and saving them again upgrades them to the current version.
+/// Those saved by a later version fail with `Error::UnsupportedFormat`, and
+/// need a newer version of the library to load and save them back in the
+/// older format.
+pub const FORMAT_VERSION: u32 = 3;
+
+/// Start of the binary encoding, which models saved before the format had a
+/// version lack, and first field of the JSON encoding since version 2.
+#[cfg(feature = "serde")]
+const MAGIC: &str = "RPSN";
+
+/// Model being saved, tagged so that a legacy one can be told apart.
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+pub(crate) enum Saving<'a> {
+    Network(&'a RPSNetwork),
+    Legacy(&'a LegacyRPSNetwork),
+    Store(&'a ModelStore),
+    Quantized(&'a QuantizedNetwork),
+}
+
+/// Model being loaded, the owned counterpart of `Saving`.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+pub(crate) enum Saved {
+    Network(Box<RPSNetwork>),
+    Legacy(Box<LegacyRPSNetwork>),
+    Store(Box<ModelStore>),
+    Quantized(Box<QuantizedNetwork>),
+}
+
+/// Binary encoding after the magic.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct Envelope<M> {
+    version: u32,
+    config_hash: u64,
+    model: M,
+}
+
+/// JSON encoding.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct JsonEnvelope<M> {
+    magic: String,
+    version: u32,
+    config_hash: u64,
+    model: M,
+}
+
+/// Both encodings in version 1.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct EnvelopeV1<M> {
+    #[allow(dead_code)]
+    version: u32,
+    model: M,
+}
+
+/// Leading fields of a JSON envelope, read first to check the version and
+/// magic before the model.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct Header {
+    magic: Option<String>,
+    version: Option<u32>,
+}
+
+#[cfg(feature = "serde")]
+impl Saving<'_> {
+    fn config_hash(&self) -> u64 {
+        match self {
+            Saving::Network(network) => network.config_hash(),
+            Saving::Legacy(legacy) => legacy.config_hash(),
+            Saving::Store(store) => fnv1a(store.hash_values(FORMAT_VERSION)),
+            Saving::Quantized(quantized) => fnv1a(quantized.hash_values(FORMAT_VERSION)),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Saved {
+    /// Current network for the loaded model, converting a legacy one.
+    pub(crate) fn into_network(self) -> Result<RPSNetwork, Error> {
+        match self {
+            Saved::Network(network) => Ok(*network),
+            Saved::Legacy(legacy) => {
+                legacy.validate()?;
+                RPSNetwork::from_legacy(&legacy)
+            }
+            Saved::Store(_) => Err(Error::Unsupported(
+                "the model is a ModelStore, not a network",
+            )),
+            Saved::Quantized(_) => Err(Error::Unsupported(
+                "the model is a QuantizedNetwork, not a network",
+            )),
+        }
+    }
+
+    /// Loaded store, if the model is one.
+    pub(crate) fn into_store(self) -> Result<ModelStore, Error> {
+        match self {
+            Saved::Store(store) => Ok(*store),
+            _ => Err(Error::Unsupported("the model isn't a ModelStore")),
+        }
+    }
+
+    /// Loaded quantized network, if the model is one.
+    pub(crate) fn into_quantized(self) -> Result<QuantizedNetwork, Error> {
+        match self {
+            Saved::Quantized(quantized) => Ok(*quantized),
+            _ => Err(Error::Unsupported("the model isn't a QuantizedNetwork")),
+        }
+    }
+
+    /// Model saved before the format had a version, which only networks
+    /// were.
+    pub(crate) fn unversioned_json(json: &str) -> Result<Saved, Error> {
+        Ok(Saved::Network(serde_json::from_str(json)?))
+    }
+
+    /// Same as `unversioned_json()`, for the binary encoding.
+    pub(crate) fn unversioned_bytes(bytes: &[u8]) -> Result<Saved, Error> {
+        Ok(Saved::Network(bincode::deserialize(bytes)?))
+    }
+
+    /// Checks that the model matches the hash saved in its header in
+    /// format `version`.
+    fn check_hash(self, version: u32, expected: u64) -> Result<Saved, Error> {
+        let found = fnv1a(match &self {
+            Saved::Network(network) => network.hash_values(version),
+            Saved::Legacy(legacy) => legacy.hash_values(version),
+            Saved::Store(store) => store.hash_values(version),
+            Saved::Quantized(quantized) => quantized.hash_values(version),
+        });
+        if found != expected {
+            return Err(Error::ConfigMismatch { expected, found });
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+pub(crate) fn to_json(model: Saving) -> Result<String, Error> {
+    Ok(serde_json::to_string(&JsonEnvelope {
+        magic: MAGIC.into(),
+        version: FORMAT_VERSION,
+        config_hash: model.config_hash(),
+        model,
+    })?)
+}
+
+#[cfg(feature = "serde")]
+/// Loads a model saved by `to_json()` in any version, parsing one saved
+/// before the format had a version with `unversioned`.
+#[cfg(feature = "serde")]
+pub(crate) fn from_json(
+    json: &str,
+    unversioned: impl FnOnce(&str) -> Result<Saved, Error>,
+) -> Result<Saved, Error> {
+    let header = serde_json::from_str::<Header>(json)?;
+    match header.version {
+        None => unversioned(json),
+        Some(1) => Ok(serde_json::from_str::<EnvelopeV1<Saved>>(json)?.model),
+        Some(version @ 2..=FORMAT_VERSION) => {
+            if header.magic.as_deref() != Some(MAGIC) {
+                return Err(Error::InvalidMagic);
+            }
+            let envelope = serde_json::from_str::<JsonEnvelope<Saved>>(json)?;
+            envelope.model.check_hash(version, envelope.config_hash)
+        }
+        Some(version) => Err(unknown(version)),
+    }
+}
+
+#[cfg(feature = "serde")]
+pub(crate) fn to_bytes(model: Saving) -> Result<Vec<u8>, Error> {
+    let mut bytes = MAGIC.as_bytes().to_vec();
+    bincode::serialize_into(
+        &mut bytes,
+        &Envelope {
+            version: FORMAT_VERSION,
+            config_hash: model.config_hash(),
+            model,
+        },
+    )?;
+    Ok(bytes)
+}
+
+#[cfg(feature = "serde")]
+/// Same as `from_json()`, for `to_bytes()`.
+#[cfg(feature = "serde")]
+pub(crate) fn from_bytes(
+    bytes: &[u8],
+    unversioned: impl FnOnce(&[u8]) -> Result<Saved, Error>,
+) -> Result<Saved, Error> {
+    let Some(bytes) = bytes.strip_prefix(MAGIC.as_bytes()) else {
+        return unversioned(bytes);
+    };
+    match bincode::deserialize(bytes)? {
+        1 => Ok(bincode::deserialize::<EnvelopeV1<Saved>>(bytes)?.model),
+        version @ 2..=FORMAT_VERSION => {
+            let envelope = bincode::deserialize::<Envelope<Saved>>(bytes)?;
+            envelope.model.check_hash(version, envelope.config_hash)
+        }
+        version => Err(unknown(version)),
+    }
+}
+
+/// Error for a header with a version this crate can't read: one written by
+/// a later version, or one that never existed.
+#[cfg(feature = "serde")]
+fn unknown(version: u32) -> Error {
+    if version > FORMAT_VERSION {
+        Error::UnsupportedFormat {
+            version,
+            supported: FORMAT_VERSION,
+        }
+    } else {
+        Error::Serialization(alloc::format!("unknown model format version {}", version))
+    }
+}
+
+/// FNV-1a hash of `values`, stable across platforms and float types.
+#[cfg(feature = "serde")]
+pub(crate) fn fnv1a(values: impl IntoIterator<Item = usize>) -> u64 {
+    values
+        .into_iter()
+        .flat_map(|value| (value as u64).to_le_bytes())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+#[cfg(feature = "serde")]
+impl RPSNetwork {
+    /// Hash of the configuration, saved in the header.
+    fn config_hash(&self) -> u64 {
+        fnv1a(self.hash_values(FORMAT_VERSION))
+    }
+
+    /// Values hashed in the header in format `version`: the sizes and
+    /// parameter shapes, then from version 3 the architecture, optimizer
+    /// and activation of each layer, by their position in their enum.
+    pub(crate) fn hash_values(&self, version: u32) -> Vec<usize> {
+        let mut values = vec![0, self.input_size, self.history_size, self.output_size];
+        for param in self.params() {
+            values.push(param.ndim());
+            values.extend_from_slice(param.shape());
+        }
+        if version >= 3 {
+            values.push(self.config.architecture as usize);
+            values.push(self.optim.optimizer() as usize);
+            values.extend(
+                self.layers
+                    .iter()
+                    .map(|layer| activation_value(layer.activation)),
+            );
+        }
+        values
+    }
+}
+
+#[cfg(feature = "serde")]
+impl LegacyRPSNetwork {
+    /// Same as `RPSNetwork::config_hash()`, for the flattened parameters.
+    fn config_hash(&self) -> u64 {
+        fnv1a(self.hash_values(FORMAT_VERSION))
+    }
+
+    /// Same as `RPSNetwork::hash_values()`.
+    fn hash_values(&self, version: u32) -> Vec<usize> {
+        let mut values = vec![
+            1,
+            self.input_size,
+            self.history_size,
+            self.hidden_size,
+            self.output_size,
+            self.w1.len(),
+            self.b1.len(),
+            self.w2.len(),
+            self.b2.len(),
+        ];
+        if version >= 3 {
+            values.push(self.optim.optimizer() as usize);
+            values.push(activation_value(Some(self.activation)));
+        }
+        values
+    }
+}
+
+/// Hashed value of a layer's activation, 0 for none.
+#[cfg(feature = "serde")]
+pub(crate) fn activation_value(activation: Option<Activation>) -> usize {
+    activation.map_or(0, |activation| activation as usize + 1)
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl RPSNetwork {
+    /// Network equivalent to `legacy`, with the same sizes, activation,
+    /// optimizer and weight decay, and a copy of its weights, optimizer
+    /// state and history, so that it predicts and keeps learning the same
+    /// way. Everything the legacy network doesn't have is left at its
+    /// default. Models saved by `LegacyRPSNetwork::to_json()` or `to_bytes()`
+    /// are converted the same way by `from_json()` and `from_bytes()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn from_legacy(legacy: &LegacyRPSNetwork) -> Result<RPSNetwork, Error> {
+        let config = NetworkConfig::with_sizes(
+            legacy.input_size,
+            legacy.history_size,
+            legacy.hidden_size,
+            legacy.output_size,
+        )
+        .activation(legacy.activation)
+        .optimizer(legacy.optim.optimizer())
+        .weight_decay(legacy.weight_decay);
+        // Without std there is no entropy to seed the network from
+        #[cfg(not(feature = "std"))]
+        let config = config.seed(0);
+        let mut network = config.build()?;
+
+        // The legacy weights are `(inputs, outputs)` matrices flattened row
+        // by row, like the layers store them
+        for (layer, weights, bias) in [(0, &legacy.w1, &legacy.b1), (1, &legacy.w2, &legacy.b2)] {
+            let layer = &mut network.layers[layer];
+            let shape = layer.weights.dim();
+            layer
+                .weights
+                .assign(&ArrayView2::from_shape(shape, weights).map_err(|_| {
+                    Error::ShapeMismatch {
+                        name: "weights",
+                        expected: vec![shape.0 * shape.1],
+                        found: vec![weights.len()],
+                    }
+                })?);
+            layer.bias.assign(&ArrayView1::from(&bias[..]));
+        }
+        network.optim = legacy.optim.clone();
+        network.seed_history(&legacy.history)?;
+        network.evaluate();
+        Ok(network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::one_hot;
+    use crate::{Activation, Optimizer, Real};
+
+    fn legacy(optimizer: Optimizer) -> LegacyRPSNetwork {
+        let mut legacy = NetworkConfig::new()
+            .moves(3)
+            .history_size(4)
+            .hidden_size(6)
+            .activation(Activation::Tanh)
+            .optimizer(optimizer)
+            .weight_decay(0.01)
+            .seed(7)
+            .build_legacy()
+            .unwrap();
+        for round in 0..12 {
+            legacy
+                .train_step(&one_hot(round % 3, 3), round * 2 % 3, 0.05)
+                .unwrap();
+        }
+        legacy
+    }
+
+    fn assert_close(a: &[Real], b: &[Real]) {
+        for (a, b) in a.iter().zip(b) {
+            assert!((a - b).abs() < 1e-5, "{:?} {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn converts_legacy() {
+        for optimizer in [Optimizer::Sgd, Optimizer::Momentum, Optimizer::Adam] {
+            let mut legacy = legacy(optimizer);
+            let mut network = RPSNetwork::from_legacy(&legacy).unwrap();
+            assert_eq!(network.hidden_sizes(), vec![6]);
+            assert_eq!(network.history(), legacy.history());
+            legacy.forward(&one_hot(2, 3)).unwrap();
+            network.forward(&one_hot(2, 3)).unwrap();
+            assert_close(&network.probs(), &legacy.probs());
+
+            // Both keep learning the same way
+            for round in 0..5 {
+                let input = one_hot(round % 3, 3);
+                legacy.train_step(&input, 1, 0.05).unwrap();
+                network.forward(&input).unwrap();
+                network.backward(1, Some(0.05)).unwrap();
+            }
+            legacy.forward(&one_hot(0, 3)).unwrap();
+            network.forward(&one_hot(0, 3)).unwrap();
+            assert_close(&network.probs(), &legacy.probs());
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn loads_legacy_models() {
+        let legacy = legacy(Optimizer::Adam);
+        let (json, bytes) = (legacy.to_json().unwrap(), legacy.to_bytes().unwrap());
+        let mut restored = LegacyRPSNetwork::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.probs(), legacy.probs());
+        restored.forward(&one_hot(2, 3)).unwrap();
+        for mut network in [
+            RPSNetwork::from_json(&json).unwrap(),
+            RPSNetwork::from_bytes(&bytes).unwrap(),
+        ] {
+            network.forward(&one_hot(2, 3)).unwrap();
+            assert_close(&network.probs(), &restored.probs());
+        }
+
+        let network = RPSNetwork::from_legacy(&legacy).unwrap();
+        assert!(LegacyRPSNetwork::from_json(&network.to_json().unwrap()).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn versions() {
+        let network = RPSNetwork::from_legacy(&legacy(Optimizer::Sgd)).unwrap();
+        let json = network.to_json().unwrap();
+        assert!(json.starts_with("{\"magic\":\"RPSN\",\"version\":3,\"config_hash\":"));
+        let bytes = network.to_bytes().unwrap();
+        assert!(bytes.starts_with(MAGIC.as_bytes()));
+
+        // Models saved before the format had a version
+        let unversioned = serde_json::to_string(&network).unwrap();
+        assert_eq!(
+            RPSNetwork::from_json(&unversioned).unwrap().probs(),
+            network.probs()
+        );
+        let unversioned = bincode::serialize(&network).unwrap();
+        assert_eq!(
+            RPSNetwork::from_bytes(&unversioned).unwrap().probs(),
+            network.probs()
+        );
+
+        // Models saved in version 1, without the hash
+        let v1 = serde_json::json!({ "version": 1, "model": Saving::Network(&network) });
+        assert_eq!(
+            RPSNetwork::from_json(&v1.to_string()).unwrap().probs(),
+            network.probs()
+        );
+        // which saving again upgrades to the current version
+        let upgraded = RPSNetwork::from_json(&v1.to_string())
+            .unwrap()
+            .to_json()
+            .unwrap();
+        assert_eq!(upgraded, json);
+        let mut v1 = MAGIC.as_bytes().to_vec();
+        bincode::serialize_into(&mut v1, &(1u32, Saving::Network(&network))).unwrap();
+        let restored = RPSNetwork::from_bytes(&v1).unwrap();
+        assert_eq!(restored.probs(), network.probs());
+        assert_eq!(restored.to_bytes().unwrap(), bytes);
+
+        // Models saved in version 2, whose hash only covers the shapes
+        let hash = network.config_hash();
+        let v2_hash = fnv1a(network.hash_values(2));
+        assert_ne!(v2_hash, hash);
+        let v2 = json.replacen("\"version\":3", "\"version\":2", 1).replacen(
+            &alloc::format!("\"config_hash\":{}", hash),
+            &alloc::format!("\"config_hash\":{}", v2_hash),
+            1,
+        );
+        let restored = RPSNetwork::from_json(&v2).unwrap();
+        assert_eq!(restored.probs(), network.probs());
+        assert_eq!(restored.to_json().unwrap(), json);
+        let mut v2 = MAGIC.as_bytes().to_vec();
+        bincode::serialize_into(&mut v2, &(2u32, v2_hash, Saving::Network(&network))).unwrap();
+        assert_eq!(
+            RPSNetwork::from_bytes(&v2).unwrap().to_bytes().unwrap(),
+            bytes
+        );
+
+        // Versions that never existed
+        let unknown = json.replacen("\"version\":3", "\"version\":0", 1);
+        assert!(matches!(
+            RPSNetwork::from_json(&unknown),
+            Err(Error::Serialization(_))
+        ));
+        let mut unknown = bytes.clone();
+        unknown[MAGIC.len()] = 0;
+        assert!(matches!(
+            RPSNetwork::from_bytes(&unknown),
+            Err(Error::Serialization(_))
+        ));
+
+        // Models saved by a later version
+        let newer = json.replacen("\"version\":3", "\"version\":4", 1);
+        assert_eq!(
+            RPSNetwork::from_json(&newer).err(),
+            Some(Error::UnsupportedFormat {
+                version: 4,
+                supported: 3
+            })
+        );
+        let mut newer = bytes.clone();
+        newer[MAGIC.len()] = 4;
+        assert!(matches!(
+            RPSNetwork::from_bytes(&newer),
+            Err(Error::UnsupportedFormat { version: 4, .. })
+        ));
+
+        // Headers that don't match the model
+        let renamed = json.replacen("RPSN", "ABCD", 1);
+        assert_eq!(
+            RPSNetwork::from_json(&renamed).err(),
+            Some(Error::InvalidMagic)
+        );
+        let tampered = json.replacen(
+            &alloc::format!("\"config_hash\":{}", hash),
+            &alloc::format!("\"config_hash\":{}", hash ^ 1),
+            1,
+        );
+        assert_eq!(
+            RPSNetwork::from_json(&tampered).err(),
+            Some(Error::ConfigMismatch {
+                expected: hash ^ 1,
+                found: hash
+            })
+        );
+        let mut tampered = bytes;
+        tampered[MAGIC.len() + 4] ^= 1;
+        assert!(matches!(
+            RPSNetwork::from_bytes(&tampered),
+            Err(Error::ConfigMismatch { .. })
+        ));
+        let legacy = legacy(Optimizer::Sgd);
+        assert_ne!(legacy.config_hash(), hash);
+        assert!(LegacyRPSNetwork::from_bytes(&legacy.to_bytes().unwrap()).is_ok());
+        assert!(LegacyRPSNetwork::from_json(&json).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn hash_covers_configuration() {
+        let config = NetworkConfig::new().moves(3).seed(1);
+        let hash = config.build().unwrap().config_hash();
+        for other in [
+            config.clone().activation(Activation::ReLU),
+            config.clone().optimizer(Optimizer::Adam),
+            config.clone().architecture(crate::Architecture::Elman),
+        ] {
+            assert_ne!(other.build().unwrap().config_hash(), hash);
+        }
+        // The weights and the seed they were drawn from don't matter
+        let reseeded = config.clone().seed(2).build().unwrap();
+        assert_eq!(reseeded.config_hash(), hash);
+    }
+}
@@ -0,0 +1,178 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{check_input, Error, Prediction, Real};
+
+/// A move of rock-paper-scissors, numbered like the outputs of a 3-move
+/// network.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Move {
+    Rock = 0,
+    Paper = 1,
+    Scissors = 2,
+}
+
+/// Result of a round for one of the players.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Win = 0,
+    Lose = 1,
+    Draw = 2,
+}
+
+impl Move {
+    /// Every move, in index order.
+    pub const ALL: [Move; 3] = [Move::Rock, Move::Paper, Move::Scissors];
+
+    /// Move numbered `index`, which must be below 3.
+    pub fn from_index(index: usize) -> Result<Move, Error> {
+        Self::ALL.get(index).copied().ok_or(Error::InvalidMove {
+            index,
+            moves: Self::ALL.len(),
+        })
+    }
+
+    /// Index of the move in the inputs and outputs of a network.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Encodes the move as a network input, with a 1 at its index.
+    pub fn one_hot(self) -> Vec<Real> {
+        one_hot(self.index(), Self::ALL.len())
+    }
+
+    /// Move with the largest entry of `encoding`, so that an output
+    /// distribution converts to its most likely move.
+    pub fn from_one_hot(encoding: &[Real]) -> Result<Move, Error> {
+        check_input(encoding, Self::ALL.len())?;
+        Self::from_index(Prediction::argmax(encoding).index)
+    }
+
+    /// Move that beats this one.
+    pub fn beaten_by(self) -> Move {
+        Self::ALL[(self.index() + 1) % 3]
+    }
+
+    /// Outcome of playing this move against `other`.
+    pub fn against(self, other: Move) -> Outcome {
+        if self == other {
+            Outcome::Draw
+        } else if self.beaten_by() == other {
+            Outcome::Lose
+        } else {
+            Outcome::Win
+        }
+    }
+}
+
+impl Outcome {
+    /// Every outcome, in index order.
+    pub const ALL: [Outcome; 3] = [Outcome::Win, Outcome::Lose, Outcome::Draw];
+
+    /// Index of the outcome in its one-hot encoding.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Encodes the outcome as a network input, with a 1 at its index.
+    pub fn one_hot(self) -> Vec<Real> {
+        one_hot(self.index(), Self::ALL.len())
+    }
+
+    /// Outcome with the largest entry of `encoding`.
+    pub fn from_one_hot(encoding: &[Real]) -> Result<Outcome, Error> {
+        check_input(encoding, Self::ALL.len())?;
+        Ok(Self::ALL[Prediction::argmax(encoding).index])
+    }
+}
+
+impl TryFrom<usize> for Move {
+    type Error = Error;
+
+    fn try_from(index: usize) -> Result<Self, Error> {
+        Self::from_index(index)
+    }
+}
+
+/// Encoding of move or outcome number `index` out of `len`.
+pub(crate) fn one_hot(index: usize, len: usize) -> Vec<Real> {
+    let mut encoding = vec![0.0; len];
+    encoding[index] = 1.0;
+    encoding
+}
+
+/// One-hot encoding of `m`, as passed to `forward()`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn move_to_one_hot(m: Move) -> Vec<Real> {
+    m.one_hot()
+}
+
+/// Most likely move of `encoding`, a one-hot encoding or an output
+/// distribution.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn move_from_one_hot(encoding: &[Real]) -> Result<Move, Error> {
+    Move::from_one_hot(encoding)
+}
+
+/// Outcome of playing `m` against `other`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn judge_move(m: Move, other: Move) -> Outcome {
+    m.against(other)
+}
+
+/// One-hot encoding of `outcome`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn outcome_to_one_hot(outcome: Outcome) -> Vec<Real> {
+    outcome.one_hot()
+}
+
+/// Most likely outcome of `encoding`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn outcome_from_one_hot(encoding: &[Real]) -> Result<Outcome, Error> {
+    Outcome::from_one_hot(encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_hot_round_trip() {
+        for m in Move::ALL {
+            assert_eq!(Move::from_one_hot(&m.one_hot()), Ok(m));
+            assert_eq!(Move::try_from(m.index()), Ok(m));
+        }
+        for outcome in Outcome::ALL {
+            assert_eq!(Outcome::from_one_hot(&outcome.one_hot()), Ok(outcome));
+        }
+        assert_eq!(Move::Paper.one_hot(), vec![0.0, 1.0, 0.0]);
+        assert_eq!(Move::from_one_hot(&[0.2, 0.1, 0.7]), Ok(Move::Scissors));
+
+        assert_eq!(
+            Move::from_index(3),
+            Err(Error::InvalidMove { index: 3, moves: 3 })
+        );
+        assert!(Move::from_one_hot(&[1.0, 0.0]).is_err());
+    }
+
+    #[test]
+    fn rules() {
+        assert_eq!(Move::Rock.beaten_by(), Move::Paper);
+        assert_eq!(Move::Scissors.beaten_by(), Move::Rock);
+
+        assert_eq!(Move::Rock.against(Move::Scissors), Outcome::Win);
+        assert_eq!(Move::Rock.against(Move::Paper), Outcome::Lose);
+        assert_eq!(Move::Paper.against(Move::Paper), Outcome::Draw);
+    }
+}
@@ -0,0 +1,330 @@
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::{String, ToString};
+
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{check_label, Error, GameEngine, NetworkConfig, Outcome};
+
+/// Message from a player to the match server, encoded as JSON with a `type`
+/// field naming the variant, e.g. `{"type": "Join", "player": "alice"}`.
+///
+/// Each round, the player commits to a move, gets the bot's commitment, and
+/// only then reveals the move, so that neither side can pick its move
+/// knowing the other's: the bot's is revealed in the `Result`, where the
+/// player can check it against the bot's commitment with `commitment()`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ClientMessage {
+    /// Starts or resumes the match of `player`, whose network is kept
+    /// across connections.
+    Join { player: String },
+    /// Commits to the move of the coming round, as returned by
+    /// `commitment()` for the move and a random nonce kept secret until the
+    /// reveal.
+    Commit { commitment: String },
+    /// Reveals the committed move and its nonce.
+    Reveal { player_move: usize, nonce: String },
+}
+
+/// Answer of the match server to a `ClientMessage`, encoded like it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ServerMessage {
+    /// Answer to `Join`, with the number of rounds already played.
+    Joined { player: String, rounds: u32 },
+    /// Answer to `Commit`, with the bot's commitment to its move.
+    Committed { commitment: String },
+    /// Answer to `Reveal`: the bot's move and nonce, the outcome for the
+    /// player, and the score so far.
+    Result {
+        round: u32,
+        player_move: usize,
+        bot_move: usize,
+        bot_nonce: String,
+        outcome: Outcome,
+        player_wins: u32,
+        bot_wins: u32,
+        draws: u32,
+    },
+    /// The message failed, or couldn't be decoded. The round, if any, is
+    /// left as it was.
+    Error { message: String },
+}
+
+/// Commitment to playing `m` in a round, for `ClientMessage::Commit` and to
+/// check `ServerMessage::Committed`: the hex-encoded SHA-256 of the move and
+/// `nonce`, which should be random and used once.
+pub fn commitment(m: usize, nonce: &str) -> String {
+    Sha256::digest(format!("{}:{}", m, nonce))
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Round of a match between commit and reveal.
+#[derive(Debug, Clone)]
+struct Pending {
+    commitment: String,
+    bot_move: usize,
+    bot_nonce: String,
+}
+
+/// Match of one player against their own network.
+struct Match {
+    engine: GameEngine,
+    pending: Option<Pending>,
+}
+
+/// Server side of the match protocol, transport aside: plays human-vs-bot
+/// matches over the network, one network per player, built from the
+/// coordinator's configuration when the player first joins. Like a
+/// `ModelStore`, it keeps at most `capacity` players, evicting the one who
+/// played least recently. The transport, e.g. a WebSocket, remembers which
+/// player joined on each connection.
+pub struct MatchCoordinator {
+    config: NetworkConfig,
+    capacity: usize,
+    /// Matches by player ID, from the least recently played.
+    matches: VecDeque<(String, Match)>,
+    rng: ChaCha8Rng,
+}
+
+impl MatchCoordinator {
+    /// Starts a coordinator for at most `capacity` players, whose networks
+    /// are built from `config` and must play rock-paper-scissors. The bot's
+    /// nonces are drawn from `config`'s seed if any, which makes them
+    /// predictable, otherwise from entropy.
+    pub fn new(config: &NetworkConfig, capacity: usize) -> Result<MatchCoordinator, Error> {
+        if capacity == 0 {
+            return Err(Error::InvalidSize {
+                name: "capacity",
+                value: 0,
+                min: 1,
+            });
+        }
+        // Fail now rather than on the first player
+        GameEngine::new(config.build()?)?;
+        Ok(Self {
+            config: config.clone(),
+            capacity,
+            matches: VecDeque::with_capacity(capacity),
+            rng: config.rng()?,
+        })
+    }
+
+    /// Applies `message` from the connection on which `player` joined, if
+    /// any, and returns the answer.
+    pub fn handle(&mut self, player: Option<&str>, message: ClientMessage) -> ServerMessage {
+        self.try_handle(player, message)
+            .unwrap_or_else(|error| ServerMessage::Error {
+                message: error.to_string(),
+            })
+    }
+
+    /// Number of players with a match.
+    pub fn len(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// Whether no player has joined.
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    fn try_handle(
+        &mut self,
+        player: Option<&str>,
+        message: ClientMessage,
+    ) -> Result<ServerMessage, Error> {
+        if let ClientMessage::Join { player } = message {
+            let rounds = self.get_mut(&player)?.engine.rounds();
+            return Ok(ServerMessage::Joined { player, rounds });
+        }
+        let player = player.ok_or(Error::Unsupported("join a match first"))?;
+        let nonce = self.nonce();
+        let current = self.get_mut(player)?;
+        match message {
+            ClientMessage::Join { .. } => unreachable!("handled above"),
+            ClientMessage::Commit { commitment } => {
+                if current.pending.is_some() {
+                    return Err(Error::Unsupported("the move was already committed"));
+                }
+                let bot_move = current.engine.bot_move();
+                let committed = self::commitment(bot_move, &nonce);
+                current.pending = Some(Pending {
+                    commitment,
+                    bot_move,
+                    bot_nonce: nonce,
+                });
+                Ok(ServerMessage::Committed {
+                    commitment: committed,
+                })
+            }
+            ClientMessage::Reveal { player_move, nonce } => {
+                let pending = current
+                    .pending
+                    .as_ref()
+                    .ok_or(Error::Unsupported("commit to a move first"))?;
+                check_label(player_move, 3)?;
+                if self::commitment(player_move, &nonce) != pending.commitment {
+                    return Err(Error::Unsupported("the move doesn't match the commitment"));
+                }
+                let Pending {
+                    bot_move,
+                    bot_nonce,
+                    ..
+                } = current.pending.take().unwrap();
+                let engine = &mut current.engine;
+                let outcome = engine.play(player_move, bot_move)?;
+                Ok(ServerMessage::Result {
+                    round: engine.rounds(),
+                    player_move,
+                    bot_move,
+                    bot_nonce,
+                    outcome,
+                    player_wins: engine.player_wins(),
+                    bot_wins: engine.bot_wins(),
+                    draws: engine.draws(),
+                })
+            }
+        }
+    }
+
+    /// Match of `player`, started if the player is new, and marked as the
+    /// most recently played.
+    fn get_mut(&mut self, player: &str) -> Result<&mut Match, Error> {
+        let current = match self.matches.iter().position(|(id, _)| id == player) {
+            Some(index) => self.matches.remove(index).unwrap().1,
+            None => Match {
+                engine: GameEngine::new(self.config.build()?)?,
+                pending: None,
+            },
+        };
+        if self.matches.len() == self.capacity {
+            self.matches.pop_front();
+        }
+        self.matches.push_back((player.to_string(), current));
+        Ok(&mut self.matches.back_mut().unwrap().1)
+    }
+
+    fn nonce(&mut self) -> String {
+        format!("{:032x}", self.rng.gen::<u128>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coordinator(capacity: usize) -> MatchCoordinator {
+        MatchCoordinator::new(&NetworkConfig::new().moves(3).seed(42), capacity).unwrap()
+    }
+
+    /// Plays a round of `player` with move `m`, checking the bot's
+    /// commitment.
+    fn round(coordinator: &mut MatchCoordinator, player: &str, m: usize) -> ServerMessage {
+        let nonce = format!("nonce-{}", m);
+        let commit = ClientMessage::Commit {
+            commitment: commitment(m, &nonce),
+        };
+        let ServerMessage::Committed {
+            commitment: committed,
+        } = coordinator.handle(Some(player), commit)
+        else {
+            panic!("the commitment failed");
+        };
+        let reveal = ClientMessage::Reveal {
+            player_move: m,
+            nonce,
+        };
+        let result = coordinator.handle(Some(player), reveal);
+        if let ServerMessage::Result {
+            bot_move,
+            bot_nonce,
+            ..
+        } = &result
+        {
+            assert_eq!(commitment(*bot_move, bot_nonce), committed);
+        }
+        result
+    }
+
+    #[test]
+    fn plays_matches() {
+        let mut coordinator = coordinator(4);
+        let join = ClientMessage::Join {
+            player: "alice".into(),
+        };
+        assert_eq!(
+            coordinator.handle(None, join.clone()),
+            ServerMessage::Joined {
+                player: "alice".into(),
+                rounds: 0
+            }
+        );
+        for _ in 0..30 {
+            round(&mut coordinator, "alice", 0);
+        }
+        let ServerMessage::Result {
+            round: 31,
+            bot_wins,
+            ..
+        } = round(&mut coordinator, "alice", 0)
+        else {
+            panic!("unexpected result");
+        };
+        // The network learned that alice always plays rock
+        assert!(bot_wins > 20);
+
+        // Each player has their own network, kept across connections
+        assert!(matches!(
+            round(&mut coordinator, "bob", 0),
+            ServerMessage::Result { round: 1, .. }
+        ));
+        assert_eq!(coordinator.len(), 2);
+        assert!(matches!(
+            coordinator.handle(None, join),
+            ServerMessage::Joined { rounds: 31, .. }
+        ));
+    }
+
+    #[test]
+    fn rejects_out_of_order_messages() {
+        let mut coordinator = coordinator(1);
+        let commit = ClientMessage::Commit {
+            commitment: commitment(1, "secret"),
+        };
+        let reveal = |m| ClientMessage::Reveal {
+            player_move: m,
+            nonce: "secret".into(),
+        };
+        let is_error = |message| matches!(message, ServerMessage::Error { .. });
+
+        assert!(is_error(coordinator.handle(None, commit.clone())));
+        assert!(is_error(coordinator.handle(Some("alice"), reveal(1))));
+        assert!(!is_error(coordinator.handle(Some("alice"), commit.clone())));
+        assert!(is_error(coordinator.handle(Some("alice"), commit)));
+        // Revealing another move than the committed one
+        assert!(is_error(coordinator.handle(Some("alice"), reveal(2))));
+        assert!(is_error(coordinator.handle(Some("alice"), reveal(3))));
+        assert!(!is_error(coordinator.handle(Some("alice"), reveal(1))));
+
+        // The least recently played match is evicted
+        round(&mut coordinator, "bob", 0);
+        assert!(matches!(
+            coordinator.handle(
+                None,
+                ClientMessage::Join {
+                    player: "alice".into()
+                }
+            ),
+            ServerMessage::Joined { rounds: 0, .. }
+        ));
+        assert!(MatchCoordinator::new(&NetworkConfig::new().moves(3), 0).is_err());
+        assert!(MatchCoordinator::new(&NetworkConfig::new().moves(4), 1).is_err());
+    }
+}
@@ -0,0 +1,336 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::config::LEAKY_RELU_SLOPE;
+use crate::{Activation, Error, RPSNetwork, Real};
+
+/// Version of the ONNX file format, the one of ONNX 1.13.
+const IR_VERSION: u64 = 8;
+/// Version of the default operator set, which has every operator used.
+const OPSET_VERSION: u64 = 13;
+
+/// `TensorProto.DataType` of `Real`.
+#[cfg(not(feature = "f64"))]
+const ELEM_TYPE: u64 = 1;
+#[cfg(feature = "f64")]
+const ELEM_TYPE: u64 = 11;
+
+/// Protocol buffer message under construction, written field by field in
+/// the wire format, which is all the export needs from protobuf.
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(mut self, field: u32, value: u64) -> Self {
+        self.key(field, 0);
+        self.raw_varint(value);
+        self
+    }
+
+    fn float(mut self, field: u32, value: f32) -> Self {
+        self.key(field, 5);
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn bytes(mut self, field: u32, value: &[u8]) -> Self {
+        self.key(field, 2);
+        self.raw_varint(value.len() as u64);
+        self.0.extend_from_slice(value);
+        self
+    }
+
+    fn string(self, field: u32, value: &str) -> Self {
+        self.bytes(field, value.as_bytes())
+    }
+
+    fn message(self, field: u32, value: Message) -> Self {
+        self.bytes(field, &value.0)
+    }
+
+    fn key(&mut self, field: u32, wire_type: u32) {
+        self.raw_varint(u64::from(field << 3 | wire_type));
+    }
+
+    fn raw_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+}
+
+/// `TensorProto` named `name`, holding `values` with shape `dims`.
+fn tensor(name: &str, dims: &[usize], values: impl IntoIterator<Item = Real>) -> Message {
+    let data: Vec<u8> = values.into_iter().flat_map(Real::to_le_bytes).collect();
+    dims.iter()
+        .fold(Message::default(), |m, &d| m.varint(1, d as u64))
+        .varint(2, ELEM_TYPE)
+        .string(8, name)
+        .bytes(9, &data)
+}
+
+/// `ValueInfoProto` of a `(batch, size)` tensor, with a free batch size.
+fn value_info(name: &str, size: usize, doc: &str) -> Message {
+    let shape = Message::default()
+        .message(1, Message::default().string(2, "batch"))
+        .message(1, Message::default().varint(1, size as u64));
+    let tensor_type = Message::default().varint(1, ELEM_TYPE).message(2, shape);
+    Message::default()
+        .string(1, name)
+        .message(2, Message::default().message(1, tensor_type))
+        .string(3, doc)
+}
+
+/// `NodeProto` applying `op` to `inputs`, with its output named `output`.
+fn node(op: &str, inputs: &[&str], output: &str) -> Message {
+    inputs
+        .iter()
+        .fold(Message::default(), |m, input| m.string(1, input))
+        .string(2, output)
+        .string(3, output)
+        .string(4, op)
+}
+
+/// `StringStringEntryProto` of the model metadata.
+fn entry(key: &str, value: impl ToString) -> Message {
+    Message::default()
+        .string(1, key)
+        .string(2, &value.to_string())
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl RPSNetwork {
+    /// Serializes the network as an ONNX model, to inspect it in Netron or
+    /// run it in another runtime. The model maps a `history` input of shape
+    /// `(batch, history_size * input_size)`, the history window flattened
+    /// slot by slot from the oldest round to the newest as `history()`
+    /// returns it, to the `probs` output of shape `(batch, output_size)`,
+    /// which matches `probs()` after `forward()`. The history decay,
+    /// temperature and exploration are folded into the graph, and the sizes
+    /// are recorded in the model metadata. Like `quantize()`, it only
+    /// supports feedforward networks without attention.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn export_onnx(&self) -> Result<Vec<u8>, Error> {
+        if self.recurrent.is_some() || self.attention.is_some() {
+            return Err(Error::Unsupported(
+                "only feedforward networks without attention can be exported to ONNX",
+            ));
+        }
+        let inputs = self.history_size * self.input_size;
+        let mut graph = Message::default().string(2, "rps_network");
+        let mut x = String::from("history");
+        for (i, layer) in self.layers.iter().enumerate() {
+            let (rows, cols) = layer.weights.dim();
+            let weights = format!("layer{}.weights", i);
+            let bias = format!("layer{}.bias", i);
+            let decay = |row: usize| {
+                if i == 0 {
+                    self.slot_decay(row / self.input_size)
+                } else {
+                    1.0
+                }
+            };
+            let values = layer
+                .weights
+                .indexed_iter()
+                .map(|((row, _), &w)| w * decay(row));
+            graph = graph
+                .message(5, tensor(&weights, &[rows, cols], values))
+                .message(5, tensor(&bias, &[cols], layer.bias.iter().cloned()));
+
+            let product = format!("layer{}.matmul", i);
+            let linear = format!("layer{}.linear", i);
+            graph = graph
+                .message(1, node("MatMul", &[&x, &weights], &product))
+                .message(1, node("Add", &[&product, &bias], &linear));
+            x = linear;
+            if let Some(activation) = layer.activation {
+                let output = format!("layer{}.output", i);
+                let op = match activation {
+                    Activation::Tanh => "Tanh",
+                    Activation::ReLU => "Relu",
+                    Activation::LeakyReLU => "LeakyRelu",
+                    Activation::Sigmoid => "Sigmoid",
+                };
+                let mut activation_node = node(op, &[&x], &output);
+                if activation == Activation::LeakyReLU {
+                    // AttributeProto of type FLOAT, which is f32 even when Real
+                    // is f64
+                    #[allow(clippy::unnecessary_cast)]
+                    let alpha = Message::default()
+                        .string(1, "alpha")
+                        .float(2, LEAKY_RELU_SLOPE as f32)
+                        .varint(20, 1);
+                    activation_node = activation_node.message(5, alpha);
+                }
+                graph = graph.message(1, activation_node);
+                x = output;
+            }
+        }
+
+        if self.temperature != 1.0 {
+            graph = graph
+                .message(5, tensor("temperature", &[], [self.temperature]))
+                .message(1, node("Div", &[&x, "temperature"], "scaled"));
+            x = String::from("scaled");
+        }
+        if self.exploration > 0.0 {
+            let floor = self.exploration / self.output_size as Real;
+            graph = graph
+                .message(1, node("Softmax", &[&x], "softmax"))
+                .message(5, tensor("keep", &[], [1.0 - self.exploration]))
+                .message(5, tensor("floor", &[], [floor]))
+                .message(1, node("Mul", &["softmax", "keep"], "kept"))
+                .message(1, node("Add", &["kept", "floor"], "probs"));
+        } else {
+            graph = graph.message(1, node("Softmax", &[&x], "probs"));
+        }
+        graph = graph
+            .message(
+                11,
+                value_info(
+                    "history",
+                    inputs,
+                    "history window, history_size slots of input_size values from the oldest \
+                     round to the newest",
+                ),
+            )
+            .message(
+                12,
+                value_info("probs", self.output_size, "probability of each next move"),
+            );
+
+        let model = Message::default()
+            .varint(1, IR_VERSION)
+            .string(2, "rps-network")
+            .string(3, env!("CARGO_PKG_VERSION"))
+            .message(7, graph)
+            .message(8, Message::default().varint(2, OPSET_VERSION))
+            .message(14, entry("input_size", self.input_size))
+            .message(14, entry("history_size", self.history_size))
+            .message(14, entry("output_size", self.output_size))
+            .message(14, entry("history_decay", self.history_decay));
+        Ok(model.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Architecture, NetworkConfig};
+
+    /// Reads the fields of a message as `(field, varint or bytes)`.
+    fn fields(mut bytes: &[u8]) -> Vec<(u32, Result<u64, &[u8]>)> {
+        fn varint(bytes: &mut &[u8]) -> u64 {
+            let mut value = 0;
+            for shift in (0..).step_by(7) {
+                let byte = bytes[0];
+                *bytes = &bytes[1..];
+                value |= u64::from(byte & 0x7f) << shift;
+                if byte < 0x80 {
+                    break;
+                }
+            }
+            value
+        }
+        let mut fields = Vec::new();
+        while !bytes.is_empty() {
+            let key = varint(&mut bytes) as u32;
+            let value = match key & 7 {
+                0 => Ok(varint(&mut bytes)),
+                5 => {
+                    let (value, rest) = bytes.split_at(4);
+                    bytes = rest;
+                    Err(value)
+                }
+                _ => {
+                    let len = varint(&mut bytes) as usize;
+                    let (value, rest) = bytes.split_at(len);
+                    bytes = rest;
+                    Err(value)
+                }
+            };
+            fields.push((key >> 3, value));
+        }
+        fields
+    }
+
+    fn field<'a>(fields: &[(u32, Result<u64, &'a [u8]>)], number: u32) -> Vec<&'a [u8]> {
+        fields
+            .iter()
+            .filter(|(n, _)| *n == number)
+            .filter_map(|(_, value)| value.err())
+            .collect()
+    }
+
+    #[test]
+    fn exports_graph() {
+        let network = NetworkConfig::with_sizes(3, 4, 8, 3)
+            .hidden_sizes(&[8, 6])
+            .activation(Activation::LeakyReLU)
+            .seed(42)
+            .build()
+            .unwrap();
+        let model = network.export_onnx().unwrap();
+        let model = fields(&model);
+        assert_eq!(model[0], (1, Ok(IR_VERSION)));
+        let metadata = field(&model, 14);
+        assert_eq!(metadata.len(), 4);
+        assert_eq!(field(&fields(metadata[1]), 2), [b"4"]);
+
+        let graph = fields(field(&model, 7)[0]);
+        let ops: Vec<&[u8]> = field(&graph, 1)
+            .into_iter()
+            .map(|node| field(&fields(node), 4)[0])
+            .collect();
+        let expected: [&[u8]; 9] = [
+            b"MatMul",
+            b"Add",
+            b"LeakyRelu",
+            b"MatMul",
+            b"Add",
+            b"LeakyRelu",
+            b"MatMul",
+            b"Add",
+            b"Softmax",
+        ];
+        assert_eq!(ops, expected);
+        // Weights and biases of the 3 layers
+        let initializers = field(&graph, 5);
+        assert_eq!(initializers.len(), 6);
+        let raw = field(&fields(initializers[0]), 9)[0];
+        assert_eq!(raw.len(), 12 * 8 * core::mem::size_of::<Real>());
+        assert_eq!(
+            raw[..core::mem::size_of::<Real>()],
+            network.layers[0].weights[[0, 0]].to_le_bytes()
+        );
+    }
+
+    #[test]
+    fn folds_settings() {
+        let mut network = RPSNetwork::new_with_seed(3, 5, 10, 3, 42).unwrap();
+        network.set_temperature(2.0).unwrap();
+        network.set_exploration(0.1).unwrap();
+        let model = network.export_onnx().unwrap();
+        let graph = fields(field(&fields(&model), 7)[0]);
+        let outputs: Vec<&[u8]> = field(&graph, 1)
+            .into_iter()
+            .map(|node| field(&fields(node), 2)[0])
+            .collect();
+        assert_eq!(outputs.last().unwrap(), b"probs");
+        assert!(outputs.contains(&&b"scaled"[..]));
+
+        let recurrent = NetworkConfig::with_sizes(3, 5, 10, 3)
+            .architecture(Architecture::Elman)
+            .seed(42)
+            .build()
+            .unwrap();
+        assert!(recurrent.export_onnx().is_err());
+    }
+}
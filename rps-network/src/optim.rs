@@ -0,0 +1,303 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use ndarray::{ArrayView, ArrayViewMut, Dimension};
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::Real;
+
+#[cfg(feature = "serde")]
+use crate::Error;
+
+const MOMENTUM: Real = 0.9;
+const ADAM_BETA1: Real = 0.9;
+const ADAM_BETA2: Real = 0.999;
+const ADAM_EPSILON: Real = 1e-8;
+
+/// Update rule used by `backward()`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Optimizer {
+    /// Plain stochastic gradient descent.
+    Sgd,
+    /// Stochastic gradient descent with classical momentum (0.9).
+    Momentum,
+    /// Adam, with the usual defaults (beta1 = 0.9, beta2 = 0.999).
+    Adam,
+}
+
+/// First and second moment estimates of one parameter tensor, flattened in
+/// logical order. Momentum only uses `m`, as the velocity.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq)]
+struct Moments {
+    m: Vec<Real>,
+    v: Vec<Real>,
+}
+
+impl Clone for Moments {
+    fn clone(&self) -> Self {
+        Self {
+            m: self.m.clone(),
+            v: self.v.clone(),
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.m.clone_from(&source.m);
+        self.v.clone_from(&source.v);
+    }
+}
+
+/// Optimizer state owned by a network. Parameters are identified by a stable
+/// index, and must be updated in the same order on every step.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq)]
+pub(crate) struct OptimizerState {
+    optimizer: Optimizer,
+    step: i32,
+    moments: Vec<Moments>,
+}
+
+/// `clone_from()` reuses the moment buffers, as `backward()` saves the state
+/// for `undo_backward()` on every step.
+impl Clone for OptimizerState {
+    fn clone(&self) -> Self {
+        Self {
+            optimizer: self.optimizer,
+            step: self.step,
+            moments: self.moments.clone(),
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.optimizer = source.optimizer;
+        self.step = source.step;
+        self.moments.clone_from(&source.moments);
+    }
+}
+
+impl OptimizerState {
+    pub(crate) fn new(optimizer: Optimizer) -> Self {
+        Self {
+            optimizer,
+            step: 0,
+            moments: Vec::new(),
+        }
+    }
+
+    pub(crate) fn optimizer(&self) -> Optimizer {
+        self.optimizer
+    }
+
+    /// Number of values held by the moment buffers allocated so far.
+    pub(crate) fn len(&self) -> usize {
+        self.moments
+            .iter()
+            .map(|moments| moments.m.len() + moments.v.len())
+            .sum()
+    }
+
+    /// Checks the moment buffers allocated so far against the parameter
+    /// tensors, of `lens` values each.
+    #[cfg(feature = "serde")]
+    pub(crate) fn validate(&self, lens: &[usize]) -> Result<(), Error> {
+        if self.moments.len() > lens.len() {
+            return Err(Error::ShapeMismatch {
+                name: "moments",
+                expected: vec![lens.len()],
+                found: vec![self.moments.len()],
+            });
+        }
+        for (moments, &len) in self.moments.iter().zip(lens) {
+            let v = match self.optimizer {
+                Optimizer::Adam => len,
+                _ => 0,
+            };
+            for (name, expected, found) in [("m", len, moments.m.len()), ("v", v, moments.v.len())]
+            {
+                if expected != found {
+                    return Err(Error::ShapeMismatch {
+                        name,
+                        expected: vec![expected],
+                        found: vec![found],
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts a new training step. Must be called once per `backward()`,
+    /// before updating any parameter.
+    pub(crate) fn begin_step(&mut self) {
+        self.step += 1;
+    }
+
+    /// Applies the gradient `grad` to the parameter tensor number `index`.
+    pub(crate) fn update<D: Dimension>(
+        &mut self,
+        index: usize,
+        mut param: ArrayViewMut<Real, D>,
+        grad: ArrayView<Real, D>,
+        learning_rate: Real,
+    ) {
+        match self.optimizer {
+            Optimizer::Sgd => param.scaled_add(-learning_rate, &grad),
+            Optimizer::Momentum => {
+                let Moments { m: velocity, .. } = self.moments(index, param.len());
+
+                for ((p, g), u) in param.iter_mut().zip(grad.iter()).zip(velocity.iter_mut()) {
+                    *u = MOMENTUM * *u + g;
+                    *p -= learning_rate * *u;
+                }
+            }
+            Optimizer::Adam => {
+                let step = self.step;
+                let Moments { m, v } = self.moments(index, param.len());
+
+                let m_correction = 1.0 - ADAM_BETA1.powi(step);
+                let v_correction = 1.0 - ADAM_BETA2.powi(step);
+                for (((p, g), m), v) in param
+                    .iter_mut()
+                    .zip(grad.iter())
+                    .zip(m.iter_mut())
+                    .zip(v.iter_mut())
+                {
+                    *m = ADAM_BETA1 * *m + (1.0 - ADAM_BETA1) * g;
+                    *v = ADAM_BETA2 * *v + (1.0 - ADAM_BETA2) * g * g;
+                    let m_hat = *m / m_correction;
+                    let v_hat = *v / v_correction;
+                    *p -= learning_rate * m_hat / (v_hat.sqrt() + ADAM_EPSILON);
+                }
+            }
+        }
+    }
+
+    /// Resizes the buffers of the parameter tensor number `index` to `len`
+    /// values, keeping the last ones and adding zeros at the front, after
+    /// the tensor itself was resized the same way.
+    pub(crate) fn resize_front(&mut self, index: usize, len: usize) {
+        self.map_moments(index, |buffer| {
+            let kept = len.min(buffer.len());
+            let mut resized = vec![0.0; len - kept];
+            resized.extend_from_slice(&buffer[buffer.len() - kept..]);
+            resized
+        });
+    }
+
+    /// Replaces each buffer of the parameter tensor number `index`, if
+    /// already allocated, with `f` of it, after the tensor was reshaped.
+    pub(crate) fn map_moments(&mut self, index: usize, mut f: impl FnMut(&[Real]) -> Vec<Real>) {
+        if let Some(moments) = self.moments.get_mut(index) {
+            for buffer in [&mut moments.m, &mut moments.v] {
+                if !buffer.is_empty() {
+                    *buffer = f(buffer);
+                }
+            }
+        }
+    }
+
+    /// Buffers of the parameter tensor number `index`, allocated on its first
+    /// update.
+    fn moments(&mut self, index: usize, len: usize) -> &mut Moments {
+        if index == self.moments.len() {
+            let v = match self.optimizer {
+                Optimizer::Adam => vec![0.0; len],
+                _ => Vec::new(),
+            };
+            self.moments.push(Moments {
+                m: vec![0.0; len],
+                v,
+            });
+        }
+        &mut self.moments[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::arr1;
+
+    #[test]
+    fn sgd_step() {
+        let mut state = OptimizerState::new(Optimizer::Sgd);
+        let mut param = arr1(&[1.0, -1.0]);
+
+        state.begin_step();
+        state.update(0, param.view_mut(), arr1(&[0.5, -2.0]).view(), 0.1);
+
+        assert_eq!(param, arr1(&[0.95, -0.8]));
+    }
+
+    #[test]
+    fn momentum_accumulates_velocity() {
+        let mut state = OptimizerState::new(Optimizer::Momentum);
+        let mut param = arr1(&[1.0]);
+
+        state.begin_step();
+        state.update(0, param.view_mut(), arr1(&[1.0]).view(), 0.1);
+        assert!((param[0] - 0.9).abs() < 1e-6);
+
+        state.begin_step();
+        state.update(0, param.view_mut(), arr1(&[1.0]).view(), 0.1);
+        assert!((param[0] - 0.71).abs() < 1e-6);
+    }
+
+    #[test]
+    fn adam_first_step_is_normalized() {
+        let mut state = OptimizerState::new(Optimizer::Adam);
+        let mut param = arr1(&[1.0, -1.0]);
+
+        state.begin_step();
+        state.update(0, param.view_mut(), arr1(&[0.5, -200.0]).view(), 0.1);
+
+        // Bias-corrected Adam moves every parameter by ~lr on the first step,
+        // regardless of the gradient magnitude.
+        assert!((param[0] - 0.9).abs() < 1e-5);
+        assert!((param[1] + 0.9).abs() < 1e-5);
+    }
+
+    #[test]
+    fn clone_from_reuses_buffers() {
+        let mut state = OptimizerState::new(Optimizer::Adam);
+        let mut param = arr1(&[1.0, -1.0]);
+        state.begin_step();
+        state.update(0, param.view_mut(), arr1(&[0.5, -2.0]).view(), 0.1);
+        let mut saved = state.clone();
+        let buffers = [saved.moments[0].m.as_ptr(), saved.moments[0].v.as_ptr()];
+
+        state.begin_step();
+        state.update(0, param.view_mut(), arr1(&[0.5, -2.0]).view(), 0.1);
+        saved.clone_from(&state);
+        assert_eq!(saved, state);
+        assert_eq!(
+            [saved.moments[0].m.as_ptr(), saved.moments[0].v.as_ptr()],
+            buffers
+        );
+    }
+
+    #[test]
+    fn resize_front() {
+        let mut state = OptimizerState::new(Optimizer::Adam);
+        let mut param = arr1(&[1.0, 2.0, 3.0]);
+        state.begin_step();
+        state.update(0, param.view_mut(), arr1(&[1.0, 2.0, 3.0]).view(), 0.1);
+        let m = state.moments[0].m.clone();
+
+        state.resize_front(0, 2);
+        assert_eq!(state.moments[0].m, m[1..]);
+        state.resize_front(0, 4);
+        assert_eq!(state.moments[0].m, [0.0, 0.0, m[1], m[2]]);
+        assert_eq!(state.moments[0].v.len(), 4);
+    }
+}
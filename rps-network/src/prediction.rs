@@ -0,0 +1,372 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+#[cfg(any(feature = "serde", feature = "wasm"))]
+use serde::Serialize;
+
+use crate::{check_label, Error, Outcome, Real};
+
+/// Most likely opponent move according to the last `forward()`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Prediction {
+    /// Index of the predicted move in the output distribution.
+    pub index: usize,
+    /// Probability assigned to the predicted move.
+    pub probability: Real,
+}
+
+impl Prediction {
+    /// Picks the most likely entry of `probs`, preferring the lowest index
+    /// on ties.
+    pub(crate) fn argmax(probs: &[Real]) -> Self {
+        let mut best = Prediction {
+            index: 0,
+            probability: probs[0],
+        };
+        for (index, &probability) in probs.iter().enumerate().skip(1) {
+            if probability > best.probability {
+                best = Prediction { index, probability };
+            }
+        }
+        best
+    }
+}
+
+/// Everything a front-end shows about the last `forward()`, so that it
+/// doesn't have to find the predicted move and its counter in `probs()`
+/// itself. JS gets a plain object typed as the `PredictionResult` interface:
+/// `{ probs, predicted, confidence, counterMove }`.
+#[cfg_attr(any(feature = "serde", feature = "wasm"), derive(Serialize))]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+#[cfg_attr(
+    any(feature = "serde", feature = "wasm"),
+    serde(rename_all = "camelCase")
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PredictionResult {
+    /// Probability of each move, as returned by `probs()`.
+    pub probs: Vec<Real>,
+    /// Index of the most likely move.
+    pub predicted: usize,
+    /// Probability of the predicted move.
+    pub confidence: Real,
+    /// Index of the move to play against the predicted one, see
+    /// `RPSNetwork::counter_move()`.
+    pub counter_move: usize,
+}
+
+impl PredictionResult {
+    pub(crate) fn new(probs: Vec<Real>, counter_move: usize) -> Self {
+        let Prediction { index, probability } = Prediction::argmax(&probs);
+        Self {
+            probs,
+            predicted: index,
+            confidence: probability,
+            counter_move,
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(typescript_custom_section)]
+const PREDICTION_RESULT: &str = r#"
+export interface PredictionResult {
+    probs: number[];
+    predicted: number;
+    confidence: number;
+    counterMove: number;
+}
+"#;
+
+// Passed to JS as a plain object rather than a class wrapping Rust memory.
+// Methods returning it declare the TS type with `unchecked_return_type`.
+#[cfg(feature = "wasm")]
+impl wasm_bindgen::describe::WasmDescribe for PredictionResult {
+    fn describe() {
+        JsValue::describe()
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl wasm_bindgen::convert::IntoWasmAbi for PredictionResult {
+    type Abi = <JsValue as wasm_bindgen::convert::IntoWasmAbi>::Abi;
+
+    fn into_abi(self) -> Self::Abi {
+        serde_wasm_bindgen::to_value(&self)
+            .expect_throw("predictions convert to JS")
+            .into_abi()
+    }
+}
+
+/// Rules of a game of `n` moves, indexed like the output distribution: which
+/// moves beat which, and what each player scores in every round.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Beats {
+    /// `beaten_by[m]` lists the moves that beat move `m`.
+    beaten_by: Vec<Vec<usize>>,
+    /// `n` x `n` matrix of what a player of the row move scores against the
+    /// column move, flattened row by row.
+    payoff: Vec<Real>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Beats {
+    /// Creates a table from `table[m]`, the move that beats move `m`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(table: &[usize]) -> Result<Beats, Error> {
+        if let Some(&index) = table.iter().find(|&&m| m >= table.len()) {
+            return Err(Error::InvalidMove {
+                index,
+                moves: table.len(),
+            });
+        }
+        Ok(Self::from_beaten_by(
+            table.iter().map(|&m| vec![m]).collect(),
+        ))
+    }
+
+    /// Creates a table from an `n` x `n` matrix flattened row by row, where
+    /// a non-zero entry at row `a` and column `b` means that move `a` beats
+    /// move `b`. Every move must be beaten by at least one other.
+    pub fn from_matrix(matrix: &[u8]) -> Result<Beats, Error> {
+        let n = square_size(matrix.len())?;
+        let beaten_by: Vec<Vec<usize>> = (0..n)
+            .map(|b| (0..n).filter(|&a| matrix[a * n + b] != 0).collect())
+            .collect();
+        if beaten_by.iter().any(|beaters| beaters.is_empty()) {
+            return Err(Error::Unsupported("every move must be beaten by another"));
+        }
+        Ok(Self::from_beaten_by(beaten_by))
+    }
+
+    /// Creates a table from an `n` x `n` payoff matrix flattened row by row,
+    /// whose entry at row `a` and column `b` is what a player of move `a`
+    /// scores against move `b`. Move `a` beats move `b` when it scores more
+    /// against `b` than `b` does against it.
+    pub fn from_payoff(payoff: &[Real]) -> Result<Beats, Error> {
+        let n = square_size(payoff.len())?;
+        let beaten_by = (0..n)
+            .map(|b| {
+                (0..n)
+                    .filter(|&a| payoff[a * n + b] > payoff[b * n + a])
+                    .collect()
+            })
+            .collect();
+        Ok(Self {
+            beaten_by,
+            payoff: payoff.to_vec(),
+        })
+    }
+
+    /// Rock (0) is beaten by paper (1), paper by scissors (2), and scissors
+    /// by rock.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn rock_paper_scissors() -> Beats {
+        Self::new(&[1, 2, 0]).unwrap()
+    }
+
+    /// Rock (0), paper (1), scissors (2), lizard (3) and Spock (4), where
+    /// every move beats two others and is beaten by the remaining two.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn rock_paper_scissors_lizard_spock() -> Beats {
+        Self::from_beaten_by(vec![
+            vec![1, 4],
+            vec![2, 3],
+            vec![0, 4],
+            vec![0, 2],
+            vec![1, 3],
+        ])
+    }
+
+    /// Number of moves in the game.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn moves(&self) -> usize {
+        self.beaten_by.len()
+    }
+
+    /// Outcome of a round for the player of `a`, against `b`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn judge(&self, a: usize, b: usize) -> Result<Outcome, Error> {
+        for index in [a, b] {
+            check_label(index, self.moves())?;
+        }
+        let (score, other) = (self.payoff(a, b), self.payoff(b, a));
+        Ok(if a == b || score == other {
+            Outcome::Draw
+        } else if score > other {
+            Outcome::Win
+        } else {
+            Outcome::Lose
+        })
+    }
+}
+
+impl Beats {
+    /// Table where each move gets 1 against the moves it beats and -1
+    /// against the moves beating it.
+    fn from_beaten_by(beaten_by: Vec<Vec<usize>>) -> Self {
+        let n = beaten_by.len();
+        let mut payoff = vec![0.0; n * n];
+        for (b, beaters) in beaten_by.iter().enumerate() {
+            for &a in beaters.iter().filter(|&&a| a != b) {
+                payoff[a * n + b] += 1.0;
+                payoff[b * n + a] -= 1.0;
+            }
+        }
+        Self { beaten_by, payoff }
+    }
+
+    /// What a player of move `a` scores against move `b`.
+    pub(crate) fn payoff(&self, a: usize, b: usize) -> Real {
+        self.payoff[a * self.moves() + b]
+    }
+
+    /// Move that beats the most likely move of `probs`, checking that the
+    /// table covers the `moves` outputs of the network. When several moves
+    /// beat it, or none does, picks the one scoring best on average against
+    /// the whole distribution.
+    pub(crate) fn counter(&self, probs: &[Real], moves: usize) -> Result<usize, Error> {
+        if self.moves() != moves {
+            return Err(Error::ShapeMismatch {
+                name: "beats",
+                expected: vec![moves],
+                found: vec![self.moves()],
+            });
+        }
+        let score = |a: usize| -> Real { (0..moves).map(|b| probs[b] * self.payoff(a, b)).sum() };
+        let candidates = match &self.beaten_by[Prediction::argmax(probs).index][..] {
+            [] => (0..moves).collect(),
+            beaters => beaters.to_vec(),
+        };
+        let mut best = candidates[0];
+        for &a in &candidates[1..] {
+            if score(a) > score(best) {
+                best = a;
+            }
+        }
+        Ok(best)
+    }
+}
+
+/// Side of a square matrix of `len` entries.
+fn square_size(len: usize) -> Result<usize, Error> {
+    let n = (0..=len).find(|n| n * n >= len).unwrap();
+    if n * n == len {
+        Ok(n)
+    } else {
+        Err(Error::ShapeMismatch {
+            name: "beats",
+            expected: vec![n, n],
+            found: vec![len],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn prediction_result_json() {
+        let result = PredictionResult::new(vec![0.25, 0.5, 0.25], 2);
+        assert_eq!((result.predicted, result.confidence), (1, 0.5));
+        assert_eq!(
+            serde_json::to_string(&result).unwrap(),
+            r#"{"probs":[0.25,0.5,0.25],"predicted":1,"confidence":0.5,"counterMove":2}"#
+        );
+    }
+
+    #[test]
+    fn argmax_prefers_first_tie() {
+        let prediction = Prediction::argmax(&[0.2, 0.4, 0.4]);
+        assert_eq!(prediction.index, 1);
+        assert_eq!(prediction.probability, 0.4);
+    }
+
+    #[test]
+    fn beats_table() {
+        let rps = Beats::rock_paper_scissors();
+        let probs = [0.1, 0.2, 0.7];
+        assert_eq!(rps.counter(&probs, 3), Ok(0));
+        assert!(rps.counter(&probs, 4).is_err());
+
+        assert_eq!(Beats::new(&[1, 2, 0]), Ok(rps));
+        assert_eq!(
+            Beats::new(&[1, 3, 0]),
+            Err(Error::InvalidMove { index: 3, moves: 3 })
+        );
+    }
+
+    #[test]
+    fn beats_matrix() {
+        let rps = Beats::from_matrix(&[0, 0, 1, 1, 0, 0, 0, 1, 0]).unwrap();
+        assert_eq!(rps, Beats::rock_paper_scissors());
+        assert_eq!(rps.judge(0, 2), Ok(Outcome::Win));
+        assert_eq!(rps.judge(0, 1), Ok(Outcome::Lose));
+        assert_eq!(rps.judge(1, 1), Ok(Outcome::Draw));
+        assert!(rps.judge(0, 3).is_err());
+
+        assert!(Beats::from_matrix(&[0, 1, 1, 0, 0]).is_err());
+        assert!(Beats::from_matrix(&[0, 1, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn rock_paper_scissors_lizard_spock() {
+        let rpsls = Beats::rock_paper_scissors_lizard_spock();
+        for a in 0..5 {
+            let wins = (0..5)
+                .filter(|&b| rpsls.judge(a, b) == Ok(Outcome::Win))
+                .count();
+            assert_eq!(wins, 2);
+            for b in 0..5 {
+                let flipped = match rpsls.judge(b, a).unwrap() {
+                    Outcome::Win => Outcome::Lose,
+                    Outcome::Lose => Outcome::Win,
+                    Outcome::Draw => Outcome::Draw,
+                };
+                assert_eq!(rpsls.judge(a, b), Ok(flipped));
+            }
+        }
+        // Spock vaporizes rock, lizard poisons Spock
+        assert_eq!(rpsls.judge(4, 0), Ok(Outcome::Win));
+        assert_eq!(rpsls.judge(3, 4), Ok(Outcome::Win));
+
+        // Rock is beaten by paper and Spock, and Spock also beats scissors
+        let probs = [0.5, 0.1, 0.3, 0.05, 0.05];
+        assert_eq!(rpsls.counter(&probs, 5), Ok(4));
+    }
+
+    #[test]
+    fn payoff_matrix() {
+        // A cycle where 0 beats 1, 1 beats 2 and 2 beats 0, and wins against
+        // 0 score double
+        let rules = Beats::from_payoff(&[
+            0.0, 1.0, -2.0, //
+            -1.0, 0.0, 1.0, //
+            2.0, -1.0, 0.0,
+        ])
+        .unwrap();
+        assert_eq!(rules.moves(), 3);
+        assert_eq!(rules.judge(0, 1), Ok(Outcome::Win));
+        assert_eq!(rules.judge(2, 0), Ok(Outcome::Win));
+        assert_eq!(rules.judge(2, 1), Ok(Outcome::Lose));
+        assert_eq!(rules.payoff(2, 0), 2.0);
+
+        assert_eq!(rules.counter(&[0.6, 0.3, 0.1], 3), Ok(2));
+        assert!(Beats::from_payoff(&[0.0; 5]).is_err());
+
+        // Nothing beats anything: counter the distribution as a whole
+        let rules = Beats::from_payoff(&[0.0; 4]).unwrap();
+        assert_eq!(rules.judge(0, 1), Ok(Outcome::Draw));
+        assert_eq!(rules.counter(&[0.9, 0.1], 2), Ok(0));
+    }
+}
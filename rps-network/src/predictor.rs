@@ -0,0 +1,124 @@
+use alloc::vec::Vec;
+
+use crate::{Error, LegacyRPSNetwork, Prediction, RPSNetwork, Real};
+
+/// Operations shared by both network implementations, so that Rust code such
+/// as tournament runners or ensembles can be generic over them.
+pub trait RpsPredictor {
+    /// Adds the moves of a new round to the history and predicts the next
+    /// one.
+    fn forward(&mut self, input: &[Real]) -> Result<(), Error>;
+
+    /// Trains the network towards `label` for the last input.
+    fn backward(&mut self, label: usize, learning_rate: Real) -> Result<(), Error>;
+
+    /// Output distribution of the last `forward()`.
+    fn probs(&self) -> Vec<Real>;
+
+    /// Most likely move according to `probs()`.
+    fn predict(&self) -> Prediction;
+
+    /// Length of the inputs `forward()` takes, or `None` if it reads the
+    /// moves at the start of inputs of any length.
+    fn input_size(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl RpsPredictor for RPSNetwork {
+    fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        RPSNetwork::forward(self, input)
+    }
+
+    fn backward(&mut self, label: usize, learning_rate: Real) -> Result<(), Error> {
+        RPSNetwork::backward(self, label, Some(learning_rate))
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        RPSNetwork::probs(self)
+    }
+
+    fn predict(&self) -> Prediction {
+        RPSNetwork::predict(self)
+    }
+
+    fn input_size(&self) -> Option<usize> {
+        Some(self.input_size)
+    }
+}
+
+/// Lets a caller lend a predictor, e.g. to a `PredictorPlayer`, and keep it.
+impl<P: RpsPredictor + ?Sized> RpsPredictor for &mut P {
+    fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        (**self).forward(input)
+    }
+
+    fn backward(&mut self, label: usize, learning_rate: Real) -> Result<(), Error> {
+        (**self).backward(label, learning_rate)
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        (**self).probs()
+    }
+
+    fn predict(&self) -> Prediction {
+        (**self).predict()
+    }
+
+    fn input_size(&self) -> Option<usize> {
+        (**self).input_size()
+    }
+}
+
+impl RpsPredictor for LegacyRPSNetwork {
+    fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        LegacyRPSNetwork::forward(self, input)
+    }
+
+    fn backward(&mut self, label: usize, learning_rate: Real) -> Result<(), Error> {
+        LegacyRPSNetwork::backward(self, label, learning_rate)
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        LegacyRPSNetwork::probs(self)
+    }
+
+    fn predict(&self) -> Prediction {
+        LegacyRPSNetwork::predict(self)
+    }
+
+    fn input_size(&self) -> Option<usize> {
+        Some(self.input_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkConfig;
+
+    /// Trains `predictor` to answer rock with paper.
+    fn train<P: RpsPredictor + ?Sized>(predictor: &mut P) {
+        let input = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        for _ in 0..50 {
+            predictor.forward(&input).unwrap();
+            predictor.backward(1, 0.1).unwrap();
+        }
+        predictor.forward(&input).unwrap();
+    }
+
+    #[test]
+    fn generic_over_implementations() {
+        let config = NetworkConfig::new().seed(0);
+        let mut predictors: Vec<Box<dyn RpsPredictor>> = vec![
+            Box::new(config.build().unwrap()),
+            Box::new(config.build_legacy().unwrap()),
+        ];
+
+        for predictor in predictors.iter_mut() {
+            train(predictor.as_mut());
+            assert_eq!(predictor.predict().index, 1);
+            assert_eq!(predictor.predict().probability, predictor.probs()[1]);
+        }
+    }
+}
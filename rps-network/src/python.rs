@@ -0,0 +1,335 @@
+//! Python bindings, so that strategy experiments in notebooks run the same
+//! code as the browser. The classes wrap the Rust types of the same name,
+//! and errors are raised as `ValueError`.
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::{Error, Real};
+
+impl From<Error> for PyErr {
+    fn from(error: Error) -> Self {
+        PyValueError::new_err(error.to_string())
+    }
+}
+
+/// See `crate::RPSNetwork`.
+#[pyclass(name = "RPSNetwork")]
+pub struct RPSNetwork(crate::RPSNetwork);
+
+#[pymethods]
+impl RPSNetwork {
+    #[new]
+    #[pyo3(signature = (input_size, history_size, hidden_size, output_size, seed = None))]
+    fn new(
+        input_size: usize,
+        history_size: usize,
+        hidden_size: usize,
+        output_size: usize,
+        seed: Option<u64>,
+    ) -> PyResult<Self> {
+        let mut config =
+            crate::NetworkConfig::with_sizes(input_size, history_size, hidden_size, output_size);
+        if let Some(seed) = seed {
+            config = config.seed(seed);
+        }
+        Ok(Self(config.build()?))
+    }
+
+    /// Builds a network from a `NetworkConfig` serialized as JSON.
+    #[staticmethod]
+    fn from_config(json: &str) -> PyResult<Self> {
+        let config: crate::NetworkConfig = serde_json::from_str(json).map_err(Error::from)?;
+        Ok(Self(config.build()?))
+    }
+
+    #[getter]
+    fn input_size(&self) -> usize {
+        self.0.input_size
+    }
+
+    #[getter]
+    fn output_size(&self) -> usize {
+        self.0.output_size
+    }
+
+    fn forward(&mut self, input: Vec<Real>) -> PyResult<()> {
+        Ok(self.0.forward(&input)?)
+    }
+
+    #[pyo3(signature = (label, learning_rate = None))]
+    fn backward(&mut self, label: usize, learning_rate: Option<Real>) -> PyResult<()> {
+        Ok(self.0.backward(label, learning_rate)?)
+    }
+
+    #[pyo3(signature = (input, label, learning_rate = None))]
+    fn train_step(
+        &mut self,
+        input: Vec<Real>,
+        label: usize,
+        learning_rate: Option<Real>,
+    ) -> PyResult<Real> {
+        Ok(self.0.train_step(&input, label, learning_rate)?)
+    }
+
+    /// Trains on a recorded game, e.g. from a published dataset, and
+    /// returns the mean loss of the last epoch.
+    #[pyo3(signature = (moves, epochs, learning_rate = None))]
+    fn train_on_sequence(
+        &mut self,
+        moves: Vec<u8>,
+        epochs: usize,
+        learning_rate: Option<Real>,
+    ) -> PyResult<Real> {
+        Ok(self.0.train_on_sequence(&moves, epochs, learning_rate)?)
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        self.0.probs()
+    }
+
+    /// Moves the weights a share `alpha` of the way to those of `other`.
+    fn merge(&mut self, other: &RPSNetwork, alpha: Real) -> PyResult<()> {
+        Ok(self.0.merge(&other.0, alpha)?)
+    }
+
+    /// Output logits before the softmax.
+    fn logits(&self) -> Vec<Real> {
+        self.0.logits()
+    }
+
+    /// Index of the most likely move.
+    fn predict(&self) -> usize {
+        self.0.predict().index
+    }
+
+    fn entropy(&self) -> Real {
+        self.0.entropy()
+    }
+
+    fn history(&self) -> Vec<Real> {
+        self.0.history()
+    }
+
+    fn reset_history(&mut self) {
+        self.0.reset_history()
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        Ok(self.0.to_json()?)
+    }
+
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        Ok(Self(crate::RPSNetwork::from_json(json)?))
+    }
+
+    fn to_bytes(&self) -> PyResult<Cow<'static, [u8]>> {
+        Ok(Cow::Owned(self.0.to_bytes()?))
+    }
+
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        Ok(Self(crate::RPSNetwork::from_bytes(bytes)?))
+    }
+}
+
+/// See `crate::FrequencyPredictor`.
+#[pyclass(name = "FrequencyPredictor")]
+pub struct FrequencyPredictor(crate::FrequencyPredictor);
+
+#[pymethods]
+impl FrequencyPredictor {
+    #[new]
+    #[pyo3(signature = (moves = 3, prior = 1.0))]
+    fn new(moves: usize, prior: Real) -> PyResult<Self> {
+        Ok(Self(crate::FrequencyPredictor::new(moves, prior)?))
+    }
+
+    fn forward(&mut self, input: Vec<Real>) -> PyResult<()> {
+        Ok(self.0.forward(&input)?)
+    }
+
+    fn backward(&mut self, label: usize) -> PyResult<()> {
+        Ok(self.0.backward(label)?)
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        self.0.probs()
+    }
+
+    fn predict(&self) -> usize {
+        self.0.predict().index
+    }
+}
+
+/// See `crate::MarkovPredictor`.
+#[pyclass(name = "MarkovPredictor")]
+pub struct MarkovPredictor(crate::MarkovPredictor);
+
+#[pymethods]
+impl MarkovPredictor {
+    #[new]
+    #[pyo3(signature = (order, moves = 3))]
+    fn new(order: usize, moves: usize) -> PyResult<Self> {
+        Ok(Self(crate::MarkovPredictor::new(order, moves)?))
+    }
+
+    fn forward(&mut self, input: Vec<Real>) -> PyResult<()> {
+        Ok(self.0.forward(&input)?)
+    }
+
+    fn backward(&mut self, label: usize) -> PyResult<()> {
+        Ok(self.0.backward(label)?)
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        self.0.probs()
+    }
+
+    fn predict(&self) -> usize {
+        self.0.predict().index
+    }
+}
+
+/// See `crate::PatternDetector`.
+#[pyclass(name = "PatternDetector")]
+pub struct PatternDetector(crate::PatternDetector);
+
+#[pymethods]
+impl PatternDetector {
+    #[new]
+    fn new(window: usize) -> PyResult<Self> {
+        Ok(Self(crate::PatternDetector::new(window)?))
+    }
+
+    fn forward(&mut self, input: Vec<Real>) -> PyResult<()> {
+        Ok(self.0.forward(&input)?)
+    }
+
+    fn backward(&mut self, label: usize) -> PyResult<()> {
+        Ok(self.0.backward(label)?)
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        self.0.probs()
+    }
+
+    fn predict(&self) -> usize {
+        self.0.predict().index
+    }
+}
+
+/// See `crate::CycleDetector`.
+#[pyclass(name = "CycleDetector")]
+pub struct CycleDetector(crate::CycleDetector);
+
+#[pymethods]
+impl CycleDetector {
+    #[new]
+    #[pyo3(signature = (window, moves = 3))]
+    fn new(window: usize, moves: usize) -> PyResult<Self> {
+        Ok(Self(crate::CycleDetector::new(window, moves)?))
+    }
+
+    fn forward(&mut self, input: Vec<Real>) -> PyResult<()> {
+        Ok(self.0.forward(&input)?)
+    }
+
+    fn backward(&mut self, label: usize) -> PyResult<()> {
+        Ok(self.0.backward(label)?)
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        self.0.probs()
+    }
+
+    fn predict(&self) -> usize {
+        self.0.predict().index
+    }
+
+    /// Length of the detected cycle, if any.
+    fn period(&self) -> Option<usize> {
+        self.0.period()
+    }
+
+    /// Moves of the detected cycle, from the next one.
+    fn cycle(&self) -> Vec<usize> {
+        self.0.cycle()
+    }
+}
+
+/// See `crate::Ensemble`. Predictors are copied into the ensemble, so the
+/// ones passed to the `add_*()` methods are left as they were.
+#[pyclass(name = "Ensemble", unsendable)]
+pub struct Ensemble(crate::Ensemble);
+
+#[pymethods]
+impl Ensemble {
+    #[new]
+    #[pyo3(signature = (eta = 0.5))]
+    fn new(eta: Real) -> PyResult<Self> {
+        Ok(Self(crate::Ensemble::new(eta)?))
+    }
+
+    fn add_network(&mut self, network: &RPSNetwork) -> PyResult<()> {
+        Ok(self.0.add_network(network.0.clone())?)
+    }
+
+    fn add_markov(&mut self, predictor: &MarkovPredictor) -> PyResult<()> {
+        Ok(self.0.add_markov(predictor.0.clone())?)
+    }
+
+    fn add_frequency(&mut self, predictor: &FrequencyPredictor) -> PyResult<()> {
+        Ok(self.0.add_frequency(predictor.0.clone())?)
+    }
+
+    fn add_pattern_detector(&mut self, detector: &PatternDetector) -> PyResult<()> {
+        Ok(self.0.add_pattern_detector(detector.0.clone())?)
+    }
+
+    fn add_cycle_detector(&mut self, detector: &CycleDetector) -> PyResult<()> {
+        Ok(self.0.add_cycle_detector(detector.0.clone())?)
+    }
+
+    fn forward(&mut self, input: Vec<Real>) -> PyResult<()> {
+        Ok(self.0.forward(&input)?)
+    }
+
+    fn backward(&mut self, label: usize, learning_rate: Real) -> PyResult<()> {
+        Ok(self.0.backward(label, learning_rate)?)
+    }
+
+    fn probs(&self) -> Vec<Real> {
+        self.0.probs()
+    }
+
+    fn predict(&self) -> usize {
+        self.0.predict().index
+    }
+
+    /// Weight of each predictor, in the order they were added.
+    fn weights(&self) -> Vec<Real> {
+        self.0.weights()
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// The `rps_network` Python module.
+#[pymodule]
+fn rps_network(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<RPSNetwork>()?;
+    module.add_class::<FrequencyPredictor>()?;
+    module.add_class::<MarkovPredictor>()?;
+    module.add_class::<PatternDetector>()?;
+    module.add_class::<CycleDetector>()?;
+    module.add_class::<Ensemble>()?;
+    Ok(())
+}
@@ -0,0 +1,277 @@
+use alloc::vec::Vec;
+use ndarray::{aview1, s, Array1, Array2, ArrayView1};
+
+#[cfg(feature = "serde")]
+use alloc::vec;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::layer::Layer;
+#[cfg(feature = "serde")]
+use crate::migration::{self, activation_value, Saved, Saving};
+use crate::{check_input, entropy, Activation, Beats, Error, Prediction, Real};
+
+/// Largest magnitude of a quantized value, so that the int8 range is
+/// symmetric around zero.
+const QUANT_MAX: Real = 127.0;
+
+/// A dense layer with int8 weights. Each weight stands for `scale` times its
+/// quantized value; the bias stays in floating point.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+struct QuantizedLayer {
+    /// `(inputs, outputs)` quantized weight matrix.
+    weights: Array2<i8>,
+    scale: Real,
+    bias: Array1<Real>,
+    activation: Option<Activation>,
+}
+
+impl QuantizedLayer {
+    fn new(layer: &Layer) -> Self {
+        let (weights, scale) = quantize(layer.weights.iter().cloned());
+        Self {
+            weights: Array2::from_shape_vec(layer.weights.raw_dim(), weights).unwrap(),
+            scale,
+            bias: layer.bias.clone(),
+            activation: layer.activation,
+        }
+    }
+
+    /// Computes the output of the layer for `input`, which is itself
+    /// quantized so that the products accumulate in integers.
+    fn forward(&self, input: ArrayView1<Real>) -> Array1<Real> {
+        let (input, input_scale) = quantize(input.iter().cloned());
+        let scale = input_scale * self.scale;
+        let mut output = self.bias.clone();
+        for (o, column) in output.iter_mut().zip(self.weights.columns()) {
+            let acc: i32 = input
+                .iter()
+                .zip(column)
+                .map(|(&x, &w)| x as i32 * w as i32)
+                .sum();
+            *o += acc as Real * scale;
+            if let Some(activation) = self.activation {
+                *o = activation.apply(*o);
+            }
+        }
+        output
+    }
+}
+
+/// Inference-only copy of a feedforward network, created by
+/// `RPSNetwork::quantize()`. Weights are stored as int8 with one scale per
+/// layer, which makes the model about four times smaller to serialize, and
+/// `forward()` accumulates the products in integers.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizedNetwork {
+    pub input_size: usize,
+    pub history_size: usize,
+    pub output_size: usize,
+    history: Array2<Real>,
+    layers: Vec<QuantizedLayer>,
+    probs: Array1<Real>,
+    temperature: Real,
+    exploration: Real,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl QuantizedNetwork {
+    /// Adds the move of the last round, one-hot encoded, to the end of the
+    /// history and predicts the next one.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn forward(&mut self, input: &[Real]) -> Result<(), Error> {
+        check_input(input, self.input_size)?;
+
+        let past = self.history.slice(s![1.., ..]).to_owned();
+        self.history.slice_mut(s![..-1, ..]).assign(&past);
+        self.history.slice_mut(s![-1, ..]).assign(&aview1(input));
+
+        let mut x: Array1<Real> = self.history.iter().cloned().collect();
+        for layer in &self.layers {
+            x = layer.forward(x.view());
+        }
+
+        let max = x.fold(Real::NEG_INFINITY, |a, &b| a.max(b));
+        x.mapv_inplace(|v| ((v - max) / self.temperature).exp());
+        self.probs = &x / x.sum();
+        Ok(())
+    }
+
+    /// Output distribution of the last `forward()`, mixed with the uniform
+    /// distribution according to the exploration setting of the network it
+    /// was quantized from.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn probs(&self) -> Vec<Real> {
+        let floor = self.exploration / self.output_size as Real;
+        self.probs
+            .iter()
+            .map(|p| (1.0 - self.exploration) * p + floor)
+            .collect()
+    }
+
+    /// Shannon entropy of `probs()` in nats.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn entropy(&self) -> Real {
+        entropy(&self.probs())
+    }
+
+    /// Most likely opponent move according to `probs()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn predict(&self) -> Prediction {
+        Prediction::argmax(&self.probs())
+    }
+
+    /// Move that beats the predicted one according to `beats`, which must
+    /// cover every output. When several moves beat it, picks the one doing
+    /// best against the whole of `probs()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn counter_move(&self, beats: &Beats) -> Result<usize, Error> {
+        beats.counter(&self.probs(), self.output_size)
+    }
+
+    /// Inputs of the last `history_size` rounds, from the oldest to the
+    /// newest, flattened round by round.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn history(&self) -> Vec<Real> {
+        self.history.iter().cloned().collect()
+    }
+
+    /// Clears the history, keeping the weights.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn reset_history(&mut self) {
+        self.history.fill(0.0);
+    }
+
+    /// Serializes the network to a compact binary encoding, with the
+    /// header of `RPSNetwork::to_bytes()`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        migration::to_bytes(Saving::Quantized(self))
+    }
+
+    /// Restores a network previously serialized with `to_bytes()`,
+    /// checking its header like `RPSNetwork::from_bytes()`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<QuantizedNetwork, Error> {
+        let network = migration::from_bytes(bytes, |bytes| {
+            Ok(Saved::Quantized(bincode::deserialize(bytes)?))
+        })?
+        .into_quantized()?;
+        network.validate()?;
+        Ok(network)
+    }
+}
+
+impl QuantizedNetwork {
+    /// Quantizes the dense `layers` of a feedforward network, starting from
+    /// its current history and output distribution.
+    pub(crate) fn new(
+        layers: &[Layer],
+        history: &Array2<Real>,
+        probs: &Array1<Real>,
+        temperature: Real,
+        exploration: Real,
+    ) -> Self {
+        Self {
+            input_size: history.ncols(),
+            history_size: history.nrows(),
+            output_size: probs.len(),
+            history: history.clone(),
+            layers: layers.iter().map(QuantizedLayer::new).collect(),
+            probs: probs.clone(),
+            temperature,
+            exploration,
+        }
+    }
+
+    /// Values hashed in the header, see `RPSNetwork::hash_values()`.
+    #[cfg(feature = "serde")]
+    pub(crate) fn hash_values(&self, _version: u32) -> Vec<usize> {
+        let mut values = vec![3, self.input_size, self.history_size, self.output_size];
+        for layer in &self.layers {
+            values.extend_from_slice(layer.weights.shape());
+            values.push(activation_value(layer.activation));
+        }
+        values
+    }
+
+    /// Checks that every tensor has the shape implied by the network sizes.
+    #[cfg(feature = "serde")]
+    fn validate(&self) -> Result<(), Error> {
+        let check = |name, expected: &[usize], found: &[usize]| {
+            if expected == found {
+                Ok(())
+            } else {
+                Err(Error::ShapeMismatch {
+                    name,
+                    expected: expected.to_vec(),
+                    found: found.to_vec(),
+                })
+            }
+        };
+
+        check(
+            "history",
+            &[self.history_size, self.input_size],
+            self.history.shape(),
+        )?;
+        if self.layers.is_empty() {
+            return Err(Error::ShapeMismatch {
+                name: "layers",
+                expected: vec![1],
+                found: vec![0],
+            });
+        }
+        let mut inputs = self.history_size * self.input_size;
+        for (i, layer) in self.layers.iter().enumerate() {
+            let outputs = if i + 1 == self.layers.len() {
+                self.output_size
+            } else {
+                layer.weights.ncols()
+            };
+            check("weights", &[inputs, outputs], layer.weights.shape())?;
+            check("bias", &[outputs], layer.bias.shape())?;
+            inputs = outputs;
+        }
+        check("probs", &[self.output_size], self.probs.shape())?;
+
+        Ok(())
+    }
+}
+
+/// Maps `values` linearly to `[-127, 127]`, returning the quantized values
+/// and the scale to multiply them by to recover the originals.
+fn quantize(values: impl Iterator<Item = Real> + Clone) -> (Vec<i8>, Real) {
+    let max = values.clone().fold(0.0, |a: Real, b| a.max(b.abs()));
+    let scale = if max > 0.0 { max / QUANT_MAX } else { 1.0 };
+    let quantized = values.map(|v| (v / scale).round() as i8).collect();
+    (quantized, scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantize_round_trip() {
+        let (values, scale) = quantize([0.5, -1.27, 0.0, 1.0].iter().cloned());
+        assert_eq!(values, vec![50, -127, 0, 100]);
+        assert!((scale - 0.01).abs() < 1e-6);
+
+        let (values, scale) = quantize([0.0, 0.0].iter().cloned());
+        assert_eq!(values, vec![0, 0]);
+        assert_eq!(scale, 1.0);
+    }
+}
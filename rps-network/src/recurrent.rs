@@ -0,0 +1,627 @@
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use ndarray::{
+    concatenate, s, Array1, Array2, ArrayD, ArrayView1, ArrayViewD, ArrayViewMutD, Axis,
+};
+use rand::Rng;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::layer::outer;
+use crate::{Activation, Init, Real};
+
+#[cfg(feature = "serde")]
+use crate::Error;
+
+/// How `RPSNetwork` turns the opponent's past moves into its first hidden
+/// layer.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Architecture {
+    /// A dense layer over the sliding window of the last `history_size`
+    /// inputs.
+    Feedforward,
+    /// An Elman recurrent layer, whose state is carried from round to round
+    /// and trained with truncated backpropagation through time.
+    Elman,
+    /// A long short-term memory layer, whose input, forget and output gates
+    /// let it keep track of patterns much longer than the history window. Its
+    /// non-linearities are fixed, so the first layer's activation is ignored.
+    Lstm,
+    /// A gated recurrent unit layer, with update and reset gates. It learns
+    /// long patterns like `Lstm` with three quarters of the parameters, and
+    /// also ignores the first layer's activation.
+    Gru,
+}
+
+/// Recurrent first hidden layer, stepped once per `forward()`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Recurrent {
+    cell: Cell,
+    /// Number of past steps that gradients are propagated through.
+    window: usize,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+enum Cell {
+    Elman(Elman),
+    Lstm(Lstm),
+    Gru(Gru),
+}
+
+/// `h_t = f(x_t · wx + h_{t-1} · wh + b)`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+struct Elman {
+    wx: Array2<Real>,
+    wh: Array2<Real>,
+    b: Array1<Real>,
+    activation: Activation,
+    /// State before the oldest cached step.
+    initial: Array1<Real>,
+    /// Input and resulting state of the last steps, oldest first.
+    steps: VecDeque<(Array1<Real>, Array1<Real>)>,
+}
+
+/// ```text
+/// [i, f, o, g] = [σ, σ, σ, tanh](x_t · wx + h_{t-1} · wh + b)
+/// c_t = f * c_{t-1} + i * g
+/// h_t = o * tanh(c_t)
+/// ```
+///
+/// The gates are packed along the columns of `wx`, `wh` and `b`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+struct Lstm {
+    wx: Array2<Real>,
+    wh: Array2<Real>,
+    b: Array1<Real>,
+    /// Output and cell state before the oldest cached step.
+    initial: (Array1<Real>, Array1<Real>),
+    steps: VecDeque<LstmStep>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+struct LstmStep {
+    x: Array1<Real>,
+    /// Gate activations `[i, f, o, g]`.
+    gates: Array1<Real>,
+    c: Array1<Real>,
+    h: Array1<Real>,
+}
+
+/// ```text
+/// [z, r] = σ(x_t · wx_zr + h_{t-1} · wh_zr + b_zr)
+/// n = tanh(x_t · wx_n + (r * h_{t-1}) · wh_n + b_n)
+/// h_t = (1 - z) * n + z * h_{t-1}
+/// ```
+///
+/// The gates are packed along the columns of `wx`, `wh` and `b`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+struct Gru {
+    wx: Array2<Real>,
+    wh: Array2<Real>,
+    b: Array1<Real>,
+    /// State before the oldest cached step.
+    initial: Array1<Real>,
+    steps: VecDeque<GruStep>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+struct GruStep {
+    x: Array1<Real>,
+    /// Gate activations `[z, r, n]`.
+    gates: Array1<Real>,
+    h: Array1<Real>,
+}
+
+fn sigmoid(x: Real) -> Real {
+    1.0 / (1.0 + (-x).exp())
+}
+
+impl Lstm {
+    fn hidden_size(&self) -> usize {
+        self.b.len() / 4
+    }
+
+    /// Output and cell state after the step `t`, or before the oldest step
+    /// for `None`.
+    fn state(&self, t: Option<usize>) -> (&Array1<Real>, &Array1<Real>) {
+        match t {
+            Some(t) => (&self.steps[t].h, &self.steps[t].c),
+            None => (&self.initial.0, &self.initial.1),
+        }
+    }
+}
+
+impl Gru {
+    fn hidden_size(&self) -> usize {
+        self.b.len() / 3
+    }
+
+    /// State after the step `t`, or before the oldest step for `None`.
+    fn state(&self, t: Option<usize>) -> &Array1<Real> {
+        t.map_or(&self.initial, |t| &self.steps[t].h)
+    }
+}
+
+impl Recurrent {
+    /// Returns `None` for `Architecture::Feedforward`.
+    pub(crate) fn new<R: Rng>(
+        architecture: Architecture,
+        inputs: usize,
+        hidden: usize,
+        activation: Activation,
+        window: usize,
+        init: Init,
+        rng: &mut R,
+    ) -> Option<Self> {
+        let fan_in = inputs + hidden;
+        let mut matrix = |rows, cols| {
+            Array2::from_shape_simple_fn((rows, cols), || init.sample(fan_in, hidden, rng))
+        };
+        let cell = match architecture {
+            Architecture::Feedforward => return None,
+            Architecture::Elman => Cell::Elman(Elman {
+                wx: matrix(inputs, hidden),
+                wh: matrix(hidden, hidden),
+                b: Array1::zeros(hidden),
+                activation,
+                initial: Array1::zeros(hidden),
+                steps: VecDeque::with_capacity(window + 1),
+            }),
+            Architecture::Lstm => {
+                // A forget bias of 1 keeps the cell state by default, so that
+                // gradients flow back through time from the first rounds
+                let mut b = Array1::zeros(4 * hidden);
+                b.slice_mut(s![hidden..2 * hidden]).fill(1.0);
+                Cell::Lstm(Lstm {
+                    wx: matrix(inputs, 4 * hidden),
+                    wh: matrix(hidden, 4 * hidden),
+                    b,
+                    initial: (Array1::zeros(hidden), Array1::zeros(hidden)),
+                    steps: VecDeque::with_capacity(window + 1),
+                })
+            }
+            Architecture::Gru => Cell::Gru(Gru {
+                wx: matrix(inputs, 3 * hidden),
+                wh: matrix(hidden, 3 * hidden),
+                b: Array1::zeros(3 * hidden),
+                initial: Array1::zeros(hidden),
+                steps: VecDeque::with_capacity(window + 1),
+            }),
+        };
+        Some(Self { cell, window })
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn architecture(&self) -> Architecture {
+        match &self.cell {
+            Cell::Elman(_) => Architecture::Elman,
+            Cell::Lstm(_) => Architecture::Lstm,
+            Cell::Gru(_) => Architecture::Gru,
+        }
+    }
+
+    pub(crate) fn hidden_size(&self) -> usize {
+        match &self.cell {
+            Cell::Elman(elman) => elman.b.len(),
+            Cell::Lstm(lstm) => lstm.hidden_size(),
+            Cell::Gru(gru) => gru.hidden_size(),
+        }
+    }
+
+    /// State after the last step.
+    pub(crate) fn state(&self) -> &Array1<Real> {
+        match &self.cell {
+            Cell::Elman(elman) => elman.steps.back().map_or(&elman.initial, |(_, h)| h),
+            Cell::Lstm(lstm) => lstm.state(lstm.steps.len().checked_sub(1)).0,
+            Cell::Gru(gru) => gru.state(gru.steps.len().checked_sub(1)),
+        }
+    }
+
+    /// Number of values cached for backpropagation through time, the
+    /// initial state included.
+    pub(crate) fn cache_len(&self) -> usize {
+        match &self.cell {
+            Cell::Elman(elman) => {
+                elman.initial.len()
+                    + elman
+                        .steps
+                        .iter()
+                        .map(|(x, h)| x.len() + h.len())
+                        .sum::<usize>()
+            }
+            Cell::Lstm(lstm) => {
+                lstm.initial.0.len()
+                    + lstm.initial.1.len()
+                    + lstm
+                        .steps
+                        .iter()
+                        .map(|step| step.x.len() + step.gates.len() + step.c.len() + step.h.len())
+                        .sum::<usize>()
+            }
+            Cell::Gru(gru) => {
+                gru.initial.len()
+                    + gru
+                        .steps
+                        .iter()
+                        .map(|step| step.x.len() + step.gates.len() + step.h.len())
+                        .sum::<usize>()
+            }
+        }
+    }
+
+    /// Forgets the past rounds, starting again from a zero state.
+    pub(crate) fn reset_state(&mut self) {
+        match &mut self.cell {
+            Cell::Elman(elman) => {
+                elman.initial.fill(0.0);
+                elman.steps.clear();
+            }
+            Cell::Lstm(lstm) => {
+                lstm.initial.0.fill(0.0);
+                lstm.initial.1.fill(0.0);
+                lstm.steps.clear();
+            }
+            Cell::Gru(gru) => {
+                gru.initial.fill(0.0);
+                gru.steps.clear();
+            }
+        }
+    }
+
+    /// Advances the state with the input of a new round.
+    pub(crate) fn step(&mut self, x: ArrayView1<Real>) {
+        match &mut self.cell {
+            Cell::Elman(elman) => {
+                let h_prev = elman.steps.back().map_or(&elman.initial, |(_, h)| h);
+                let a = x.dot(&elman.wx) + h_prev.dot(&elman.wh) + &elman.b;
+                let h = a.mapv(|v| elman.activation.apply(v));
+                elman.steps.push_back((x.to_owned(), h));
+                if elman.steps.len() > self.window {
+                    let (_, h) = elman.steps.pop_front().unwrap();
+                    elman.initial = h;
+                }
+            }
+            Cell::Lstm(lstm) => {
+                let n = lstm.hidden_size();
+                let (h_prev, c_prev) = lstm.state(lstm.steps.len().checked_sub(1));
+                let mut gates = x.dot(&lstm.wx) + h_prev.dot(&lstm.wh) + &lstm.b;
+                gates.slice_mut(s![..3 * n]).mapv_inplace(sigmoid);
+                gates.slice_mut(s![3 * n..]).mapv_inplace(Real::tanh);
+                let (i, f, o, g) = (
+                    gates.slice(s![..n]),
+                    gates.slice(s![n..2 * n]),
+                    gates.slice(s![2 * n..3 * n]),
+                    gates.slice(s![3 * n..]),
+                );
+                let c = &f * c_prev + &i * &g;
+                let h = &o * &c.mapv(Real::tanh);
+                lstm.steps.push_back(LstmStep {
+                    x: x.to_owned(),
+                    gates,
+                    c,
+                    h,
+                });
+                if lstm.steps.len() > self.window {
+                    let LstmStep { h, c, .. } = lstm.steps.pop_front().unwrap();
+                    lstm.initial = (h, c);
+                }
+            }
+            Cell::Gru(gru) => {
+                let n = gru.hidden_size();
+                let h_prev = gru.state(gru.steps.len().checked_sub(1));
+                let mut gates = x.dot(&gru.wx) + &gru.b;
+                let mut zr = gates.slice_mut(s![..2 * n]);
+                zr += &h_prev.dot(&gru.wh.slice(s![.., ..2 * n]));
+                zr.mapv_inplace(sigmoid);
+                let reset = &gates.slice(s![n..2 * n]) * h_prev;
+                let mut candidate = gates.slice_mut(s![2 * n..]);
+                candidate += &reset.dot(&gru.wh.slice(s![.., 2 * n..]));
+                candidate.mapv_inplace(Real::tanh);
+                let (z, candidate) = (gates.slice(s![..n]), gates.slice(s![2 * n..]));
+                let h = &candidate + &(&z * &(h_prev - &candidate));
+                gru.steps.push_back(GruStep {
+                    x: x.to_owned(),
+                    gates,
+                    h,
+                });
+                if gru.steps.len() > self.window {
+                    gru.initial = gru.steps.pop_front().unwrap().h;
+                }
+            }
+        }
+    }
+
+    /// Backpropagates `dstate`, the gradient of the loss with respect to the
+    /// current state, through the cached steps. Returns the gradients of the
+    /// parameters, in the order of `params_mut()`.
+    pub(crate) fn backward(&self, dstate: Array1<Real>, weight_decay: Real) -> Vec<ArrayD<Real>> {
+        match &self.cell {
+            Cell::Elman(elman) => {
+                let mut dwx = weight_decay * &elman.wx;
+                let mut dwh = weight_decay * &elman.wh;
+                let mut db = Array1::zeros(elman.b.len());
+                let mut dh = dstate;
+                for t in (0..elman.steps.len()).rev() {
+                    let (x, h) = &elman.steps[t];
+                    let h_prev = if t > 0 {
+                        &elman.steps[t - 1].1
+                    } else {
+                        &elman.initial
+                    };
+                    let da = dh * h.mapv(|y| elman.activation.derivative(y));
+                    dwx += &outer(x.view(), da.view());
+                    dwh += &outer(h_prev.view(), da.view());
+                    db += &da;
+                    dh = elman.wh.dot(&da);
+                }
+                vec![dwx.into_dyn(), dwh.into_dyn(), db.into_dyn()]
+            }
+            Cell::Lstm(lstm) => {
+                let n = lstm.hidden_size();
+                let mut dwx = weight_decay * &lstm.wx;
+                let mut dwh = weight_decay * &lstm.wh;
+                let mut db = Array1::zeros(lstm.b.len());
+                let mut dh = dstate;
+                let mut dc = Array1::zeros(n);
+                for t in (0..lstm.steps.len()).rev() {
+                    let LstmStep { x, gates, c, .. } = &lstm.steps[t];
+                    let (h_prev, c_prev) = lstm.state(t.checked_sub(1));
+                    let (i, f, o, g) = (
+                        gates.slice(s![..n]),
+                        gates.slice(s![n..2 * n]),
+                        gates.slice(s![2 * n..3 * n]),
+                        gates.slice(s![3 * n..]),
+                    );
+                    let tanh_c = c.mapv(Real::tanh);
+                    dc += &(&dh * &o * tanh_c.mapv(|y| 1.0 - y * y));
+                    let di = &dc * &g * &i.mapv(|y| y * (1.0 - y));
+                    let df = &dc * c_prev * f.mapv(|y| y * (1.0 - y));
+                    let do_ = &dh * &tanh_c * o.mapv(|y| y * (1.0 - y));
+                    let dg = &dc * &i * g.mapv(|y| 1.0 - y * y);
+                    let dgates =
+                        concatenate(Axis(0), &[di.view(), df.view(), do_.view(), dg.view()])
+                            .unwrap();
+                    dwx += &outer(x.view(), dgates.view());
+                    dwh += &outer(h_prev.view(), dgates.view());
+                    db += &dgates;
+                    dh = lstm.wh.dot(&dgates);
+                    dc *= &f;
+                }
+                vec![dwx.into_dyn(), dwh.into_dyn(), db.into_dyn()]
+            }
+            Cell::Gru(gru) => {
+                let n = gru.hidden_size();
+                let (wh_zr, wh_n) = (gru.wh.slice(s![.., ..2 * n]), gru.wh.slice(s![.., 2 * n..]));
+                let mut dwx = weight_decay * &gru.wx;
+                let mut dwh = weight_decay * &gru.wh;
+                let mut db = Array1::zeros(gru.b.len());
+                let mut dh = dstate;
+                for t in (0..gru.steps.len()).rev() {
+                    let GruStep { x, gates, .. } = &gru.steps[t];
+                    let h_prev = gru.state(t.checked_sub(1));
+                    let (z, r, candidate) = (
+                        gates.slice(s![..n]),
+                        gates.slice(s![n..2 * n]),
+                        gates.slice(s![2 * n..]),
+                    );
+                    let dcandidate = &dh * &z.mapv(|y| 1.0 - y) * candidate.mapv(|y| 1.0 - y * y);
+                    let dreset = wh_n.dot(&dcandidate);
+                    let dz = &dh * &(h_prev - &candidate) * z.mapv(|y| y * (1.0 - y));
+                    let dr = &dreset * h_prev * r.mapv(|y| y * (1.0 - y));
+                    let dzr = concatenate(Axis(0), &[dz.view(), dr.view()]).unwrap();
+                    let dgates = concatenate(Axis(0), &[dzr.view(), dcandidate.view()]).unwrap();
+                    dwx += &outer(x.view(), dgates.view());
+                    dwh.slice_mut(s![.., ..2 * n])
+                        .scaled_add(1.0, &outer(h_prev.view(), dzr.view()));
+                    dwh.slice_mut(s![.., 2 * n..])
+                        .scaled_add(1.0, &outer((&r * h_prev).view(), dcandidate.view()));
+                    db += &dgates;
+                    dh = &dh * &z + wh_zr.dot(&dzr) + dreset * r;
+                }
+                vec![dwx.into_dyn(), dwh.into_dyn(), db.into_dyn()]
+            }
+        }
+    }
+
+    /// Input weights, recurrent weights and bias, in the order used by the
+    /// optimizer.
+    pub(crate) fn params(&self) -> Vec<ArrayViewD<'_, Real>> {
+        match &self.cell {
+            Cell::Elman(elman) => vec![
+                elman.wx.view().into_dyn(),
+                elman.wh.view().into_dyn(),
+                elman.b.view().into_dyn(),
+            ],
+            Cell::Lstm(lstm) => vec![
+                lstm.wx.view().into_dyn(),
+                lstm.wh.view().into_dyn(),
+                lstm.b.view().into_dyn(),
+            ],
+            Cell::Gru(gru) => vec![
+                gru.wx.view().into_dyn(),
+                gru.wh.view().into_dyn(),
+                gru.b.view().into_dyn(),
+            ],
+        }
+    }
+
+    pub(crate) fn params_mut(&mut self) -> Vec<ArrayViewMutD<'_, Real>> {
+        match &mut self.cell {
+            Cell::Elman(elman) => vec![
+                elman.wx.view_mut().into_dyn(),
+                elman.wh.view_mut().into_dyn(),
+                elman.b.view_mut().into_dyn(),
+            ],
+            Cell::Lstm(lstm) => vec![
+                lstm.wx.view_mut().into_dyn(),
+                lstm.wh.view_mut().into_dyn(),
+                lstm.b.view_mut().into_dyn(),
+            ],
+            Cell::Gru(gru) => vec![
+                gru.wx.view_mut().into_dyn(),
+                gru.wh.view_mut().into_dyn(),
+                gru.b.view_mut().into_dyn(),
+            ],
+        }
+    }
+
+    /// Weight matrices, excluding the biases.
+    pub(crate) fn weights(&self) -> Vec<&Array2<Real>> {
+        match &self.cell {
+            Cell::Elman(elman) => vec![&elman.wx, &elman.wh],
+            Cell::Lstm(lstm) => vec![&lstm.wx, &lstm.wh],
+            Cell::Gru(gru) => vec![&gru.wx, &gru.wh],
+        }
+    }
+
+    /// Checks the parameter shapes against the network sizes.
+    #[cfg(feature = "serde")]
+    pub(crate) fn validate(&self, inputs: usize, hidden: usize) -> Result<(), Error> {
+        let check = |name, expected: &[usize], found: &[usize]| {
+            if expected == found {
+                Ok(())
+            } else {
+                Err(Error::ShapeMismatch {
+                    name,
+                    expected: expected.to_vec(),
+                    found: found.to_vec(),
+                })
+            }
+        };
+
+        match &self.cell {
+            Cell::Elman(elman) => {
+                check("wx", &[inputs, hidden], elman.wx.shape())?;
+                check("wh", &[hidden, hidden], elman.wh.shape())?;
+                check("b", &[hidden], elman.b.shape())?;
+                check("initial", &[hidden], elman.initial.shape())?;
+                for (x, h) in &elman.steps {
+                    check("steps", &[inputs], x.shape())?;
+                    check("steps", &[hidden], h.shape())?;
+                }
+            }
+            Cell::Lstm(lstm) => {
+                check("wx", &[inputs, 4 * hidden], lstm.wx.shape())?;
+                check("wh", &[hidden, 4 * hidden], lstm.wh.shape())?;
+                check("b", &[4 * hidden], lstm.b.shape())?;
+                check("initial", &[hidden], lstm.initial.0.shape())?;
+                check("initial", &[hidden], lstm.initial.1.shape())?;
+                for step in &lstm.steps {
+                    check("steps", &[inputs], step.x.shape())?;
+                    check("steps", &[4 * hidden], step.gates.shape())?;
+                    check("steps", &[hidden], step.c.shape())?;
+                    check("steps", &[hidden], step.h.shape())?;
+                }
+            }
+            Cell::Gru(gru) => {
+                check("wx", &[inputs, 3 * hidden], gru.wx.shape())?;
+                check("wh", &[hidden, 3 * hidden], gru.wh.shape())?;
+                check("b", &[3 * hidden], gru.b.shape())?;
+                check("initial", &[hidden], gru.initial.shape())?;
+                for step in &gru.steps {
+                    check("steps", &[inputs], step.x.shape())?;
+                    check("steps", &[3 * hidden], step.gates.shape())?;
+                    check("steps", &[hidden], step.h.shape())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    const INPUTS: usize = 3;
+    const HIDDEN: usize = 4;
+    const STEPS: usize = 3;
+
+    /// Compares the gradients of `backward()` with finite differences of
+    /// `state() · dstate` after `STEPS` steps.
+    fn check_gradients(architecture: Architecture) {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let fresh = Recurrent::new(
+            architecture,
+            INPUTS,
+            HIDDEN,
+            Activation::Tanh,
+            STEPS,
+            Init::Xavier,
+            &mut rng,
+        )
+        .unwrap();
+        let inputs: Vec<Array1<Real>> = (0..STEPS)
+            .map(|_| Array1::from_shape_simple_fn(INPUTS, || rng.gen_range(-1.0..1.0)))
+            .collect();
+        let dstate = Array1::from_shape_simple_fn(HIDDEN, || rng.gen_range(-1.0..1.0));
+        let loss = |recurrent: &mut Recurrent| {
+            for x in &inputs {
+                recurrent.step(x.view());
+            }
+            recurrent.state().dot(&dstate)
+        };
+
+        let mut recurrent = fresh.clone();
+        loss(&mut recurrent);
+        let grads = recurrent.backward(dstate.clone(), 0.0);
+
+        let eps = 1e-2;
+        for (k, grad) in grads.iter().enumerate() {
+            for (j, analytical) in grad.iter().enumerate() {
+                let perturbed = |delta| {
+                    let mut recurrent = fresh.clone();
+                    recurrent.params_mut()[k].as_slice_mut().unwrap()[j] += delta;
+                    loss(&mut recurrent)
+                };
+                let numerical = (perturbed(eps) - perturbed(-eps)) / (2.0 * eps);
+
+                assert!(
+                    (numerical - analytical).abs() < 1e-2,
+                    "{:?} param {}[{}]: {}, expected {}",
+                    architecture,
+                    k,
+                    j,
+                    analytical,
+                    numerical
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn elman_gradients() {
+        check_gradients(Architecture::Elman);
+    }
+
+    #[test]
+    fn lstm_gradients() {
+        check_gradients(Architecture::Lstm);
+    }
+
+    #[test]
+    fn gru_gradients() {
+        check_gradients(Architecture::Gru);
+    }
+}
@@ -0,0 +1,157 @@
+use alloc::collections::VecDeque;
+
+use ndarray::Array2;
+use rand::Rng;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{keep_last_rows, Real};
+
+#[cfg(feature = "serde")]
+use crate::Error;
+
+/// Bounded buffer of past rounds, as the history window a prediction was
+/// made from and the move that was actually played. Once full, the oldest
+/// round is dropped for each new one.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ReplayBuffer {
+    rounds: VecDeque<(Array2<Real>, usize)>,
+    capacity: usize,
+}
+
+impl ReplayBuffer {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            rounds: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.rounds.len()
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Checks the capacity, and the windows and moves of the stored rounds
+    /// against the network sizes.
+    #[cfg(feature = "serde")]
+    pub(crate) fn validate(
+        &self,
+        capacity: usize,
+        history_size: usize,
+        input_size: usize,
+        moves: usize,
+    ) -> Result<(), Error> {
+        for (name, expected, found) in [
+            ("replay_capacity", capacity, self.capacity),
+            ("replay", capacity.min(self.rounds.len()), self.rounds.len()),
+        ] {
+            if expected != found {
+                return Err(Error::ShapeMismatch {
+                    name,
+                    expected: vec![expected],
+                    found: vec![found],
+                });
+            }
+        }
+        for (window, label) in &self.rounds {
+            if window.shape() != [history_size, input_size] {
+                return Err(Error::ShapeMismatch {
+                    name: "replay",
+                    expected: vec![history_size, input_size],
+                    found: window.shape().to_vec(),
+                });
+            }
+            if *label >= moves {
+                return Err(Error::InvalidMove {
+                    index: *label,
+                    moves,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a round, returning the oldest one if it had to be dropped.
+    pub(crate) fn push(
+        &mut self,
+        history: Array2<Real>,
+        label: usize,
+    ) -> Option<(Array2<Real>, usize)> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let evicted = if self.rounds.len() == self.capacity {
+            self.rounds.pop_front()
+        } else {
+            None
+        };
+        self.rounds.push_back((history, label));
+        evicted
+    }
+
+    /// Reverts the last `push()`, given the round it returned.
+    pub(crate) fn unpush(&mut self, evicted: Option<(Array2<Real>, usize)>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.rounds.pop_back();
+        if let Some(round) = evicted {
+            self.rounds.push_front(round);
+        }
+    }
+
+    /// Resizes the stored history windows to `history_size` slots, see
+    /// `RPSNetwork::resize_history()`.
+    pub(crate) fn resize_windows(&mut self, history_size: usize) {
+        for (window, _) in self.rounds.iter_mut() {
+            *window = keep_last_rows(window.view(), history_size);
+        }
+    }
+
+    /// Draws a round uniformly at random, or `None` when the buffer is
+    /// empty.
+    pub(crate) fn sample<R: Rng>(&self, rng: &mut R) -> Option<&(Array2<Real>, usize)> {
+        if self.rounds.is_empty() {
+            None
+        } else {
+            self.rounds.get(rng.gen_range(0..self.rounds.len()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn bounded_capacity() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut buffer = ReplayBuffer::new(2);
+        assert!(buffer.sample(&mut rng).is_none());
+
+        buffer.push(Array2::zeros((1, 1)), 0);
+        buffer.push(Array2::zeros((1, 1)), 1);
+        let evicted = buffer.push(Array2::zeros((1, 1)), 2);
+
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(evicted, Some((Array2::zeros((1, 1)), 0)));
+        assert!((0..20).all(|_| buffer.sample(&mut rng).unwrap().1 > 0));
+
+        let before = buffer.clone();
+        let evicted = buffer.push(Array2::ones((1, 1)), 0);
+        buffer.unpush(evicted);
+        assert_eq!(buffer, before);
+
+        let mut disabled = ReplayBuffer::new(0);
+        disabled.push(Array2::zeros((1, 1)), 0);
+        assert_eq!(disabled.len(), 0);
+    }
+}
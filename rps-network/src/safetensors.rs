@@ -0,0 +1,199 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use std::collections::HashMap;
+
+use ::safetensors::tensor::TensorView;
+use ::safetensors::{Dtype, SafeTensorError, SafeTensors};
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::optim::OptimizerState;
+use crate::{Error, RPSNetwork, Real};
+
+#[cfg(not(feature = "f64"))]
+const DTYPE: Dtype = Dtype::F32;
+#[cfg(feature = "f64")]
+const DTYPE: Dtype = Dtype::F64;
+
+impl From<SafeTensorError> for Error {
+    fn from(err: SafeTensorError) -> Self {
+        Error::Serialization(err.to_string())
+    }
+}
+
+impl RPSNetwork {
+    /// Names of the parameter tensors, in the order of `get_weights()`.
+    fn param_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = (0..self.layers.len())
+            .flat_map(|i| {
+                [
+                    format!("layers.{}.weights", i),
+                    format!("layers.{}.bias", i),
+                ]
+            })
+            .collect();
+        let extra: &[&str] = match (&self.recurrent, &self.attention) {
+            (Some(_), _) => &[
+                "recurrent.input_weights",
+                "recurrent.recurrent_weights",
+                "recurrent.bias",
+            ],
+            (None, Some(_)) => &["attention.query", "attention.position"],
+            (None, None) => &[],
+        };
+        names.extend(extra.iter().map(|name| name.to_string()));
+        if self.outcome.is_some() {
+            names.extend(["outcome.weights".into(), "outcome.bias".into()]);
+        }
+        names
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl RPSNetwork {
+    /// Serializes the parameters in the safetensors format, for training
+    /// pipelines in Python. Dense layers are named `layers.{i}.weights` and
+    /// `layers.{i}.bias` from the input side, with `(inputs, outputs)`
+    /// weights, the transpose of `torch.nn.Linear`. A recurrent layer, the
+    /// attention and the outcome head follow under `recurrent.`,
+    /// `attention.` and `outcome.`. The sizes are recorded in the metadata.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn to_safetensors(&self) -> Result<Vec<u8>, Error> {
+        let params = self.params();
+        let data: Vec<Vec<u8>> = params
+            .iter()
+            .map(|param| param.iter().flat_map(|v| v.to_le_bytes()).collect())
+            .collect();
+        let tensors = self
+            .param_names()
+            .into_iter()
+            .zip(params.iter().zip(&data))
+            .map(|(name, (param, data))| {
+                Ok((name, TensorView::new(DTYPE, param.shape().to_vec(), data)?))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let metadata = HashMap::from([
+            ("format".to_string(), "rps-network".to_string()),
+            ("input_size".to_string(), self.input_size.to_string()),
+            ("history_size".to_string(), self.history_size.to_string()),
+            ("output_size".to_string(), self.output_size.to_string()),
+        ]);
+        Ok(::safetensors::serialize(tensors, Some(metadata))?)
+    }
+
+    /// Overwrites the parameters with those of safetensors `bytes`, named
+    /// and shaped like `to_safetensors()` writes them, e.g. pre-trained in
+    /// Python on recorded games. The tensors may be F32 or F64, and extra
+    /// ones are ignored. The optimizer starts over, and the network predicts
+    /// again from its current history. Nothing changes on error.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn load_safetensors(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let file = SafeTensors::deserialize(bytes)?;
+        let mut values = Vec::new();
+        for (name, param) in self.param_names().iter().zip(self.params()) {
+            let tensor = file.tensor(name)?;
+            if tensor.shape() != param.shape() {
+                return Err(Error::ShapeMismatch {
+                    name: "safetensors",
+                    expected: param.shape().to_vec(),
+                    found: tensor.shape().to_vec(),
+                });
+            }
+            values.push(read(&tensor)?);
+        }
+        for (mut param, values) in self.params_mut().into_iter().zip(values) {
+            for (p, v) in param.iter_mut().zip(values) {
+                *p = v;
+            }
+        }
+        self.optim = OptimizerState::new(self.optim.optimizer());
+        self.undo = None;
+        self.evaluate();
+        Ok(())
+    }
+}
+
+/// Values of a F32 or F64 tensor.
+fn read(tensor: &TensorView) -> Result<Vec<Real>, Error> {
+    let data = tensor.data();
+    Ok(match tensor.dtype() {
+        Dtype::F32 => data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()) as Real)
+            .collect(),
+        Dtype::F64 => data
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()) as Real)
+            .collect(),
+        _ => return Err(Error::Unsupported("only F32 and F64 tensors can be loaded")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Architecture, NetworkConfig};
+
+    #[test]
+    fn round_trips() {
+        let config = NetworkConfig::new()
+            .moves(3)
+            .architecture(Architecture::Gru)
+            .outcome_head(true);
+        let mut trained = config.clone().seed(1).build().unwrap();
+        for i in 0..20 {
+            let mut input = [0.0; 3];
+            input[i % 3] = 1.0;
+            trained.forward(&input).unwrap();
+            trained.backward(i % 3, None).unwrap();
+        }
+        let bytes = trained.to_safetensors().unwrap();
+        let file = SafeTensors::deserialize(&bytes).unwrap();
+        assert_eq!(file.len(), trained.parameter_count());
+        assert_eq!(file.tensor("recurrent.bias").unwrap().dtype(), DTYPE);
+
+        let mut network = config.seed(2).build().unwrap();
+        network.load_safetensors(&bytes).unwrap();
+        for i in 0..network.parameter_count() {
+            assert_eq!(network.get_weights(i), trained.get_weights(i));
+        }
+    }
+
+    #[test]
+    fn checks_tensors() {
+        let network = RPSNetwork::new_with_seed(3, 5, 10, 3, 42).unwrap();
+        let bytes = network.to_safetensors().unwrap();
+        let mut other = RPSNetwork::new_with_seed(3, 4, 10, 3, 7).unwrap();
+        let before = other.get_weights(0);
+        assert!(matches!(
+            other.load_safetensors(&bytes),
+            Err(Error::ShapeMismatch { .. })
+        ));
+        assert_eq!(other.get_weights(0), before);
+        assert!(other.load_safetensors(b"not safetensors").is_err());
+
+        // F64 tensors, as numpy writes them by default
+        let weights: Vec<u8> = (0..15 * 10).flat_map(|_| 0.5f64.to_le_bytes()).collect();
+        let bias = [0u8; 80];
+        let mut tensors = Vec::new();
+        tensors.push((
+            "layers.0.weights",
+            TensorView::new(Dtype::F64, vec![15, 10], &weights).unwrap(),
+        ));
+        tensors.push((
+            "layers.0.bias",
+            TensorView::new(Dtype::F64, vec![10], &bias).unwrap(),
+        ));
+        let file = SafeTensors::deserialize(&bytes).unwrap();
+        for name in ["layers.1.weights", "layers.1.bias"] {
+            tensors.push((name, file.tensor(name).unwrap()));
+        }
+        let bytes = ::safetensors::serialize(tensors, None).unwrap();
+        other = RPSNetwork::new_with_seed(3, 5, 10, 3, 7).unwrap();
+        other.load_safetensors(&bytes).unwrap();
+        assert!(other.get_weights(0).unwrap().iter().all(|&w| w == 0.5));
+        assert_eq!(other.get_weights(2), network.get_weights(2));
+    }
+}
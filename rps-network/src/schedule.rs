@@ -0,0 +1,265 @@
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Real};
+
+/// Learning rate used by `backward()` when no schedule has been set.
+pub const DEFAULT_LEARNING_RATE: Real = 0.1;
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScheduleKind {
+    Constant,
+    StepDecay {
+        step_size: u32,
+        gamma: Real,
+    },
+    ExponentialDecay {
+        gamma: Real,
+    },
+    Warmup {
+        warmup_steps: u32,
+    },
+    /// Between `min` and the schedule's learning rate, according to the
+    /// last rolling accuracy observed.
+    AccuracyAdaptive {
+        min: Real,
+        accuracy: Real,
+    },
+}
+
+/// Learning-rate schedule, advanced by one step on every `backward()` call
+/// that doesn't pass an explicit learning rate.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Schedule {
+    kind: ScheduleKind,
+    learning_rate: Real,
+    step: u32,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Schedule {
+    /// Always uses `learning_rate`. Learning rates, like the `gamma` of the
+    /// decaying schedules, must be positive.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn constant(learning_rate: Real) -> Result<Schedule, Error> {
+        Self::with_kind(ScheduleKind::Constant, learning_rate)
+    }
+
+    /// Multiplies the learning rate by `gamma` every `step_size` steps.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn step_decay(learning_rate: Real, step_size: u32, gamma: Real) -> Result<Schedule, Error> {
+        Self::with_kind(
+            ScheduleKind::StepDecay {
+                step_size: step_size.max(1),
+                gamma: check_positive("gamma", gamma)?,
+            },
+            learning_rate,
+        )
+    }
+
+    /// Multiplies the learning rate by `gamma` on every step.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn exponential_decay(learning_rate: Real, gamma: Real) -> Result<Schedule, Error> {
+        let gamma = check_positive("gamma", gamma)?;
+        Self::with_kind(ScheduleKind::ExponentialDecay { gamma }, learning_rate)
+    }
+
+    /// Ramps the learning rate up linearly over the first `warmup_steps`
+    /// steps, then keeps it constant.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn warmup(learning_rate: Real, warmup_steps: u32) -> Result<Schedule, Error> {
+        Self::with_kind(ScheduleKind::Warmup { warmup_steps }, learning_rate)
+    }
+
+    /// Follows the rolling accuracy of the network, see
+    /// `RPSNetwork::recent_accuracy()`: the learning rate goes down to `min`
+    /// as predictions get right, and back up to `max` as they get wrong, so
+    /// that the network settles against a steady opponent and adapts fast
+    /// when it changes strategy. Starts at `max`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn accuracy_adaptive(min: Real, max: Real) -> Result<Schedule, Error> {
+        let min = check_positive("learning_rate", min)?;
+        Self::with_kind(ScheduleKind::AccuracyAdaptive { min, accuracy: 0.0 }, max)
+    }
+
+    /// Learning rate that the next step will use.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn current(&self) -> Real {
+        let step = self.step;
+        match self.kind {
+            ScheduleKind::Constant => self.learning_rate,
+            ScheduleKind::StepDecay { step_size, gamma } => {
+                self.learning_rate * gamma.powi(saturate(step / step_size))
+            }
+            ScheduleKind::ExponentialDecay { gamma } => {
+                self.learning_rate * gamma.powi(saturate(step))
+            }
+            ScheduleKind::Warmup { warmup_steps } => {
+                if step >= warmup_steps {
+                    self.learning_rate
+                } else {
+                    self.learning_rate * (step + 1) as Real / (warmup_steps + 1) as Real
+                }
+            }
+            ScheduleKind::AccuracyAdaptive { min, accuracy } => {
+                min * accuracy + self.learning_rate * (1.0 - accuracy)
+            }
+        }
+    }
+}
+
+impl Schedule {
+    fn with_kind(kind: ScheduleKind, learning_rate: Real) -> Result<Self, Error> {
+        Ok(Self {
+            kind,
+            learning_rate: check_positive("learning_rate", learning_rate)?,
+            step: 0,
+        })
+    }
+
+    /// Same schedule, back at its first step.
+    pub(crate) fn restarted(self) -> Self {
+        let mut schedule = Self { step: 0, ..self };
+        schedule.observe_accuracy(0.0);
+        schedule
+    }
+
+    /// Records the rolling accuracy of the network, which only an
+    /// accuracy-adaptive schedule depends on.
+    pub(crate) fn observe_accuracy(&mut self, observed: Real) {
+        if let ScheduleKind::AccuracyAdaptive { accuracy, .. } = &mut self.kind {
+            *accuracy = observed;
+        }
+    }
+
+    /// Returns the learning rate for the current step and moves to the next.
+    pub(crate) fn next(&mut self) -> Real {
+        let learning_rate = self.current();
+        self.step = self.step.saturating_add(1);
+        learning_rate
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            kind: ScheduleKind::Constant,
+            learning_rate: DEFAULT_LEARNING_RATE,
+            step: 0,
+        }
+    }
+}
+
+/// Returns `value` if it's positive and finite.
+fn check_positive(name: &'static str, value: Real) -> Result<Real, Error> {
+    if !(value > 0.0 && value.is_finite()) {
+        return Err(Error::OutOfRange {
+            name,
+            value,
+            min: Real::MIN_POSITIVE,
+            max: Real::INFINITY,
+        });
+    }
+    Ok(value)
+}
+
+/// Exponent of `step` for `powi()`, which the step counter may exceed.
+fn saturate(step: u32) -> i32 {
+    i32::try_from(step).unwrap_or(i32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rates(mut schedule: Schedule, n: usize) -> Vec<Real> {
+        (0..n).map(|_| schedule.next()).collect()
+    }
+
+    #[test]
+    fn constant() {
+        assert_eq!(
+            rates(Schedule::constant(0.5).unwrap(), 3),
+            vec![0.5, 0.5, 0.5]
+        );
+    }
+
+    #[test]
+    fn step_decay() {
+        assert_eq!(
+            rates(Schedule::step_decay(1.0, 2, 0.5).unwrap(), 5),
+            vec![1.0, 1.0, 0.5, 0.5, 0.25]
+        );
+    }
+
+    #[test]
+    fn exponential_decay() {
+        assert_eq!(
+            rates(Schedule::exponential_decay(1.0, 0.5).unwrap(), 3),
+            vec![1.0, 0.5, 0.25]
+        );
+    }
+
+    #[test]
+    fn warmup() {
+        assert_eq!(
+            rates(Schedule::warmup(1.0, 3).unwrap(), 5),
+            vec![0.25, 0.5, 0.75, 1.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn accuracy_adaptive() {
+        let mut schedule = Schedule::accuracy_adaptive(0.1, 0.5).unwrap();
+        assert_eq!(schedule.next(), 0.5);
+        schedule.observe_accuracy(1.0);
+        assert_eq!(schedule.next(), 0.1);
+        schedule.observe_accuracy(0.5);
+        assert!((schedule.current() - 0.3).abs() < 1e-6);
+        assert_eq!(schedule.restarted().current(), 0.5);
+
+        // Other schedules ignore the accuracy
+        let mut constant = Schedule::constant(0.5).unwrap();
+        constant.observe_accuracy(1.0);
+        assert_eq!(constant.current(), 0.5);
+    }
+
+    #[test]
+    fn rejects_invalid_rates() {
+        for rate in [0.0, -0.1, Real::NAN, Real::INFINITY] {
+            assert!(matches!(
+                Schedule::constant(rate),
+                Err(Error::OutOfRange {
+                    name: "learning_rate",
+                    ..
+                })
+            ));
+            assert!(matches!(
+                Schedule::exponential_decay(0.1, rate),
+                Err(Error::OutOfRange { name: "gamma", .. })
+            ));
+            assert!(Schedule::step_decay(0.1, 2, rate).is_err());
+            assert!(Schedule::accuracy_adaptive(rate, 0.1).is_err());
+            assert!(Schedule::accuracy_adaptive(0.1, rate).is_err());
+        }
+    }
+
+    #[test]
+    fn decay_saturates() {
+        let mut schedule = Schedule::exponential_decay(1.0, 0.5).unwrap();
+        schedule.step = u32::MAX;
+        assert_eq!(schedule.current(), 0.0);
+        let mut schedule = Schedule::step_decay(1.0, 1, 2.0).unwrap();
+        schedule.step = u32::MAX;
+        assert_eq!(schedule.current(), Real::INFINITY);
+    }
+}
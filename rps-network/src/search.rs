@@ -0,0 +1,284 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::play_matches;
+use crate::{Activation, Beats, Error, NetworkConfig, Real, ScriptedBot};
+
+/// Values tried by `grid_search()` and `random_search()` for each
+/// hyperparameter.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchSpace {
+    hidden_sizes: Vec<usize>,
+    history_sizes: Vec<usize>,
+    learning_rates: Vec<Real>,
+    activations: Vec<Activation>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SearchSpace {
+    /// Starts from hidden sizes of 10, 20 and 40, histories of 2, 5 and 10
+    /// rounds, learning rates of 0.01, 0.05 and 0.1, and the tanh and ReLU
+    /// activations.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> Self {
+        Self {
+            hidden_sizes: vec![10, 20, 40],
+            history_sizes: vec![2, 5, 10],
+            learning_rates: vec![0.01, 0.05, 0.1],
+            activations: vec![Activation::Tanh, Activation::ReLU],
+        }
+    }
+
+    /// Sizes of the single hidden layer to try.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn hidden_sizes(mut self, hidden_sizes: &[usize]) -> Self {
+        self.hidden_sizes = hidden_sizes.to_vec();
+        self
+    }
+
+    /// History sizes to try.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn history_sizes(mut self, history_sizes: &[usize]) -> Self {
+        self.history_sizes = history_sizes.to_vec();
+        self
+    }
+
+    /// Learning rates to train with, passed to every `backward()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn learning_rates(mut self, learning_rates: &[Real]) -> Self {
+        self.learning_rates = learning_rates.to_vec();
+        self
+    }
+
+    /// Hidden activations to try.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn activations(mut self, activations: Vec<Activation>) -> Self {
+        self.activations = activations;
+        self
+    }
+
+    /// Number of combinations, i.e. of trials of a grid search.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn combinations(&self) -> usize {
+        self.hidden_sizes.len()
+            * self.history_sizes.len()
+            * self.learning_rates.len()
+            * self.activations.len()
+    }
+}
+
+impl Default for SearchSpace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchSpace {
+    /// Combination number `index`, in the order of a grid search: the last
+    /// hyperparameter changes fastest.
+    fn combination(&self, base: &NetworkConfig, mut index: usize) -> Trial {
+        let mut pick = |len: usize| {
+            let i = index % len;
+            index /= len;
+            i
+        };
+        let activation = self.activations[pick(self.activations.len())];
+        let learning_rate = self.learning_rates[pick(self.learning_rates.len())];
+        let history_size = self.history_sizes[pick(self.history_sizes.len())];
+        let hidden_size = self.hidden_sizes[pick(self.hidden_sizes.len())];
+        Trial {
+            config: base
+                .clone()
+                .hidden_size(hidden_size)
+                .history_size(history_size)
+                .activation(activation),
+            learning_rate,
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        for (name, len) in [
+            ("hidden_sizes", self.hidden_sizes.len()),
+            ("history_sizes", self.history_sizes.len()),
+            ("learning_rates", self.learning_rates.len()),
+            ("activations", self.activations.len()),
+        ] {
+            if len == 0 {
+                return Err(Error::InvalidSize {
+                    name,
+                    value: 0,
+                    min: 1,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A configuration and learning rate to score.
+struct Trial {
+    config: NetworkConfig,
+    learning_rate: Real,
+}
+
+/// Best hyperparameters found by a search.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    config: NetworkConfig,
+    /// Learning rate to pass to `backward()`.
+    pub learning_rate: Real,
+    /// Average score against the bots, from -1 to 1.
+    pub score: Real,
+    /// Number of combinations scored.
+    pub trials: u32,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SearchResult {
+    /// Configuration of the best network, the base one with the best hidden
+    /// size, history size and activation.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn config(&self) -> NetworkConfig {
+        self.config.clone()
+    }
+}
+
+/// Scores every combination of `space`, applied to `base`, and returns the
+/// best. See `random_search()`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn grid_search(
+    base: &NetworkConfig,
+    space: &SearchSpace,
+    rounds: u32,
+) -> Result<SearchResult, Error> {
+    space.validate()?;
+    search(
+        (0..space.combinations()).map(|i| space.combination(base, i)),
+        rounds,
+    )
+}
+
+/// Scores `trials` combinations of `space` drawn at random from `seed`,
+/// applied to `base`, and returns the best. Each combination plays
+/// `rounds` rounds of rock-paper-scissors against every bot of
+/// `ScriptedBot::library()`, starting from a new network built from the
+/// configuration and learning online, and scores its wins minus its losses
+/// per round, averaged over the bots. `base` must have 3 outputs and an
+/// input size matching an `Encoding`, and should have a seed so that every
+/// combination starts from comparable weights.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn random_search(
+    base: &NetworkConfig,
+    space: &SearchSpace,
+    trials: usize,
+    rounds: u32,
+    seed: u64,
+) -> Result<SearchResult, Error> {
+    space.validate()?;
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let combinations = space.combinations();
+    search(
+        (0..trials).map(|_| space.combination(base, rng.gen_range(0..combinations))),
+        rounds,
+    )
+}
+
+/// Best of `trials`, preferring the first on ties.
+fn search(trials: impl Iterator<Item = Trial>, rounds: u32) -> Result<SearchResult, Error> {
+    if rounds == 0 {
+        return Err(Error::InvalidSize {
+            name: "rounds",
+            value: 0,
+            min: 1,
+        });
+    }
+    let bots = ScriptedBot::library();
+    let trials: Vec<Trial> = trials.collect();
+    let matches = trials
+        .iter()
+        .flat_map(|trial| {
+            bots.iter()
+                .map(move |bot| (&trial.config, trial.learning_rate, bot))
+        })
+        .collect();
+    let simulations = play_matches(matches, &Beats::rock_paper_scissors(), rounds)?;
+    let count = trials.len() as u32;
+    let mut best: Option<SearchResult> = None;
+    for (trial, simulations) in trials.into_iter().zip(simulations.chunks(bots.len())) {
+        let score = simulations
+            .iter()
+            .map(|simulation| simulation.a_win_rate() - simulation.b_win_rate())
+            .sum::<Real>()
+            / bots.len() as Real;
+        if best.as_ref().is_none_or(|best| score > best.score) {
+            best = Some(SearchResult {
+                config: trial.config,
+                learning_rate: trial.learning_rate,
+                score,
+                trials: 0,
+            });
+        }
+    }
+    let mut best = best.ok_or(Error::InvalidSize {
+        name: "trials",
+        value: 0,
+        min: 1,
+    })?;
+    best.trials = count;
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> NetworkConfig {
+        NetworkConfig::new().moves(3).seed(42)
+    }
+
+    #[test]
+    fn grid() {
+        let space = SearchSpace::new()
+            .hidden_sizes(&[8])
+            .history_sizes(&[1, 3])
+            .learning_rates(&[0.001, 0.5]);
+        assert_eq!(space.combinations(), 8);
+        let result = grid_search(&base(), &space, 30).unwrap();
+        assert_eq!(result.trials, 8);
+        // A learning rate too small to learn anything in 30 rounds loses
+        assert_eq!(result.learning_rate, 0.5);
+        assert!(result.score > 0.0);
+        assert_eq!(result.config().hidden_sizes, vec![8]);
+    }
+
+    #[test]
+    fn random() {
+        let space = SearchSpace::new().hidden_sizes(&[8]);
+        let result = random_search(&base(), &space, 4, 30, 0).unwrap();
+        assert_eq!(result.trials, 4);
+        assert_eq!(random_search(&base(), &space, 4, 30, 0).unwrap(), result);
+    }
+
+    #[test]
+    fn invalid() {
+        let empty = SearchSpace::new().learning_rates(&[]);
+        assert!(grid_search(&base(), &empty, 10).is_err());
+        assert!(random_search(&base(), &SearchSpace::new(), 0, 10, 0).is_err());
+        assert!(grid_search(&base(), &SearchSpace::new(), 0).is_err());
+        let five = NetworkConfig::new().moves(5).seed(0);
+        assert!(grid_search(&five, &SearchSpace::new(), 10).is_err());
+    }
+}
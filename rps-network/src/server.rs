@@ -0,0 +1,321 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use std::sync::mpsc;
+
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response as HttpResponse};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::worker::Dispatcher;
+#[cfg(feature = "net")]
+use crate::{ClientMessage, Error, MatchCoordinator, NetworkConfig, ServerMessage};
+use crate::{RPSNetwork, Real, Request, Response};
+#[cfg(feature = "net")]
+use alloc::string::ToString;
+#[cfg(feature = "net")]
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+
+/// Body of `POST /forward`.
+#[derive(Deserialize)]
+struct Forward {
+    input: Vec<Real>,
+}
+
+/// Body of `POST /train`.
+#[derive(Deserialize)]
+struct Train {
+    label: usize,
+    #[serde(default)]
+    learning_rate: Option<Real>,
+}
+
+/// Answer of `POST /forward` and `GET /probs`.
+#[derive(Serialize)]
+struct Probs {
+    probs: Vec<Real>,
+}
+
+/// Answer of a failed request.
+#[derive(Serialize)]
+struct Failure {
+    error: String,
+}
+
+/// Sends requests of type `Q` to a thread owning the networks, which
+/// answers them with an `A` one at a time, like a `Dispatcher` in a Web
+/// Worker. Training needs exclusive access to the network, so the requests
+/// are serialized rather than locking it in each task; see `SharedNetwork`
+/// to serve predictions concurrently.
+struct Handle<Q, A> {
+    requests: mpsc::Sender<(Q, oneshot::Sender<A>)>,
+}
+
+impl<Q: Send + 'static, A: Send + 'static> Handle<Q, A> {
+    /// Starts the thread, which builds its state with `init` and then
+    /// applies `handle` to each request.
+    fn spawn<S>(
+        init: impl FnOnce() -> S + Send + 'static,
+        mut handle: impl FnMut(&mut S, Q) -> A + Send + 'static,
+    ) -> Self {
+        let (requests, received) = mpsc::channel::<(Q, oneshot::Sender<A>)>();
+        std::thread::spawn(move || {
+            let mut state = init();
+            for (request, reply) in received {
+                // The client may have gone away
+                let _ = reply.send(handle(&mut state, request));
+            }
+        });
+        Self { requests }
+    }
+
+    /// Answer to `request`, or `None` if the thread stopped.
+    async fn send(&self, request: Q) -> Option<A> {
+        let (reply, answer) = oneshot::channel();
+        self.requests.send((request, reply)).ok()?;
+        answer.await.ok()
+    }
+}
+
+impl<Q, A> Clone for Handle<Q, A> {
+    fn clone(&self) -> Self {
+        Self {
+            requests: self.requests.clone(),
+        }
+    }
+}
+
+/// Handle to the `Dispatcher` of `router()`.
+type NetworkHandle = Handle<Request, Response>;
+
+const STOPPED: &str = "the network thread stopped";
+
+/// HTTP service around a copy of `network`, so that clients without the JS
+/// bindings, such as mobile apps or game backends, can use it. Bodies are
+/// JSON:
+///
+/// - `POST /forward` with `{"input": [1, 0, 0]}` calls
+///   `RPSNetwork::forward()` and answers `{"probs": [...]}`.
+/// - `POST /train` with `{"label": 1}`, and optionally `"learning_rate"`,
+///   calls `RPSNetwork::backward()`.
+/// - `GET /probs` answers `{"probs": [...]}`.
+/// - `GET /model` answers the network serialized with
+///   `RPSNetwork::to_json()`, and `PUT /model` replaces it with such a body.
+///
+/// Failed requests answer 400 with `{"error": "..."}`. The network runs on
+/// a thread of its own and handles the requests in the order they arrive.
+/// Serve the router with `axum::serve()`.
+pub fn router(network: &RPSNetwork) -> Router {
+    let network = network.clone();
+    let handle = Handle::spawn(move || Dispatcher::new(network), Dispatcher::handle);
+
+    Router::new()
+        .route("/forward", post(forward))
+        .route("/train", post(train))
+        .route("/probs", get(probs))
+        .route("/model", get(save).put(load))
+        .with_state(handle)
+}
+
+/// WebSocket service playing matches with a `MatchCoordinator` for at most
+/// `capacity` players, whose networks are built from `config`. Clients
+/// connect to `/match` and exchange `ClientMessage`s and `ServerMessage`s as
+/// JSON text messages, starting with a `Join`. The coordinator runs on a
+/// thread of its own, like the network of `router()`.
+#[cfg(feature = "net")]
+pub fn match_router(config: &NetworkConfig, capacity: usize) -> Result<Router, Error> {
+    // Fail now rather than on the coordinator's thread
+    MatchCoordinator::new(config, capacity)?;
+    let config = config.clone();
+    let handle = Handle::spawn(
+        move || MatchCoordinator::new(&config, capacity).expect("the configuration was checked"),
+        |coordinator, (player, message): (Option<String>, ClientMessage)| {
+            coordinator.handle(player.as_deref(), message)
+        },
+    );
+    Ok(Router::new()
+        .route("/match", get(join_match))
+        .with_state(handle))
+}
+
+async fn forward(State(handle): State<NetworkHandle>, Json(body): Json<Forward>) -> HttpResponse {
+    respond(handle.send(Request::Forward { input: body.input }).await)
+}
+
+async fn train(State(handle): State<NetworkHandle>, Json(body): Json<Train>) -> HttpResponse {
+    respond(
+        handle
+            .send(Request::Backward {
+                label: body.label,
+                learning_rate: body.learning_rate,
+            })
+            .await,
+    )
+}
+
+async fn probs(State(handle): State<NetworkHandle>) -> HttpResponse {
+    respond(handle.send(Request::Probs).await)
+}
+
+async fn save(State(handle): State<NetworkHandle>) -> HttpResponse {
+    respond(handle.send(Request::Save).await)
+}
+
+async fn load(State(handle): State<NetworkHandle>, model: String) -> HttpResponse {
+    respond(handle.send(Request::Load { model }).await)
+}
+
+fn respond(response: Option<Response>) -> HttpResponse {
+    let response = response.unwrap_or_else(|| Response::Error {
+        message: STOPPED.into(),
+    });
+    match response {
+        Response::Done => StatusCode::NO_CONTENT.into_response(),
+        Response::Probs { probs } => Json(Probs { probs }).into_response(),
+        Response::Saved { model } => {
+            ([(header::CONTENT_TYPE, "application/json")], model).into_response()
+        }
+        Response::Error { message } => {
+            (StatusCode::BAD_REQUEST, Json(Failure { error: message })).into_response()
+        }
+    }
+}
+
+#[cfg(feature = "net")]
+type MatchHandle = Handle<(Option<String>, ClientMessage), ServerMessage>;
+
+#[cfg(feature = "net")]
+async fn join_match(State(handle): State<MatchHandle>, upgrade: WebSocketUpgrade) -> HttpResponse {
+    upgrade.on_upgrade(move |socket| play_match(socket, handle))
+}
+
+/// Relays the messages of a connection to the coordinator until it closes.
+#[cfg(feature = "net")]
+async fn play_match(mut socket: WebSocket, handle: MatchHandle) {
+    // Player who joined on this connection
+    let mut player = None;
+    while let Some(Ok(message)) = socket.recv().await {
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let answer = match serde_json::from_str::<ClientMessage>(&text) {
+            Ok(message) => handle.send((player.clone(), message)).await,
+            Err(error) => Some(ServerMessage::Error {
+                message: Error::from(error).to_string(),
+            }),
+        };
+        let answer = answer.unwrap_or_else(|| ServerMessage::Error {
+            message: STOPPED.into(),
+        });
+        if let ServerMessage::Joined { player: joined, .. } = &answer {
+            player = Some(joined.clone());
+        }
+        let json = serde_json::to_string(&answer).expect("messages always serialize");
+        if socket.send(Message::Text(json.into())).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http;
+    use tower::ServiceExt;
+
+    async fn call(router: &Router, method: &str, uri: &str, body: &str) -> (StatusCode, String) {
+        let request = http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(String::from(body)))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn endpoints() {
+        let network = RPSNetwork::new_with_seed(3, 5, 10, 3, 42).unwrap();
+        let service = router(&network);
+
+        let (status, body) = call(&service, "POST", "/forward", r#"{"input": [1, 0, 0]}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with(r#"{"probs":["#));
+        let (status, _) = call(&service, "POST", "/train", r#"{"label": 0}"#).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, body) = call(&service, "POST", "/train", r#"{"label": 3}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.starts_with(r#"{"error":"#));
+
+        let (status, model) = call(&service, "GET", "/model", "").await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, probs) = call(&service, "GET", "/probs", "").await;
+
+        // Loading a model replaces the network
+        let other = router(&RPSNetwork::new_with_seed(3, 5, 10, 3, 7).unwrap());
+        let (status, _) = call(&other, "PUT", "/model", &model).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(call(&other, "GET", "/probs", "").await.1, probs);
+        let (status, _) = call(&other, "PUT", "/model", "{}").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn plays_matches() {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite;
+
+        let config = NetworkConfig::new().moves(3).seed(42);
+        let service = match_router(&config, 4).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, service).await });
+
+        let url = alloc::format!("ws://{}/match", addr);
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        let commitment = crate::commitment(1, "secret");
+        for message in [
+            ClientMessage::Commit {
+                commitment: commitment.clone(),
+            },
+            ClientMessage::Join {
+                player: "alice".into(),
+            },
+            ClientMessage::Commit { commitment },
+            ClientMessage::Reveal {
+                player_move: 1,
+                nonce: "secret".into(),
+            },
+        ] {
+            let json = serde_json::to_string(&message).unwrap();
+            socket.send(tungstenite::Message::text(json)).await.unwrap();
+        }
+        let mut answers = Vec::new();
+        for _ in 0..4 {
+            let answer = socket.next().await.unwrap().unwrap();
+            answers.push(serde_json::from_str::<ServerMessage>(answer.to_text().unwrap()).unwrap());
+        }
+        assert!(matches!(answers[0], ServerMessage::Error { .. }));
+        assert!(matches!(
+            answers[1],
+            ServerMessage::Joined { rounds: 0, .. }
+        ));
+        assert!(matches!(answers[2], ServerMessage::Committed { .. }));
+        assert!(matches!(
+            answers[3],
+            ServerMessage::Result {
+                round: 1,
+                player_move: 1,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,132 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Beats, Encoding, Error, GameEngine, GameLog, RPSNetwork, Real, SessionStats};
+
+/// A game where the bot commits to its move before the player reveals
+/// theirs, so that a whole round is a single `play_round()` call.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct GameSession {
+    engine: GameEngine,
+    /// Move the bot plays in the coming round.
+    committed: usize,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl GameSession {
+    /// Starts a session with `network`, which must have 3 inputs and 3
+    /// outputs like for `GameEngine`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(network: RPSNetwork) -> Result<GameSession, Error> {
+        Self::start(GameEngine::new(network)?)
+    }
+
+    /// Starts a session playing the game described by `rules`, see
+    /// `GameEngine::with_rules()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn with_rules(network: RPSNetwork, rules: &Beats) -> Result<GameSession, Error> {
+        Self::start(GameEngine::with_rules(network, rules)?)
+    }
+
+    /// Starts a session feeding rounds to the network as encoded by
+    /// `encoding`, see `GameEngine::with_encoding()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn with_encoding(
+        network: RPSNetwork,
+        rules: &Beats,
+        encoding: Encoding,
+    ) -> Result<GameSession, Error> {
+        Self::start(GameEngine::with_encoding(network, rules, encoding)?)
+    }
+
+    /// Move the bot has committed to for the coming round, chosen before
+    /// the player's move is known.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn committed_move(&self) -> usize {
+        self.committed
+    }
+
+    /// Plays a round against the player's `opponent_move`: judges it
+    /// against the committed move, trains the network on the player's move,
+    /// and commits to the bot's move for the next round. Returns the move
+    /// the bot played in this round.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn play_round(&mut self, opponent_move: usize) -> Result<usize, Error> {
+        let bot = self.committed;
+        self.engine.play(opponent_move, bot)?;
+        self.committed = self.engine.bot_move();
+        Ok(bot)
+    }
+
+    /// Statistics of the rounds played so far.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn stats(&self) -> SessionStats {
+        self.engine.stats()
+    }
+
+    /// Records every round played from now on, see
+    /// `GameEngine::set_recording()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_recording(&mut self, recording: bool) {
+        self.engine.set_recording(recording)
+    }
+
+    /// Rounds recorded so far.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn log(&self) -> GameLog {
+        self.engine.log()
+    }
+
+    /// Keeps the bot from becoming fully deterministic from the next
+    /// commitment on, see `GameEngine::set_policy_floor()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_policy_floor(&mut self, floor: Real) -> Result<(), Error> {
+        self.engine.set_policy_floor(floor)
+    }
+
+    /// Falls back to uniform play from the next commitment on whenever the
+    /// network is guessing, see `GameEngine::set_nash_threshold()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_nash_threshold(&mut self, threshold: Option<Real>) -> Result<(), Error> {
+        self.engine.set_nash_threshold(threshold)
+    }
+}
+
+impl GameSession {
+    fn start(mut engine: GameEngine) -> Result<GameSession, Error> {
+        let committed = engine.bot_move();
+        Ok(Self { engine, committed })
+    }
+
+    /// Engine keeping the score of the session.
+    pub fn engine(&self) -> &GameEngine {
+        &self.engine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Move, Outcome};
+
+    #[test]
+    fn play_round() {
+        let mut session =
+            GameSession::new(RPSNetwork::new_with_seed(3, 5, 10, 3, 42).unwrap()).unwrap();
+
+        let mut late_bot_wins = 0;
+        for round in 0..300 {
+            let committed = session.committed_move();
+            let player = Move::ALL[round % 3];
+            let bot = session.play_round(player.index()).unwrap();
+
+            assert_eq!(bot, committed);
+            if round >= 200 && player.against(Move::ALL[bot]) == Outcome::Lose {
+                late_bot_wins += 1;
+            }
+        }
+
+        assert_eq!(session.stats().rounds, 300);
+        assert!(late_bot_wins > 90);
+    }
+}
@@ -0,0 +1,136 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use std::sync::{Mutex, PoisonError, RwLock};
+
+use crate::{Error, RPSNetwork, Real};
+
+/// Network shared between the threads of a server: any number of clients
+/// read predictions from the last published version while a trainer updates
+/// the weights, without waiting for each other. The network is double
+/// buffered: `update()` trains a copy and publishes it once done, so readers
+/// always see a complete version, and those still holding an older
+/// `snapshot()` keep it until they drop it. Clones share the same network.
+#[derive(Clone)]
+pub struct SharedNetwork {
+    published: Arc<RwLock<Arc<RPSNetwork>>>,
+    /// Serializes the updates, so that none of them is lost.
+    updating: Arc<Mutex<()>>,
+}
+
+impl SharedNetwork {
+    /// Shares `network`, which becomes the first published version.
+    pub fn new(network: RPSNetwork) -> Self {
+        Self {
+            published: Arc::new(RwLock::new(Arc::new(network))),
+            updating: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Last published version of the network.
+    pub fn snapshot(&self) -> Arc<RPSNetwork> {
+        // The lock only guards the swap of a pointer, which can't be left
+        // half done
+        self.published
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Output distributions of the last published version for `n` history
+    /// windows, see `RPSNetwork::forward_batch()`, so that each client can
+    /// keep the history of its own game.
+    pub fn predict(&self, windows: &[Real], n: usize) -> Result<Vec<Real>, Error> {
+        self.snapshot().forward_batch(windows, n)
+    }
+
+    /// Applies `train` to a copy of the last published version, then
+    /// publishes the copy if `train` succeeds. Readers go on with the
+    /// previous version meanwhile, and concurrent updates apply one after
+    /// the other.
+    pub fn update<T>(
+        &self,
+        train: impl FnOnce(&mut RPSNetwork) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let _updating = self.updating.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut network = RPSNetwork::clone(&self.snapshot());
+        let result = train(&mut network)?;
+        self.publish(network);
+        Ok(result)
+    }
+
+    /// Publishes `network` as the new version, e.g. from a trainer thread
+    /// keeping a network of its own.
+    pub fn publish(&self, network: RPSNetwork) {
+        *self
+            .published
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Arc::new(network);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Beats, GameEngine, LegacyRPSNetwork, QuantizedNetwork};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn networks_are_send_and_sync() {
+        assert_send_sync::<RPSNetwork>();
+        assert_send_sync::<LegacyRPSNetwork>();
+        assert_send_sync::<QuantizedNetwork>();
+        assert_send_sync::<GameEngine>();
+        assert_send_sync::<Beats>();
+        assert_send_sync::<SharedNetwork>();
+    }
+
+    #[test]
+    fn trains_while_serving() {
+        let network = RPSNetwork::new_with_seed(3, 2, 10, 3, 42).unwrap();
+        let mut expected = network.clone();
+        let shared = SharedNetwork::new(network);
+        let window = [0.0, 0.0, 1.0, 1.0, 0.0, 0.0];
+        let before = shared.predict(&window, 1).unwrap();
+        let old = shared.snapshot();
+
+        let trainer = {
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                for _ in 0..50 {
+                    shared
+                        .update(|network| network.train_step(&[1.0, 0.0, 0.0], 0, Some(0.1)))
+                        .unwrap();
+                }
+            })
+        };
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..50 {
+                        let probs = shared.predict(&window, 1).unwrap();
+                        assert!((probs.iter().sum::<Real>() - 1.0).abs() < 1e-4);
+                    }
+                })
+            })
+            .collect();
+        trainer.join().unwrap();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        // Every update was applied, and the old snapshot is unchanged
+        for _ in 0..50 {
+            expected.train_step(&[1.0, 0.0, 0.0], 0, Some(0.1)).unwrap();
+        }
+        let after = shared.predict(&window, 1).unwrap();
+        assert_eq!(after, expected.forward_batch(&window, 1).unwrap());
+        assert_ne!(after, before);
+        assert_eq!(old.forward_batch(&window, 1).unwrap(), before);
+        assert!(shared
+            .update(|network| network.backward(3, None).map(|_| ()))
+            .is_err());
+        assert_eq!(shared.predict(&window, 1).unwrap(), after);
+    }
+}
@@ -0,0 +1,465 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cross_entropy, Beats, Encoding, Ensemble, Error, GameEngine, NetworkConfig, Outcome, Player,
+    RPSNetwork, Real, RpsPredictor, ScriptedBot,
+};
+
+/// Plays with any predictor: the move beating its most likely prediction
+/// of the opponent's next move, training it on every round.
+#[derive(Debug, Clone)]
+pub struct PredictorPlayer<P> {
+    predictor: P,
+    rules: Beats,
+    encoding: Encoding,
+    learning_rate: Real,
+    rounds: u32,
+    /// Own move in the last round.
+    previous: Option<usize>,
+}
+
+impl<P: RpsPredictor> PredictorPlayer<P> {
+    /// Plays the game described by `rules` with `predictor`, feeding it the
+    /// rounds encoded by `encoding` and training it with `learning_rate`.
+    pub fn new(
+        predictor: P,
+        rules: &Beats,
+        encoding: Encoding,
+        learning_rate: Real,
+    ) -> Result<Self, Error> {
+        let moves = predictor.probs().len();
+        if moves != rules.moves() {
+            return Err(Error::ShapeMismatch {
+                name: "probs",
+                expected: vec![rules.moves()],
+                found: vec![moves],
+            });
+        }
+        Ok(Self {
+            predictor,
+            rules: rules.clone(),
+            encoding,
+            learning_rate,
+            rounds: 0,
+            previous: None,
+        })
+    }
+
+    /// Predictor choosing the moves.
+    pub fn predictor(&self) -> &P {
+        &self.predictor
+    }
+}
+
+impl<P: RpsPredictor> Player for PredictorPlayer<P> {
+    fn next_move(&mut self) -> usize {
+        let moves = self.rules.moves();
+        self.rules
+            .counter(&self.predictor.probs(), moves)
+            .expect("the rules cover every output")
+    }
+
+    fn record(&mut self, own: usize, opponent: usize) -> Result<(), Error> {
+        let outcome = self.rules.judge(opponent, own)?;
+        // The first round has no prediction to learn from
+        if self.rounds > 0 {
+            self.predictor.backward(opponent, self.learning_rate)?;
+        }
+        let moves = self.rules.moves();
+        let input = self
+            .encoding
+            .encode_after(opponent, own, outcome, self.previous, moves)?;
+        self.predictor.forward(&input)?;
+        self.previous = Some(own);
+        self.rounds += 1;
+        Ok(())
+    }
+
+    fn probs(&self) -> Option<Vec<Real>> {
+        Some(self.predictor.probs())
+    }
+}
+
+impl Player for GameEngine {
+    fn next_move(&mut self) -> usize {
+        self.bot_move()
+    }
+
+    fn record(&mut self, own: usize, opponent: usize) -> Result<(), Error> {
+        self.play(opponent, own)?;
+        Ok(())
+    }
+
+    fn probs(&self) -> Option<Vec<Real>> {
+        Some(GameEngine::probs(self))
+    }
+}
+
+/// Any `Player`, to pass to `simulate()` from JavaScript.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Contestant {
+    player: Box<dyn Player>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Contestant {
+    /// Plays with a scripted bot.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn bot(bot: ScriptedBot) -> Contestant {
+        Self::new(Box::new(bot))
+    }
+
+    /// Plays with a game engine, as against a human player.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn engine(engine: GameEngine) -> Contestant {
+        Self::new(Box::new(engine))
+    }
+
+    /// Plays with an ensemble, see `PredictorPlayer::new()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn ensemble(
+        ensemble: Ensemble,
+        rules: &Beats,
+        encoding: Encoding,
+        learning_rate: Real,
+    ) -> Result<Contestant, Error> {
+        let player = PredictorPlayer::new(ensemble, rules, encoding, learning_rate)?;
+        Ok(Self::new(Box::new(player)))
+    }
+}
+
+impl Contestant {
+    /// Plays with any player, e.g. a `PredictorPlayer`.
+    pub fn new(player: Box<dyn Player>) -> Self {
+        Self { player }
+    }
+}
+
+impl Player for Contestant {
+    fn next_move(&mut self) -> usize {
+        self.player.next_move()
+    }
+
+    fn record(&mut self, own: usize, opponent: usize) -> Result<(), Error> {
+        self.player.record(own, opponent)
+    }
+
+    fn probs(&self) -> Option<Vec<Real>> {
+        self.player.probs()
+    }
+}
+
+/// Results of a simulation between two players, A and B.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simulation {
+    /// Number of rounds played.
+    pub rounds: u32,
+    /// Rounds won by A.
+    pub a_wins: u32,
+    /// Rounds won by B.
+    pub b_wins: u32,
+    /// Rounds ending in a draw.
+    pub draws: u32,
+    /// Average cross-entropy of A's predictions of B's moves, if A predicts
+    /// them.
+    pub a_loss: Option<Real>,
+    /// Average cross-entropy of B's predictions of A's moves, if B predicts
+    /// them.
+    pub b_loss: Option<Real>,
+    a_moves: Vec<u32>,
+    b_moves: Vec<u32>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Simulation {
+    /// Share of the rounds won by A.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn a_win_rate(&self) -> Real {
+        self.a_wins as Real / self.rounds.max(1) as Real
+    }
+
+    /// Share of the rounds won by B.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn b_win_rate(&self) -> Real {
+        self.b_wins as Real / self.rounds.max(1) as Real
+    }
+
+    /// Move A played in each round.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn a_moves(&self) -> Vec<u32> {
+        self.a_moves.clone()
+    }
+
+    /// Move B played in each round.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn b_moves(&self) -> Vec<u32> {
+        self.b_moves.clone()
+    }
+}
+
+/// Plays `rounds` rounds of rock-paper-scissors between `a` and `b`, see
+/// `simulate_with_rules()`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn simulate(a: &mut Contestant, b: &mut Contestant, rounds: u32) -> Result<Simulation, Error> {
+    simulate_with_rules(a, b, &Beats::rock_paper_scissors(), rounds)
+}
+
+/// Plays `rounds` rounds between `a` and `b` of the game described by
+/// `rules`, each player choosing its move before seeing the other's, and
+/// returns the score, the losses of the players that predict their
+/// opponent, and the moves of every round.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn simulate_with_rules(
+    a: &mut Contestant,
+    b: &mut Contestant,
+    rules: &Beats,
+    rounds: u32,
+) -> Result<Simulation, Error> {
+    simulate_players(a, b, rules, rounds)
+}
+
+/// `simulate_with_rules()` for any players.
+pub fn simulate_players<A: Player + ?Sized, B: Player + ?Sized>(
+    a: &mut A,
+    b: &mut B,
+    rules: &Beats,
+    rounds: u32,
+) -> Result<Simulation, Error> {
+    let mut simulation = Simulation {
+        rounds,
+        a_wins: 0,
+        b_wins: 0,
+        draws: 0,
+        a_loss: None,
+        b_loss: None,
+        a_moves: Vec::with_capacity(rounds as usize),
+        b_moves: Vec::with_capacity(rounds as usize),
+    };
+    let (mut a_loss, mut b_loss) = (0.0, 0.0);
+    for _ in 0..rounds {
+        let (a_move, b_move) = (a.next_move(), b.next_move());
+        match rules.judge(a_move, b_move)? {
+            Outcome::Win => simulation.a_wins += 1,
+            Outcome::Lose => simulation.b_wins += 1,
+            Outcome::Draw => simulation.draws += 1,
+        }
+        if let Some(probs) = a.probs() {
+            a_loss += cross_entropy(probs[b_move]);
+        }
+        if let Some(probs) = b.probs() {
+            b_loss += cross_entropy(probs[a_move]);
+        }
+        a.record(a_move, b_move)?;
+        b.record(b_move, a_move)?;
+        simulation.a_moves.push(a_move as u32);
+        simulation.b_moves.push(b_move as u32);
+    }
+
+    let average = |loss: Real| loss / rounds.max(1) as Real;
+    simulation.a_loss = a.probs().map(|_| average(a_loss));
+    simulation.b_loss = b.probs().map(|_| average(b_loss));
+    Ok(simulation)
+}
+
+/// Trains `a` and `b` against each other for `rounds` rounds of
+/// rock-paper-scissors with `learning_rate`, each network predicting the
+/// other's moves and playing what beats its prediction, so that a model can
+/// be warmed up before it ever faces a human. Both networks must have 3
+/// outputs and take rounds in any `Encoding`, told apart by their input
+/// size. Their histories end up holding self-play rounds, which
+/// `reset_history()` clears.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn self_play(
+    a: &mut RPSNetwork,
+    b: &mut RPSNetwork,
+    rounds: u32,
+    learning_rate: Real,
+) -> Result<Simulation, Error> {
+    let rules = Beats::rock_paper_scissors();
+    let (a_encoding, b_encoding) = (encoding_of(a, &rules)?, encoding_of(b, &rules)?);
+    let mut a = PredictorPlayer::new(a, &rules, a_encoding, learning_rate)?;
+    let mut b = PredictorPlayer::new(b, &rules, b_encoding, learning_rate)?;
+    simulate_players(&mut a, &mut b, &rules, rounds)
+}
+
+/// Trains `network` for `rounds` rounds against a frozen snapshot of itself
+/// taken beforehand, see `self_play()`, so that it learns to exploit its
+/// current habits. The network is player A of the results.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn self_play_snapshot(
+    network: &mut RPSNetwork,
+    rounds: u32,
+    learning_rate: Real,
+) -> Result<Simulation, Error> {
+    let rules = Beats::rock_paper_scissors();
+    let encoding = encoding_of(network, &rules)?;
+    let mut snapshot = PredictorPlayer::new(network.clone(), &rules, encoding, 0.0)?;
+    let mut network = PredictorPlayer::new(network, &rules, encoding, learning_rate)?;
+    simulate_players(&mut network, &mut snapshot, &rules, rounds)
+}
+
+/// Plays a new network built from each of `configs` against every bot of
+/// `bots`, for `rounds` rounds of rock-paper-scissors, learning online with
+/// `learning_rate` like a `PredictorPlayer`. Returns the simulations of each
+/// configuration in turn, in the order of the bots, with the network as
+/// player A. Every configuration must have 3 outputs and an input size
+/// matching an `Encoding`. With the `parallel` feature, the matches run on
+/// rayon's thread pool.
+pub fn tournament(
+    configs: &[NetworkConfig],
+    bots: &[ScriptedBot],
+    learning_rate: Real,
+    rounds: u32,
+) -> Result<Vec<Simulation>, Error> {
+    let matches = configs
+        .iter()
+        .flat_map(|config| bots.iter().map(move |bot| (config, learning_rate, bot)))
+        .collect();
+    play_matches(matches, &Beats::rock_paper_scissors(), rounds)
+}
+
+/// Plays each `(config, learning_rate, bot)` match of `matches`, see
+/// `tournament()`, in parallel with the `parallel` feature.
+pub(crate) fn play_matches(
+    matches: Vec<(&NetworkConfig, Real, &ScriptedBot)>,
+    rules: &Beats,
+    rounds: u32,
+) -> Result<Vec<Simulation>, Error> {
+    let play = |(config, learning_rate, bot): (&NetworkConfig, Real, &ScriptedBot)| {
+        let network = config.build()?;
+        let encoding = encoding_of(&network, rules)?;
+        let mut player = PredictorPlayer::new(network, rules, encoding, learning_rate)?;
+        simulate_players(&mut player, &mut bot.clone(), rules, rounds)
+    };
+    #[cfg(feature = "parallel")]
+    let simulations = {
+        use rayon::prelude::*;
+        matches.into_par_iter().map(play).collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let simulations = matches.into_iter().map(play).collect();
+    simulations
+}
+
+/// Encoding whose input size for `rules` is that of `network`.
+pub(crate) fn encoding_of(network: &RPSNetwork, rules: &Beats) -> Result<Encoding, Error> {
+    let moves = rules.moves();
+    Encoding::ALL
+        .into_iter()
+        .find(|encoding| encoding.input_size(moves) == network.input_size)
+        .ok_or(Error::Unsupported(
+            "the input size of the network matches no encoding",
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MarkovPredictor, NetworkConfig};
+
+    #[test]
+    fn engine_beats_constant_bot() {
+        let network = NetworkConfig::new().moves(3).seed(42).build().unwrap();
+        let mut engine = Contestant::engine(GameEngine::new(network).unwrap());
+        let mut rock = Contestant::bot(ScriptedBot::constant(0, 3).unwrap());
+
+        let simulation = simulate(&mut engine, &mut rock, 100).unwrap();
+        assert_eq!(simulation.rounds, 100);
+        assert_eq!(
+            simulation.a_wins + simulation.b_wins + simulation.draws,
+            100
+        );
+        assert!(simulation.a_win_rate() > 0.9);
+        assert_eq!(simulation.b_moves(), vec![0; 100]);
+        assert!(simulation.a_loss.unwrap() < 0.5);
+        assert_eq!(simulation.b_loss, None);
+    }
+
+    #[test]
+    fn scripted_bots() {
+        let rules = Beats::rock_paper_scissors();
+        let mut cycle = ScriptedBot::cycle(&[0, 1, 2], 3).unwrap();
+        let mut copy = ScriptedBot::copy_opponent(3).unwrap();
+
+        // Copying the last move of a rising cycle always loses
+        let simulation = simulate_players(&mut cycle, &mut copy, &rules, 30).unwrap();
+        assert_eq!(simulation.a_wins, 29);
+        assert_eq!(simulation.draws, 1);
+        assert_eq!(simulation.a_moves()[..4], [0, 1, 2, 0]);
+        assert_eq!(simulation.b_moves()[..4], [0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn predictor_player() {
+        let rules = Beats::rock_paper_scissors();
+        let markov = MarkovPredictor::new(2, 3).unwrap();
+        let mut player = PredictorPlayer::new(markov, &rules, Encoding::OpponentMove, 0.1).unwrap();
+
+        // An order-2 model learns an order-2 de Bruijn sequence by heart,
+        // but not an order-3 one
+        let mut de_bruijn = ScriptedBot::de_bruijn(2, 3).unwrap();
+        let simulation = simulate_players(&mut player, &mut de_bruijn, &rules, 90).unwrap();
+        assert!(simulation.a_win_rate() > 0.7);
+        let mut de_bruijn = ScriptedBot::de_bruijn(3, 3).unwrap();
+        let simulation = simulate_players(&mut player, &mut de_bruijn, &rules, 270).unwrap();
+        assert!(simulation.a_win_rate() < 0.5);
+
+        let five = MarkovPredictor::new(1, 5).unwrap();
+        assert!(PredictorPlayer::new(five, &rules, Encoding::OpponentMove, 0.1).is_err());
+    }
+
+    #[test]
+    fn self_play_trains_both() {
+        let config = NetworkConfig::new().moves(3);
+        let mut a = config.clone().seed(1).build().unwrap();
+        let mut b = config.input_size(6).seed(2).build().unwrap();
+        let (a_before, b_before) = (a.get_weights(0).unwrap(), b.get_weights(0).unwrap());
+
+        let simulation = self_play(&mut a, &mut b, 50, 0.1).unwrap();
+        assert_eq!(simulation.rounds, 50);
+        assert_eq!(simulation.a_moves().len(), 50);
+        assert!(simulation.a_loss.is_some() && simulation.b_loss.is_some());
+        assert_ne!(a.get_weights(0).unwrap(), a_before);
+        assert_ne!(b.get_weights(0).unwrap(), b_before);
+
+        let mut five = NetworkConfig::new().moves(5).build().unwrap();
+        assert!(self_play(&mut a, &mut five, 10, 0.1).is_err());
+    }
+
+    #[test]
+    fn self_play_against_snapshot() {
+        let mut network = NetworkConfig::new().moves(3).seed(3).build().unwrap();
+        let before = network.get_weights(0).unwrap();
+        let simulation = self_play_snapshot(&mut network, 100, 0.1).unwrap();
+        // The snapshot keeps its habits, which the network learns to beat
+        assert!(simulation.a_wins > simulation.b_wins);
+        assert_ne!(network.get_weights(0).unwrap(), before);
+    }
+
+    #[test]
+    fn tournament_plays_every_bot() {
+        let configs = [
+            NetworkConfig::new().moves(3).seed(1),
+            NetworkConfig::new().moves(3).hidden_size(8).seed(2),
+        ];
+        let bots = ScriptedBot::library();
+        let simulations = tournament(&configs, &bots, 0.1, 40).unwrap();
+        assert_eq!(simulations.len(), 2 * bots.len());
+        // Matches are independent of each other and of the thread pool
+        let second = tournament(&configs[1..], &bots[..1], 0.1, 40).unwrap();
+        assert_eq!(second[0], simulations[bots.len()]);
+
+        let five = [NetworkConfig::new().moves(5)];
+        assert!(tournament(&five, &bots, 0.1, 10).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Outcome, Real};
+
+/// Statistics of a game between a player and the bot, updated after every
+/// round.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionStats {
+    /// Number of rounds played.
+    pub rounds: u32,
+    /// Rounds won by the player.
+    pub player_wins: u32,
+    /// Rounds won by the bot.
+    pub bot_wins: u32,
+    /// Rounds ending in a draw.
+    pub draws: u32,
+    /// Total payoff of the player, see `Beats::from_payoff()`.
+    pub player_score: Real,
+    /// Length of the current winning streak: positive for the player,
+    /// negative for the bot, and 0 right after a draw.
+    pub streak: i32,
+    /// Most consecutive wins of the player.
+    pub longest_player_streak: u32,
+    /// Most consecutive wins of the bot.
+    pub longest_bot_streak: u32,
+    player_moves: Vec<u32>,
+    bot_moves: Vec<u32>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SessionStats {
+    /// Number of times the player played each move, by move index.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn player_moves(&self) -> Vec<u32> {
+        self.player_moves.clone()
+    }
+
+    /// Number of times the bot played each move, by move index.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn bot_moves(&self) -> Vec<u32> {
+        self.bot_moves.clone()
+    }
+}
+
+impl SessionStats {
+    pub(crate) fn new(moves: usize) -> Self {
+        Self {
+            rounds: 0,
+            player_wins: 0,
+            bot_wins: 0,
+            draws: 0,
+            player_score: 0.0,
+            streak: 0,
+            longest_player_streak: 0,
+            longest_bot_streak: 0,
+            player_moves: vec![0; moves],
+            bot_moves: vec![0; moves],
+        }
+    }
+
+    /// Records a round where the player played `player` and the bot `bot`,
+    /// with the given `outcome` and `payoff` for the player.
+    pub(crate) fn record(&mut self, player: usize, bot: usize, outcome: Outcome, payoff: Real) {
+        self.rounds += 1;
+        self.player_score += payoff;
+        self.player_moves[player] += 1;
+        self.bot_moves[bot] += 1;
+        match outcome {
+            Outcome::Win => {
+                self.player_wins += 1;
+                self.streak = self.streak.max(0) + 1;
+                self.longest_player_streak = self.longest_player_streak.max(self.streak as u32);
+            }
+            Outcome::Lose => {
+                self.bot_wins += 1;
+                self.streak = self.streak.min(0) - 1;
+                self.longest_bot_streak = self.longest_bot_streak.max(-self.streak as u32);
+            }
+            Outcome::Draw => {
+                self.draws += 1;
+                self.streak = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Move;
+
+    #[test]
+    fn record() {
+        let mut stats = SessionStats::new(3);
+        let rounds = [
+            (Move::Rock, Move::Scissors, Outcome::Win, 1),
+            (Move::Paper, Move::Rock, Outcome::Win, 2),
+            (Move::Paper, Move::Scissors, Outcome::Lose, -1),
+            (Move::Rock, Move::Rock, Outcome::Draw, 0),
+            (Move::Scissors, Move::Rock, Outcome::Lose, -1),
+            (Move::Scissors, Move::Rock, Outcome::Lose, -2),
+        ];
+        for (player, bot, outcome, streak) in rounds {
+            assert_eq!(player.against(bot), outcome);
+            let payoff = match outcome {
+                Outcome::Win => 1.0,
+                Outcome::Lose => -1.0,
+                Outcome::Draw => 0.0,
+            };
+            stats.record(player.index(), bot.index(), outcome, payoff);
+            assert_eq!(stats.streak, streak);
+        }
+
+        assert_eq!(stats.rounds, 6);
+        assert_eq!(stats.player_wins, 2);
+        assert_eq!(stats.bot_wins, 3);
+        assert_eq!(stats.draws, 1);
+        assert_eq!(stats.player_score, -1.0);
+        assert_eq!(stats.longest_player_streak, 2);
+        assert_eq!(stats.longest_bot_streak, 2);
+        assert_eq!(stats.player_moves(), vec![2, 2, 2]);
+        assert_eq!(stats.bot_moves(), vec![4, 0, 2]);
+    }
+}
@@ -0,0 +1,114 @@
+use alloc::format;
+use js_sys::{Promise, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+use web_sys::{IdbDatabase, IdbRequest, IdbTransactionMode, Storage};
+
+use crate::{Error, RPSNetwork};
+
+/// IndexedDB database and object store holding the saved networks, keyed by
+/// the names passed to `save_to_indexed_db()`.
+const DB_NAME: &str = "rps-network";
+const STORE_NAME: &str = "networks";
+
+#[wasm_bindgen]
+impl RPSNetwork {
+    /// Saves the network to `localStorage` under `key`, as `to_json()`.
+    pub fn save_to_local_storage(&self, key: &str) -> Result<(), Error> {
+        local_storage()?
+            .set_item(key, &self.to_json()?)
+            .map_err(storage_error)
+    }
+
+    /// Loads a network saved with `save_to_local_storage()`, or returns
+    /// `undefined` if there is none under `key`.
+    pub fn load_from_local_storage(key: &str) -> Result<Option<RPSNetwork>, Error> {
+        local_storage()?
+            .get_item(key)
+            .map_err(storage_error)?
+            .map(|json| RPSNetwork::from_json(&json))
+            .transpose()
+    }
+
+    /// Saves the network to IndexedDB under `key`, as `to_bytes()`. The
+    /// returned promise resolves once the network is stored.
+    pub fn save_to_indexed_db(&self, key: &str) -> Result<Promise, Error> {
+        let bytes = Uint8Array::from(&self.to_bytes()?[..]);
+        let key = JsValue::from_str(key);
+        Ok(future_to_promise(async move {
+            let db = open_db().await?;
+            let store = db
+                .transaction_with_str_and_mode(STORE_NAME, IdbTransactionMode::Readwrite)?
+                .object_store(STORE_NAME)?;
+            request(&store.put_with_key(&bytes, &key)?).await?;
+            Ok(JsValue::UNDEFINED)
+        }))
+    }
+
+    /// Loads a network saved with `save_to_indexed_db()`. The returned
+    /// promise resolves to the network, or to `undefined` if there is none
+    /// under `key`.
+    pub fn load_from_indexed_db(key: &str) -> Promise {
+        let key = JsValue::from_str(key);
+        future_to_promise(async move {
+            let db = open_db().await?;
+            let store = db
+                .transaction_with_str(STORE_NAME)?
+                .object_store(STORE_NAME)?;
+            let value = request(&store.get(&key)?).await?;
+            if value.is_undefined() {
+                return Ok(JsValue::UNDEFINED);
+            }
+            let bytes = value.dyn_into::<Uint8Array>()?.to_vec();
+            Ok(RPSNetwork::from_bytes(&bytes)?.into())
+        })
+    }
+}
+
+fn local_storage() -> Result<Storage, Error> {
+    web_sys::window()
+        .ok_or_else(|| Error::Storage("no window".into()))?
+        .local_storage()
+        .map_err(storage_error)?
+        .ok_or_else(|| Error::Storage("localStorage is unavailable".into()))
+}
+
+/// Opens the database, creating its object store on first use.
+async fn open_db() -> Result<IdbDatabase, JsValue> {
+    let factory = web_sys::window()
+        .ok_or_else(|| Error::Storage("no window".into()))?
+        .indexed_db()?
+        .ok_or_else(|| Error::Storage("IndexedDB is unavailable".into()))?;
+    let open = factory.open_with_u32(DB_NAME, 1)?;
+    let upgrade = open.clone();
+    let on_upgrade = Closure::once_into_js(move || {
+        let db: IdbDatabase = upgrade.result().unwrap().unchecked_into();
+        db.create_object_store(STORE_NAME).unwrap();
+    });
+    open.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+    Ok(request(&open).await?.unchecked_into())
+}
+
+/// Waits for `request` to complete, and returns its result.
+async fn request(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        let done = request.clone();
+        let on_success = Closure::once_into_js(move || {
+            resolve
+                .call1(&JsValue::NULL, &done.result().unwrap())
+                .unwrap();
+        });
+        let on_error = Closure::once_into_js(move || {
+            let error = Error::Storage("IndexedDB request failed".into());
+            reject.call1(&JsValue::NULL, &error.into()).unwrap();
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    JsFuture::from(promise).await
+}
+
+fn storage_error(err: JsValue) -> Error {
+    Error::Storage(format!("{:?}", err))
+}
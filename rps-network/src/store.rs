@@ -0,0 +1,347 @@
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "serde")]
+use crate::migration::{self, Saved, Saving};
+use crate::{Error, NetworkConfig, Prediction, RPSNetwork, Real};
+
+/// Independent networks keyed by opponent ID, so that a multiplayer frontend
+/// learns each player separately instead of blending everyone into one
+/// model. A network is built from the store's configuration the first time
+/// its opponent is seen, and the least recently used one is evicted once the
+/// store is full.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
+pub struct ModelStore {
+    config: NetworkConfig,
+    capacity: usize,
+    /// Networks by opponent ID, from the least recently used.
+    entries: VecDeque<(String, RPSNetwork)>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl ModelStore {
+    /// Starts an empty store holding at most `capacity` networks built from
+    /// `config`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(config: &NetworkConfig, capacity: usize) -> Result<ModelStore, Error> {
+        if capacity == 0 {
+            return Err(Error::InvalidSize {
+                name: "capacity",
+                value: 0,
+                min: 1,
+            });
+        }
+        // Fail now rather than on the first opponent
+        config.build()?;
+        Ok(Self {
+            config: config.clone(),
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        })
+    }
+
+    /// Calls `RPSNetwork::forward()` on the network of opponent `id`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn forward(&mut self, id: &str, input: &[Real]) -> Result<(), Error> {
+        self.get_mut(id)?.forward(input)
+    }
+
+    /// Calls `RPSNetwork::backward()` on the network of opponent `id`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn backward(
+        &mut self,
+        id: &str,
+        label: usize,
+        learning_rate: Option<Real>,
+    ) -> Result<(), Error> {
+        self.get_mut(id)?.backward(label, learning_rate)
+    }
+
+    /// Output distribution of the network of opponent `id`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn probs(&mut self, id: &str) -> Result<Vec<Real>, Error> {
+        Ok(self.get_mut(id)?.probs())
+    }
+
+    /// Most likely next move of opponent `id`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn predict(&mut self, id: &str) -> Result<Prediction, Error> {
+        Ok(self.get_mut(id)?.predict())
+    }
+
+    /// Stores `network` for opponent `id`, replacing any previous one.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn insert(&mut self, id: &str, network: RPSNetwork) {
+        self.remove(id);
+        self.push(id, network);
+    }
+
+    /// Takes the network of opponent `id` out of the store, if any.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn remove(&mut self, id: &str) -> Option<RPSNetwork> {
+        let index = self.position(id)?;
+        self.entries.remove(index).map(|(_, network)| network)
+    }
+
+    /// Copy of the network of opponent `id`, if any, without marking it as
+    /// used.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn network(&self, id: &str) -> Option<RPSNetwork> {
+        self.get(id).cloned()
+    }
+
+    /// Whether the store holds a network for opponent `id`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn contains(&self, id: &str) -> bool {
+        self.position(id).is_some()
+    }
+
+    /// IDs of the stored opponents, from the least recently used.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn ids(&self) -> Vec<String> {
+        self.entries.iter().map(|(id, _)| id.clone()).collect()
+    }
+
+    /// Number of stored networks.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no network is stored.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Serializes the store, including every network, to a JSON string,
+    /// with the header of `RPSNetwork::to_json()`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn to_json(&self) -> Result<String, Error> {
+        migration::to_json(Saving::Store(self))
+    }
+
+    /// Restores a store previously serialized with `to_json()`, checking
+    /// its header like `RPSNetwork::from_json()`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn from_json(json: &str) -> Result<ModelStore, Error> {
+        migration::from_json(json, |json| Ok(Saved::Store(serde_json::from_str(json)?)))?
+            .into_store()?
+            .validated()
+    }
+
+    /// Serializes the store to a compact binary encoding, see
+    /// `RPSNetwork::to_bytes()`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        migration::to_bytes(Saving::Store(self))
+    }
+
+    /// Restores a store previously serialized with `to_bytes()`.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<ModelStore, Error> {
+        migration::from_bytes(bytes, |bytes| {
+            Ok(Saved::Store(bincode::deserialize(bytes)?))
+        })?
+        .into_store()?
+        .validated()
+    }
+}
+
+impl ModelStore {
+    /// Network of opponent `id`, built if the opponent is new, and marked
+    /// as the most recently used.
+    pub fn get_mut(&mut self, id: &str) -> Result<&mut RPSNetwork, Error> {
+        let network = match self.position(id) {
+            Some(index) => self.entries.remove(index).unwrap().1,
+            None => self.config.build()?,
+        };
+        self.push(id, network);
+        Ok(&mut self.entries.back_mut().unwrap().1)
+    }
+
+    /// Network of opponent `id`, if any, without marking it as used.
+    pub fn get(&self, id: &str) -> Option<&RPSNetwork> {
+        self.position(id).map(|index| &self.entries[index].1)
+    }
+
+    fn position(&self, id: &str) -> Option<usize> {
+        self.entries.iter().position(|(entry, _)| entry == id)
+    }
+
+    /// Adds a network as the most recently used, evicting the least
+    /// recently used one if the store is full.
+    fn push(&mut self, id: &str, network: RPSNetwork) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((id.to_string(), network));
+    }
+
+    /// Values hashed in the header, see `RPSNetwork::hash_values()`: the
+    /// capacity and the configuration's sizes, architecture, optimizer and
+    /// activations, then those of each stored network.
+    #[cfg(feature = "serde")]
+    pub(crate) fn hash_values(&self, version: u32) -> Vec<usize> {
+        let config = &self.config;
+        let mut values = vec![
+            2,
+            self.capacity,
+            config.input_size,
+            config.history_size,
+            config.output_size,
+            config.architecture as usize,
+            config.optimizer as usize,
+        ];
+        for (layer, &hidden_size) in config.hidden_sizes.iter().enumerate() {
+            values.push(hidden_size);
+            values.push(config.layer_activation_or_default(layer) as usize);
+        }
+        values.push(self.entries.len());
+        for (_, network) in &self.entries {
+            values.extend(network.hash_values(version));
+        }
+        values
+    }
+
+    /// Checks a deserialized store like `RPSNetwork::from_json()` checks a
+    /// network.
+    #[cfg(feature = "serde")]
+    fn validated(mut self) -> Result<Self, Error> {
+        if self.capacity == 0 || self.entries.len() > self.capacity {
+            return Err(Error::InvalidSize {
+                name: "capacity",
+                value: self.capacity,
+                min: self.entries.len().max(1),
+            });
+        }
+        for (_, network) in self.entries.iter_mut() {
+            network.validate()?;
+            network.mix_probs();
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(capacity: usize) -> ModelStore {
+        ModelStore::new(&NetworkConfig::new().moves(3).seed(42), capacity).unwrap()
+    }
+
+    /// Plays `rounds` rounds where opponent `id` always plays `m`.
+    fn play(store: &mut ModelStore, id: &str, m: usize, rounds: usize) {
+        let input = crate::moves::one_hot(m, 3);
+        store.forward(id, &input).unwrap();
+        for _ in 0..rounds {
+            store.backward(id, m, Some(0.1)).unwrap();
+            store.forward(id, &input).unwrap();
+        }
+    }
+
+    #[test]
+    fn learns_each_opponent() {
+        let mut store = store(4);
+        play(&mut store, "alice", 0, 30);
+        play(&mut store, "bob", 2, 30);
+        assert_eq!(store.predict("alice").unwrap().index, 0);
+        assert_eq!(store.predict("bob").unwrap().index, 2);
+        assert_eq!(store.len(), 2);
+        assert!(store.contains("alice"));
+        assert!(!store.contains("carol"));
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut store = store(2);
+        play(&mut store, "alice", 0, 1);
+        play(&mut store, "bob", 1, 1);
+        store.probs("alice").unwrap();
+        play(&mut store, "carol", 2, 1);
+        assert_eq!(store.ids(), vec!["alice", "carol"]);
+
+        // Peeking doesn't count as a use
+        assert!(store.network("alice").is_some());
+        play(&mut store, "dave", 2, 1);
+        assert_eq!(store.ids(), vec!["carol", "dave"]);
+
+        let network = store.remove("carol").unwrap();
+        store.insert("erin", network);
+        assert_eq!(store.ids(), vec!["dave", "erin"]);
+        assert!(store.remove("carol").is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialization() {
+        let mut store = store(3);
+        play(&mut store, "alice", 0, 10);
+        play(&mut store, "bob", 1, 10);
+        for restored in [
+            ModelStore::from_json(&store.to_json().unwrap()).unwrap(),
+            ModelStore::from_bytes(&store.to_bytes().unwrap()).unwrap(),
+        ] {
+            assert_eq!(restored.ids(), store.ids());
+            for id in ["alice", "bob"] {
+                assert_eq!(
+                    restored.get(id).unwrap().probs(),
+                    store.get(id).unwrap().probs()
+                );
+            }
+        }
+        assert!(ModelStore::from_json("{}").is_err());
+
+        // The header tells the models apart and is checked on load
+        let json = store.to_json().unwrap();
+        assert!(json.starts_with("{\"magic\":\"RPSN\""));
+        assert!(RPSNetwork::from_json(&json).is_err());
+        let network = store.get("alice").unwrap();
+        assert!(ModelStore::from_json(&network.to_json().unwrap()).is_err());
+        assert!(ModelStore::from_bytes(&network.to_bytes().unwrap()).is_err());
+        let hash = crate::migration::fnv1a(store.hash_values(crate::FORMAT_VERSION));
+        let tampered = json.replacen(
+            &format!("\"config_hash\":{}", hash),
+            &format!("\"config_hash\":{}", hash ^ 1),
+            1,
+        );
+        assert!(matches!(
+            ModelStore::from_json(&tampered),
+            Err(Error::ConfigMismatch { .. })
+        ));
+
+        // Stores saved before they had a header
+        let unversioned = serde_json::to_string(&store).unwrap();
+        assert_eq!(
+            ModelStore::from_json(&unversioned).unwrap().ids(),
+            store.ids()
+        );
+        let unversioned = bincode::serialize(&store).unwrap();
+        assert_eq!(
+            ModelStore::from_bytes(&unversioned).unwrap().ids(),
+            store.ids()
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let config = NetworkConfig::new().moves(3).seed(42);
+        assert!(ModelStore::new(&config, 0).is_err());
+        assert!(ModelStore::new(&config.clone().hidden_size(0), 2).is_err());
+        assert!(store(2).forward("alice", &[1.0, 0.0]).is_err());
+    }
+}
@@ -0,0 +1,420 @@
+use alloc::vec::Vec;
+use ndarray::ArrayD;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::optim::OptimizerState;
+use crate::{Error, RPSNetwork, Real};
+
+/// Settings of `RPSNetwork::train_with_validation()`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingOptions {
+    max_epochs: u32,
+    validation_split: Real,
+    patience: u32,
+    plateau_patience: u32,
+    decay: Real,
+    min_learning_rate: Real,
+    min_improvement: Real,
+    learning_rate: Option<Real>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl TrainingOptions {
+    /// Starts from at most 100 epochs, the last 20% of the rounds held out
+    /// for validation, stopping after 10 epochs without an improvement of
+    /// the validation loss by 1e-4, and halving the learning rate after 3,
+    /// down to 1e-5.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> Self {
+        Self {
+            max_epochs: 100,
+            validation_split: 0.2,
+            patience: 10,
+            plateau_patience: 3,
+            decay: 0.5,
+            min_learning_rate: 1e-5,
+            min_improvement: 1e-4,
+            learning_rate: None,
+        }
+    }
+
+    /// Largest number of passes over the training rounds.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn max_epochs(mut self, max_epochs: u32) -> Self {
+        self.max_epochs = max_epochs;
+        self
+    }
+
+    /// Share of the rounds, taken from the end of the game, held out to
+    /// measure the validation loss, strictly between 0 and 1.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn validation_split(mut self, validation_split: Real) -> Self {
+        self.validation_split = validation_split;
+        self
+    }
+
+    /// Number of epochs without improvement after which training stops.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn patience(mut self, patience: u32) -> Self {
+        self.patience = patience;
+        self
+    }
+
+    /// Number of epochs without improvement after which the learning rate
+    /// is multiplied by `decay`, counted again after each reduction.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn plateau_patience(mut self, plateau_patience: u32) -> Self {
+        self.plateau_patience = plateau_patience;
+        self
+    }
+
+    /// Factor applied to the learning rate on a plateau, strictly between 0
+    /// and 1.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn decay(mut self, decay: Real) -> Self {
+        self.decay = decay;
+        self
+    }
+
+    /// Learning rate below which plateaus don't reduce it further.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn min_learning_rate(mut self, min_learning_rate: Real) -> Self {
+        self.min_learning_rate = min_learning_rate;
+        self
+    }
+
+    /// Smallest decrease of the validation loss counted as an improvement.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn min_improvement(mut self, min_improvement: Real) -> Self {
+        self.min_improvement = min_improvement;
+        self
+    }
+
+    /// Learning rate of the first epoch, instead of the one the schedule
+    /// would use next.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn learning_rate(mut self, learning_rate: Real) -> Self {
+        self.learning_rate = Some(learning_rate);
+        self
+    }
+}
+
+impl Default for TrainingOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TrainingOptions {
+    fn validate(&self) -> Result<(), Error> {
+        for (name, value) in [
+            ("validation_split", self.validation_split),
+            ("decay", self.decay),
+        ] {
+            if !(value > 0.0 && value < 1.0) {
+                return Err(Error::OutOfRange {
+                    name,
+                    value,
+                    min: 0.0,
+                    max: 1.0,
+                });
+            }
+        }
+        for (name, value) in [
+            ("max_epochs", self.max_epochs),
+            ("patience", self.patience),
+            ("plateau_patience", self.plateau_patience),
+        ] {
+            if value == 0 {
+                return Err(Error::InvalidSize {
+                    name,
+                    value: 0,
+                    min: 1,
+                });
+            }
+        }
+        for (name, value) in [
+            ("min_learning_rate", self.min_learning_rate),
+            ("min_improvement", self.min_improvement),
+            ("learning_rate", self.learning_rate.unwrap_or(0.0)),
+        ] {
+            if !(value >= 0.0 && value.is_finite()) {
+                return Err(Error::OutOfRange {
+                    name,
+                    value,
+                    min: 0.0,
+                    max: Real::INFINITY,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What happened during `RPSNetwork::train_with_validation()`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingReport {
+    /// Number of epochs run.
+    pub epochs: u32,
+    /// Epoch whose weights the network kept, from 1.
+    pub best_epoch: u32,
+    /// Validation loss after that epoch.
+    pub best_validation_loss: Real,
+    /// Whether training stopped before the largest number of epochs.
+    pub stopped_early: bool,
+    /// Number of times a plateau reduced the learning rate.
+    pub reductions: u32,
+    /// Learning rate of the last epoch.
+    pub learning_rate: Real,
+    train_losses: Vec<Real>,
+    validation_losses: Vec<Real>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl TrainingReport {
+    /// Mean loss over the training rounds during each epoch.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn train_losses(&self) -> Vec<Real> {
+        self.train_losses.clone()
+    }
+
+    /// Mean loss over the validation rounds after each epoch.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn validation_losses(&self) -> Vec<Real> {
+        self.validation_losses.clone()
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl RPSNetwork {
+    /// Trains the network over a recorded game like `train_on_sequence()`,
+    /// holding out the last rounds to decide when to stop instead of running
+    /// a fixed number of epochs. After each pass over the training rounds,
+    /// the network predicts the validation rounds that follow them, without
+    /// dropout or training, and the learning rate drops on plateaus of the
+    /// validation loss. The network keeps the weights and optimizer state of
+    /// the epoch with the lowest validation loss, and its history is
+    /// cleared.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn train_with_validation(
+        &mut self,
+        moves: &[u8],
+        options: &TrainingOptions,
+    ) -> Result<TrainingReport, Error> {
+        options.validate()?;
+        let rounds = self.sequence_rounds(moves)?;
+        let validation = ((rounds.len() as Real * options.validation_split) as usize).max(1);
+        if rounds.len() < validation + 2 {
+            return Err(Error::InvalidSize {
+                name: "rounds",
+                value: rounds.len(),
+                min: validation + 2,
+            });
+        }
+        let split = rounds.len() - validation;
+
+        let mut learning_rate = options
+            .learning_rate
+            .unwrap_or_else(|| self.learning_rate());
+        let mut best: Option<(Vec<ArrayD<Real>>, OptimizerState)> = None;
+        let mut report = TrainingReport {
+            epochs: 0,
+            best_epoch: 0,
+            best_validation_loss: Real::INFINITY,
+            stopped_early: false,
+            reductions: 0,
+            learning_rate,
+            train_losses: Vec::new(),
+            validation_losses: Vec::new(),
+        };
+        let (mut since_best, mut since_reduction) = (0, 0);
+        while report.epochs < options.max_epochs {
+            self.reset_history();
+            let train_loss = self.sequence_pass(&rounds[..split], Some(learning_rate))?;
+            let validation_loss = self.validation_loss(&rounds[split - 1..])?;
+            report.epochs += 1;
+            report.learning_rate = learning_rate;
+            report.train_losses.push(train_loss);
+            report.validation_losses.push(validation_loss);
+
+            if validation_loss < report.best_validation_loss - options.min_improvement {
+                report.best_epoch = report.epochs;
+                report.best_validation_loss = validation_loss;
+                let params = self.params().iter().map(|p| p.to_owned()).collect();
+                best = Some((params, self.optim.clone()));
+                since_best = 0;
+                since_reduction = 0;
+                continue;
+            }
+            since_best += 1;
+            since_reduction += 1;
+            if since_best >= options.patience {
+                report.stopped_early = report.epochs < options.max_epochs;
+                break;
+            }
+            if since_reduction >= options.plateau_patience
+                && learning_rate > options.min_learning_rate
+            {
+                learning_rate = (learning_rate * options.decay).max(options.min_learning_rate);
+                report.reductions += 1;
+                since_reduction = 0;
+            }
+        }
+
+        if let Some((params, optim)) = best {
+            for (mut param, best) in self.params_mut().into_iter().zip(params) {
+                param.assign(&best);
+            }
+            // Later epochs' moments would otherwise carry on from weights
+            // the network no longer has
+            self.optim = optim;
+        }
+        self.reset_history();
+        self.undo = None;
+        Ok(report)
+    }
+}
+
+impl RPSNetwork {
+    /// Mean loss of the predictions of each of `rounds` from the ones
+    /// before, continuing from the current history, without dropout.
+    fn validation_loss(&mut self, rounds: &[(Vec<Real>, usize)]) -> Result<Real, Error> {
+        let training = self.training;
+        self.training = false;
+        let loss: Result<Real, Error> = rounds.windows(2).try_fold(0.0, |loss, pair| {
+            self.forward(&pair[0].0)?;
+            Ok(loss + self.loss(pair[1].1)?)
+        });
+        self.training = training;
+        Ok(loss? / (rounds.len() - 1) as Real)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NetworkConfig, Optimizer};
+
+    fn network() -> RPSNetwork {
+        NetworkConfig::new()
+            .moves(3)
+            .history_size(3)
+            .hidden_size(8)
+            .seed(3)
+            .build()
+            .unwrap()
+    }
+
+    fn cycle(rounds: usize) -> Vec<u8> {
+        [0, 0, 1, 2].iter().cycle().take(rounds).copied().collect()
+    }
+
+    #[test]
+    fn stops_when_validation_loss_settles() {
+        let mut network = network();
+        let options = TrainingOptions::new()
+            .max_epochs(500)
+            .learning_rate(0.1)
+            .min_improvement(1e-3);
+        let report = network.train_with_validation(&cycle(60), &options).unwrap();
+
+        assert!(report.stopped_early);
+        assert!(report.epochs < 500);
+        assert_eq!(report.train_losses().len(), report.epochs as usize);
+        assert_eq!(report.validation_losses().len(), report.epochs as usize);
+        assert_eq!(report.epochs - report.best_epoch, 10);
+        assert_eq!(
+            report.validation_losses()[report.best_epoch as usize - 1],
+            report.best_validation_loss
+        );
+        assert!(report.best_validation_loss < 0.1, "{:?}", report);
+        assert!(report.reductions > 0);
+        assert!(report.learning_rate < 0.1);
+
+        // The network kept the best weights
+        let validation = network.sequence_rounds(&cycle(60)).unwrap();
+        for pair in validation[..48].windows(2) {
+            network.forward(&pair[0].0).unwrap();
+        }
+        let loss = network.validation_loss(&validation[47..]).unwrap();
+        assert!((loss - report.best_validation_loss).abs() < 1e-5);
+    }
+
+    #[test]
+    fn restores_optimizer_of_best_epoch() {
+        let config = NetworkConfig::new()
+            .moves(3)
+            .history_size(3)
+            .hidden_size(8)
+            .optimizer(Optimizer::Adam)
+            .seed(3);
+        let options = TrainingOptions::new()
+            .max_epochs(500)
+            .learning_rate(0.01)
+            .min_improvement(1e-3);
+        let mut network = config.clone().build().unwrap();
+        let report = network.train_with_validation(&cycle(60), &options).unwrap();
+        assert!(report.stopped_early);
+
+        // Stopping at the best epoch leaves the same weights and moments
+        let mut stopped = config.build().unwrap();
+        let options = options.max_epochs(report.best_epoch);
+        stopped.train_with_validation(&cycle(60), &options).unwrap();
+        assert_eq!(network.params(), stopped.params());
+        assert_eq!(network.optim, stopped.optim);
+    }
+
+    #[test]
+    fn validation_keeps_training_mode_on_error() {
+        let mut network = network();
+        network.set_training(true);
+        // The first input is too short
+        let rounds = [(vec![0.0, 1.0], 1), (vec![1.0, 0.0, 0.0], 0)];
+        assert!(network.validation_loss(&rounds).is_err());
+        assert!(network.training);
+    }
+
+    #[test]
+    fn runs_every_epoch_while_improving() {
+        let mut network = network();
+        let options = TrainingOptions::new().max_epochs(5).learning_rate(0.05);
+        let report = network.train_with_validation(&cycle(40), &options).unwrap();
+
+        assert_eq!(report.epochs, 5);
+        assert_eq!(report.best_epoch, 5);
+        assert!(!report.stopped_early);
+        assert_eq!(report.reductions, 0);
+        let losses = report.validation_losses();
+        assert!(
+            losses.windows(2).all(|pair| pair[1] < pair[0]),
+            "{:?}",
+            losses
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let mut network = network();
+        for options in [
+            TrainingOptions::new().validation_split(0.0),
+            TrainingOptions::new().validation_split(1.0),
+            TrainingOptions::new().decay(1.5),
+            TrainingOptions::new().patience(0),
+            TrainingOptions::new().learning_rate(Real::NAN),
+        ] {
+            assert!(network.train_with_validation(&cycle(40), &options).is_err());
+        }
+        let options = TrainingOptions::new();
+        assert!(network.train_with_validation(&cycle(2), &options).is_err());
+        assert!(network.train_with_validation(&[0, 3, 1], &options).is_err());
+    }
+}
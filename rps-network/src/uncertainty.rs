@@ -0,0 +1,171 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::StandardNormal;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Error, RPSNetwork, Real};
+
+/// Spread of the predictions of slightly perturbed copies of a network, see
+/// `RPSNetwork::set_uncertainty()`.
+#[derive(Clone)]
+pub(crate) struct Uncertainty {
+    samples: usize,
+    /// Standard deviation of the noise added to the weights.
+    noise: Real,
+    /// Seed of the noise, drawn once so that every `forward()` evaluates
+    /// the same perturbations.
+    seed: u64,
+    mean: Vec<Real>,
+    variance: Vec<Real>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl RPSNetwork {
+    /// Makes `forward()` also evaluate `samples` copies of the hidden and
+    /// output layers, each with fixed Gaussian noise of standard deviation
+    /// `noise` added to its weights and biases, and dropout if training, so
+    /// that `probs_mean()` and `probs_variance()` tell a confident
+    /// prediction from one that small changes of the weights would flip.
+    /// `probs()` stays that of the network itself. 0 samples turns the
+    /// estimates off; they aren't saved with the network.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_uncertainty(&mut self, samples: usize, noise: Real) -> Result<(), Error> {
+        if samples == 1 {
+            return Err(Error::InvalidSize {
+                name: "samples",
+                value: samples,
+                min: 2,
+            });
+        }
+        if !(noise >= 0.0 && noise.is_finite()) {
+            return Err(Error::OutOfRange {
+                name: "noise",
+                value: noise,
+                min: 0.0,
+                max: Real::INFINITY,
+            });
+        }
+        self.uncertainty = (samples > 0).then(|| Uncertainty {
+            samples,
+            noise,
+            seed: self.rng.next_u64(),
+            mean: Vec::new(),
+            variance: Vec::new(),
+        });
+        self.evaluate_with_uncertainty();
+        Ok(())
+    }
+
+    /// Mean over the perturbed copies of their output distributions, mixed
+    /// like `probs()`, or `None` without `set_uncertainty()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn probs_mean(&self) -> Option<Vec<Real>> {
+        self.uncertainty.as_ref().map(|u| u.mean.clone())
+    }
+
+    /// Variance over the perturbed copies of the probability of each move,
+    /// or `None` without `set_uncertainty()`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn probs_variance(&self) -> Option<Vec<Real>> {
+        self.uncertainty.as_ref().map(|u| u.variance.clone())
+    }
+}
+
+impl RPSNetwork {
+    /// Evaluates the perturbed copies for the current history, if any, then
+    /// the network itself, so that `probs()` and the caches used by
+    /// `backward()` are left as the network computes them.
+    pub(crate) fn evaluate_with_uncertainty(&mut self) {
+        let Some(mut uncertainty) = self.uncertainty.take() else {
+            self.evaluate();
+            return;
+        };
+
+        let layers = self.layers.clone();
+        let mut rng = ChaCha8Rng::seed_from_u64(uncertainty.seed);
+        let mut sum = vec![0.0; self.output_size];
+        let mut squares = vec![0.0; self.output_size];
+        for _ in 0..uncertainty.samples {
+            for layer in &mut self.layers {
+                for w in layer.weights.iter_mut().chain(layer.bias.iter_mut()) {
+                    *w += uncertainty.noise * rng.sample::<Real, _>(StandardNormal);
+                }
+            }
+            self.evaluate();
+            for ((sum, squares), p) in sum.iter_mut().zip(&mut squares).zip(&self.mixed_probs) {
+                *sum += p;
+                *squares += p * p;
+            }
+            self.layers.clone_from(&layers);
+        }
+        self.evaluate();
+
+        let samples = uncertainty.samples as Real;
+        uncertainty.mean = sum.iter().map(|sum| sum / samples).collect();
+        uncertainty.variance = squares
+            .iter()
+            .zip(&uncertainty.mean)
+            .map(|(squares, mean)| (squares / samples - mean * mean).max(0.0))
+            .collect();
+        self.uncertainty = Some(uncertainty);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NetworkConfig, RPSNetwork};
+
+    fn network() -> RPSNetwork {
+        let mut network = NetworkConfig::new().moves(3).seed(7).build().unwrap();
+        for _ in 0..30 {
+            network.forward(&[1.0, 0.0, 0.0]).unwrap();
+            network.backward(0, Some(0.1)).unwrap();
+        }
+        network.forward(&[1.0, 0.0, 0.0]).unwrap();
+        network
+    }
+
+    #[test]
+    fn spreads_with_noise() {
+        let mut network = network();
+        let probs = network.probs();
+        assert_eq!(network.probs_mean(), None);
+
+        network.set_uncertainty(8, 0.0).unwrap();
+        let mean = network.probs_mean().unwrap();
+        assert!(mean.iter().zip(&probs).all(|(m, p)| (m - p).abs() < 1e-6));
+        assert!(network.probs_variance().unwrap().iter().all(|&v| v < 1e-9));
+
+        let mut total = 0.0;
+        for noise in [0.1, 0.5] {
+            network.set_uncertainty(8, noise).unwrap();
+            network.forward(&[1.0, 0.0, 0.0]).unwrap();
+            let mean = network.probs_mean().unwrap();
+            assert!((mean.iter().sum::<Real>() - 1.0).abs() < 1e-5);
+            let variance: Real = network.probs_variance().unwrap().iter().sum();
+            assert!(variance > total, "{} at noise {}", variance, noise);
+            total = variance;
+        }
+
+        // The network itself predicts as without perturbations
+        let mut plain = network.clone();
+        plain.set_uncertainty(0, 0.0).unwrap();
+        plain.forward(&[0.0, 1.0, 0.0]).unwrap();
+        network.forward(&[0.0, 1.0, 0.0]).unwrap();
+        assert_eq!(network.probs(), plain.probs());
+        assert_eq!(plain.probs_variance(), None);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut network = network();
+        assert!(network.set_uncertainty(1, 0.1).is_err());
+        assert!(network.set_uncertainty(4, -0.1).is_err());
+        assert!(network.set_uncertainty(4, Real::NAN).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, RPSNetwork, Real};
+
+/// Message sent to a network running in a Web Worker. Encoded as JSON with a
+/// `type` field naming the variant, e.g. `{"type": "Forward", "input": [1, 0,
+/// 0]}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Request {
+    /// Calls `RPSNetwork::forward()` and answers with the new `Probs`.
+    Forward { input: Vec<Real> },
+    /// Calls `RPSNetwork::backward()`.
+    Backward {
+        label: usize,
+        #[serde(default)]
+        learning_rate: Option<Real>,
+    },
+    /// Asks for the current `Probs`.
+    Probs,
+    /// Asks for the network serialized with `RPSNetwork::to_json()`.
+    Save,
+    /// Replaces the network with one serialized with `RPSNetwork::to_json()`.
+    Load { model: String },
+}
+
+/// Answer to a `Request`, encoded like it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Response {
+    /// The request succeeded and has nothing to return.
+    Done,
+    /// Output of `RPSNetwork::probs()`.
+    Probs { probs: Vec<Real> },
+    /// Answer to `Request::Save`.
+    Saved { model: String },
+    /// The request failed, or couldn't be decoded.
+    Error { message: String },
+}
+
+/// Owns a network on the worker side and applies the requests posted to it.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Dispatcher {
+    network: RPSNetwork,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Dispatcher {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(network: RPSNetwork) -> Self {
+        Self { network }
+    }
+
+    /// Decodes a JSON `Request`, applies it and returns the JSON `Response`,
+    /// ready to be posted back to the main thread.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn handle_json(&mut self, message: &str) -> String {
+        let response = match serde_json::from_str(message) {
+            Ok(request) => self.handle(request),
+            Err(error) => Response::from(Error::from(error)),
+        };
+        serde_json::to_string(&response).expect("responses always serialize")
+    }
+}
+
+impl Dispatcher {
+    /// Applies `request` to the network.
+    pub fn handle(&mut self, request: Request) -> Response {
+        self.try_handle(request).unwrap_or_else(Response::from)
+    }
+
+    /// Network the requests are applied to.
+    pub fn network(&self) -> &RPSNetwork {
+        &self.network
+    }
+
+    fn try_handle(&mut self, request: Request) -> Result<Response, Error> {
+        Ok(match request {
+            Request::Forward { input } => {
+                self.network.forward(&input)?;
+                Response::Probs {
+                    probs: self.network.probs(),
+                }
+            }
+            Request::Backward {
+                label,
+                learning_rate,
+            } => {
+                self.network.backward(label, learning_rate)?;
+                Response::Done
+            }
+            Request::Probs => Response::Probs {
+                probs: self.network.probs(),
+            },
+            Request::Save => Response::Saved {
+                model: self.network.to_json()?,
+            },
+            Request::Load { model } => {
+                self.network = RPSNetwork::from_json(&model)?;
+                Response::Done
+            }
+        })
+    }
+}
+
+impl From<Error> for Response {
+    fn from(error: Error) -> Self {
+        Response::Error {
+            message: error.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dispatcher() -> Dispatcher {
+        Dispatcher::new(RPSNetwork::new_with_seed(3, 5, 10, 3, 42).unwrap())
+    }
+
+    #[test]
+    fn json_protocol() {
+        let mut dispatcher = dispatcher();
+
+        let response = dispatcher.handle_json(r#"{"type": "Forward", "input": [1, 0, 0]}"#);
+        let probs = dispatcher.network().probs();
+        assert_eq!(
+            serde_json::from_str::<Response>(&response).unwrap(),
+            Response::Probs { probs }
+        );
+
+        let response = dispatcher.handle_json(r#"{"type": "Backward", "label": 1}"#);
+        assert_eq!(response, r#"{"type":"Done"}"#);
+
+        let response = dispatcher.handle_json(r#"{"type": "Backward", "label": 3}"#);
+        assert!(response.starts_with(r#"{"type":"Error""#));
+
+        let response = dispatcher.handle_json(r#"{"type": "Train"}"#);
+        assert!(response.starts_with(r#"{"type":"Error""#));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut dispatcher = dispatcher();
+        dispatcher.handle(Request::Forward {
+            input: vec![0.0, 1.0, 0.0],
+        });
+        let model = match dispatcher.handle(Request::Save) {
+            Response::Saved { model } => model,
+            response => panic!("unexpected response {:?}", response),
+        };
+
+        let mut other = Dispatcher::new(RPSNetwork::new_with_seed(3, 5, 10, 3, 7).unwrap());
+        assert_eq!(other.handle(Request::Load { model }), Response::Done);
+        assert_eq!(
+            other.handle(Request::Probs),
+            dispatcher.handle(Request::Probs)
+        );
+    }
+}